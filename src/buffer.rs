@@ -1,20 +1,43 @@
 use std::{
     borrow::Cow,
-    fs::{self, File},
+    collections::{BTreeMap, VecDeque},
+    fs,
     hash::{Hash, Hasher},
-    io::stdin,
+    io::{Read, stdin},
+    mem,
+    num::NonZeroUsize,
+    ops::Range,
     path::PathBuf,
+    str::FromStr,
 };
 
 use ahash::AHasher;
 use color_eyre::eyre::{bail, eyre};
+use rand::{Rng, seq::SliceRandom};
+use ratatui::layout::{Direction, Position, Rect};
 
 use crate::{
     CsvTableWidgetStyle, MoveDirection, Selection,
-    content::{CellLocation, CellLocationDelta, CellRect, CsvTable},
+    compression::{self, Compression},
+    content::{CellLocation, CellLocationDelta, CellRect, CsvTable, LoadSettings},
+    diff::TableDiff,
+    filter::RowFilter,
+    formula::Formulas,
+    notes::{self, Notes},
+    numeric::{AggregateCell, Heatmap},
+    results::ResultList,
+    search::Search,
+    series::Series,
+    sort::{self, SortKey},
+    task::Progress,
     undo::{UndoStack, Undoee},
 };
 
+/// Rows and columns, that can be scrolled beyond the used extent, when the view is clamped
+const CLAMP_VIEW_MARGIN: usize = 1;
+const SELECTION_HISTORY_LENGTH: usize = 16;
+const JUMPLIST_LENGTH: usize = 100;
+
 #[derive(Debug, Clone)]
 pub(crate) struct CsvBuffer {
     pub(crate) visible_cols: usize,
@@ -22,39 +45,169 @@ pub(crate) struct CsvBuffer {
     pub(crate) cell_height_wanted: u16,
     pub(crate) cell_width_wanted: u16,
     pub(crate) cell_height: u16,
-    pub(crate) cell_width: u16,
+    /// Rendered widths of the visible columns, which fill the whole width of the view
+    pub(crate) col_view_widths: Vec<u16>,
+    /// Widths of columns, that differ from `cell_width_wanted`
+    pub(crate) col_widths: BTreeMap<usize, u16>,
     pub(crate) style: CsvTableWidgetStyle,
     pub(crate) top_left_cell_location: CellLocation,
     pub(crate) csv_table: CsvTable,
     pub(crate) selection: Selection,
     pub(crate) selection_yanked: Option<Selection>,
+    /// Dropped visual selections, that can be restored with `gv`
+    selection_history: VecDeque<Selection>,
+    selection_history_cursor: usize,
+    /// Locations by their letter, set with `m` and jumped to with `'`
+    pub(crate) marks: BTreeMap<char, CellLocation>,
+    /// Locations before jumps, that `Ctrl-o` and `Ctrl-i` walk through
+    jumps: Vec<CellLocation>,
+    /// Position in `jumps`, which is its length, while not walking through it
+    jump_index: usize,
     pub(crate) file: Option<PathBuf>,
-    pub(crate) undo_stack: UndoStack<CsvTable>,
+    /// Compression of the file, that saving to it keeps
+    pub(crate) compression: Compression,
+    pub(crate) undo_stack: UndoStack<CsvBuffer>,
+    pub(crate) search: Option<Search>,
+    /// Highlight the matches of the last search, until `:noh`
+    pub(crate) search_highlight: bool,
+    /// Match, that is highlighted while a substitution asks for confirmation
+    pub(crate) pending_match: Option<(CellLocation, Range<usize>)>,
+    /// Screen area of the cells at the last render
+    pub(crate) area: Rect,
+    /// Screen areas of the scrollbars at the last render, empty if they are hidden
+    pub(crate) vscrollbar_area: Rect,
+    pub(crate) hscrollbar_area: Rect,
+    /// The scrollbar, that the mouse drags
+    pub(crate) scrollbar_drag: Option<Direction>,
+    /// Columns, that are colored by value
+    pub(crate) heatmaps: BTreeMap<usize, Heatmap>,
+    /// Aggregates of the footer row by column
+    pub(crate) aggregates: BTreeMap<usize, AggregateCell>,
+    pub(crate) formulas: Formulas,
+    /// Keep the view and selection near the used extent of the table
+    pub(crate) clamp_view: bool,
+    /// Notes of cells, which are saved in a sidecar file next to the CSV file
+    pub(crate) notes: Notes,
+    notes_changed: bool,
+    /// The list of the last `:matches` or `:changes`
+    pub(crate) results: Option<ResultList>,
+    /// Show the results in a pane below the table
+    pub(crate) results_open: bool,
+    /// Pin the first row at the top of the view and label the columns with it
+    pub(crate) header: bool,
+    /// Hides the rows, that don't match
+    pub(crate) filter: Option<RowFilter>,
+    /// Identifies the buffer in `:ls` and `:b`, assigned when it is opened
+    pub(crate) number: usize,
+    /// Differences of the rows, if the buffer compares two tables
+    pub(crate) diff: Option<TableDiff>,
     saved_hash: Option<u64>,
 }
 
 impl Default for CsvBuffer {
     fn default() -> Self {
         let csv_table = CsvTable::default();
+        let defaults = BufferDefaults::default();
         Self {
             visible_cols: 5,
             visible_rows: 20,
-            cell_height_wanted: 1,
-            cell_width_wanted: 25,
+            cell_height_wanted: defaults.cell_height,
+            cell_width_wanted: defaults.cell_width,
             cell_height: 0,
-            cell_width: 0,
+            col_view_widths: Vec::new(),
+            col_widths: BTreeMap::new(),
             style: Default::default(),
             top_left_cell_location: Default::default(),
             saved_hash: None,
             csv_table,
             selection: Default::default(),
             selection_yanked: Default::default(),
+            selection_history: VecDeque::with_capacity(SELECTION_HISTORY_LENGTH),
+            selection_history_cursor: 0,
+            marks: BTreeMap::new(),
+            jumps: Vec::new(),
+            jump_index: 0,
             file: None,
+            compression: Compression::None,
             undo_stack: UndoStack::new(),
+            search: None,
+            search_highlight: false,
+            pending_match: None,
+            area: Rect::default(),
+            vscrollbar_area: Rect::default(),
+            hscrollbar_area: Rect::default(),
+            scrollbar_drag: None,
+            clamp_view: defaults.clamp_view,
+            heatmaps: BTreeMap::new(),
+            aggregates: BTreeMap::new(),
+            formulas: Formulas::default(),
+            notes: Notes::new(),
+            notes_changed: false,
+            results: None,
+            results_open: false,
+            header: false,
+            filter: None,
+            number: 0,
+            diff: None,
         }
     }
 }
 
+/// Settings, that new buffers start with.
+#[derive(Debug, Clone)]
+pub(crate) struct BufferDefaults {
+    pub(crate) cell_width: u16,
+    pub(crate) cell_height: u16,
+    pub(crate) clamp_view: bool,
+    /// Delimiter of loaded files, unless one is given
+    pub(crate) delimiter: Option<u8>,
+    /// Encoding and faithfulness of loaded files
+    pub(crate) load: LoadSettings,
+    pub(crate) style: CsvTableWidgetStyle,
+}
+
+impl Default for BufferDefaults {
+    fn default() -> Self {
+        Self {
+            cell_width: 25,
+            cell_height: 1,
+            clamp_view: false,
+            delimiter: None,
+            load: LoadSettings::default(),
+            style: CsvTableWidgetStyle::default(),
+        }
+    }
+}
+
+/// Position of the view and the selection, which differ between two panes showing a buffer.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BufferView {
+    top_left_cell_location: CellLocation,
+    selection: Selection,
+}
+
+/// How `:fix-ragged` gives all rows the same number of fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RaggedFix {
+    /// Pad the rows to the longest one
+    Pad,
+    /// Pad or truncate the rows to the number of fields of most rows
+    Truncate,
+}
+
+impl FromStr for RaggedFix {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s {
+            "pad" => Self::Pad,
+            "truncate" => Self::Truncate,
+            _ => bail!("Unknown fix: {s}. Use `pad` or `truncate`"),
+        };
+        Ok(res)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum LoadOption {
     File(PathBuf),
@@ -62,28 +215,69 @@ pub(crate) enum LoadOption {
 }
 
 impl CsvBuffer {
-    pub(crate) fn load(load_option: LoadOption, delimiter: Option<u8>) -> color_eyre::Result<Self> {
-        let (csv_table, file, saved_hash) = match load_option {
+    pub(crate) fn new(defaults: &BufferDefaults) -> Self {
+        Self {
+            cell_width_wanted: defaults.cell_width,
+            cell_height_wanted: defaults.cell_height,
+            clamp_view: defaults.clamp_view,
+            style: defaults.style.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn load(
+        load_option: LoadOption,
+        delimiter: Option<u8>,
+        defaults: &BufferDefaults,
+    ) -> color_eyre::Result<Self> {
+        let delimiter = delimiter.or(defaults.delimiter);
+        let (csv_table, compression, file, saved_hash, notes) = match load_option {
             LoadOption::File(path_buf) => {
-                let file = File::open(&path_buf)?;
-                let csv_table = CsvTable::load(file, delimiter)?;
+                let (file, compression) = compression::open(&path_buf)?;
+                let csv_table = CsvTable::load_with(file, delimiter, defaults.load)?;
                 let hash = hash_table(&csv_table);
-                (csv_table, Some(path_buf), Some(hash))
-            }
-            LoadOption::Stdin => {
-                let stdin = stdin();
-                (CsvTable::load(stdin, delimiter)?, None, None)
+                let notes = notes::load(&path_buf)?;
+                (csv_table, compression, Some(path_buf), Some(hash), notes)
             }
+            LoadOption::Stdin => return Self::from_read(stdin(), delimiter, defaults),
         };
         let res = Self {
             saved_hash,
             csv_table,
+            compression,
             file,
-            ..Default::default()
+            notes,
+            ..Self::new(defaults)
         };
         Ok(res)
     }
 
+    /// A buffer without a file, that is read from `read`, e.g. stdin or a download.
+    pub(crate) fn from_read(
+        read: impl Read,
+        delimiter: Option<u8>,
+        defaults: &BufferDefaults,
+    ) -> color_eyre::Result<Self> {
+        let (read, compression) = compression::decompress(read)?;
+        let csv_table = CsvTable::load_with(read, delimiter.or(defaults.delimiter), defaults.load)?;
+        Ok(Self {
+            csv_table,
+            compression,
+            ..Self::new(defaults)
+        })
+    }
+
+    /// A buffer without a file, that shows the differences of `old` to `new`, see
+    /// [`TableDiff::new`].
+    pub(crate) fn diff(old: &CsvTable, new: &CsvTable, defaults: &BufferDefaults) -> Self {
+        let (csv_table, diff) = TableDiff::new(old, new);
+        Self {
+            csv_table,
+            diff: Some(diff),
+            ..Self::new(defaults)
+        }
+    }
+
     pub(crate) fn save(
         &mut self,
         file_name: Option<PathBuf>,
@@ -106,15 +300,53 @@ impl CsvBuffer {
                 bail!("File does not exist!");
             }
         }
-        let mut file = File::create(&file_path)?;
-        self.csv_table.normalize_and_save(&mut file)?;
+        // The extension asks for a compression, else the loaded file keeps its own
+        let compression = match Compression::from_path(&file_path) {
+            Compression::None if self.file.as_deref() == Some(&file_path) => self.compression,
+            compression => compression,
+        };
+        // Written at once, so a failing encoding doesn't leave a truncated file behind
+        let mut content = Vec::new();
+        self.csv_table.normalize_and_save(&mut content)?;
+        fs::write(&file_path, compression.compress(content)?)?;
+        self.compression = compression;
         self.saved_hash = Some(hash_table(&self.csv_table));
+        self.csv_table.mark_saved();
+        notes::save(&file_path, &self.notes)?;
+        self.notes_changed = false;
         let file_path = file_path.into_owned();
         self.file = Some(file_path.clone());
         Ok(file_path)
     }
 
+    /// Reads an unchanged file again with `settings`, which clears the undo history. Returns
+    /// whether it was read again. Otherwise only the encoding applies to saving, and the rows can
+    /// only stop being faithful.
+    pub(crate) fn set_load_settings(&mut self, settings: LoadSettings) -> color_eyre::Result<bool> {
+        if let Some(path) = &self.file
+            && self.saved_hash.is_some()
+            && !self.is_dirty()
+        {
+            let (file, compression) = compression::open(path)?;
+            let csv_table = CsvTable::load_with(file, self.csv_table.delimiter, settings)?;
+            self.compression = compression;
+            self.saved_hash = Some(hash_table(&csv_table));
+            self.csv_table = csv_table;
+            self.formulas = Formulas::default();
+            self.undo_stack = UndoStack::new();
+            return Ok(true);
+        }
+        self.csv_table.encoding = settings.encoding;
+        if !settings.faithful {
+            self.csv_table.forget_raw_rows();
+        }
+        Ok(false)
+    }
+
     pub(crate) fn is_dirty(&self) -> bool {
+        if self.notes_changed {
+            return true;
+        }
         let Some(saved_hash) = self.saved_hash else {
             return !self.is_empty();
         };
@@ -126,17 +358,160 @@ impl CsvBuffer {
     }
 
     pub(crate) fn move_selection(&mut self, direction: MoveDirection, n: usize) {
-        self.selection.primary += CellLocationDelta::from_direction(direction, n);
+        let delta = CellLocationDelta::from_direction(direction, n);
+        let primary = self.selection.primary + CellLocationDelta { y: 0, ..delta };
+        self.selection.primary = CellLocation {
+            row: self.step_row(primary.row, delta.y),
+            ..primary
+        };
+        self.clamp_selection();
         self.ensure_selection_in_view();
     }
 
     pub(crate) fn move_selection_to(&mut self, location: CellLocation) {
         self.selection.primary = location;
+        self.clamp_selection();
         self.ensure_selection_in_view();
     }
 
+    /// Moves the selection to the next edge of the data in `direction` within the used extent,
+    /// see [`CsvTable::data_edge`]. Rows, that the filter hides, are skipped.
+    pub(crate) fn move_selection_to_data_edge(&mut self, direction: MoveDirection) {
+        let extent = self.csv_table.used_extent();
+        let step = |location: CellLocation| {
+            let CellLocation { row, col } = location;
+            let (row, col) = match direction {
+                MoveDirection::Left => (row, col.checked_sub(1)?),
+                MoveDirection::Right if col + 1 < extent.col_count => (row, col + 1),
+                MoveDirection::Up => (self.shown_row(self.shown_index(row).checked_sub(1)?), col),
+                MoveDirection::Down if self.step_row(row, 1) < extent.row_count => {
+                    (self.step_row(row, 1), col)
+                }
+                MoveDirection::Right | MoveDirection::Down => return None,
+            };
+            Some(CellLocation { row, col })
+        };
+        let location = self.csv_table.data_edge(self.selection.primary, step);
+        self.move_selection_to(location);
+    }
+
+    /// Moves the selection to `location` and remembers the previous location in the jumplist.
+    pub(crate) fn jump_to(&mut self, location: CellLocation) {
+        let from = self.selection.primary;
+        self.jumps.truncate(self.jump_index);
+        if self.jumps.last() != Some(&from) {
+            self.jumps.push(from);
+        }
+        if self.jumps.len() > JUMPLIST_LENGTH {
+            self.jumps.remove(0);
+        }
+        self.jump_index = self.jumps.len();
+        self.move_selection_to(location);
+    }
+
+    /// Moves the selection to the location before the last jump.
+    pub(crate) fn jump_back(&mut self) -> color_eyre::Result<()> {
+        // The location, where the walk starts, is kept to come back to it
+        if self.jump_index == self.jumps.len() {
+            let current = self.selection.primary;
+            if self.jumps.last() != Some(&current) {
+                self.jumps.push(current);
+            }
+            self.jump_index = self.jumps.len() - 1;
+        }
+        if self.jump_index == 0 {
+            bail!("Already at the oldest jump!");
+        }
+        self.jump_index -= 1;
+        self.move_selection_to(self.jumps[self.jump_index]);
+        Ok(())
+    }
+
+    /// Undoes [`Self::jump_back`].
+    pub(crate) fn jump_forward(&mut self) -> color_eyre::Result<()> {
+        if self.jump_index + 1 >= self.jumps.len() {
+            bail!("Already at the newest jump!");
+        }
+        self.jump_index += 1;
+        self.move_selection_to(self.jumps[self.jump_index]);
+        Ok(())
+    }
+
+    /// The visual selection or, without one, the used extent of the table.
+    pub(crate) fn selection_or_used_extent(&self) -> CellRect {
+        if self.selection.opposite.is_some() {
+            self.selection.rect()
+        } else {
+            self.csv_table.used_extent()
+        }
+    }
+
+    /// Drops the visual selection and remembers it in the selection history.
+    pub(crate) fn drop_selection(&mut self) {
+        if self.selection.opposite.is_none() {
+            return;
+        }
+        if self.selection_history.len() == SELECTION_HISTORY_LENGTH {
+            self.selection_history.pop_front();
+        }
+        self.selection_history.push_back(self.selection);
+        self.selection_history_cursor = 0;
+        self.selection.opposite = None;
+    }
+
+    /// Restores the most recently dropped selection. Repeated calls cycle backwards through the
+    /// selection history. Returns `false`, if there is no selection to restore.
+    pub(crate) fn restore_selection(&mut self) -> bool {
+        let len = self.selection_history.len();
+        if len == 0 {
+            return false;
+        }
+        let index = len - 1 - self.selection_history_cursor % len;
+        self.selection_history_cursor = (self.selection_history_cursor + 1) % len;
+        self.selection = self.selection_history[index];
+        self.ensure_selection_in_view();
+        true
+    }
+
     pub(crate) fn move_view(&mut self, direction: MoveDirection, n: usize) {
-        self.top_left_cell_location += CellLocationDelta::from_direction(direction, n);
+        let delta = CellLocationDelta::from_direction(direction, n);
+        let top_left = self.top_left_cell_location + CellLocationDelta { y: 0, ..delta };
+        self.top_left_cell_location = CellLocation {
+            row: self.step_row(top_left.row, delta.y),
+            ..top_left
+        };
+        self.clamp_view();
+    }
+
+    /// The top left cell location of the view, when scrolled to the end of the used extent.
+    pub(crate) fn max_top_left_cell_location(&self) -> CellLocation {
+        let extent = self.csv_table.used_extent();
+        let row_index = self.shown_index(extent.row_count) + CLAMP_VIEW_MARGIN;
+        CellLocation {
+            row: self.shown_row(row_index.saturating_sub(self.visible_rows)),
+            col: (extent.col_count + CLAMP_VIEW_MARGIN).saturating_sub(self.visible_cols),
+        }
+    }
+
+    fn clamp_view(&mut self) {
+        if !self.clamp_view {
+            return;
+        }
+        let max = self.max_top_left_cell_location();
+        let top_left = &mut self.top_left_cell_location;
+        top_left.row = top_left.row.min(max.row);
+        top_left.col = top_left.col.min(max.col);
+    }
+
+    /// Allows the selection to move one cell beyond the used extent, so new cells can be created.
+    fn clamp_selection(&mut self) {
+        if !self.clamp_view {
+            return;
+        }
+        let extent = self.csv_table.used_extent();
+        let primary = &mut self.selection.primary;
+        primary.row = primary.row.min(extent.row_count);
+        primary.col = primary.col.min(extent.col_count);
     }
 
     #[expect(unused)]
@@ -155,20 +530,51 @@ impl CsvBuffer {
         } else if sel.col >= self.top_left_cell_location.col + self.visible_cols - col_buffer {
             self.top_left_cell_location.col = sel.col + col_buffer - self.visible_cols + 1;
         }
+        // Columns differ in width, so the number of visible columns changes while scrolling
+        if self.area.width > 0 {
+            let mut left = self.top_left_cell_location.col;
+            while left < sel.col
+                && sel.col + col_buffer >= left + self.fitting_col_count(left, self.area.width)
+            {
+                left += 1;
+            }
+            self.top_left_cell_location.col = left;
+        }
 
-        if sel.row < self.top_left_cell_location.row + row_buffer {
-            self.top_left_cell_location.row = sel.row.saturating_sub(row_buffer);
-        } else if sel.row >= self.top_left_cell_location.row + self.visible_rows - row_buffer {
-            self.top_left_cell_location.row = sel.row + row_buffer - self.visible_rows + 1;
+        // Rows are counted among the shown ones
+        let sel_row = self.shown_index(sel.row);
+        let mut top = self.shown_index(self.top_left_cell_location.row);
+        if sel_row < top + row_buffer {
+            top = sel_row.saturating_sub(row_buffer);
+        } else if sel_row >= top + self.visible_rows - row_buffer {
+            top = sel_row + row_buffer - self.visible_rows + 1;
         }
+        self.top_left_cell_location.row = self.shown_row(top);
+        self.clamp_view();
     }
 
     pub(crate) fn center_primary_selection(&mut self) {
-        self.top_left_cell_location = self.selection.primary
-            - CellLocationDelta {
-                x: (self.visible_cols / 2) as isize,
-                y: (self.visible_rows / 2) as isize,
-            }
+        self.center_primary_selection_row();
+        self.center_primary_selection_col();
+    }
+
+    /// Centers the view vertically on the primary selection.
+    pub(crate) fn center_primary_selection_row(&mut self) {
+        let top = self
+            .shown_index(self.selection.primary.row)
+            .saturating_sub(self.visible_rows / 2);
+        self.top_left_cell_location.row = self.shown_row(top);
+        self.clamp_view();
+    }
+
+    /// Centers the view horizontally on the primary selection.
+    pub(crate) fn center_primary_selection_col(&mut self) {
+        self.top_left_cell_location.col = self
+            .selection
+            .primary
+            .col
+            .saturating_sub(self.visible_cols / 2);
+        self.clamp_view();
     }
 
     pub(crate) fn recalculate_dimensions(&mut self, available_cols: u16, available_rows: u16) {
@@ -180,25 +586,755 @@ impl CsvBuffer {
             self.cell_height = self.cell_height_wanted + available_rows % self.cell_height_wanted;
         }
 
-        self.visible_cols = (available_cols / self.cell_width_wanted) as usize;
-        if self.visible_cols == 0 {
-            self.visible_cols = if available_cols == 0 { 0 } else { 1 };
-            self.cell_width = available_cols;
+        let left = self.top_left_cell_location.col;
+        self.visible_cols = self.fitting_col_count(left, available_cols);
+        let mut widths = (0..self.visible_cols)
+            .map(|i| self.col_width(left + i).min(available_cols))
+            .collect::<Vec<_>>();
+        // The remaining space is distributed, so the columns fill the whole width
+        if let Some(count) = NonZeroUsize::new(widths.len()) {
+            let remaining = available_cols.saturating_sub(widths.iter().sum()) as usize;
+            for (i, width) in widths.iter_mut().enumerate() {
+                *width += (remaining / count + usize::from(i < remaining % count)) as u16;
+            }
+        }
+        self.col_view_widths = widths;
+    }
+
+    /// Width of a column, before the remaining space is distributed.
+    pub(crate) fn col_width(&self, col: usize) -> u16 {
+        self.col_widths
+            .get(&col)
+            .copied()
+            .unwrap_or(self.cell_width_wanted)
+            .max(1)
+    }
+
+    /// Widens a column to at least `width`. Returns whether it changed.
+    pub(crate) fn grow_col(&mut self, col: usize, width: u16) -> bool {
+        if width <= self.col_width(col) {
+            return false;
+        }
+        self.col_widths.insert(col, width);
+        true
+    }
+
+    /// Number of columns starting at `left`, that completely fit into `available_cols`, but at
+    /// least one, if there is any space.
+    fn fitting_col_count(&self, left: usize, available_cols: u16) -> usize {
+        let mut remaining = available_cols;
+        let mut count = 0;
+        while let Some(rest) = remaining.checked_sub(self.col_width(left + count)) {
+            remaining = rest;
+            count += 1;
+        }
+        if count == 0 && available_cols > 0 {
+            1
         } else {
-            self.cell_width = self.cell_width_wanted + available_cols % self.cell_width_wanted;
+            count
+        }
+    }
+
+    /// The visible cell at a position of the screen.
+    pub(crate) fn cell_at(&self, position: Position) -> Option<CellLocation> {
+        if !self.area.contains(position) {
+            return None;
+        }
+        let row_view = ((position.y - self.area.y) / self.cell_height.max(1)) as usize;
+        let mut x = self.area.x;
+        let col_view = self.col_view_widths.iter().position(|width| {
+            x += width;
+            position.x < x
+        })?;
+        if row_view >= self.visible_rows {
+            return None;
         }
+        Some(CellLocation {
+            row: self.view_row(row_view),
+            col: self.top_left_cell_location.col + col_view,
+        })
+    }
+
+    /// The scrollbar at a position of the screen.
+    pub(crate) fn scrollbar_at(&self, position: Position) -> Option<Direction> {
+        if self.vscrollbar_area.contains(position) {
+            Some(Direction::Vertical)
+        } else if self.hscrollbar_area.contains(position) {
+            Some(Direction::Horizontal)
+        } else {
+            None
+        }
+    }
+
+    /// Scrolls the view to the point of `scrollbar`, that is nearest to `position`. The ends of
+    /// the track scroll to the ends of the used extent.
+    pub(crate) fn scroll_to_scrollbar(&mut self, scrollbar: Direction, position: Position) {
+        let max = self.max_top_left_cell_location();
+        match scrollbar {
+            Direction::Vertical => {
+                let area = self.vscrollbar_area;
+                let offset = position.y.clamp(area.top(), area.bottom().max(1) - 1) - area.y;
+                let index = track_position(offset, area.height, self.shown_index(max.row));
+                self.top_left_cell_location.row = self.shown_row(index);
+            }
+            Direction::Horizontal => {
+                let area = self.hscrollbar_area;
+                let offset = position.x.clamp(area.left(), area.right().max(1) - 1) - area.x;
+                self.top_left_cell_location.col = track_position(offset, area.width, max.col);
+            }
+        }
+    }
+
+    /// Row of the table, that is shown in the `row_view`th row of the view.
+    pub(crate) fn view_row(&self, row_view: usize) -> usize {
+        if self.header && row_view == 0 {
+            0
+        } else {
+            self.shown_row(self.shown_index(self.top_left_cell_location.row) + row_view)
+        }
+    }
+
+    pub(crate) fn view(&self) -> BufferView {
+        BufferView {
+            top_left_cell_location: self.top_left_cell_location,
+            selection: self.selection,
+        }
+    }
+
+    /// Exchanges the view of the buffer with `view`.
+    pub(crate) fn swap_view(&mut self, view: &mut BufferView) {
+        std::mem::swap(
+            &mut self.top_left_cell_location,
+            &mut view.top_left_cell_location,
+        );
+        std::mem::swap(&mut self.selection, &mut view.selection);
+    }
+
+    /// Position of `row` among the rows, that the filter shows. A hidden row maps to the next
+    /// shown one.
+    pub(crate) fn shown_index(&self, row: usize) -> usize {
+        self.filter
+            .as_ref()
+            .map_or(row, |filter| filter.index_of(row))
+    }
+
+    /// The row at `index` among the rows, that the filter shows.
+    pub(crate) fn shown_row(&self, index: usize) -> usize {
+        self.filter
+            .as_ref()
+            .map_or(index, |filter| filter.row_at(index))
+    }
+
+    /// The row `delta` shown rows away from `row`.
+    fn step_row(&self, row: usize, delta: isize) -> usize {
+        self.shown_row(self.shown_index(row).saturating_add_signed(delta))
+    }
+
+    /// Recomputes the shown rows, if the table changed.
+    pub(crate) fn update_filter(&mut self) {
+        if let Some(filter) = &mut self.filter {
+            filter.update(&self.csv_table, self.header);
+        }
+    }
+
+    /// Screen area of a cell, if it is visible.
+    pub(crate) fn cell_area(&self, location: CellLocation) -> Option<Rect> {
+        let row_view = if self.header && location.row == 0 {
+            0
+        } else {
+            let index = self.shown_index(location.row);
+            if self.shown_row(index) != location.row {
+                // Hidden by the filter
+                return None;
+            }
+            match index.checked_sub(self.shown_index(self.top_left_cell_location.row))? {
+                // Hidden behind the header
+                0 if self.header => return None,
+                row_view => row_view,
+            }
+        };
+        let col_view = location.col.checked_sub(self.top_left_cell_location.col)?;
+        if row_view >= self.visible_rows || col_view >= self.visible_cols {
+            return None;
+        }
+        let width = *self.col_view_widths.get(col_view)?;
+        let x = self.area.x + self.col_view_widths[..col_view].iter().sum::<u16>();
+        let y = self.area.y + row_view as u16 * self.cell_height;
+        Some(self.area.intersection(Rect {
+            x,
+            y,
+            width,
+            height: self.cell_height,
+        }))
+    }
+
+    /// Clears the cells of `rect` as one undo action and returns their previous values.
+    pub(crate) fn delete_rect(&mut self, rect: CellRect) -> Vec<Option<String>> {
+        let from_values = self.csv_table.delete_rect(rect);
+        self.undo_stack.push(UndoAction::ChangeCells {
+            mode: UndoChangeCellMode::Fill,
+            rect,
+            values: from_values.clone(),
+        });
+        from_values
+    }
+
+    /// Copies the first row (`Down`) or column (`Right`) of `rect` into the rest of it as one
+    /// undo action.
+    pub(crate) fn fill_rect(&mut self, rect: CellRect, direction: MoveDirection) {
+        let cells = self.csv_table.get_rect_cloned(rect);
+        let values = (0..rect.row_count)
+            .flat_map(|row| (0..rect.col_count).map(move |col| (row, col)))
+            .map(|(row, col)| match direction {
+                MoveDirection::Right | MoveDirection::Left => cells[row * rect.col_count].clone(),
+                MoveDirection::Down | MoveDirection::Up => cells[col].clone(),
+            })
+            .collect::<Vec<_>>();
+        let from_values = self.csv_table.set_rect(rect, values);
+        self.undo_stack.push(UndoAction::ChangeCells {
+            mode: UndoChangeCellMode::Edit,
+            rect,
+            values: from_values,
+        });
+    }
+
+    /// Writes the values of [`transposed`] for `rect` as one undo action and returns the
+    /// transposed rect.
+    pub(crate) fn transpose_rect(&mut self, rect: CellRect, transposed: Transposed) -> CellRect {
+        match transposed {
+            Transposed::Table(rows) => self.replace_cols(0, rect.col_count, rect.row_count, rows),
+            Transposed::Cells(values) => {
+                let values = self.csv_table.set_cell_list(values);
+                self.undo_stack.push(UndoAction::RestoreCells { values });
+            }
+        }
+        CellRect {
+            top_left_cell_location: rect.top_left_cell_location,
+            col_count: rect.row_count,
+            row_count: rect.col_count,
+        }
+    }
+
+    /// Continues the series, that starts in the first cells, down each column of `rect` or,
+    /// with a single row, across it. The seeds stay as they are. Records one undo action.
+    pub(crate) fn fill_series(
+        &mut self,
+        rect: CellRect,
+        step: Option<f64>,
+    ) -> color_eyre::Result<()> {
+        let mut values = self.csv_table.get_rect_cloned(rect);
+        let (lanes, lane_len) = if rect.row_count > 1 {
+            (rect.col_count, rect.row_count)
+        } else {
+            (1, rect.col_count)
+        };
+        // Index of the cell `n` of a lane in the row-major values
+        let index = |lane: usize, n: usize| {
+            if rect.row_count > 1 {
+                n * rect.col_count + lane
+            } else {
+                n
+            }
+        };
+        for lane in 0..lanes {
+            let first = values[index(lane, 0)].as_deref().unwrap_or_default();
+            let second = values.get(index(lane, 1)).cloned().flatten();
+            let (series, seeds) =
+                Series::detect(first, second.as_deref().filter(|_| lane_len > 1), step)?;
+            for n in seeds..lane_len {
+                values[index(lane, n)] = Some(series.nth(n)?);
+            }
+        }
+        let from_values = self.csv_table.set_rect(rect, values);
+        self.undo_stack.push(UndoAction::ChangeCells {
+            mode: UndoChangeCellMode::Edit,
+            rect,
+            values: from_values,
+        });
+        Ok(())
+    }
+
+    /// Recomputes the ranges of the heatmaps, whose columns changed.
+    pub(crate) fn update_heatmaps(&mut self) {
+        let revision = self.csv_table.revision();
+        for (col, heatmap) in &mut self.heatmaps {
+            heatmap.update(revision, self.csv_table.column_values(*col));
+        }
+    }
+
+    /// Recomputes the aggregates of the footer row, whose columns changed. A header row is left
+    /// out.
+    pub(crate) fn update_aggregates(&mut self) {
+        let revision = self.csv_table.revision();
+        for (col, aggregate) in &mut self.aggregates {
+            aggregate.update(revision, self.header, self.csv_table.column_values(*col));
+        }
+    }
+
+    /// Recalculates the formulas, if the table changed.
+    pub(crate) fn update_formulas(&mut self) {
+        self.formulas
+            .update(self.csv_table.revision(), &self.csv_table);
+    }
+
+    /// Position of the cell value in the range of its heatmap column.
+    pub(crate) fn heatmap_position(&self, location: CellLocation) -> Option<f32> {
+        let heatmap = self.heatmaps.get(&location.col)?;
+        heatmap.position(self.csv_table.get(location)?)
+    }
+
+    /// Sets every cell of the selection, for which `f` returns a new value, and records the
+    /// changes as one undo action. Returns the number of changed cells.
+    pub(crate) fn map_selection(&mut self, f: impl FnMut(Option<&str>) -> Option<String>) -> usize {
+        self.map_rect(self.selection.rect(), f)
+    }
+
+    /// Like [`Self::map_selection`] for the cells of `rect`.
+    pub(crate) fn map_rect(
+        &mut self,
+        rect: CellRect,
+        mut f: impl FnMut(Option<&str>) -> Option<String>,
+    ) -> usize {
+        let old_values = self.csv_table.get_rect_cloned(rect);
+        let mut count = 0;
+        let new_values = old_values
+            .iter()
+            .map(|old_value| match f(old_value.as_deref()) {
+                Some(new_value) if Some(&new_value) != old_value.as_ref() => {
+                    count += 1;
+                    Some(new_value)
+                }
+                _ => old_value.clone(),
+            })
+            .collect::<Vec<_>>();
+        if count == 0 {
+            return 0;
+        }
+        let old_values = self.csv_table.set_rect(rect, new_values);
+        self.undo_stack.push(UndoAction::ChangeCells {
+            mode: UndoChangeCellMode::Edit,
+            rect,
+            values: old_values,
+        });
+        count
+    }
+
+    /// Attaches a note to a cell or, with `None`, removes it.
+    pub(crate) fn set_note(&mut self, location: CellLocation, note: Option<String>) {
+        match note {
+            Some(note) => self.notes.insert(location, note),
+            None => self.notes.remove(&location),
+        };
+        self.notes_changed = true;
+    }
+
+    /// Gives the ragged rows the same number of fields as one undo action. Returns the number
+    /// of fixed rows.
+    pub(crate) fn fix_ragged(&mut self, fix: RaggedFix) -> usize {
+        let count = match fix {
+            RaggedFix::Pad => self.csv_table.max_field_count(),
+            RaggedFix::Truncate => self.csv_table.majority_field_count(),
+        };
+        let rows = self.csv_table.rows_without_field_count(count);
+        if rows.is_empty() {
+            return 0;
+        }
+        let values = (rows.iter())
+            .flat_map(|&row| {
+                (count..self.csv_table.field_count(row)).map(move |col| CellLocation { row, col })
+            })
+            .filter(|location| self.csv_table.get(*location).is_some())
+            .map(|location| (location, None))
+            .collect();
+        let mut field_counts = rows.iter().map(|&row| (row, count)).collect::<Vec<_>>();
+        let values = self.resize_rows(&mut field_counts, values);
+        self.undo_stack.push(UndoAction::ResizeRows {
+            field_counts,
+            values,
+        });
+        rows.len()
+    }
+
+    /// Sets the cells to `values` and then gives the rows their field counts, see
+    /// [`CsvTable::resize_row`]. The field counts are swapped with the previous ones and the
+    /// previous values are returned.
+    fn resize_rows(
+        &mut self,
+        field_counts: &mut [(usize, usize)],
+        values: Vec<(CellLocation, Option<String>)>,
+    ) -> Vec<(CellLocation, Option<String>)> {
+        let previous_counts = (field_counts.iter())
+            .map(|&(row, _)| self.csv_table.field_count(row))
+            .collect::<Vec<_>>();
+        let values = self.csv_table.set_cell_list(values);
+        for ((row, count), previous) in field_counts.iter_mut().zip(previous_counts) {
+            self.csv_table.resize_row(*row, *count);
+            *count = previous;
+        }
+        values
+    }
+
+    /// Sets the cells to the new values as one undo action.
+    pub(crate) fn set_cells(&mut self, changes: Vec<(CellLocation, String)>) {
+        let old_values = changes
+            .into_iter()
+            .map(|(location, value)| (location, self.csv_table.set(location, Some(value))))
+            .collect::<Vec<_>>();
+        self.push_cell_list_undo(old_values);
+    }
+
+    /// Records the previous values of changed, possibly scattered cells as one undo action.
+    pub(crate) fn push_cell_list_undo(&mut self, values: Vec<(CellLocation, Option<String>)>) {
+        if values.is_empty() {
+            return;
+        }
+        self.undo_stack.push(UndoAction::RestoreCells { values });
+    }
+
+    /// Randomly reorders the shown rows among `row_count` rows starting at `start` as one undo
+    /// action. The primary selection stays on its row.
+    pub(crate) fn shuffle_rows(&mut self, start: usize, row_count: usize, rng: &mut impl Rng) {
+        self.reorder_shown_rows(start, row_count, |offsets| offsets.shuffle(rng));
+    }
+
+    /// Reverses the order of the shown rows among `row_count` rows starting at `start` as one
+    /// undo action. The primary selection stays on its row.
+    pub(crate) fn reverse_rows(&mut self, start: usize, row_count: usize) {
+        self.reorder_shown_rows(start, row_count, <[usize]>::reverse);
+    }
+
+    /// Reorders the rows, that the filter shows, among `row_count` rows starting at `start`,
+    /// where `reorder` permutes their offsets. Rows hidden by the filter keep their place.
+    fn reorder_shown_rows(
+        &mut self,
+        start: usize,
+        row_count: usize,
+        reorder: impl FnOnce(&mut [usize]),
+    ) {
+        self.update_filter();
+        let shown = (0..row_count)
+            .filter(|offset| {
+                let row = start + offset;
+                self.shown_row(self.shown_index(row)) == row
+            })
+            .collect::<Vec<_>>();
+        let mut sources = shown.clone();
+        reorder(&mut sources);
+        let mut permutation = (0..row_count).collect::<Vec<_>>();
+        for (offset, source) in shown.into_iter().zip(sources) {
+            permutation[offset] = source;
+        }
+        self.reorder_rows(start, &permutation);
+    }
+
+    /// Sorts `row_count` rows starting at `start` by `keys` as one undo action. The primary
+    /// selection stays on its row.
+    pub(crate) fn sort_rows(&mut self, start: usize, row_count: usize, keys: &[SortKey]) {
+        let permutation = sort::sort_permutation(&self.csv_table, start, row_count, keys);
+        self.reorder_rows(start, &permutation);
+    }
+
+    /// Reorders rows as one undo action, see [`CsvTable::permute_rows`]. The primary selection
+    /// stays on its row.
+    fn reorder_rows(&mut self, start: usize, permutation: &[usize]) {
+        let row_count = permutation.len();
+        let inverse = self.permute_anchored_rows(start, permutation);
+        let row = self.selection.primary.row;
+        if self.selection.opposite.is_none() && (start..start + row_count).contains(&row) {
+            self.selection.primary.row = start + inverse[row - start];
+            self.ensure_selection_in_view();
+        }
+        self.undo_stack.push(UndoAction::ReorderRows {
+            start,
+            permutation: inverse,
+        });
+    }
+
+    /// Inserts `count` empty rows before the row `at` as one undo action.
+    pub(crate) fn insert_rows(&mut self, at: usize, count: usize) {
+        self.insert_anchored_rows(at, vec![Vec::new(); count], Anchored::default());
+        self.undo_stack
+            .push(UndoAction::DeleteRows { start: at, count });
+    }
+
+    /// Inserts a copy of `count` rows starting at `start` below them as one undo action.
+    pub(crate) fn duplicate_rows(&mut self, start: usize, count: usize) {
+        let col_count = self.csv_table.used_extent().col_count;
+        let rows = (start..start + count)
+            .map(|row| {
+                (0..col_count)
+                    .map(|col| {
+                        self.csv_table
+                            .get(CellLocation { row, col })
+                            .map(str::to_owned)
+                    })
+                    .collect()
+            })
+            .collect();
+        self.insert_anchored_rows(start + count, rows, Anchored::default());
+        self.undo_stack.push(UndoAction::DeleteRows {
+            start: start + count,
+            count,
+        });
+    }
+
+    /// Inserts a copy of `count` columns starting at `start` right of them as one undo action.
+    pub(crate) fn duplicate_cols(&mut self, start: usize, count: usize) {
+        let row_count = self.csv_table.used_extent().row_count;
+        let cols = (0..row_count)
+            .map(|row| {
+                (start..start + count)
+                    .map(|col| {
+                        self.csv_table
+                            .get(CellLocation { row, col })
+                            .map(str::to_owned)
+                    })
+                    .collect()
+            })
+            .collect();
+        self.insert_anchored_cols(start + count, count, cols, Anchored::default());
+        self.undo_stack.push(UndoAction::DeleteCols {
+            start: start + count,
+            count,
+        });
+    }
+
+    /// Deletes `count` rows starting at `start` as one undo action. Returns the number of
+    /// deleted rows.
+    pub(crate) fn delete_rows(&mut self, start: usize, count: usize) -> usize {
+        let (rows, anchored) = self.delete_anchored_rows(start, count);
+        let deleted = rows.len();
+        if deleted > 0 {
+            self.undo_stack.push(UndoAction::InsertRows {
+                start,
+                rows,
+                anchored,
+            });
+        }
+        deleted
+    }
+
+    /// Inserts `count` empty columns before the column `at` as one undo action.
+    pub(crate) fn insert_cols(&mut self, at: usize, count: usize) {
+        self.insert_anchored_cols(at, count, Vec::new(), Anchored::default());
+        self.undo_stack
+            .push(UndoAction::DeleteCols { start: at, count });
+    }
+
+    /// Deletes `count` columns starting at `start` as one undo action.
+    pub(crate) fn delete_cols(&mut self, start: usize, count: usize) {
+        let (cols, anchored) = self.delete_anchored_cols(start, count);
+        self.undo_stack.push(UndoAction::InsertCols {
+            start,
+            count,
+            cols,
+            anchored,
+        });
+    }
+
+    /// Replaces `delete` columns at `start` with the `insert` columns `cols` as one undo action.
+    pub(crate) fn replace_cols(
+        &mut self,
+        start: usize,
+        delete: usize,
+        insert: usize,
+        cols: Vec<Vec<Option<String>>>,
+    ) {
+        let (cols, anchored) =
+            self.replace_anchored_cols(start, delete, insert, cols, Anchored::default());
+        self.undo_stack.push(UndoAction::ReplaceCols {
+            start,
+            delete: insert,
+            insert: delete,
+            cols,
+            anchored,
+        });
     }
 
     pub(crate) fn undo(&mut self) {
-        self.undo_stack.undo(&mut self.csv_table);
+        let mut undo_stack = mem::take(&mut self.undo_stack);
+        undo_stack.undo(self);
+        self.undo_stack = undo_stack;
     }
 
     pub(crate) fn redo(&mut self) {
-        self.undo_stack.redo(&mut self.csv_table);
+        let mut undo_stack = mem::take(&mut self.undo_stack);
+        undo_stack.redo(self);
+        self.undo_stack = undo_stack;
+    }
+
+    /// Moves the notes, marks, column widths, heatmaps and aggregates to the rows and columns, that
+    /// `row` and `col` map them to. The ones, that are mapped to `None`, are removed and returned.
+    fn move_anchored(
+        &mut self,
+        row: impl Fn(usize) -> Option<usize>,
+        col: impl Fn(usize) -> Option<usize>,
+    ) -> Anchored {
+        let map = |location: CellLocation| {
+            Some(CellLocation {
+                row: row(location.row)?,
+                col: col(location.col)?,
+            })
+        };
+        let mut removed = Anchored::default();
+        for (location, note) in mem::take(&mut self.notes) {
+            let moved = map(location);
+            self.notes_changed |= moved != Some(location);
+            match moved {
+                Some(moved) => self.notes.insert(moved, note),
+                None => removed.notes.insert(location, note),
+            };
+        }
+        for (c, location) in mem::take(&mut self.marks) {
+            match map(location) {
+                Some(moved) => {
+                    self.marks.insert(c, moved);
+                }
+                None => removed.marks.push((c, location)),
+            }
+        }
+        for (c, width) in mem::take(&mut self.col_widths) {
+            match col(c) {
+                Some(moved) => self.col_widths.insert(moved, width),
+                None => removed.col_widths.insert(c, width),
+            };
+        }
+        for (c, heatmap) in mem::take(&mut self.heatmaps) {
+            match col(c) {
+                Some(moved) => self.heatmaps.insert(moved, heatmap),
+                None => removed.heatmaps.insert(c, heatmap),
+            };
+        }
+        for (c, aggregate) in mem::take(&mut self.aggregates) {
+            match col(c) {
+                Some(moved) => self.aggregates.insert(moved, aggregate),
+                None => removed.aggregates.insert(c, aggregate),
+            };
+        }
+        removed
+    }
+
+    /// Puts back the notes, marks, column widths, heatmaps and aggregates, that
+    /// [`Self::move_anchored`] removed.
+    fn restore_anchored(&mut self, anchored: Anchored) {
+        self.notes_changed |= !anchored.notes.is_empty();
+        self.notes.extend(anchored.notes);
+        self.marks.extend(anchored.marks);
+        self.col_widths.extend(anchored.col_widths);
+        self.heatmaps.extend(anchored.heatmaps);
+        self.aggregates.extend(anchored.aggregates);
+    }
+
+    /// Inserts `rows` before the row `at` and moves the notes and marks of the following rows
+    /// along. `anchored` are put back, e.g. the notes of deleted rows.
+    fn insert_anchored_rows(
+        &mut self,
+        at: usize,
+        rows: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    ) {
+        let count = rows.len();
+        self.csv_table.insert_rows(at, rows);
+        self.move_anchored(|row| Some(if row < at { row } else { row + count }), Some);
+        self.restore_anchored(anchored);
+    }
+
+    /// Reorders rows, see [`CsvTable::permute_rows`], and moves their notes and marks along.
+    /// Returns the inverse permutation.
+    fn permute_anchored_rows(&mut self, start: usize, permutation: &[usize]) -> Vec<usize> {
+        let inverse = self.csv_table.permute_rows(start, permutation);
+        let end = start + inverse.len();
+        self.move_anchored(
+            |row| {
+                Some(if (start..end).contains(&row) {
+                    start + inverse[row - start]
+                } else {
+                    row
+                })
+            },
+            Some,
+        );
+        inverse
+    }
+
+    /// Deletes `count` rows starting at `start` with their notes and marks, which are returned
+    /// with the rows.
+    fn delete_anchored_rows(
+        &mut self,
+        start: usize,
+        count: usize,
+    ) -> (Vec<Vec<Option<String>>>, Anchored) {
+        let rows = self.csv_table.delete_rows(start, count);
+        let end = start + rows.len();
+        let anchored = self.move_anchored(
+            |row| match row {
+                row if row < start => Some(row),
+                row if row < end => None,
+                row => Some(row - rows.len()),
+            },
+            Some,
+        );
+        (rows, anchored)
+    }
+
+    /// Inserts `count` columns before the column `at`, see [`CsvTable::insert_cols`], and moves
+    /// the notes, marks and widths of the following columns along. `anchored` are put back.
+    fn insert_anchored_cols(
+        &mut self,
+        at: usize,
+        count: usize,
+        cols: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    ) {
+        self.csv_table.insert_cols(at, count, cols);
+        self.move_anchored(Some, |col| Some(if col < at { col } else { col + count }));
+        self.restore_anchored(anchored);
+    }
+
+    /// Deletes `count` columns starting at `start` with their notes, marks and widths, which are
+    /// returned with the values of the columns.
+    fn delete_anchored_cols(
+        &mut self,
+        start: usize,
+        count: usize,
+    ) -> (Vec<Vec<Option<String>>>, Anchored) {
+        let cols = self.csv_table.delete_cols(start, count);
+        let anchored = self.move_anchored(Some, |col| match col {
+            col if col < start => Some(col),
+            col if col < start + count => None,
+            col => Some(col - count),
+        });
+        (cols, anchored)
+    }
+
+    /// Replaces `delete` columns at `start` with the `insert` columns `cols`, see
+    /// [`CsvTable::insert_cols`]. Returns the removed columns with their notes, marks and widths.
+    fn replace_anchored_cols(
+        &mut self,
+        start: usize,
+        delete: usize,
+        insert: usize,
+        cols: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    ) -> (Vec<Vec<Option<String>>>, Anchored) {
+        let removed = self.delete_anchored_cols(start, delete);
+        self.insert_anchored_cols(start, insert, cols, anchored);
+        removed
     }
 }
 
-impl Undoee for CsvTable {
+/// Notes, marks, column widths, heatmaps and aggregates of deleted rows or columns, that undo
+/// puts back.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Anchored {
+    notes: Notes,
+    marks: Vec<(char, CellLocation)>,
+    col_widths: BTreeMap<usize, u16>,
+    heatmaps: BTreeMap<usize, Heatmap>,
+    aggregates: BTreeMap<usize, AggregateCell>,
+}
+
+impl Undoee for CsvBuffer {
     type UndoAction = UndoAction;
     type RedoAction = RedoAction;
 
@@ -209,7 +1345,7 @@ impl Undoee for CsvTable {
                 rect,
                 values: from_values,
             } => {
-                let to_values = self.set_rect(rect, from_values);
+                let to_values = self.csv_table.set_rect(rect, from_values);
                 if mode == UndoChangeCellMode::Fill {
                     return RedoAction::FillCells {
                         rect,
@@ -226,7 +1362,7 @@ impl Undoee for CsvTable {
                 cell_location,
                 value: from_value,
             } => {
-                let to_value = self.set(cell_location, from_value);
+                let to_value = self.csv_table.set(cell_location, from_value);
                 if mode == UndoChangeCellMode::Fill {
                     return RedoAction::FillCell {
                         cell_location,
@@ -238,6 +1374,75 @@ impl Undoee for CsvTable {
                     value: to_value,
                 }
             }
+            UndoAction::RestoreCells { values } => RedoAction::EditCellList {
+                values: self.csv_table.set_cell_list(values),
+            },
+            UndoAction::ResizeRows {
+                mut field_counts,
+                values,
+            } => {
+                let values = self.resize_rows(&mut field_counts, values);
+                RedoAction::ResizeRows {
+                    field_counts,
+                    values,
+                }
+            }
+            UndoAction::ReorderRows { start, permutation } => RedoAction::ReorderRows {
+                start,
+                permutation: self.permute_anchored_rows(start, &permutation),
+            },
+            UndoAction::InsertRows {
+                start,
+                rows,
+                anchored,
+            } => {
+                let count = rows.len();
+                self.insert_anchored_rows(start, rows, anchored);
+                RedoAction::DeleteRows { start, count }
+            }
+            UndoAction::DeleteRows { start, count } => {
+                let (rows, anchored) = self.delete_anchored_rows(start, count);
+                RedoAction::InsertRows {
+                    start,
+                    rows,
+                    anchored,
+                }
+            }
+            UndoAction::InsertCols {
+                start,
+                count,
+                cols,
+                anchored,
+            } => {
+                self.insert_anchored_cols(start, count, cols, anchored);
+                RedoAction::DeleteCols { start, count }
+            }
+            UndoAction::DeleteCols { start, count } => {
+                let (cols, anchored) = self.delete_anchored_cols(start, count);
+                RedoAction::InsertCols {
+                    start,
+                    count,
+                    cols,
+                    anchored,
+                }
+            }
+            UndoAction::ReplaceCols {
+                start,
+                delete,
+                insert,
+                cols,
+                anchored,
+            } => {
+                let (cols, anchored) =
+                    self.replace_anchored_cols(start, delete, insert, cols, anchored);
+                RedoAction::ReplaceCols {
+                    start,
+                    delete: insert,
+                    insert: delete,
+                    cols,
+                    anchored,
+                }
+            }
         }
     }
 
@@ -247,7 +1452,7 @@ impl Undoee for CsvTable {
                 values: to_values,
                 rect,
             } => {
-                let from_values = self.set_rect(rect, to_values);
+                let from_values = self.csv_table.set_rect(rect, to_values);
                 UndoAction::ChangeCells {
                     mode: UndoChangeCellMode::Edit,
                     rect,
@@ -258,7 +1463,7 @@ impl Undoee for CsvTable {
                 cell_location,
                 value: to_value,
             } => {
-                let from_value = self.set(cell_location, to_value);
+                let from_value = self.csv_table.set(cell_location, to_value);
                 UndoAction::ChangeCell {
                     mode: UndoChangeCellMode::Edit,
                     cell_location,
@@ -266,7 +1471,7 @@ impl Undoee for CsvTable {
                 }
             }
             RedoAction::FillCells { rect, value } => {
-                let from_values = self.fill_rect(rect, value);
+                let from_values = self.csv_table.fill_rect(rect, value);
                 UndoAction::ChangeCells {
                     mode: UndoChangeCellMode::Edit,
                     rect,
@@ -277,13 +1482,82 @@ impl Undoee for CsvTable {
                 cell_location,
                 value,
             } => {
-                let from_value = self.set(cell_location, value);
+                let from_value = self.csv_table.set(cell_location, value);
                 UndoAction::ChangeCell {
                     mode: UndoChangeCellMode::Edit,
                     cell_location,
                     value: from_value,
                 }
             }
+            RedoAction::EditCellList { values } => UndoAction::RestoreCells {
+                values: self.csv_table.set_cell_list(values),
+            },
+            RedoAction::ResizeRows {
+                mut field_counts,
+                values,
+            } => {
+                let values = self.resize_rows(&mut field_counts, values);
+                UndoAction::ResizeRows {
+                    field_counts,
+                    values,
+                }
+            }
+            RedoAction::ReorderRows { start, permutation } => UndoAction::ReorderRows {
+                start,
+                permutation: self.permute_anchored_rows(start, &permutation),
+            },
+            RedoAction::InsertRows {
+                start,
+                rows,
+                anchored,
+            } => {
+                let count = rows.len();
+                self.insert_anchored_rows(start, rows, anchored);
+                UndoAction::DeleteRows { start, count }
+            }
+            RedoAction::DeleteRows { start, count } => {
+                let (rows, anchored) = self.delete_anchored_rows(start, count);
+                UndoAction::InsertRows {
+                    start,
+                    rows,
+                    anchored,
+                }
+            }
+            RedoAction::InsertCols {
+                start,
+                count,
+                cols,
+                anchored,
+            } => {
+                self.insert_anchored_cols(start, count, cols, anchored);
+                UndoAction::DeleteCols { start, count }
+            }
+            RedoAction::DeleteCols { start, count } => {
+                let (cols, anchored) = self.delete_anchored_cols(start, count);
+                UndoAction::InsertCols {
+                    start,
+                    count,
+                    cols,
+                    anchored,
+                }
+            }
+            RedoAction::ReplaceCols {
+                start,
+                delete,
+                insert,
+                cols,
+                anchored,
+            } => {
+                let (cols, anchored) =
+                    self.replace_anchored_cols(start, delete, insert, cols, anchored);
+                UndoAction::ReplaceCols {
+                    start,
+                    delete: insert,
+                    insert: delete,
+                    cols,
+                    anchored,
+                }
+            }
         }
     }
 }
@@ -300,6 +1574,44 @@ pub(crate) enum UndoAction {
         cell_location: CellLocation,
         value: Option<String>,
     },
+    RestoreCells {
+        values: Vec<(CellLocation, Option<String>)>,
+    },
+    /// Sets the cells and then gives the rows their field counts
+    ResizeRows {
+        field_counts: Vec<(usize, usize)>,
+        values: Vec<(CellLocation, Option<String>)>,
+    },
+    /// See [`CsvTable::permute_rows`]
+    ReorderRows {
+        start: usize,
+        permutation: Vec<usize>,
+    },
+    /// Restores deleted rows
+    InsertRows {
+        start: usize,
+        rows: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    },
+    /// Removes inserted rows
+    DeleteRows { start: usize, count: usize },
+    /// Restores deleted columns, see [`CsvTable::insert_cols`]
+    InsertCols {
+        start: usize,
+        count: usize,
+        cols: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    },
+    /// Removes inserted columns
+    DeleteCols { start: usize, count: usize },
+    /// Replaces `delete` columns at `start` with the `insert` columns `cols`
+    ReplaceCols {
+        start: usize,
+        delete: usize,
+        insert: usize,
+        cols: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -326,6 +1638,43 @@ pub(crate) enum RedoAction {
         cell_location: CellLocation,
         value: Option<String>,
     },
+    EditCellList {
+        values: Vec<(CellLocation, Option<String>)>,
+    },
+    ResizeRows {
+        field_counts: Vec<(usize, usize)>,
+        values: Vec<(CellLocation, Option<String>)>,
+    },
+    ReorderRows {
+        start: usize,
+        permutation: Vec<usize>,
+    },
+    InsertRows {
+        start: usize,
+        rows: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    },
+    DeleteRows {
+        start: usize,
+        count: usize,
+    },
+    InsertCols {
+        start: usize,
+        count: usize,
+        cols: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    },
+    DeleteCols {
+        start: usize,
+        count: usize,
+    },
+    ReplaceCols {
+        start: usize,
+        delete: usize,
+        insert: usize,
+        cols: Vec<Vec<Option<String>>>,
+        anchored: Anchored,
+    },
 }
 
 fn hash_table(table: &CsvTable) -> u64 {
@@ -333,3 +1682,65 @@ fn hash_table(table: &CsvTable) -> u64 {
     table.hash(&mut hasher);
     hasher.finish()
 }
+
+/// Position between 0 and `max`, that the `offset`th cell of a scrollbar track with `length`
+/// cells stands for.
+fn track_position(offset: u16, length: u16, max: usize) -> usize {
+    if length <= 1 {
+        return 0;
+    }
+    usize::from(offset) * max / usize::from(length - 1)
+}
+
+/// The new values of a transposed rect, see [`transposed`].
+pub(crate) enum Transposed {
+    /// The rows of the whole transposed table
+    Table(Vec<Vec<Option<String>>>),
+    /// The cells of the rect and of the transposed rect
+    Cells(Vec<(CellLocation, Option<String>)>),
+}
+
+/// Swaps the rows and columns of `rect`. Cells of `rect`, that the transposed rect doesn't
+/// cover, are cleared. Fails, if it would overwrite cells outside of `rect`.
+pub(crate) fn transposed(
+    table: &CsvTable,
+    rect: CellRect,
+    progress: &Progress,
+) -> color_eyre::Result<Transposed> {
+    let top_left = rect.top_left_cell_location;
+    let location = |row, col| CellLocation {
+        row: top_left.row + row,
+        col: top_left.col + col,
+    };
+    let value = |row, col| table.get(location(row, col)).map(str::to_owned);
+    if rect == table.used_extent() {
+        let rows = (0..rect.col_count)
+            .map(|col| {
+                progress.report(col, rect.col_count)?;
+                Ok((0..rect.row_count).map(|row| value(row, col)).collect())
+            })
+            .collect::<color_eyre::Result<_>>()?;
+        return Ok(Transposed::Table(rows));
+    }
+    let mut values = Vec::new();
+    // The transposed rect has a row for each column of `rect`
+    for row in 0..rect.col_count {
+        progress.report(row, rect.col_count)?;
+        for col in 0..rect.row_count {
+            if (row >= rect.row_count || col >= rect.col_count)
+                && !table.get(location(row, col)).unwrap_or_default().is_empty()
+            {
+                bail!("Transposing would overwrite {}!", location(row, col));
+            }
+            values.push((location(row, col), value(col, row)));
+        }
+    }
+    for row in rect.col_count..rect.row_count {
+        values.extend((0..rect.col_count).map(|col| (location(row, col), None)));
+    }
+    for col in rect.row_count..rect.col_count {
+        values
+            .extend((0..rect.row_count.min(rect.col_count)).map(|row| (location(row, col), None)));
+    }
+    Ok(Transposed::Cells(values))
+}
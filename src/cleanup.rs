@@ -0,0 +1,71 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::bail;
+
+/// A bulk text cleanup of cells, named like its command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Cleanup {
+    /// Strips leading and trailing whitespace
+    Trim,
+    /// Collapses runs of whitespace into a single space
+    Squeeze,
+    Upper,
+    Lower,
+    /// Uppercases the first letter of every word and lowercases the rest
+    Title,
+}
+
+impl Cleanup {
+    pub(crate) fn apply(self, value: &str) -> String {
+        match self {
+            Cleanup::Trim => value.trim().to_owned(),
+            Cleanup::Squeeze => {
+                let mut res = String::with_capacity(value.len());
+                let mut in_whitespace = false;
+                for c in value.chars() {
+                    if c.is_whitespace() {
+                        if !in_whitespace {
+                            res.push(' ');
+                        }
+                        in_whitespace = true;
+                    } else {
+                        res.push(c);
+                        in_whitespace = false;
+                    }
+                }
+                res
+            }
+            Cleanup::Upper => value.to_uppercase(),
+            Cleanup::Lower => value.to_lowercase(),
+            Cleanup::Title => {
+                let mut res = String::with_capacity(value.len());
+                let mut word_start = true;
+                for c in value.chars() {
+                    if word_start {
+                        res.extend(c.to_uppercase());
+                    } else {
+                        res.extend(c.to_lowercase());
+                    }
+                    word_start = !c.is_alphanumeric() && c != '\'';
+                }
+                res
+            }
+        }
+    }
+}
+
+impl FromStr for Cleanup {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s {
+            "trim" => Cleanup::Trim,
+            "squeeze" => Cleanup::Squeeze,
+            "upper" => Cleanup::Upper,
+            "lower" => Cleanup::Lower,
+            "title" => Cleanup::Title,
+            _ => bail!("Unknown cleanup: {s}"),
+        };
+        Ok(res)
+    }
+}
@@ -0,0 +1,19 @@
+use std::io::Write;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use ratatui::backend::CrosstermBackend;
+
+/// Backends, that can copy text to the system clipboard.
+pub(crate) trait ClipboardBackend {
+    fn copy_to_clipboard(&mut self, text: &str) -> std::io::Result<()>;
+}
+
+/// Uses the OSC 52 escape sequence, which is supported by most terminal emulators (also over
+/// ssh).
+impl<W: Write> ClipboardBackend for CrosstermBackend<W> {
+    fn copy_to_clipboard(&mut self, text: &str) -> std::io::Result<()> {
+        let encoded = BASE64_STANDARD.encode(text);
+        write!(self, "\x1b]52;c;{encoded}\x07")?;
+        Write::flush(self)
+    }
+}
@@ -1,6 +1,19 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use ansi_colours::*;
 use ratatui::style::Color;
 
+static MONOCHROME: AtomicBool = AtomicBool::new(false);
+
+/// Disables all colors. Mixing colors then always results in [`Color::Reset`].
+pub fn set_monochrome(monochrome: bool) {
+    MONOCHROME.store(monochrome, Ordering::Relaxed);
+}
+
+pub fn is_monochrome() -> bool {
+    MONOCHROME.load(Ordering::Relaxed)
+}
+
 pub trait ColorExt {
     fn to_rgb(self, is_fg: bool) -> (u8, u8, u8);
     fn mix(self, other: Color, t: f32, is_fg: bool) -> Color;
@@ -32,6 +45,9 @@ impl ColorExt for Color {
     }
 
     fn mix(self, other: Color, t: f32, is_fg: bool) -> Color {
+        if is_monochrome() {
+            return Color::Reset;
+        }
         match (self, other) {
             (Color::Reset, Color::Reset) => return Color::Reset,
             (Color::Reset, c) => return c,
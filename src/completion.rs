@@ -0,0 +1,298 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use crate::{
+    content::CsvTable,
+    search::{self, LITERAL_PREFIX},
+    settings::Settings,
+};
+
+/// Candidates are collected from at most this many distinct values, so huge tables stay fast
+const COMPLETION_MAX_DISTINCT: usize = 10_000;
+
+/// The console commands, that are completed
+const COMMANDS: [&str; 104] = [
+    "agg",
+    "aggregate",
+    "append-row",
+    "apply",
+    "b",
+    "bc",
+    "bc!",
+    "bn",
+    "bp",
+    "buffer",
+    "buffer-close",
+    "buffer-close!",
+    "buffer-next",
+    "buffer-previous",
+    "buffers",
+    "cclose",
+    "changes",
+    "check",
+    "col-delete",
+    "col-insert",
+    "copen",
+    "count",
+    "delimiter",
+    "diff",
+    "dup-col",
+    "dup-row",
+    "echo",
+    "edit-cell",
+    "export",
+    "fill",
+    "filter",
+    "fix-ragged",
+    "freq",
+    "goto",
+    "goto-end",
+    "h",
+    "header",
+    "heatmap",
+    "help",
+    "hist",
+    "join",
+    "lower",
+    "ls",
+    "matches",
+    "merge-cols",
+    "n",
+    "new",
+    "noh",
+    "nohlsearch",
+    "note",
+    "notes",
+    "now",
+    "o",
+    "only",
+    "open",
+    "pad",
+    "pad-right",
+    "paste-special",
+    "pipe",
+    "pivot",
+    "q",
+    "q!",
+    "quit",
+    "quit!",
+    "recent",
+    "registers",
+    "reverse",
+    "row-delete",
+    "row-insert",
+    "sample",
+    "save-path",
+    "seq",
+    "set",
+    "shuffle",
+    "sort",
+    "sp",
+    "split",
+    "split-col",
+    "sql",
+    "squeeze",
+    "stats",
+    "title",
+    "today",
+    "transpose",
+    "trim",
+    "upper",
+    "uuid",
+    "vs",
+    "vsplit",
+    "w",
+    "w!",
+    "wq",
+    "wq!",
+    "write",
+    "write!",
+    "write-quit",
+    "write-quit!",
+    "write-yank",
+    "write-yank!",
+    "x",
+    "x!",
+    "yank-all",
+    "yank-all!",
+    "yank-txt",
+];
+
+/// The words before a file argument, e.g. `w` in `w data.csv`
+const PATH_COMMANDS: [&[&str]; 16] = [
+    &["o"],
+    &["open"],
+    &["w"],
+    &["w!"],
+    &["write"],
+    &["write!"],
+    &["wq"],
+    &["wq!"],
+    &["x"],
+    &["x!"],
+    &["write-quit"],
+    &["write-quit!"],
+    &["write-yank"],
+    &["write-yank!"],
+    &["diff"],
+    &["export", "txt"],
+];
+
+/// Where the candidates of a completion come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CompletionScope {
+    Column(usize),
+    Table,
+    /// Names of console commands
+    Command,
+    /// Files and directories of a file argument
+    Path,
+}
+
+/// Completion of the input with `Tab`: the search input from the values of the table, the
+/// console from the commands and files.
+#[derive(Clone, Debug)]
+pub(crate) struct Completion {
+    /// The input before completing, which the candidates start with
+    original: String,
+    pub(crate) scope: CompletionScope,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl Completion {
+    /// Collects the distinct values of `scope`, that start with the input (without a `\V`).
+    pub(crate) fn new(
+        original: String,
+        scope: CompletionScope,
+        table: &CsvTable,
+        settings: &Settings,
+    ) -> Self {
+        let prefix = original.strip_prefix(LITERAL_PREFIX).unwrap_or(&original);
+        let values: Box<dyn Iterator<Item = &str>> = match scope {
+            CompletionScope::Column(col) => Box::new(table.column_values(col).flatten()),
+            CompletionScope::Table => Box::new(table.cells().map(|(_, value)| value)),
+            // See `Self::command_line`
+            CompletionScope::Command | CompletionScope::Path => Box::new(std::iter::empty()),
+        };
+        let starts_with = |value: &str| {
+            if settings.ignorecase {
+                value.to_lowercase().starts_with(&prefix.to_lowercase())
+            } else {
+                value.starts_with(prefix)
+            }
+        };
+        let mut distinct = HashSet::new();
+        let mut candidates = Vec::new();
+        for value in values.filter(|value| !value.is_empty() && !value.contains('\n')) {
+            if distinct.len() == COMPLETION_MAX_DISTINCT {
+                break;
+            }
+            if distinct.insert(value) && starts_with(value) && value != prefix {
+                candidates.push(value.to_owned());
+            }
+        }
+        Self {
+            original,
+            scope,
+            candidates,
+            index: 0,
+        }
+    }
+
+    /// Completes the command name or the file argument of a console input.
+    pub(crate) fn command_line(original: String) -> Self {
+        let split = original
+            .rfind(char::is_whitespace)
+            .map_or(0, |index| index + 1);
+        let (head, word) = original.split_at(split);
+        let words = head.split_whitespace().collect::<Vec<_>>();
+        let (scope, candidates) = if words.is_empty() {
+            let candidates = COMMANDS
+                .into_iter()
+                .filter(|command| command.starts_with(word) && *command != word)
+                .map(|command| format!("{head}{command}"))
+                .collect();
+            (CompletionScope::Command, candidates)
+        } else if PATH_COMMANDS.contains(&&words[..]) {
+            let candidates = path_candidates(word)
+                .into_iter()
+                .map(|path| format!("{head}{path}"))
+                .collect();
+            (CompletionScope::Path, candidates)
+        } else {
+            (CompletionScope::Command, Vec::new())
+        };
+        Self {
+            original,
+            scope,
+            candidates,
+            index: 0,
+        }
+    }
+
+    pub(crate) fn original(&self) -> &str {
+        &self.original
+    }
+
+    pub(crate) fn next(&mut self) {
+        self.index = (self.index + 1) % self.candidates.len().max(1);
+    }
+
+    /// The input with the current candidate, which is regex-escaped, unless the pattern is
+    /// literal.
+    pub(crate) fn input(&self, settings: &Settings) -> Option<String> {
+        let candidate = self.candidates.get(self.index)?;
+        let input = if matches!(self.scope, CompletionScope::Command | CompletionScope::Path) {
+            candidate.clone()
+        } else if !search::is_literal(&self.original, settings) {
+            regex::escape(candidate)
+        } else if self.original.starts_with(LITERAL_PREFIX) {
+            format!("{LITERAL_PREFIX}{candidate}")
+        } else {
+            candidate.clone()
+        };
+        Some(input)
+    }
+
+    /// E.g. `column 2/5`
+    pub(crate) fn status(&self) -> String {
+        let scope = match self.scope {
+            CompletionScope::Column(_) => "column",
+            CompletionScope::Table => "table",
+            CompletionScope::Command => "command",
+            CompletionScope::Path => "path",
+        };
+        format!("{scope} {}/{}", self.index + 1, self.candidates.len())
+    }
+}
+
+/// The files and directories, that start with `prefix`, relative to the current directory.
+/// Directories end with a `/`, hidden entries are only completed after a `.`.
+fn path_candidates(prefix: &str) -> Vec<String> {
+    let (dir, name) = match prefix.rfind('/') {
+        Some(index) => prefix.split_at(index + 1),
+        None => ("", prefix),
+    };
+    let Ok(entries) = fs::read_dir(if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    }) else {
+        return Vec::new();
+    };
+    let mut candidates = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            if !file_name.starts_with(name)
+                || (file_name.starts_with('.') && !name.starts_with('.'))
+            {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(format!("{dir}{file_name}{}", if is_dir { "/" } else { "" }))
+        })
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates
+}
@@ -0,0 +1,73 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+const ZSTD_MAGIC: &[u8] = b"\x28\xb5\x2f\xfd";
+
+/// Compression of a file, which is detected from its first bytes on load and kept on save.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression, that the extension of `path` asks for, e.g. `data.csv.gz`.
+    pub(crate) fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => Self::Gzip,
+            Some("zst") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+
+    fn detect(start: &[u8]) -> Self {
+        if start.starts_with(GZIP_MAGIC) {
+            Self::Gzip
+        } else if start.starts_with(ZSTD_MAGIC) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// Compresses `content`.
+    pub(crate) fn compress(self, content: Vec<u8>) -> color_eyre::Result<Vec<u8>> {
+        let compressed = match self {
+            Self::None => content,
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&content)?;
+                encoder.finish()?
+            }
+            Self::Zstd => zstd::encode_all(content.as_slice(), 0)?,
+        };
+        Ok(compressed)
+    }
+}
+
+/// Opens `path` for reading, decompressed if it is compressed.
+pub(crate) fn open(path: &Path) -> color_eyre::Result<(Box<dyn Read>, Compression)> {
+    decompress(File::open(path)?)
+}
+
+/// Decompresses `read`, if it starts like compressed data.
+pub(crate) fn decompress<'a>(
+    read: impl Read + 'a,
+) -> color_eyre::Result<(Box<dyn Read + 'a>, Compression)> {
+    let mut read = BufReader::new(read);
+    let compression = Compression::detect(read.fill_buf()?);
+    let read: Box<dyn Read + 'a> = match compression {
+        Compression::None => Box::new(read),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(read)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(read)?),
+    };
+    Ok((read, compression))
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    fmt::{self, Display},
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use color_eyre::eyre::{WrapErr, bail, eyre};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+/// Settings from the configuration file, e.g.
+///
+/// ```toml
+/// cell-width = 20
+/// delimiter = ";"
+///
+/// [style.primary-selection]
+/// bg = "#88c0d0"
+/// modifiers = ["bold"]
+///
+/// [keys]
+/// "<C-j>" = "J"
+///
+/// [bind]
+/// "<C-d>" = "none"
+/// goto.e = "goto-col-start"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct Config {
+    pub(crate) cell_width: Option<u16>,
+    pub(crate) cell_height: Option<u16>,
+    pub(crate) delimiter: Option<char>,
+    /// Styles of the table by name, e.g. `primary-selection`
+    pub(crate) style: BTreeMap<String, StyleConfig>,
+    /// Keys, that act like other keys in the table, e.g. `"<C-j>" = "J"`
+    pub(crate) keys: BTreeMap<String, String>,
+    /// Actions of keys, see [`crate::keymap::Keymap::apply`]
+    pub(crate) bind: BTreeMap<String, Binding>,
+}
+
+/// The action of a key or the bindings of the keys, that complete a combo.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Binding {
+    Action(String),
+    Combo(BTreeMap<String, String>),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct StyleConfig {
+    fg: Option<String>,
+    bg: Option<String>,
+    modifiers: Vec<String>,
+}
+
+impl StyleConfig {
+    /// The style, that is patched onto the default style.
+    pub(crate) fn to_style(&self) -> color_eyre::Result<Style> {
+        let color =
+            |color: &str| Color::from_str(color).map_err(|_| eyre!("Invalid color: {color}"));
+        let mut style = Style::new();
+        if let Some(fg) = &self.fg {
+            style = style.fg(color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(color(bg)?);
+        }
+        for modifier in &self.modifiers {
+            let modifier = match modifier.as_str() {
+                "bold" => Modifier::BOLD,
+                "dim" => Modifier::DIM,
+                "italic" => Modifier::ITALIC,
+                "underlined" => Modifier::UNDERLINED,
+                "reversed" => Modifier::REVERSED,
+                "crossed-out" => Modifier::CROSSED_OUT,
+                modifier => bail!("Invalid modifier: {modifier}"),
+            };
+            style = style.add_modifier(modifier);
+        }
+        Ok(style)
+    }
+}
+
+impl Config {
+    /// Reads the configuration file. Without one, the defaults are used.
+    pub(crate) fn load() -> color_eyre::Result<Self> {
+        let Some(path) = config_file() else {
+            return Ok(Self::default());
+        };
+        Self::load_from(&path)
+    }
+
+    pub(crate) fn load_from(path: &Path) -> color_eyre::Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        toml::from_str(&content)
+            .wrap_err_with(|| format!("Invalid config file: {}", path.to_string_lossy()))
+    }
+
+    /// The key remaps by the key, that is pressed.
+    pub(crate) fn key_remaps(&self) -> color_eyre::Result<HashMap<KeyStroke, KeyEvent>> {
+        self.keys
+            .iter()
+            .map(|(from, to)| {
+                let to = parse_key(to)?;
+                Ok((KeyStroke::from(parse_key(from)?), to))
+            })
+            .collect()
+    }
+}
+
+/// A key with its modifiers, ignoring `Shift` for characters, which is part of the character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct KeyStroke {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for KeyStroke {
+    fn from(key: KeyEvent) -> Self {
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        Self {
+            code: key.code,
+            modifiers,
+        }
+    }
+}
+
+/// Written like the keys of the configuration, e.g. `j`, `<C-r>` or `<Left>`.
+impl Display for KeyStroke {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.code {
+            KeyCode::Char(' ') => "Space".to_owned(),
+            KeyCode::Char('<') => "lt".to_owned(),
+            KeyCode::Char(c) if self.modifiers.is_empty() => return write!(f, "{c}"),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            KeyCode::Backspace => "BS".to_owned(),
+            KeyCode::Delete => "Del".to_owned(),
+            code => format!("{code:?}"),
+        };
+        f.write_str("<")?;
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "A-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(prefix)?;
+            }
+        }
+        write!(f, "{name}>")
+    }
+}
+
+/// Parses a key like `j`, `<C-r>`, `<A-Left>` or `<Esc>`.
+pub(crate) fn parse_key(key: &str) -> color_eyre::Result<KeyEvent> {
+    let invalid = || eyre!("Invalid key: {key}");
+    let Some(inner) = key.strip_prefix('<').and_then(|key| key.strip_suffix('>')) else {
+        let mut chars = key.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(KeyEvent::from(KeyCode::Char(c))),
+            _ => Err(invalid()),
+        };
+    };
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = inner;
+    while let Some((modifier, rest)) = name.split_once('-')
+        && !rest.is_empty()
+    {
+        modifiers |= match modifier {
+            "C" => KeyModifiers::CONTROL,
+            "A" | "M" => KeyModifiers::ALT,
+            "S" => KeyModifiers::SHIFT,
+            _ => return Err(invalid()),
+        };
+        name = rest;
+    }
+    let code = match name {
+        "Esc" => KeyCode::Esc,
+        "Enter" | "CR" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "BS" | "Backspace" => KeyCode::Backspace,
+        "Del" => KeyCode::Delete,
+        "Space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        name => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => match name.strip_prefix('F').and_then(|n| n.parse().ok()) {
+                    Some(n) => KeyCode::F(n),
+                    None => return Err(invalid()),
+                },
+            }
+        }
+    };
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+/// `$XDG_CONFIG_HOME/ratcsv/config.toml`, falling back to `~/.config/ratcsv/config.toml`.
+fn config_file() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("ratcsv").join("config.toml"))
+}
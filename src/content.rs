@@ -1,27 +1,340 @@
 use std::{
+    cell::Cell,
+    collections::{BTreeMap, HashMap, VecDeque},
     fmt::Display,
     io::{Read, Write},
-    ops::{Add, AddAssign, Sub, SubAssign},
+    ops::{Add, AddAssign, Range, Sub, SubAssign},
+    str::FromStr,
 };
 
-use csv::{ReaderBuilder, WriterBuilder};
+use color_eyre::eyre::{bail, eyre};
+use csv::{ByteRecord, ReaderBuilder, WriterBuilder};
+use encoding_rs::Encoding;
+
+use crate::{MoveDirection, encoding, search::SearchDirection};
+
+/// Number of bytes at the start of the input, that the delimiter is detected from
+const DELIMITER_SAMPLE_LEN: usize = 8 * 1024;
+/// The delimiters, that are detected, preferred in this order
+const DELIMITER_CANDIDATES: [u8; 4] = [b',', b'\t', b';', b'|'];
+/// Number of changes, that a table remembers for [`CsvTable::edits_since`]
+const EDIT_LOG_LEN: usize = 64;
+
+/// Detects the delimiter of CSV text like Python's `csv.Sniffer`: The candidate, that occurs the
+/// same number of times outside of quotes in the most lines, wins. Ties go to the candidate, that
+/// occurs more often in the first line, and then to the earlier one in
+/// [`DELIMITER_CANDIDATES`].
+pub(crate) fn sniff_delimiter(sample: &[u8]) -> Option<u8> {
+    let mut counts = Vec::<[usize; DELIMITER_CANDIDATES.len()]>::new();
+    let mut line = [0; DELIMITER_CANDIDATES.len()];
+    let mut in_quotes = false;
+    for byte in sample {
+        match byte {
+            b'"' => in_quotes = !in_quotes,
+            b'\n' if !in_quotes => counts.push(std::mem::take(&mut line)),
+            _ if !in_quotes => {
+                if let Some(candidate) = DELIMITER_CANDIDATES.iter().position(|c| c == byte) {
+                    line[candidate] += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    // The last line of a truncated sample is incomplete
+    if !sample.ends_with(b"\n") && (counts.is_empty() || sample.len() < DELIMITER_SAMPLE_LEN) {
+        counts.push(line);
+    }
+    let first = counts.first()?;
+    (0..DELIMITER_CANDIDATES.len())
+        .filter(|candidate| first[*candidate] > 0)
+        .max_by_key(|candidate| {
+            let consistent = counts
+                .iter()
+                .filter(|line| line[*candidate] == first[*candidate])
+                .count();
+            (consistent, first[*candidate], usize::MAX - candidate)
+        })
+        .map(|candidate| DELIMITER_CANDIDATES[candidate])
+}
+
+/// When fields are quoted on save.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) enum QuoteStyle {
+    Always,
+    /// Only fields with quotes, delimiters or line breaks
+    #[default]
+    Necessary,
+    Never,
+}
+
+impl FromStr for QuoteStyle {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s {
+            "always" => Self::Always,
+            "necessary" => Self::Necessary,
+            "never" => Self::Never,
+            _ => bail!("Unknown quote style: {s}. Use `always`, `necessary` or `never`"),
+        };
+        Ok(res)
+    }
+}
+
+impl Display for QuoteStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Always => "always",
+            Self::Necessary => "necessary",
+            Self::Never => "never",
+        };
+        f.write_str(s)
+    }
+}
+
+/// How fields are quoted on save, set with `:set quote`, `:set quote-style` and `:set escape`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Quoting {
+    pub(crate) quote: u8,
+    pub(crate) style: QuoteStyle,
+    /// Escapes quotes in quoted fields instead of doubling them
+    pub(crate) escape: Option<u8>,
+}
+
+impl Default for Quoting {
+    fn default() -> Self {
+        Self {
+            quote: b'"',
+            style: QuoteStyle::default(),
+            escape: None,
+        }
+    }
+}
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
 
-use crate::MoveDirection;
+/// How a file is read besides its delimiter, which is kept for saving it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct LoadSettings {
+    /// Transcoded from on load and to on save, where UTF-8 is `None`
+    pub(crate) encoding: Option<&'static Encoding>,
+    /// Keep the text of the rows, so unchanged rows are saved byte for byte
+    pub(crate) faithful: bool,
+    /// Lines starting with it are kept as comments of the row after them
+    pub(crate) comment: Option<u8>,
+}
+
+/// Where a record of CSV text is and which comment lines are before it.
+struct ScannedRecord {
+    /// Lines before the record, that start with the comment character, without line endings
+    comments: Vec<String>,
+    /// Text of the record including its line ending and the blank lines after it
+    text: Range<usize>,
+}
+
+/// Splits CSV text without a byte order mark into the records, that [`CsvTable::load_with`]
+/// reads. Returns them and the comment lines after the last one.
+fn scan_records(
+    text: &str,
+    delimiter: Option<u8>,
+    comment: Option<u8>,
+) -> color_eyre::Result<(Vec<ScannedRecord>, Vec<String>)> {
+    let mut reader = reader_builder(delimiter, comment).from_reader(text.as_bytes());
+    // The reader skips blank and comment lines before a record and stops after the `\r` of a
+    // `\r\n`, so the text between two positions ends with a record and starts with those lines
+    let mut positions = vec![0];
+    let mut record = ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        positions.push(reader.position().byte() as usize);
+    }
+    positions.push(text.len());
+    let mut records: Vec<ScannedRecord> = Vec::new();
+    let mut trailing = Vec::new();
+    let last = positions.len() - 2;
+    for (idx, span) in positions.windows(2).enumerate() {
+        let mut comments = Vec::new();
+        let mut start = span[0];
+        for line in text[span[0]..span[1]].split_inclusive('\n') {
+            let content = line.trim_end_matches(['\r', '\n']);
+            if content.is_empty() {
+                // Blank lines stay with the record before them
+                if comments.is_empty()
+                    && let Some(previous) = records.last_mut()
+                {
+                    previous.text.end = start + line.len();
+                }
+            } else if comment.is_some_and(|comment| content.as_bytes()[0] == comment) {
+                comments.push(content.to_owned());
+            } else {
+                break;
+            }
+            start += line.len();
+        }
+        if idx == last {
+            trailing = comments;
+        } else {
+            records.push(ScannedRecord {
+                comments,
+                text: start..span[1],
+            });
+        }
+    }
+    Ok((records, trailing))
+}
+
+fn reader_builder(delimiter: Option<u8>, comment: Option<u8>) -> ReaderBuilder {
+    let mut builder = ReaderBuilder::new();
+    // Ragged rows are read as they are, so they can be found and fixed
+    builder.has_headers(false).flexible(true).comment(comment);
+    if let Some(delimiter) = delimiter {
+        builder.delimiter(delimiter);
+    }
+    builder
+}
+
+/// The text of the rows of a file by their cells, so unchanged rows are saved as they were read,
+/// even after moving them.
+#[derive(Clone, Debug)]
+struct RawRows {
+    /// Texts including the line ending by cells without trailing empty ones, in file order
+    texts: HashMap<Vec<Option<String>>, Vec<String>>,
+    /// Settings at load, after changing which all rows are formatted again
+    delimiter: Option<u8>,
+    quoting: Quoting,
+    eol: Eol,
+}
+
+impl RawRows {
+    fn new(table: &CsvTable, text: &str, records: &[ScannedRecord]) -> Self {
+        let mut texts: HashMap<_, Vec<String>> = HashMap::new();
+        for (row, record) in table.rows.iter().zip(records) {
+            texts
+                .entry(row[..trimmed_len(row)].to_vec())
+                .or_default()
+                .push(text[record.text.clone()].to_owned());
+        }
+        Self {
+            texts,
+            delimiter: table.delimiter,
+            quoting: table.quoting,
+            eol: table.eol,
+        }
+    }
+
+    /// Whether the rows can still be written as read, which changing the format prevents.
+    fn fits(&self, table: &CsvTable) -> bool {
+        (self.delimiter, self.quoting, self.eol) == (table.delimiter, table.quoting, table.eol)
+    }
+
+    /// The next unused text of a row with the same cells.
+    fn take<'a>(
+        &self,
+        row: &'a [Option<String>],
+        used: &mut HashMap<&'a [Option<String>], usize>,
+    ) -> Option<&str> {
+        let cells = &row[..trimmed_len(row)];
+        let text = self.texts.get(cells)?.get(*used.get(cells).unwrap_or(&0))?;
+        *used.entry(cells).or_default() += 1;
+        Some(text)
+    }
+}
+
+/// Length of `cells` without trailing empty ones.
+fn trimmed_len(cells: &[Option<String>]) -> usize {
+    cells
+        .iter()
+        .rposition(Option::is_some)
+        .map_or(0, |idx| idx + 1)
+}
+
+/// The line ending, that rows are written with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) enum Eol {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl FromStr for Eol {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s {
+            "lf" => Self::Lf,
+            "crlf" => Self::Crlf,
+            _ => bail!("Unknown line ending: {s}. Use `lf` or `crlf`"),
+        };
+        Ok(res)
+    }
+}
+
+impl Display for Eol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Lf => "lf",
+            Self::Crlf => "crlf",
+        };
+        f.write_str(s)
+    }
+}
 
 #[derive(Clone, Debug, Default)]
 pub(crate) struct CsvTable {
     pub(crate) delimiter: Option<u8>,
+    pub(crate) quoting: Quoting,
+    /// Detected from the first line on load
+    pub(crate) eol: Eol,
+    /// Whether the file starts with a byte order mark, which is kept on save
+    pub(crate) bom: bool,
+    /// Encoding of the file, which is transcoded on load and save, where UTF-8 is `None`
+    pub(crate) encoding: Option<&'static Encoding>,
+    /// Text of the rows as they were loaded, if the table is faithful
+    raw_rows: Option<RawRows>,
+    /// Lines starting with it are comments, which fields starting with it are quoted against
+    pub(crate) comment: Option<u8>,
+    /// Comment lines by the row after them, where rows after the last one are at the end
+    comments: BTreeMap<usize, Vec<String>>,
     rows: Vec<Vec<Option<String>>>,
+    /// Incremented on every change of the cells
+    revision: u64,
+    /// The last changes by their revision: the rect of the cells, that were set, or `None`, if
+    /// rows or columns changed
+    edit_log: VecDeque<(u64, Option<CellRect>)>,
+    used_extent_cache: Cell<Option<(u64, CellRect)>>,
+    majority_field_count_cache: Cell<Option<(u64, usize)>>,
+    /// Values at the last save of all cells, that were set since then
+    saved_values: HashMap<CellLocation, Option<String>>,
 }
 
 impl CsvTable {
+    /// Without a `delimiter`, it is detected from the start of the input, see
+    /// [`sniff_delimiter`]. A detected comma stays unset. The line ending and a byte order mark
+    /// are detected, so saving keeps them.
     pub(crate) fn load(read: impl Read, delimiter: Option<u8>) -> color_eyre::Result<Self> {
-        let mut builder = ReaderBuilder::new();
-        builder.has_headers(false);
-        if let Some(delimiter) = delimiter {
-            builder.delimiter(delimiter);
+        Self::parse(read, delimiter, None)
+    }
+
+    fn parse(
+        mut read: impl Read,
+        delimiter: Option<u8>,
+        comment: Option<u8>,
+    ) -> color_eyre::Result<Self> {
+        let mut sample = Vec::new();
+        (&mut read)
+            .take(DELIMITER_SAMPLE_LEN as u64)
+            .read_to_end(&mut sample)?;
+        let bom = sample.starts_with(BOM);
+        if bom {
+            sample.drain(..BOM.len());
         }
-        let mut reader = builder.from_reader(read);
+        let eol = match sample.iter().position(|byte| *byte == b'\n') {
+            Some(end) if end > 0 && sample[end - 1] == b'\r' => Eol::Crlf,
+            _ => Eol::Lf,
+        };
+        let delimiter =
+            delimiter.or_else(|| sniff_delimiter(&sample).filter(|delimiter| *delimiter != b','));
+        let mut reader =
+            reader_builder(delimiter, comment).from_reader(sample.as_slice().chain(read));
         let mut rows: Vec<Vec<Option<String>>> = Vec::new();
 
         for result in reader.records() {
@@ -33,7 +346,100 @@ impl CsvTable {
                     .collect(),
             );
         }
-        Ok(Self { delimiter, rows })
+        Ok(Self {
+            delimiter,
+            eol,
+            bom,
+            rows,
+            ..Default::default()
+        })
+    }
+
+    /// Like [`CsvTable::load`], but decodes the input from the encoding of `settings` first,
+    /// which a byte order mark overrides, and keeps its text or comments, if they ask for it.
+    pub(crate) fn load_with(
+        mut read: impl Read,
+        delimiter: Option<u8>,
+        settings: LoadSettings,
+    ) -> color_eyre::Result<Self> {
+        if settings.encoding.is_none() && !settings.faithful && settings.comment.is_none() {
+            return Self::load(read, delimiter);
+        }
+        let mut bytes = Vec::new();
+        read.read_to_end(&mut bytes)?;
+        let (mut text, encoding, bom) = match settings.encoding {
+            Some(encoding) => encoding::decode(&bytes, encoding)?,
+            None => (String::from_utf8(bytes)?, None, false),
+        };
+        // The reader takes a comment in the last line as a record without a line ending
+        if settings.comment.is_some() && !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let mut table = Self::parse(text.as_bytes(), delimiter, settings.comment)?;
+        table.encoding = encoding;
+        table.bom |= bom;
+        table.comment = settings.comment;
+        if settings.faithful || settings.comment.is_some() {
+            let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+            let (records, trailing) = scan_records(text, table.delimiter, settings.comment)?;
+            if settings.faithful {
+                table.raw_rows = Some(RawRows::new(&table, text, &records));
+            }
+            table.comments = (records.into_iter().map(|record| record.comments))
+                .chain([trailing])
+                .enumerate()
+                .filter(|(_, lines)| !lines.is_empty())
+                .collect();
+        }
+        Ok(table)
+    }
+
+    /// Formats all rows on save from now on.
+    pub(crate) fn forget_raw_rows(&mut self) {
+        self.raw_rows = None;
+    }
+
+    /// The settings, that the table would be loaded again with.
+    pub(crate) fn load_settings(&self) -> LoadSettings {
+        LoadSettings {
+            encoding: self.encoding,
+            faithful: self.raw_rows.is_some(),
+            comment: self.comment,
+        }
+    }
+
+    /// The comment lines before `row`.
+    pub(crate) fn comments(&self, row: usize) -> &[String] {
+        self.comments.get(&row).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn bump_revision(&mut self, edit: Option<CellRect>) {
+        self.revision += 1;
+        if self.edit_log.len() == EDIT_LOG_LEN {
+            self.edit_log.pop_front();
+        }
+        self.edit_log.push_back((self.revision, edit));
+    }
+
+    /// The rects of the cells, that were set after `revision`. `None`, if rows or columns changed
+    /// since then or the changes are too old to be remembered.
+    pub(crate) fn edits_since(&self, revision: u64) -> Option<Vec<CellRect>> {
+        let oldest = self
+            .edit_log
+            .front()
+            .map_or(self.revision, |(oldest, _)| oldest - 1);
+        if revision < oldest || revision > self.revision {
+            return None;
+        }
+        self.edit_log
+            .iter()
+            .filter(|(edit_revision, _)| *edit_revision > revision)
+            .map(|(_, edit)| *edit)
+            .collect()
     }
 
     pub(crate) fn get(&self, location: CellLocation) -> Option<&str> {
@@ -42,7 +448,15 @@ impl CsvTable {
 
     #[must_use]
     pub(crate) fn set(&mut self, location: CellLocation, value: Option<String>) -> Option<String> {
+        self.bump_revision(Some(CellRect::from_opposite_cell_locations(
+            location, location,
+        )));
         let CellLocation { row, col } = location;
+        let value = value.filter(|value| !value.is_empty());
+        // Clearing a cell, that doesn't exist, keeps the number of fields of the row
+        if value.is_none() && self.rows.get(row).is_none_or(|cells| cells.len() <= col) {
+            return None;
+        }
         // Ensure, that columns and rows exist
         if self.rows.len() <= row {
             self.rows.resize_with(row + 1, Vec::new);
@@ -54,14 +468,15 @@ impl CsvTable {
         }
 
         let old_value = row[col].take();
-        let value = value.filter(|value| !value.is_empty());
+        self.saved_values
+            .entry(location)
+            .or_insert_with(|| old_value.clone());
 
         // We can just set the cell, because we ensured, that it exists
         row[col] = value;
         old_value
     }
 
-    #[allow(unused)]
     pub(crate) fn get_rect(&self, rect: CellRect) -> Vec<Option<&str>> {
         let CellRect {
             top_left_cell_location,
@@ -118,6 +533,7 @@ impl CsvTable {
             row_count,
         } = rect;
 
+        self.bump_revision(Some(rect));
         let mut old_values = Vec::with_capacity(rect.col_count * rect.row_count);
 
         // Ensure enough rows
@@ -146,6 +562,12 @@ impl CsvTable {
                     .expect("iteration count must match new_values.len()");
                 let old_value = row[col_index].take();
                 let new_value = new_value.filter(|v| !v.is_empty());
+                self.saved_values
+                    .entry(CellLocation {
+                        row: row_index,
+                        col: col_index,
+                    })
+                    .or_insert_with(|| old_value.clone());
 
                 row[col_index] = new_value;
                 old_values.push(old_value);
@@ -159,7 +581,6 @@ impl CsvTable {
         self.set(cell_location, None)
     }
 
-    #[allow(unused)]
     pub(crate) fn delete_rect(&mut self, rect: CellRect) -> Vec<Option<String>> {
         self.set_rect(rect, std::iter::repeat(None))
     }
@@ -173,6 +594,7 @@ impl CsvTable {
     }
 
     pub(crate) fn normalize(&mut self) {
+        self.bump_revision(None);
         // Finde die letzte gesetzte Zeile und Spalte
         let mut last_row = 0;
         let mut last_col = 0;
@@ -197,13 +619,74 @@ impl CsvTable {
 
     pub(crate) fn normalize_and_save(&mut self, write: &mut impl Write) -> color_eyre::Result<()> {
         self.normalize();
-        let mut builder = WriterBuilder::new();
-        if let Some(delimiter) = self.delimiter {
-            builder.delimiter(delimiter);
+        let Some(encoding) = self.encoding else {
+            if self.bom {
+                write.write_all(BOM)?;
+            }
+            return self.write_rows(write);
+        };
+        let mut text = Vec::new();
+        self.write_rows(&mut text)?;
+        let text = String::from_utf8(text)?;
+        write.write_all(&encoding::encode(&text, encoding, self.bom)?)?;
+        Ok(())
+    }
+
+    /// Writes the rows and comments, where unchanged rows of a faithful table are written as
+    /// they were read.
+    fn write_rows(&self, write: &mut impl Write) -> color_eyre::Result<()> {
+        let raw_rows = (self.raw_rows.as_ref()).filter(|raw_rows| raw_rows.fits(self));
+        let mut used = HashMap::new();
+        let raws: Vec<Option<&str>> = match raw_rows {
+            Some(raw_rows) => (self.rows.iter())
+                .map(|row| raw_rows.take(row, &mut used))
+                .collect(),
+            None => vec![None; self.rows.len()],
+        };
+        let eol = match self.eol {
+            Eol::Lf => "\n",
+            Eol::Crlf => "\r\n",
+        };
+        let trailing = self.comments.range(self.rows.len()..);
+        let has_trailing = trailing.clone().next().is_some();
+        // Runs of raw or formatted rows without comments between them
+        let mut start = 0;
+        while start < self.rows.len() {
+            let raw = raws[start].is_some();
+            let end = (start + 1..self.rows.len())
+                .find(|idx| raws[*idx].is_some() != raw || self.comments.contains_key(idx))
+                .unwrap_or(self.rows.len());
+            for line in self.comments(start) {
+                write.write_all(line.as_bytes())?;
+                write.write_all(eol.as_bytes())?;
+            }
+            if !raw {
+                self.write_formatted(write, &self.rows[start..end])?;
+            }
+            for (idx, raw) in (start..).zip(raws[start..end].iter().flatten()) {
+                write.write_all(raw.as_bytes())?;
+                // The last row of the file may lack a line ending
+                if !raw.ends_with('\n') && (idx + 1 < self.rows.len() || has_trailing) {
+                    write.write_all(eol.as_bytes())?;
+                }
+            }
+            start = end;
+        }
+        for line in trailing.flat_map(|(_, lines)| lines) {
+            write.write_all(line.as_bytes())?;
+            write.write_all(eol.as_bytes())?;
         }
-        let mut wtr = builder.from_writer(write);
+        Ok(())
+    }
+
+    fn write_formatted(
+        &self,
+        write: &mut impl Write,
+        rows: &[Vec<Option<String>>],
+    ) -> color_eyre::Result<()> {
+        let mut wtr = self.writer_builder().from_writer(write);
 
-        for row in &self.rows {
+        for row in rows {
             let record: Vec<&str> = row
                 .iter()
                 .map(|c| c.as_deref().unwrap_or_default())
@@ -215,6 +698,440 @@ impl CsvTable {
         Ok(())
     }
 
+    /// A CSV writer with the delimiter, quoting and line ending of the table.
+    fn writer_builder(&self) -> WriterBuilder {
+        let mut builder = WriterBuilder::new();
+        if let Some(delimiter) = self.delimiter {
+            builder.delimiter(delimiter);
+        }
+        builder.comment(self.comment);
+        builder.quote(self.quoting.quote);
+        builder.quote_style(match self.quoting.style {
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+        });
+        if let Some(escape) = self.quoting.escape {
+            builder.escape(escape).double_quote(false);
+        }
+        if self.eol == Eol::Crlf {
+            builder.terminator(csv::Terminator::CRLF);
+        }
+        builder
+    }
+
+    /// Serializes the cells of `rect` as CSV text using the delimiter and quoting of the table.
+    pub(crate) fn rect_to_csv_string(&self, rect: CellRect) -> color_eyre::Result<String> {
+        let mut wtr = self.writer_builder().from_writer(Vec::new());
+
+        for row in self.get_rect(rect).chunks(rect.col_count.max(1)) {
+            let record: Vec<&str> = row.iter().map(|c| c.unwrap_or_default()).collect();
+            wtr.write_record(&record)?;
+        }
+
+        let bytes = wtr.into_inner().map_err(|err| err.into_error())?;
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    /// The smallest rectangle starting at `A1`, that contains all set cells.
+    pub(crate) fn used_extent(&self) -> CellRect {
+        if let Some((revision, extent)) = self.used_extent_cache.get()
+            && revision == self.revision
+        {
+            return extent;
+        }
+        let mut row_count = 0;
+        let mut col_count = 0;
+        for (r_idx, row) in self.rows.iter().enumerate() {
+            if let Some(c_idx) = row.iter().rposition(Option::is_some) {
+                row_count = r_idx + 1;
+                col_count = col_count.max(c_idx + 1);
+            }
+        }
+        let extent = CellRect {
+            top_left_cell_location: CellLocation::default(),
+            col_count,
+            row_count,
+        };
+        self.used_extent_cache.set(Some((self.revision, extent)));
+        extent
+    }
+
+    /// Number of fields of `row` as read, padded or extended by setting cells.
+    pub(crate) fn field_count(&self, row: usize) -> usize {
+        self.rows.get(row).map_or(0, Vec::len)
+    }
+
+    /// The most common number of fields of the rows, that have any. Ties go to the larger one.
+    pub(crate) fn majority_field_count(&self) -> usize {
+        if let Some((revision, count)) = self.majority_field_count_cache.get()
+            && revision == self.revision
+        {
+            return count;
+        }
+        let mut frequencies: HashMap<usize, usize> = HashMap::new();
+        for row in self.rows.iter().filter(|row| !row.is_empty()) {
+            *frequencies.entry(row.len()).or_default() += 1;
+        }
+        let count = frequencies
+            .into_iter()
+            .max_by_key(|(count, frequency)| (*frequency, *count))
+            .map_or(0, |(count, _)| count);
+        self.majority_field_count_cache
+            .set(Some((self.revision, count)));
+        count
+    }
+
+    /// Whether `row` has another number of fields than most rows.
+    pub(crate) fn is_ragged(&self, row: usize) -> bool {
+        let count = self.field_count(row);
+        count > 0 && count != self.majority_field_count()
+    }
+
+    /// Rows with another number of fields than most rows.
+    pub(crate) fn ragged_rows(&self) -> Vec<usize> {
+        self.rows_without_field_count(self.majority_field_count())
+    }
+
+    /// Rows with fields, but another number of them than `count`.
+    pub(crate) fn rows_without_field_count(&self, count: usize) -> Vec<usize> {
+        (self.rows.iter().enumerate())
+            .filter(|(_, cells)| !cells.is_empty() && cells.len() != count)
+            .map(|(row, _)| row)
+            .collect()
+    }
+
+    /// Number of fields of the longest row.
+    pub(crate) fn max_field_count(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Pads or truncates `row` to `count` fields, where truncated fields have to be empty. A
+    /// faithful table formats it again on save.
+    pub(crate) fn resize_row(&mut self, row: usize, count: usize) {
+        if row >= self.rows.len() {
+            return;
+        }
+        self.bump_revision(None);
+        let cells = &mut self.rows[row];
+        if let Some(raw_rows) = &mut self.raw_rows {
+            raw_rows.texts.remove(&cells[..trimmed_len(cells)]);
+        }
+        cells.resize(count, None);
+    }
+
+    /// Reorders the rows starting at `start`, so that the row at `start + i` is moved from
+    /// `start + permutation[i]`. Returns the inverse permutation, that restores the order.
+    pub(crate) fn permute_rows(&mut self, start: usize, permutation: &[usize]) -> Vec<usize> {
+        self.bump_revision(None);
+        let end = start + permutation.len();
+        if self.rows.len() < end {
+            self.rows.resize_with(end, Vec::new);
+        }
+        // Every cell of the range may change, so its saved value has to be remembered
+        self.remember_saved_rows(start, end);
+        let mut comments = self.comments.split_off(&start);
+        let after = comments.split_off(&end);
+        for (i, from) in permutation.iter().enumerate() {
+            if let Some(lines) = comments.remove(&(start + from)) {
+                self.comments.insert(start + i, lines);
+            }
+        }
+        self.comments.extend(after);
+
+        let mut old_rows = self.rows[start..end]
+            .iter_mut()
+            .map(std::mem::take)
+            .collect::<Vec<_>>();
+        let mut inverse = vec![0; permutation.len()];
+        for (i, from) in permutation.iter().enumerate() {
+            self.rows[start + i] = std::mem::take(&mut old_rows[*from]);
+            inverse[*from] = i;
+        }
+        inverse
+    }
+
+    /// Remembers the saved values of all cells in the rows `start..end`, before they change.
+    /// Rows after the last one are empty.
+    fn remember_saved_rows(&mut self, start: usize, end: usize) {
+        let existing_end = end.min(self.rows.len());
+        let col_count = self.rows[start.min(existing_end)..existing_end]
+            .iter()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0);
+        for row in start..end {
+            for col in 0..col_count {
+                let value = self
+                    .rows
+                    .get(row)
+                    .and_then(|row| row.get(col))
+                    .cloned()
+                    .flatten();
+                self.saved_values
+                    .entry(CellLocation { row, col })
+                    .or_insert(value);
+            }
+        }
+    }
+
+    /// Inserts `rows` before the row `at`, so the following rows move down.
+    pub(crate) fn insert_rows(&mut self, at: usize, rows: Vec<Vec<Option<String>>>) {
+        self.bump_revision(None);
+        if self.rows.len() < at {
+            self.rows.resize_with(at, Vec::new);
+        }
+        // All following cells move, so their saved values have to be remembered
+        self.remember_saved_rows(at, self.rows.len() + rows.len());
+        let following = self.comments.split_off(&at);
+        (self.comments).extend(
+            following
+                .into_iter()
+                .map(|(row, lines)| (row + rows.len(), lines)),
+        );
+        self.rows.splice(at..at, rows);
+    }
+
+    /// Removes `count` rows starting at `start`, so the following rows move up. Returns the
+    /// removed rows.
+    pub(crate) fn delete_rows(&mut self, start: usize, count: usize) -> Vec<Vec<Option<String>>> {
+        if start >= self.rows.len() {
+            return Vec::new();
+        }
+        self.bump_revision(None);
+        self.remember_saved_rows(start, self.rows.len());
+        let end = (start + count).min(self.rows.len());
+        // Comments of removed rows move to the row after them
+        let mut orphans = Vec::new();
+        for (row, lines) in self.comments.split_off(&start) {
+            if row < end {
+                orphans.extend(lines);
+            } else {
+                self.comments.insert(row - (end - start), lines);
+            }
+        }
+        if !orphans.is_empty() {
+            self.comments
+                .entry(start)
+                .or_default()
+                .splice(0..0, orphans);
+        }
+        self.rows.drain(start..end).collect()
+    }
+
+    /// Remembers the saved values of the cells in the columns `start..end` of all rows, before
+    /// they change.
+    fn remember_saved_cols(&mut self, start: usize, end: usize) {
+        for (row, cells) in self.rows.iter().enumerate() {
+            for col in start..end {
+                self.saved_values
+                    .entry(CellLocation { row, col })
+                    .or_insert_with(|| cells.get(col).cloned().flatten());
+            }
+        }
+    }
+
+    /// Inserts `count` columns before the column `at`, so the following columns move right.
+    /// `cols` has the values of the new columns by row and may be shorter than the table.
+    pub(crate) fn insert_cols(&mut self, at: usize, count: usize, cols: Vec<Vec<Option<String>>>) {
+        self.bump_revision(None);
+        let col_count = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        self.remember_saved_cols(at, col_count + count);
+        if self.rows.len() < cols.len() {
+            self.rows.resize_with(cols.len(), Vec::new);
+        }
+        let mut cols = cols.into_iter();
+        for row in &mut self.rows {
+            let mut values = cols.next().unwrap_or_default();
+            values.resize(count, None);
+            if row.len() < at {
+                if values.iter().all(Option::is_none) {
+                    continue;
+                }
+                row.resize(at, None);
+            }
+            row.splice(at..at, values);
+        }
+    }
+
+    /// Removes `count` columns starting at `start`, so the following columns move left. Returns
+    /// the removed values by row.
+    pub(crate) fn delete_cols(&mut self, start: usize, count: usize) -> Vec<Vec<Option<String>>> {
+        self.bump_revision(None);
+        let col_count = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        self.remember_saved_cols(start, col_count);
+        self.rows
+            .iter_mut()
+            .map(|row| {
+                let end = (start + count).min(row.len());
+                if start < end {
+                    row.drain(start..end).collect()
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn from_rows(rows: Vec<Vec<Option<String>>>, delimiter: Option<u8>) -> Self {
+        Self {
+            delimiter,
+            rows,
+            ..Default::default()
+        }
+    }
+
+    /// A new table with copies of the given rows.
+    pub(crate) fn rows_to_table(&self, rows: &[usize]) -> Self {
+        Self {
+            delimiter: self.delimiter,
+            rows: rows
+                .iter()
+                .map(|row| self.rows.get(*row).cloned().unwrap_or_default())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// The last row with a non-empty cell in `col`.
+    pub(crate) fn last_row_in_col(&self, col: usize) -> Option<usize> {
+        self.rows.iter().rposition(|row| {
+            row.get(col)
+                .is_some_and(|cell| cell.as_ref().is_some_and(|value| !value.is_empty()))
+        })
+    }
+
+    /// The bottom right cell of the used extent.
+    pub(crate) fn last_cell(&self) -> CellLocation {
+        let extent = self.used_extent();
+        CellLocation {
+            row: extent.row_count.saturating_sub(1),
+            col: extent.col_count.saturating_sub(1),
+        }
+    }
+
+    /// Values of a column within the used extent, including empty cells.
+    pub(crate) fn column_values(&self, col: usize) -> impl Iterator<Item = Option<&str>> {
+        (0..self.used_extent().row_count).map(move |row| self.get(CellLocation { row, col }))
+    }
+
+    /// Sets the cells in reverse order and returns their previous values, so that applying the
+    /// result restores the table.
+    pub(crate) fn set_cell_list(
+        &mut self,
+        values: Vec<(CellLocation, Option<String>)>,
+    ) -> Vec<(CellLocation, Option<String>)> {
+        values
+            .into_iter()
+            .rev()
+            .map(|(location, value)| (location, self.set(location, value)))
+            .collect()
+    }
+
+    /// Set cells in row-major order.
+    pub(crate) fn cells(&self) -> impl DoubleEndedIterator<Item = (CellLocation, &str)> {
+        self.rows.iter().enumerate().flat_map(|(row, cells)| {
+            cells.iter().enumerate().filter_map(move |(col, cell)| {
+                cell.as_deref()
+                    .map(|value| (CellLocation { row, col }, value))
+            })
+        })
+    }
+
+    /// Finds the next set cell after `from` in row-major order, whose value satisfies
+    /// `predicate`. With `wrap`, the search continues at the other end of the table, which is
+    /// signaled by the returned flag. `from` itself is checked last.
+    pub(crate) fn find(
+        &self,
+        from: CellLocation,
+        direction: SearchDirection,
+        wrap: bool,
+        predicate: impl Fn(&str) -> bool,
+    ) -> Option<(CellLocation, bool)> {
+        let mut matches = self
+            .cells()
+            .filter(|(_, value)| predicate(value))
+            .map(|(location, _)| location);
+        match direction {
+            SearchDirection::Forward => {
+                let mut first = None;
+                for location in matches {
+                    if location > from {
+                        return Some((location, false));
+                    }
+                    first.get_or_insert(location);
+                }
+                first.filter(|_| wrap).map(|location| (location, true))
+            }
+            SearchDirection::Backward => {
+                let mut last = None;
+                while let Some(location) = matches.next_back() {
+                    if location < from {
+                        return Some((location, false));
+                    }
+                    last.get_or_insert(location);
+                }
+                last.filter(|_| wrap).map(|location| (location, true))
+            }
+        }
+    }
+
+    /// The cell, that a spreadsheet's `Ctrl`+arrow jumps to from `from`: the end of the run of
+    /// non-empty cells, that `from` is in, or else the next non-empty cell. Without one, it is
+    /// the last cell, that `step` reaches. `step` moves one cell further in the direction.
+    pub(crate) fn data_edge(
+        &self,
+        from: CellLocation,
+        step: impl Fn(CellLocation) -> Option<CellLocation>,
+    ) -> CellLocation {
+        let is_filled = |location| self.get(location).is_some_and(|value| !value.is_empty());
+        let Some(mut location) = step(from) else {
+            return from;
+        };
+        if is_filled(from) && is_filled(location) {
+            while let Some(next) = step(location)
+                && is_filled(next)
+            {
+                location = next;
+            }
+            return location;
+        }
+        while !is_filled(location) {
+            let Some(next) = step(location) else {
+                break;
+            };
+            location = next;
+        }
+        location
+    }
+
+    /// The value of a changed cell at the last save, or `None`, if the cell is unchanged.
+    pub(crate) fn saved_value(&self, location: CellLocation) -> Option<Option<&str>> {
+        let saved = self.saved_values.get(&location)?.as_deref();
+        (saved != self.get(location)).then_some(saved)
+    }
+
+    pub(crate) fn is_changed(&self, location: CellLocation) -> bool {
+        self.saved_value(location).is_some()
+    }
+
+    /// Cells, that differ from the last save, in row-major order.
+    pub(crate) fn changed_cells(&self) -> Vec<CellLocation> {
+        let mut changed = self
+            .saved_values
+            .keys()
+            .copied()
+            .filter(|location| self.is_changed(*location))
+            .collect::<Vec<_>>();
+        changed.sort();
+        changed
+    }
+
+    /// Forgets the changes after saving.
+    pub(crate) fn mark_saved(&mut self) {
+        self.saved_values.clear();
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.rows
             .iter()
@@ -225,6 +1142,13 @@ impl CsvTable {
 impl std::hash::Hash for CsvTable {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.delimiter.hash(state);
+        self.quoting.hash(state);
+        self.eol.hash(state);
+        self.bom.hash(state);
+        self.encoding.map(Encoding::name).hash(state);
+        self.raw_rows.is_some().hash(state);
+        self.comment.hash(state);
+        self.comments.hash(state);
         for (row_idx, row) in self.rows.iter().enumerate() {
             for (col_idx, cell) in row.iter().enumerate() {
                 if let Some(value) = cell {
@@ -286,16 +1210,88 @@ impl CellRect {
         (top_row..=bottom_row).contains(&location.row)
             && (left_col..=right_col).contains(&location.col)
     }
+
+    /// Whether both rects have a cell in common.
+    pub(crate) fn intersects(&self, other: &CellRect) -> bool {
+        let overlap = |start: usize, count: usize, other_start: usize, other_count: usize| {
+            start < other_start.saturating_add(other_count)
+                && other_start < start.saturating_add(count)
+        };
+        let (top_left, other_top_left) =
+            (self.top_left_cell_location, other.top_left_cell_location);
+        overlap(
+            top_left.row,
+            self.row_count,
+            other_top_left.row,
+            other.row_count,
+        ) && overlap(
+            top_left.col,
+            self.col_count,
+            other_top_left.col,
+            other.col_count,
+        )
+    }
 }
 
+/// How cell coordinates are displayed.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum CoordinateStyle {
+    /// Column letters and row numbers, e.g. `B3`
+    #[default]
+    A1,
+    /// Row and column numbers, e.g. `R3C2`
+    R1C1,
+}
+
+impl FromStr for CoordinateStyle {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s.to_ascii_lowercase().as_str() {
+            "a1" => Self::A1,
+            "r1c1" => Self::R1C1,
+            _ => bail!("Unknown coordinate style: {s}. Use `a1` or `r1c1`"),
+        };
+        Ok(res)
+    }
+}
+
+impl Display for CoordinateStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::A1 => "a1",
+            Self::R1C1 => "r1c1",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Ordered row-major.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) struct CellLocation {
     pub(crate) row: usize,
     pub(crate) col: usize,
 }
 
 impl CellLocation {
-    pub(crate) fn col_index_to_id(mut col: usize) -> String {
+    /// Id of the cell in `style`, e.g. `B3` or `R3C2`.
+    pub(crate) fn id(self, style: CoordinateStyle) -> String {
+        let CellLocation { row, col } = self;
+        match style {
+            CoordinateStyle::A1 => format!("{}{}", Self::col_index_to_letters(col), row + 1),
+            CoordinateStyle::R1C1 => format!("R{}C{}", row + 1, col + 1),
+        }
+    }
+
+    /// Label of the column in `style`, e.g. `B` or `2`.
+    pub(crate) fn col_index_to_id(col: usize, style: CoordinateStyle) -> String {
+        match style {
+            CoordinateStyle::A1 => Self::col_index_to_letters(col),
+            CoordinateStyle::R1C1 => (col + 1).to_string(),
+        }
+    }
+
+    pub(crate) fn col_index_to_letters(mut col: usize) -> String {
         let mut col_str = String::new();
 
         loop {
@@ -309,6 +1305,58 @@ impl CellLocation {
         col_str
     }
 
+    /// Parses column letters, e.g. `AB`, into a column index.
+    pub(crate) fn col_letters_to_index(letters: &str) -> color_eyre::Result<usize> {
+        let mut result = 0usize;
+        for c in letters.chars() {
+            if !c.is_ascii_alphabetic() {
+                bail!("Not a valid column id: {letters}");
+            }
+            let val = (c.to_ascii_uppercase() as u8 - b'A') as usize + 1;
+            result = result
+                .checked_mul(26)
+                .and_then(|result| result.checked_add(val))
+                .ok_or_else(|| eyre!("Column id too big!"))?;
+        }
+        result
+            .checked_sub(1)
+            .ok_or_else(|| eyre!("Empty column id!"))
+    }
+
+    /// Parses column letters, e.g. `B`, or a column number, e.g. `2`, into a column index.
+    pub(crate) fn col_id_to_index(id: &str) -> color_eyre::Result<usize> {
+        if id.chars().all(|c| c.is_ascii_digit()) {
+            return id
+                .parse::<usize>()
+                .ok()
+                .and_then(|col| col.checked_sub(1))
+                .ok_or_else(|| eyre!("Not a valid column id: {id}"));
+        }
+        Self::col_letters_to_index(id)
+    }
+
+    /// The location in A1 notation, regardless of the [`CoordinateStyle`].
+    pub(crate) fn to_a1(self) -> String {
+        format!("{}{}", Self::col_index_to_letters(self.col), self.row + 1)
+    }
+
+    /// Parses a location in A1 notation, e.g. `B12`.
+    pub(crate) fn from_a1(id: &str) -> color_eyre::Result<Self> {
+        let split = id
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(|| eyre!("Not a valid location id: {id}"))?;
+        let (letters, digits) = id.split_at(split);
+        let row = digits
+            .parse::<usize>()
+            .ok()
+            .and_then(|row| row.checked_sub(1))
+            .ok_or_else(|| eyre!("Not a valid location id: {id}"))?;
+        Ok(Self {
+            row,
+            col: Self::col_letters_to_index(letters)?,
+        })
+    }
+
     pub(crate) fn row_index_to_id(row: usize) -> String {
         (row + 1).to_string()
     }
@@ -318,20 +1366,10 @@ impl CellLocation {
     }
 }
 
+/// In A1 notation, see [`CellLocation::id`] for the other styles.
 impl Display for CellLocation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let CellLocation { row, mut col } = *self;
-        let mut col_str = String::new();
-
-        loop {
-            let rem = col % 26;
-            col_str.insert(0, (b'A' + rem as u8) as char);
-            if col < 26 {
-                break;
-            }
-            col = col / 26 - 1;
-        }
-        write!(f, "{}{}", col_str, row + 1)
+        f.write_str(&self.id(CoordinateStyle::A1))
     }
 }
 
@@ -0,0 +1,189 @@
+use crate::content::{CellLocation, CsvTable};
+
+/// Size of the table, that aligns the differing rows, at most. Beyond, the rows aren't aligned.
+const ALIGN_MAX_CELLS: usize = 4_000_000;
+
+type Row = Vec<Option<String>>;
+
+/// How a row of a diff differs between the old and the new table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum RowDiff {
+    Same,
+    /// Only in the new table
+    Added,
+    /// Only in the old table
+    Removed,
+    /// In both tables with different values in the columns
+    Changed(Vec<usize>),
+}
+
+/// The differences of two tables, by row of their merged table.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TableDiff {
+    pub(crate) rows: Vec<RowDiff>,
+}
+
+impl TableDiff {
+    /// Aligns the rows of `old` and `new`, so that as many rows as possible stay the same. Returns
+    /// the merged table, which contains the rows of `new` with the removed rows of `old` in
+    /// between, and the differences of its rows.
+    pub(crate) fn new(old: &CsvTable, new: &CsvTable) -> (CsvTable, Self) {
+        let old_rows = used_rows(old);
+        let new_rows = used_rows(new);
+        let mut merged = Vec::new();
+        let mut diff = Vec::new();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for step in align(&old_rows, &new_rows) {
+            match step {
+                (Some(_), Some(new_row)) => {
+                    flush_hunk(&mut removed, &mut added, &mut merged, &mut diff);
+                    merged.push(new_rows[new_row].clone());
+                    diff.push(RowDiff::Same);
+                }
+                (Some(old_row), None) => removed.push(&old_rows[old_row]),
+                (None, Some(new_row)) => added.push(&new_rows[new_row]),
+                (None, None) => unreachable!(),
+            }
+        }
+        flush_hunk(&mut removed, &mut added, &mut merged, &mut diff);
+        let table = CsvTable::from_rows(merged, new.delimiter);
+        (table, Self { rows: diff })
+    }
+
+    pub(crate) fn row(&self, row: usize) -> &RowDiff {
+        self.rows.get(row).unwrap_or(&RowDiff::Same)
+    }
+
+    /// E.g. `2 added, 1 removed, 3 changed rows`
+    pub(crate) fn summary(&self) -> String {
+        let count = |f: fn(&RowDiff) -> bool| self.rows.iter().filter(|row| f(row)).count();
+        format!(
+            "{} added, {} removed, {} changed rows",
+            count(|row| *row == RowDiff::Added),
+            count(|row| *row == RowDiff::Removed),
+            count(|row| matches!(row, RowDiff::Changed(_))),
+        )
+    }
+
+    /// Locations to jump to: the changed cells and the first row of every run of added or removed
+    /// rows.
+    pub(crate) fn locations(&self) -> Vec<CellLocation> {
+        let mut locations = Vec::new();
+        let mut previous = &RowDiff::Same;
+        for (row, diff) in self.rows.iter().enumerate() {
+            match diff {
+                RowDiff::Changed(cols) => {
+                    locations.extend(cols.iter().map(|col| CellLocation { row, col: *col }));
+                }
+                RowDiff::Added | RowDiff::Removed if diff != previous => {
+                    locations.push(CellLocation { row, col: 0 });
+                }
+                _ => {}
+            }
+            previous = diff;
+        }
+        locations
+    }
+}
+
+/// Pairs up the removed and added rows of a hunk as changed rows.
+fn flush_hunk(
+    removed: &mut Vec<&Row>,
+    added: &mut Vec<&Row>,
+    merged: &mut Vec<Row>,
+    diff: &mut Vec<RowDiff>,
+) {
+    let paired = removed.len().min(added.len());
+    for (old_row, new_row) in removed.iter().zip(added.iter()) {
+        let col_count = old_row.len().max(new_row.len());
+        let cols = (0..col_count)
+            .filter(|col| cell(old_row, *col) != cell(new_row, *col))
+            .collect();
+        merged.push((*new_row).clone());
+        diff.push(RowDiff::Changed(cols));
+    }
+    for old_row in &removed[paired..] {
+        merged.push((*old_row).clone());
+        diff.push(RowDiff::Removed);
+    }
+    for new_row in &added[paired..] {
+        merged.push((*new_row).clone());
+        diff.push(RowDiff::Added);
+    }
+    removed.clear();
+    added.clear();
+}
+
+fn used_rows(table: &CsvTable) -> Vec<Row> {
+    let extent = table.used_extent();
+    (0..extent.row_count)
+        .map(|row| {
+            let mut values = (0..extent.col_count)
+                .map(|col| {
+                    table
+                        .get(CellLocation { row, col })
+                        .filter(|value| !value.is_empty())
+                        .map(str::to_owned)
+                })
+                .collect::<Vec<_>>();
+            while values.last().is_some_and(Option::is_none) {
+                values.pop();
+            }
+            values
+        })
+        .collect()
+}
+
+fn cell(row: &Row, col: usize) -> Option<&str> {
+    row.get(col).and_then(Option::as_deref)
+}
+
+/// Pairs of the indices of equal rows, and of rows, that are only in `old` or `new`, in order.
+fn align(old: &[Row], new: &[Row]) -> Vec<(Option<usize>, Option<usize>)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut steps = (0..prefix).map(|i| (Some(i), Some(i))).collect::<Vec<_>>();
+    let (n, m) = (old_middle.len(), new_middle.len());
+    if n.saturating_mul(m) > ALIGN_MAX_CELLS {
+        steps.extend((0..n).map(|i| (Some(prefix + i), None)));
+        steps.extend((0..m).map(|j| (None, Some(prefix + j))));
+    } else {
+        // Lengths of the longest common subsequences of the remaining rows
+        let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+        let index = |i: usize, j: usize| i * (m + 1) + j;
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[index(i, j)] = if old_middle[i] == new_middle[j] {
+                    lengths[index(i + 1, j + 1)] + 1
+                } else {
+                    lengths[index(i + 1, j)].max(lengths[index(i, j + 1)])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_middle[i] == new_middle[j] {
+                steps.push((Some(prefix + i), Some(prefix + j)));
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lengths[index(i + 1, j)] >= lengths[index(i, j + 1)]) {
+                steps.push((Some(prefix + i), None));
+                i += 1;
+            } else {
+                steps.push((None, Some(prefix + j)));
+                j += 1;
+            }
+        }
+    }
+    steps.extend((0..suffix).map(|k| (Some(old.len() - suffix + k), Some(new.len() - suffix + k))));
+    steps
+}
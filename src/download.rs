@@ -0,0 +1,34 @@
+use std::io::Read;
+
+use color_eyre::eyre::eyre;
+
+use crate::task::Progress;
+
+/// Whether `file` is an http or https URL, that is downloaded instead of opened as a path.
+pub(crate) fn is_url(file: &str) -> bool {
+    let scheme = file.get(..8).unwrap_or(file).to_ascii_lowercase();
+    scheme.starts_with("http://") || scheme.starts_with("https://")
+}
+
+/// Downloads the body of `url`. Progress is only reported, if the server sends the length.
+pub(crate) fn fetch(url: &str, progress: &Progress) -> color_eyre::Result<Vec<u8>> {
+    let mut response = ureq::get(url)
+        .call()
+        .map_err(|err| eyre!("Couldn't fetch {url}: {err}"))?;
+    let total = response.body().content_length();
+    let mut reader = response.body_mut().as_reader();
+    let mut bytes = Vec::new();
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        match total {
+            Some(total) => progress.report(bytes.len(), total as usize)?,
+            None => progress.check_cancelled()?,
+        }
+    }
+    Ok(bytes)
+}
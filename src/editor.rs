@@ -0,0 +1,50 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    process::Command,
+};
+
+use color_eyre::eyre::{bail, eyre};
+use uuid::Uuid;
+
+/// `$VISUAL` or `$EDITOR`, falling back to `vi`.
+pub(crate) fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_owned())
+}
+
+/// Lets `editor`, a command with optional arguments like `code --wait`, edit `text` in a
+/// temporary file and returns the result without the final newline, that editors append. The
+/// terminal has to be handed over to the editor before.
+pub(crate) fn edit_text(text: &str, editor: &str) -> color_eyre::Result<String> {
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        bail!("No editor!");
+    };
+    // A random name, that must not exist yet, so nobody can plant a file or symlink there
+    let file = env::temp_dir().join(format!("ratcsv-{}.txt", Uuid::new_v4()));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&file)?.write_all(text.as_bytes())?;
+    let status = Command::new(program).args(words).arg(&file).status();
+    let edited = fs::read_to_string(&file);
+    let _ = fs::remove_file(&file);
+    let status = status.map_err(|err| eyre!("Failed to run {program}: {err}"))?;
+    if !status.success() {
+        bail!("{program} exited with {status}");
+    }
+    let mut edited = edited?;
+    if edited.ends_with('\n') {
+        edited.pop();
+        if edited.ends_with('\r') {
+            edited.pop();
+        }
+    }
+    Ok(edited)
+}
@@ -0,0 +1,65 @@
+use color_eyre::eyre::{bail, eyre};
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+
+/// Parses an encoding label like `latin1`, `windows-1252` or `utf-16le`.
+pub(crate) fn from_label(label: &str) -> color_eyre::Result<&'static Encoding> {
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| eyre!("Unknown encoding: {label}"))
+}
+
+/// The encoding, unless it is UTF-8, which needs no transcoding.
+pub(crate) fn unless_utf8(encoding: &'static Encoding) -> Option<&'static Encoding> {
+    (encoding != UTF_8).then_some(encoding)
+}
+
+/// The name of an encoding like `windows-1252`.
+pub(crate) fn name(encoding: Option<&'static Encoding>) -> String {
+    encoding.unwrap_or(UTF_8).name().to_lowercase()
+}
+
+/// Decodes `bytes`, where a byte order mark overrides `encoding`. Returns the text, the used
+/// encoding and whether there was a byte order mark. Fails for bytes, that aren't valid in the
+/// encoding, instead of replacing them.
+pub(crate) fn decode(
+    bytes: &[u8],
+    encoding: &'static Encoding,
+) -> color_eyre::Result<(String, Option<&'static Encoding>, bool)> {
+    let bom = Encoding::for_bom(bytes).is_some();
+    let (text, encoding, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        bail!("The file isn't valid {}!", encoding.name());
+    }
+    Ok((text.into_owned(), unless_utf8(encoding), bom))
+}
+
+/// Encodes `text` with an optional byte order mark. Fails for characters, that the encoding
+/// can't represent.
+pub(crate) fn encode(
+    text: &str,
+    encoding: &'static Encoding,
+    bom: bool,
+) -> color_eyre::Result<Vec<u8>> {
+    // encoding_rs only decodes UTF-16
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let units = (bom.then_some('\u{feff}').into_iter())
+            .chain(text.chars())
+            .collect::<String>();
+        return Ok(units
+            .encode_utf16()
+            .flat_map(|unit| {
+                if encoding == UTF_16LE {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                }
+            })
+            .collect());
+    }
+    let (bytes, _, unmappable) = encoding.encode(text);
+    if unmappable {
+        bail!(
+            "The text has characters, that {} can't represent!",
+            encoding.name()
+        );
+    }
+    Ok(bytes.into_owned())
+}
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use crossterm::event::{self, Event};
+
+/// Where the events of the app come from, so they can be fed without a terminal.
+pub(crate) trait EventSource {
+    /// Whether an event is available within `timeout`.
+    fn poll(&mut self, timeout: Duration) -> color_eyre::Result<bool>;
+
+    /// Waits for the next event. `None` means, that there are no more events.
+    fn read(&mut self) -> color_eyre::Result<Option<Event>>;
+}
+
+/// The events of the terminal.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct CrosstermEvents;
+
+impl EventSource for CrosstermEvents {
+    fn poll(&mut self, timeout: Duration) -> color_eyre::Result<bool> {
+        Ok(event::poll(timeout)?)
+    }
+
+    fn read(&mut self) -> color_eyre::Result<Option<Event>> {
+        Ok(Some(event::read()?))
+    }
+}
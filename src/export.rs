@@ -0,0 +1,176 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use color_eyre::eyre::{bail, eyre};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    content::{CellLocation, CellRect, CsvTable},
+    task::Progress,
+};
+
+/// Bytes, that are written between checks for cancellation
+const WRITE_CHUNK_SIZE: usize = 1 << 16;
+
+/// Options of the aligned plain-text export, e.g. `border max=20 sep=|`.
+#[derive(Clone, Debug)]
+pub(crate) struct TextTableOptions {
+    /// Between the columns, if there are no borders
+    pub(crate) separator: String,
+    /// Draw the table with `|`, `-` and `+`
+    pub(crate) borders: bool,
+    /// Longer cells are cut off with an ellipsis
+    pub(crate) max_width: Option<usize>,
+}
+
+impl Default for TextTableOptions {
+    fn default() -> Self {
+        Self {
+            separator: "  ".to_owned(),
+            borders: false,
+            max_width: None,
+        }
+    }
+}
+
+impl TextTableOptions {
+    pub(crate) fn parse(args: &[&str]) -> color_eyre::Result<Self> {
+        let mut options = Self::default();
+        for arg in args {
+            match arg.split_once('=') {
+                None if *arg == "border" => options.borders = true,
+                Some(("sep", separator)) => options.separator = separator.to_owned(),
+                Some(("max", width)) => {
+                    let width = width
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|width| *width > 0)
+                        .ok_or_else(|| eyre!("Invalid width: {width}"))?;
+                    options.max_width = Some(width);
+                }
+                _ => bail!("Unknown option: {arg}. Use `border`, `sep=<s>` or `max=<n>`"),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Renders the cells of `rect` as a table with aligned columns.
+pub(crate) fn to_text_table(
+    table: &CsvTable,
+    rect: CellRect,
+    options: &TextTableOptions,
+    progress: &Progress,
+) -> color_eyre::Result<String> {
+    let CellRect {
+        top_left_cell_location,
+        col_count,
+        row_count,
+    } = rect;
+    let rows = (0..row_count)
+        .map(|row| {
+            progress.report(row, row_count)?;
+            let cells = (0..col_count)
+                .map(|col| {
+                    let location = top_left_cell_location + CellLocation { row, col };
+                    let value = table.get(location).unwrap_or_default().replace('\n', " ");
+                    match options.max_width {
+                        Some(max_width) => truncate(&value, max_width),
+                        None => value,
+                    }
+                })
+                .collect::<Vec<_>>();
+            Ok(cells)
+        })
+        .collect::<color_eyre::Result<Vec<_>>>()?;
+    let widths = (0..col_count)
+        .map(|col| rows.iter().map(|row| width(&row[col])).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    let border_line = widths
+        .iter()
+        .map(|width| "-".repeat(width + 2))
+        .collect::<Vec<_>>()
+        .join("+");
+    let border_line = format!("+{border_line}+\n");
+
+    let mut text = String::new();
+    if options.borders {
+        text.push_str(&border_line);
+    }
+    for row in &rows {
+        let cells = row
+            .iter()
+            .zip(&widths)
+            .map(|(value, col_width)| {
+                let padding = " ".repeat(col_width - width(value));
+                format!("{value}{padding}")
+            })
+            .collect::<Vec<_>>();
+        let line = if options.borders {
+            format!("| {} |", cells.join(" | "))
+        } else {
+            cells.join(&options.separator).trim_end().to_owned()
+        };
+        text.push_str(&line);
+        text.push('\n');
+    }
+    if options.borders {
+        text.push_str(&border_line);
+    }
+    Ok(text)
+}
+
+/// Writes `text` to a temporary file next to `path`, which replaces `path` when complete. If the
+/// task is cancelled meanwhile, the temporary file is removed and `path` stays untouched.
+pub(crate) fn write_file(path: &Path, text: &str, progress: &Progress) -> color_eyre::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| eyre!("Invalid file name: {}", path.to_string_lossy()))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    let res = write_chunks(&temp_path, text, progress).and_then(|_| {
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    });
+    if res.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    res
+}
+
+fn write_chunks(path: &Path, text: &str, progress: &Progress) -> color_eyre::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    for chunk in text.as_bytes().chunks(WRITE_CHUNK_SIZE) {
+        progress.check_cancelled()?;
+        file.write_all(chunk)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Display width in terminal columns.
+fn width(value: &str) -> usize {
+    value.chars().filter_map(UnicodeWidthChar::width).sum()
+}
+
+/// Cuts `value` to `max_width` columns including the ellipsis.
+fn truncate(value: &str, max_width: usize) -> String {
+    if width(value) <= max_width {
+        return value.to_owned();
+    }
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for c in value.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if truncated_width + char_width + 1 > max_width {
+            break;
+        }
+        truncated.push(c);
+        truncated_width += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
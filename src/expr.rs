@@ -0,0 +1,590 @@
+use std::fmt::Display;
+
+use crate::{
+    content::{CellLocation, CellRect, CsvTable},
+    formula::FormulaCells,
+    numeric::parse_number,
+};
+
+/// A parse or evaluation error at a byte position of the input.
+#[derive(Clone, Debug)]
+pub(crate) struct ExprError {
+    pub(crate) message: String,
+    pub(crate) position: usize,
+}
+
+impl ExprError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+
+    /// The message with the input, that has a caret at the position of the error, e.g.
+    /// `Expected ")": SUM(A1‸`.
+    pub(crate) fn display_with(&self, input: &str) -> String {
+        let position = (0..=self.position.min(input.len()))
+            .rev()
+            .find(|position| input.is_char_boundary(*position))
+            .unwrap_or(0);
+        let (before, after) = input.split_at(position);
+        format!("{}: {before}‸{after}", self.message)
+    }
+}
+
+/// The result of an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{number}"),
+            Value::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Concat,
+}
+
+#[derive(Clone, Debug)]
+enum ExprKind {
+    Number(f64),
+    Text(String),
+    Cell(CellLocation),
+    Range(CellRect),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    /// `value`: The cell, that the expression is applied to
+    CurrentValue,
+}
+
+/// A parsed expression over numbers, strings in double quotes, cell references like `A1`, ranges
+/// like `A1:B10`, functions like `SUM(...)` and, in `apply`, the cell's own `value`.
+#[derive(Clone, Debug)]
+pub(crate) struct Expr {
+    kind: ExprKind,
+    /// Byte position in the input, where errors are reported
+    position: usize,
+}
+
+/// The cells, that expressions refer to.
+pub(crate) trait Cells {
+    /// The value of a cell or why it has none, e.g. a failing formula.
+    fn value(&self, location: CellLocation) -> Result<Value, String>;
+
+    fn used_extent(&self) -> CellRect;
+
+    /// The value of the cell, that the expression is applied to, if any.
+    fn current_value(&self) -> Option<Value> {
+        None
+    }
+}
+
+/// Cells, where `value` refers to the cell, that an expression is applied to.
+pub(crate) struct WithValue<'a> {
+    pub(crate) cells: &'a dyn Cells,
+    pub(crate) value: Value,
+}
+
+impl Cells for WithValue<'_> {
+    fn value(&self, location: CellLocation) -> Result<Value, String> {
+        self.cells.value(location)
+    }
+
+    fn used_extent(&self) -> CellRect {
+        self.cells.used_extent()
+    }
+
+    fn current_value(&self) -> Option<Value> {
+        Some(self.value.clone())
+    }
+}
+
+/// Parses and evaluates `input` on `table`, where the formulas of the table are evaluated.
+pub(crate) fn evaluate(input: &str, table: &CsvTable) -> Result<Value, ExprError> {
+    Expr::parse(input)?.eval(&FormulaCells::new(table))
+}
+
+impl Expr {
+    pub(crate) fn parse(input: &str) -> Result<Self, ExprError> {
+        let mut parser = Parser { input, position: 0 };
+        let expr = parser.concat()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.peek() {
+            return Err(ExprError::new(
+                format!("Unexpected \"{c}\""),
+                parser.position,
+            ));
+        }
+        Ok(expr)
+    }
+
+    /// Adds the cells and ranges, that the expression refers to.
+    pub(crate) fn references(&self, cells: &mut Vec<CellLocation>, ranges: &mut Vec<CellRect>) {
+        match &self.kind {
+            ExprKind::Cell(location) => cells.push(*location),
+            ExprKind::Range(rect) => ranges.push(*rect),
+            ExprKind::Neg(expr) => expr.references(cells, ranges),
+            ExprKind::Binary(_, lhs, rhs) => {
+                lhs.references(cells, ranges);
+                rhs.references(cells, ranges);
+            }
+            ExprKind::Call(_, args) => {
+                for arg in args {
+                    arg.references(cells, ranges);
+                }
+            }
+            ExprKind::Number(_) | ExprKind::Text(_) | ExprKind::CurrentValue => {}
+        }
+    }
+
+    pub(crate) fn eval(&self, table: &dyn Cells) -> Result<Value, ExprError> {
+        let value = match &self.kind {
+            ExprKind::Number(number) => Value::Number(*number),
+            ExprKind::Text(text) => Value::Text(text.clone()),
+            ExprKind::Cell(location) => table
+                .value(*location)
+                .map_err(|message| ExprError::new(message, self.position))?,
+            ExprKind::Range(_) => {
+                return Err(ExprError::new(
+                    "A range can only be used in a function",
+                    self.position,
+                ));
+            }
+            ExprKind::Neg(expr) => Value::Number(-expr.eval_number(table)?),
+            ExprKind::Binary(BinaryOp::Concat, lhs, rhs) => {
+                Value::Text(format!("{}{}", lhs.eval(table)?, rhs.eval(table)?))
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval_number(table)?;
+                let rhs = rhs.eval_number(table)?;
+                let number = match op {
+                    BinaryOp::Add => lhs + rhs,
+                    BinaryOp::Sub => lhs - rhs,
+                    BinaryOp::Mul => lhs * rhs,
+                    BinaryOp::Div if rhs == 0.0 => {
+                        return Err(ExprError::new("Division by zero", self.position));
+                    }
+                    BinaryOp::Div => lhs / rhs,
+                    BinaryOp::Pow => lhs.powf(rhs),
+                    BinaryOp::Concat => unreachable!(),
+                };
+                Value::Number(number)
+            }
+            ExprKind::Call(name, args) => self.call(name, args, table)?,
+            ExprKind::CurrentValue => table
+                .current_value()
+                .ok_or_else(|| ExprError::new("`value` is only known in `apply`", self.position))?,
+        };
+        Ok(value)
+    }
+
+    fn eval_number(&self, table: &dyn Cells) -> Result<f64, ExprError> {
+        match self.eval(table)? {
+            Value::Number(number) => Ok(number),
+            Value::Text(text) if text.trim().is_empty() => Ok(0.0),
+            Value::Text(text) => parse_number(&text)
+                .ok_or_else(|| ExprError::new(format!("Not a number: \"{text}\""), self.position)),
+        }
+    }
+
+    fn eval_text(&self, table: &dyn Cells) -> Result<String, ExprError> {
+        Ok(self.eval(table)?.to_string())
+    }
+
+    /// The values of all arguments, where ranges are expanded into their non-empty cells.
+    fn eval_values(args: &[Expr], table: &dyn Cells) -> Result<Vec<Value>, ExprError> {
+        let mut values = Vec::new();
+        for arg in args {
+            match &arg.kind {
+                ExprKind::Range(rect) => values.extend(arg.range_values(table, *rect)?),
+                _ => values.push(arg.eval(table)?),
+            }
+        }
+        Ok(values)
+    }
+
+    /// The numeric values of all arguments. Non-numeric cells of ranges are skipped.
+    fn eval_numbers(args: &[Expr], table: &dyn Cells) -> Result<Vec<f64>, ExprError> {
+        let mut numbers = Vec::new();
+        for arg in args {
+            match &arg.kind {
+                ExprKind::Range(rect) => {
+                    numbers.extend(arg.range_values(table, *rect)?.into_iter().filter_map(
+                        |value| match value {
+                            Value::Number(number) => Some(number),
+                            Value::Text(_) => None,
+                        },
+                    ))
+                }
+                _ => numbers.push(arg.eval_number(table)?),
+            }
+        }
+        Ok(numbers)
+    }
+
+    /// The non-empty values of `rect`, which is limited to the used extent, so huge ranges are
+    /// cheap.
+    fn range_values(&self, table: &dyn Cells, rect: CellRect) -> Result<Vec<Value>, ExprError> {
+        let extent = table.used_extent();
+        let CellLocation {
+            row: top,
+            col: left,
+        } = rect.top_left_cell_location;
+        let bottom = top.saturating_add(rect.row_count).min(extent.row_count);
+        let right = left.saturating_add(rect.col_count).min(extent.col_count);
+        let mut values = Vec::new();
+        for row in top..bottom {
+            for col in left..right {
+                let value = table
+                    .value(CellLocation { row, col })
+                    .map_err(|message| ExprError::new(message, self.position))?;
+                if value != Value::Text(String::new()) {
+                    values.push(value);
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    fn call(&self, name: &str, args: &[Expr], table: &dyn Cells) -> Result<Value, ExprError> {
+        let arity = |range: std::ops::RangeInclusive<usize>| {
+            if range.contains(&args.len()) {
+                Ok(())
+            } else {
+                Err(ExprError::new(
+                    format!("Wrong number of arguments for {name}"),
+                    self.position,
+                ))
+            }
+        };
+        let number = match name {
+            "SUM" => Self::eval_numbers(args, table)?.iter().sum(),
+            "AVERAGE" | "AVG" => {
+                let numbers = Self::eval_numbers(args, table)?;
+                if numbers.is_empty() {
+                    return Err(ExprError::new("No numbers to average", self.position));
+                }
+                numbers.iter().sum::<f64>() / numbers.len() as f64
+            }
+            "MIN" => Self::eval_numbers(args, table)?
+                .into_iter()
+                .reduce(f64::min)
+                .unwrap_or(0.0),
+            "MAX" => Self::eval_numbers(args, table)?
+                .into_iter()
+                .reduce(f64::max)
+                .unwrap_or(0.0),
+            "COUNT" => Self::eval_numbers(args, table)?.len() as f64,
+            "COUNTA" => Self::eval_values(args, table)?.len() as f64,
+            "LEN" => {
+                arity(1..=1)?;
+                args[0].eval_text(table)?.chars().count() as f64
+            }
+            "ABS" => {
+                arity(1..=1)?;
+                args[0].eval_number(table)?.abs()
+            }
+            "ROUND" => {
+                arity(1..=2)?;
+                let number = args[0].eval_number(table)?;
+                let digits = match args.get(1) {
+                    Some(arg) => arg.eval_number(table)? as i32,
+                    None => 0,
+                };
+                let factor = 10f64.powi(digits);
+                (number * factor).round() / factor
+            }
+            "UPPER" | "LOWER" | "TRIM" => {
+                arity(1..=1)?;
+                let text = args[0].eval_text(table)?;
+                let text = match name {
+                    "UPPER" => text.to_uppercase(),
+                    "LOWER" => text.to_lowercase(),
+                    _ => text.trim().to_owned(),
+                };
+                return Ok(Value::Text(text));
+            }
+            _ => {
+                return Err(ExprError::new(
+                    format!("Unknown function: {name}"),
+                    self.position,
+                ));
+            }
+        };
+        Ok(Value::Number(number))
+    }
+}
+
+/// A cell, that looks like a number, is a number, so `A1+B1` adds.
+pub(crate) fn cell_value(value: &str) -> Value {
+    match parse_number(value) {
+        Some(number) => Value::Number(number),
+        None => Value::Text(value.to_owned()),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek()
+            && c.is_whitespace()
+        {
+            self.position += c.len_utf8();
+        }
+    }
+
+    /// Skips whitespace and consumes `c`, if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ExprError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(ExprError::new(format!("Expected \"{c}\""), self.position))
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[(char, BinaryOp)],
+        operand: fn(&mut Self) -> Result<Expr, ExprError>,
+    ) -> Result<Expr, ExprError> {
+        let mut lhs = operand(self)?;
+        'outer: loop {
+            for (c, op) in ops {
+                self.skip_whitespace();
+                let position = self.position;
+                if self.eat(*c) {
+                    let rhs = operand(self)?;
+                    lhs = Expr {
+                        kind: ExprKind::Binary(*op, Box::new(lhs), Box::new(rhs)),
+                        position,
+                    };
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn concat(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&[('&', BinaryOp::Concat)], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&[('+', BinaryOp::Add), ('-', BinaryOp::Sub)], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&[('*', BinaryOp::Mul), ('/', BinaryOp::Div)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        self.skip_whitespace();
+        let position = self.position;
+        if self.eat('-') {
+            let expr = self.unary()?;
+            return Ok(Expr {
+                kind: ExprKind::Neg(Box::new(expr)),
+                position,
+            });
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    /// `^` is right-associative and binds tighter than a unary minus, so `-2^2` is `-4`.
+    fn power(&mut self) -> Result<Expr, ExprError> {
+        let base = self.primary()?;
+        self.skip_whitespace();
+        let position = self.position;
+        if self.eat('^') {
+            let exponent = self.unary()?;
+            return Ok(Expr {
+                kind: ExprKind::Binary(BinaryOp::Pow, Box::new(base), Box::new(exponent)),
+                position,
+            });
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ExprError> {
+        self.skip_whitespace();
+        let position = self.position;
+        let Some(c) = self.peek() else {
+            return Err(ExprError::new("Expected a value", position));
+        };
+        let kind = if c == '(' {
+            self.position += 1;
+            let expr = self.concat()?;
+            self.expect(')')?;
+            return Ok(expr);
+        } else if c == '"' {
+            self.text()?
+        } else if c.is_ascii_digit() || c == '.' {
+            self.number()?
+        } else if c.is_ascii_alphabetic() {
+            self.reference_or_call()?
+        } else {
+            return Err(ExprError::new(format!("Unexpected \"{c}\""), position));
+        };
+        Ok(Expr { kind, position })
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &str {
+        let start = self.position;
+        while let Some(c) = self.peek()
+            && predicate(c)
+        {
+            self.position += c.len_utf8();
+        }
+        &self.input[start..self.position]
+    }
+
+    fn number(&mut self) -> Result<ExprKind, ExprError> {
+        let position = self.position;
+        let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+        number
+            .parse::<f64>()
+            .map(ExprKind::Number)
+            .map_err(|_| ExprError::new(format!("Not a number: {number}"), position))
+    }
+
+    /// A string in double quotes, where `""` is a quote.
+    fn text(&mut self) -> Result<ExprKind, ExprError> {
+        let start = self.position;
+        self.position += 1;
+        let mut text = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(ExprError::new("Unterminated string", start));
+            };
+            self.position += c.len_utf8();
+            if c == '"' {
+                if self.peek() != Some('"') {
+                    return Ok(ExprKind::Text(text));
+                }
+                self.position += 1;
+            }
+            text.push(c);
+        }
+    }
+
+    fn reference_or_call(&mut self) -> Result<ExprKind, ExprError> {
+        let position = self.position;
+        let name = self.take_while(|c| c.is_ascii_alphanumeric()).to_owned();
+        if self.eat('(') {
+            let mut args = Vec::new();
+            if !self.eat(')') {
+                loop {
+                    args.push(self.argument()?);
+                    if self.eat(')') {
+                        break;
+                    }
+                    self.expect(',')?;
+                }
+            }
+            return Ok(ExprKind::Call(name.to_uppercase(), args));
+        }
+        if name.eq_ignore_ascii_case("value") {
+            return Ok(ExprKind::CurrentValue);
+        }
+        let location = parse_location(&name, position)?;
+        Ok(ExprKind::Cell(location))
+    }
+
+    /// An expression or a range like `A1:B10` or `B:D` for whole columns.
+    fn argument(&mut self) -> Result<Expr, ExprError> {
+        self.skip_whitespace();
+        let position = self.position;
+        if let Some(rect) = self.column_range() {
+            return Ok(Expr {
+                kind: ExprKind::Range(rect),
+                position,
+            });
+        }
+        let expr = self.concat()?;
+        let ExprKind::Cell(start) = expr.kind else {
+            return Ok(expr);
+        };
+        if !self.eat(':') {
+            return Ok(expr);
+        }
+        self.skip_whitespace();
+        let position = self.position;
+        let end = self.take_while(|c| c.is_ascii_alphanumeric()).to_owned();
+        let end = parse_location(&end, position)?;
+        Ok(Expr {
+            kind: ExprKind::Range(CellRect::from_opposite_cell_locations(start, end)),
+            position: expr.position,
+        })
+    }
+
+    /// Whole columns like `B:D`. Leaves the position as it is, if there are none.
+    fn column_range(&mut self) -> Option<CellRect> {
+        let start = self.position;
+        let first = self.take_while(|c| c.is_ascii_alphabetic()).to_owned();
+        let range = if !first.is_empty() && self.eat(':') {
+            self.skip_whitespace();
+            let last = self.take_while(|c| c.is_ascii_alphabetic()).to_owned();
+            let ends = !matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric());
+            let first = CellLocation::col_letters_to_index(&first).ok();
+            let last = CellLocation::col_letters_to_index(&last).ok();
+            first
+                .zip(last)
+                .filter(|_| ends)
+                .map(|(first, last)| CellRect {
+                    top_left_cell_location: CellLocation {
+                        row: 0,
+                        col: first.min(last),
+                    },
+                    col_count: first.abs_diff(last) + 1,
+                    row_count: usize::MAX,
+                })
+        } else {
+            None
+        };
+        if range.is_none() {
+            self.position = start;
+        }
+        range
+    }
+}
+
+fn parse_location(id: &str, position: usize) -> Result<CellLocation, ExprError> {
+    CellLocation::from_a1(id)
+        .map_err(|_| ExprError::new(format!("Not a cell reference: {id}"), position))
+}
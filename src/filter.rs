@@ -0,0 +1,163 @@
+use color_eyre::eyre::{bail, eyre};
+use regex::Regex;
+
+use crate::{
+    content::{CellLocation, CsvTable},
+    numeric::parse_number,
+};
+
+/// A condition on a cell of a column, e.g. `B ~ ^foo`, `C >= 10` or `D empty`.
+#[derive(Clone, Debug)]
+pub(crate) enum RowPredicate {
+    Matches(Regex),
+    NotMatches(Regex),
+    /// Equal numbers or, if either side isn't numeric, equal text
+    Equal(String),
+    NotEqual(String),
+    Less(f64),
+    LessEqual(f64),
+    Greater(f64),
+    GreaterEqual(f64),
+    Empty,
+    NotEmpty,
+}
+
+impl RowPredicate {
+    fn is_match(&self, value: &str) -> bool {
+        let number = || parse_number(value);
+        match self {
+            Self::Matches(regex) => regex.is_match(value),
+            Self::NotMatches(regex) => !regex.is_match(value),
+            Self::Equal(other) => is_equal(value, other),
+            Self::NotEqual(other) => !is_equal(value, other),
+            Self::Less(other) => number().is_some_and(|n| n < *other),
+            Self::LessEqual(other) => number().is_some_and(|n| n <= *other),
+            Self::Greater(other) => number().is_some_and(|n| n > *other),
+            Self::GreaterEqual(other) => number().is_some_and(|n| n >= *other),
+            Self::Empty => value.is_empty(),
+            Self::NotEmpty => !value.is_empty(),
+        }
+    }
+}
+
+fn is_equal(value: &str, other: &str) -> bool {
+    match (parse_number(value), parse_number(other)) {
+        (Some(a), Some(b)) => a == b,
+        _ => value == other,
+    }
+}
+
+/// Hides the rows of the used extent, that don't match a predicate. The rows after the used
+/// extent are always shown, so new rows can be appended.
+#[derive(Clone, Debug)]
+pub(crate) struct RowFilter {
+    /// The expression as it was typed, for messages
+    pub(crate) expr: String,
+    col: usize,
+    predicate: RowPredicate,
+    /// The shown rows of the used extent in ascending order
+    rows: Vec<usize>,
+    /// The end of the used extent, from which on all rows are shown
+    end: usize,
+    /// Revision of the table and header mode, that the rows were computed for
+    computed_for: Option<(u64, bool)>,
+}
+
+impl RowFilter {
+    /// Parses `<col> <op> [operand]`, where `op` is one of `~ !~ = != < <= > >=`, `empty` or
+    /// `!empty`.
+    pub(crate) fn parse(expr: &str) -> color_eyre::Result<Self> {
+        let expr = expr.trim();
+        let usage = || eyre!("Usage: filter <col> ~|!~|=|!=|<|<=|>|>= <value>, or <col> [!]empty");
+        let split = expr
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(expr.len());
+        let (col, rest) = expr.split_at(split);
+        if col.is_empty() {
+            return Err(usage());
+        }
+        let col = CellLocation::col_id_to_index(col)?;
+        let rest = rest.trim_start();
+        // Longer operators first, so `<=` isn't taken for `<`
+        const OPERATORS: [&str; 10] = [
+            "!empty", "empty", "!~", "~", "!=", "<=", ">=", "=", "<", ">",
+        ];
+        let Some(operator) = OPERATORS.into_iter().find(|op| rest.starts_with(op)) else {
+            return Err(usage());
+        };
+        let operand = rest[operator.len()..].trim();
+        let number = || parse_number(operand).ok_or_else(|| eyre!("Not a number: {operand}"));
+        if matches!(operator, "~" | "!~") && operand.is_empty() {
+            bail!("Missing pattern!");
+        }
+        let predicate = match operator {
+            "empty" | "!empty" if !operand.is_empty() => return Err(usage()),
+            "empty" => RowPredicate::Empty,
+            "!empty" => RowPredicate::NotEmpty,
+            "~" => RowPredicate::Matches(Regex::new(operand)?),
+            "!~" => RowPredicate::NotMatches(Regex::new(operand)?),
+            "=" => RowPredicate::Equal(operand.to_owned()),
+            "!=" => RowPredicate::NotEqual(operand.to_owned()),
+            "<" => RowPredicate::Less(number()?),
+            "<=" => RowPredicate::LessEqual(number()?),
+            ">" => RowPredicate::Greater(number()?),
+            ">=" => RowPredicate::GreaterEqual(number()?),
+            _ => unreachable!(),
+        };
+        Ok(Self {
+            expr: expr.to_owned(),
+            col,
+            predicate,
+            rows: Vec::new(),
+            end: 0,
+            computed_for: None,
+        })
+    }
+
+    /// Recomputes the shown rows, if the table changed since the last update. A `header` row is
+    /// always shown.
+    pub(crate) fn update(&mut self, table: &CsvTable, header: bool) {
+        let computed_for = (table.revision(), header);
+        if self.computed_for == Some(computed_for) {
+            return;
+        }
+        self.computed_for = Some(computed_for);
+        self.end = table.used_extent().row_count;
+        self.rows = (0..self.end)
+            .filter(|row| {
+                if header && *row == 0 {
+                    return true;
+                }
+                let value = table
+                    .get(CellLocation {
+                        row: *row,
+                        col: self.col,
+                    })
+                    .unwrap_or_default();
+                self.predicate.is_match(value)
+            })
+            .collect();
+    }
+
+    /// Number of the shown rows of the used extent.
+    pub(crate) fn shown_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Position of `row` among the shown rows. A hidden row maps to the next shown one.
+    pub(crate) fn index_of(&self, row: usize) -> usize {
+        if row >= self.end {
+            self.rows.len() + row - self.end
+        } else {
+            self.rows.partition_point(|shown| *shown < row)
+        }
+    }
+
+    /// The shown row at `index`.
+    pub(crate) fn row_at(&self, index: usize) -> usize {
+        match self.rows.get(index) {
+            Some(row) => *row,
+            None => self.end + index - self.rows.len(),
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+use crate::{
+    content::{CellLocation, CellRect, CsvTable},
+    expr::{self, Cells, Expr, ExprError, Value},
+};
+
+/// Shown instead of the value of a formula, that refers to itself
+const CIRCULAR_MARKER: &str = "#CYCLE!";
+/// Shown instead of the value of a formula, that doesn't evaluate
+const ERROR_MARKER: &str = "#ERROR!";
+
+/// The expression of a cell like `=A1+B2*2`.
+pub(crate) fn formula(value: &str) -> Option<&str> {
+    value
+        .strip_prefix('=')
+        .filter(|formula| !formula.is_empty())
+}
+
+/// The parsed formula of a cell with the cells and ranges, that it refers to.
+struct Formula<'a> {
+    text: &'a str,
+    expr: Result<Expr, ExprError>,
+    cells: Vec<CellLocation>,
+    ranges: Vec<CellRect>,
+}
+
+impl<'a> Formula<'a> {
+    fn of(table: &'a CsvTable, location: CellLocation) -> Option<Self> {
+        let text = formula(table.get(location)?)?;
+        let (mut cells, mut ranges) = (Vec::new(), Vec::new());
+        let expr = Expr::parse(text).inspect(|expr| expr.references(&mut cells, &mut ranges));
+        Some(Self {
+            text,
+            expr,
+            cells,
+            ranges,
+        })
+    }
+
+    /// The formula cells, that have to be evaluated before this one. Ranges are limited to the
+    /// used extent.
+    fn dependencies(&self, table: &CsvTable) -> Vec<CellLocation> {
+        let extent = table.used_extent();
+        let mut dependencies = self.cells.clone();
+        for range in &self.ranges {
+            dependencies.extend(cells_within(*range, extent));
+        }
+        dependencies.retain(|location| table.get(*location).and_then(formula).is_some());
+        dependencies
+    }
+}
+
+/// The cells of `rect`, that are within `extent`.
+fn cells_within(rect: CellRect, extent: CellRect) -> impl Iterator<Item = CellLocation> {
+    let CellLocation {
+        row: top,
+        col: left,
+    } = rect.top_left_cell_location;
+    let bottom = top.saturating_add(rect.row_count).min(extent.row_count);
+    let right = left.saturating_add(rect.col_count).min(extent.col_count);
+    (top..bottom).flat_map(move |row| (left..right).map(move |col| CellLocation { row, col }))
+}
+
+/// The keys of `map` in `rect`, found by looking at the cells of the rect or at the keys,
+/// whichever are fewer.
+fn keys_within<V>(map: &HashMap<CellLocation, V>, rect: CellRect) -> Vec<CellLocation> {
+    if rect.row_count.saturating_mul(rect.col_count) <= map.len() {
+        let everything = CellRect {
+            top_left_cell_location: CellLocation::default(),
+            col_count: usize::MAX,
+            row_count: usize::MAX,
+        };
+        cells_within(rect, everything)
+            .filter(|location| map.contains_key(location))
+            .collect()
+    } else {
+        map.keys()
+            .copied()
+            .filter(|location| rect.contains(*location))
+            .collect()
+    }
+}
+
+/// The shown values of the formula cells of a table. After an edit, only the formulas, that
+/// depend on the edited cells, are evaluated again.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Formulas {
+    values: HashMap<CellLocation, String>,
+    results: HashMap<CellLocation, Result<Value, String>>,
+    circular: HashSet<CellLocation>,
+    /// The cells and ranges, that each formula refers to
+    references: HashMap<CellLocation, (Vec<CellLocation>, Vec<CellRect>)>,
+    /// The formulas, that refer to a cell
+    dependents: HashMap<CellLocation, HashSet<CellLocation>>,
+    /// The formulas, that refer to ranges
+    range_formulas: HashSet<CellLocation>,
+    /// Revision of the table, that the values were computed for
+    revision: Option<u64>,
+}
+
+impl Formulas {
+    /// Recalculates the formulas, that depend on the cells, which changed since the last update.
+    /// Without the edits of the table, all formulas are recalculated.
+    pub(crate) fn update(&mut self, revision: u64, table: &CsvTable) {
+        if self.revision == Some(revision) {
+            return;
+        }
+        let edits = self.revision.and_then(|last| table.edits_since(last));
+        let dirty = match edits {
+            Some(edits) => self.invalidate(table, &edits),
+            None => {
+                *self = Self::default();
+                let locations = table
+                    .cells()
+                    .filter(|(_, value)| formula(value).is_some())
+                    .map(|(location, _)| location)
+                    .collect::<HashSet<_>>();
+                for location in &locations {
+                    self.link(table, *location);
+                }
+                locations
+            }
+        };
+        self.revision = Some(revision);
+
+        let cells = FormulaCells::new(table);
+        let mut results = mem::take(&mut self.results);
+        results.retain(|location, _| !dirty.contains(location));
+        self.circular.retain(|location| !dirty.contains(location));
+        *cells.results.borrow_mut() = results;
+        *cells.circular.borrow_mut() = mem::take(&mut self.circular);
+        for location in &dirty {
+            let _ = cells.value(*location);
+        }
+        self.results = cells.results.into_inner();
+        self.circular = cells.circular.into_inner();
+        for location in dirty {
+            let value = match self.results.get(&location) {
+                Some(Ok(value)) => value.to_string(),
+                Some(Err(_)) if self.circular.contains(&location) => CIRCULAR_MARKER.to_owned(),
+                Some(Err(_)) => ERROR_MARKER.to_owned(),
+                None => {
+                    self.values.remove(&location);
+                    continue;
+                }
+            };
+            self.values.insert(location, value);
+        }
+    }
+
+    /// Updates the references of the formulas in the edited rects and returns the formulas,
+    /// that have to be evaluated again: the edited ones and all, that depend on an edited cell.
+    fn invalidate(&mut self, table: &CsvTable, edits: &[CellRect]) -> HashSet<CellLocation> {
+        let extent = table.used_extent();
+        let mut queue = Vec::new();
+        for rect in edits {
+            for location in keys_within(&self.references, *rect) {
+                self.unlink(location);
+                queue.push(location);
+            }
+            for location in cells_within(*rect, extent) {
+                if table.get(location).and_then(formula).is_some() {
+                    self.link(table, location);
+                    queue.push(location);
+                }
+            }
+            for location in keys_within(&self.dependents, *rect) {
+                queue.extend(&self.dependents[&location]);
+            }
+            queue.extend(self.range_dependents(|range| range.intersects(rect)));
+        }
+        let mut dirty = HashSet::new();
+        while let Some(location) = queue.pop() {
+            if !dirty.insert(location) {
+                continue;
+            }
+            queue.extend(self.dependents.get(&location).into_iter().flatten());
+            queue.extend(self.range_dependents(|range| range.contains(location)));
+        }
+        dirty
+    }
+
+    /// The formulas with a range, that `matches`.
+    fn range_dependents(&self, matches: impl Fn(&CellRect) -> bool) -> Vec<CellLocation> {
+        self.range_formulas
+            .iter()
+            .copied()
+            .filter(|location| self.references[location].1.iter().any(&matches))
+            .collect()
+    }
+
+    /// Records the references of the formula at `location`.
+    fn link(&mut self, table: &CsvTable, location: CellLocation) {
+        let Some(Formula { cells, ranges, .. }) = Formula::of(table, location) else {
+            return;
+        };
+        for cell in &cells {
+            self.dependents.entry(*cell).or_default().insert(location);
+        }
+        if !ranges.is_empty() {
+            self.range_formulas.insert(location);
+        }
+        self.references.insert(location, (cells, ranges));
+    }
+
+    /// Forgets the references of the formula at `location`.
+    fn unlink(&mut self, location: CellLocation) {
+        let Some((cells, _)) = self.references.remove(&location) else {
+            return;
+        };
+        for cell in cells {
+            if let Some(dependents) = self.dependents.get_mut(&cell) {
+                dependents.remove(&location);
+                if dependents.is_empty() {
+                    self.dependents.remove(&cell);
+                }
+            }
+        }
+        self.range_formulas.remove(&location);
+    }
+
+    /// The value of the formula in a cell.
+    pub(crate) fn value(&self, location: CellLocation) -> Option<&str> {
+        self.values.get(&location).map(String::as_str)
+    }
+}
+
+/// A formula, whose dependencies are evaluated before it.
+struct Pending<'a> {
+    location: CellLocation,
+    formula: Formula<'a>,
+    dependencies: Vec<CellLocation>,
+    /// The next dependency to look at
+    next: usize,
+}
+
+/// The cells of a table, where formulas are evaluated on demand with the formulas, they refer to.
+/// Results are kept, so every formula is evaluated once.
+pub(crate) struct FormulaCells<'a> {
+    table: &'a CsvTable,
+    results: RefCell<HashMap<CellLocation, Result<Value, String>>>,
+    /// Formulas, that are part of a circular reference
+    circular: RefCell<HashSet<CellLocation>>,
+}
+
+impl<'a> FormulaCells<'a> {
+    pub(crate) fn new(table: &'a CsvTable) -> Self {
+        Self {
+            table,
+            results: RefCell::default(),
+            circular: RefCell::default(),
+        }
+    }
+
+    fn pending(&self, location: CellLocation) -> Option<Pending<'a>> {
+        let formula = Formula::of(self.table, location)?;
+        Some(Pending {
+            location,
+            dependencies: formula.dependencies(self.table),
+            formula,
+            next: 0,
+        })
+    }
+
+    /// Evaluates the formula at `root` after the formulas, that it depends on, in topological
+    /// order. A stack instead of recursion lets chains of any length evaluate.
+    fn evaluate(&self, root: CellLocation) {
+        let mut stack = Vec::from_iter(self.pending(root));
+        let mut on_stack = HashSet::from([root]);
+        while let Some(pending) = stack.last_mut() {
+            if let Some(&dependency) = pending.dependencies.get(pending.next) {
+                pending.next += 1;
+                if self.results.borrow().contains_key(&dependency) {
+                    continue;
+                }
+                if on_stack.contains(&dependency) {
+                    let start = stack
+                        .iter()
+                        .position(|pending| pending.location == dependency)
+                        .unwrap_or_default();
+                    let cycle = stack[start..].iter().map(|pending| pending.location);
+                    self.circular.borrow_mut().extend(cycle);
+                    continue;
+                }
+                if let Some(pending) = self.pending(dependency) {
+                    on_stack.insert(dependency);
+                    stack.push(pending);
+                }
+                continue;
+            }
+            let Some(pending) = stack.pop() else {
+                break;
+            };
+            let location = pending.location;
+            on_stack.remove(&location);
+            let result = self.result(pending);
+            self.results.borrow_mut().insert(location, result);
+        }
+    }
+
+    /// The result of a formula, whose dependencies are evaluated. The error of a dependency is
+    /// passed on as it is, so it names the innermost failing formula.
+    fn result(&self, pending: Pending<'_>) -> Result<Value, String> {
+        let Pending {
+            location,
+            formula,
+            dependencies,
+            ..
+        } = pending;
+        if self.circular.borrow().contains(&location) {
+            return Err(format!("{location}: Circular reference"));
+        }
+        let failed = dependencies.iter().find_map(|dependency| {
+            self.results
+                .borrow()
+                .get(dependency)
+                .and_then(|result| result.clone().err())
+        });
+        if let Some(err) = failed {
+            return Err(err);
+        }
+        formula
+            .expr
+            .and_then(|expr| expr.eval(self))
+            .map_err(|err| format!("{location}: {}", err.display_with(formula.text)))
+    }
+}
+
+impl Cells for FormulaCells<'_> {
+    fn value(&self, location: CellLocation) -> Result<Value, String> {
+        let text = self.table.get(location).unwrap_or_default();
+        if formula(text).is_none() {
+            return Ok(expr::cell_value(text));
+        }
+        if let Some(result) = self.results.borrow().get(&location) {
+            return result.clone();
+        }
+        self.evaluate(location);
+        self.results
+            .borrow()
+            .get(&location)
+            .cloned()
+            .unwrap_or_else(|| Ok(Value::Text(String::new())))
+    }
+
+    fn used_extent(&self) -> CellRect {
+        self.table.used_extent()
+    }
+}
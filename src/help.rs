@@ -0,0 +1,321 @@
+use crate::keymap::{Action, Keymap};
+
+/// A single entry of the keybind table, that is shown by `:help` and on the splash screen.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct HelpEntry {
+    /// The keys, where an action in braces, e.g. `{move-down}`, stands for its key
+    keys: &'static str,
+    pub(crate) description: &'static str,
+    /// Whether the entry is part of the short cheat sheet on the splash screen
+    pub(crate) splash: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct HelpSection {
+    pub(crate) title: &'static str,
+    pub(crate) entries: &'static [HelpEntry],
+}
+
+impl HelpEntry {
+    /// The keys with the actions replaced by their first key in `keymap`, so remapped keys are
+    /// shown.
+    pub(crate) fn keys(&self, keymap: &Keymap) -> String {
+        let mut keys = String::new();
+        let mut rest = self.keys;
+        while let Some((before, after)) = rest.split_once('{')
+            && let Some((name, after)) = after.split_once('}')
+        {
+            keys.push_str(before);
+            let action = name
+                .parse::<Action>()
+                .expect("Help entries name known actions");
+            keys.push_str(keymap.keys_of(action).first().map_or("", String::as_str));
+            rest = after;
+        }
+        keys.push_str(rest);
+        keys
+    }
+}
+
+const fn entry(keys: &'static str, description: &'static str) -> HelpEntry {
+    HelpEntry {
+        keys,
+        description,
+        splash: false,
+    }
+}
+
+const fn splash_entry(keys: &'static str, description: &'static str) -> HelpEntry {
+    HelpEntry {
+        keys,
+        description,
+        splash: true,
+    }
+}
+
+pub(crate) const HELP_SECTIONS: &[HelpSection] = &[
+    HelpSection {
+        title: "Files",
+        entries: &[
+            splash_entry(":o <file|url>", "Open file or http(s) URL"),
+            entry(":recent :o", "Pick a recent file (d: remove)"),
+            splash_entry(":n", "New buffer"),
+            entry(":w [file]", "Write buffer"),
+            splash_entry(":q", "Quit"),
+            entry(":q!", "Quit without saving"),
+            entry(":wq [file]", "Write and quit"),
+            entry(":bc", "Close buffer"),
+            entry(":bn :bp", "Next/previous buffer"),
+            entry(":ls", "List buffers, `%` is the current one"),
+            entry(":b <n>", "Switch to buffer number n"),
+            entry(":vsplit [n] :split [n]", "Show buffer n beside/below"),
+            entry("{window-combo}w/h/j/k/l", "Focus the other pane"),
+            entry("{window-combo}o :only", "Close the other pane"),
+            entry(":sample <n|p%> [seed=n]", "Random rows into a new buffer"),
+            entry(":export txt <file> [opts]", "Export as aligned text table"),
+            entry(":write-yank <file> [r]", "Write register r as CSV"),
+            entry(":delimiter [d]", "Show or set the delimiter"),
+            entry(":save-path", "Show the save path"),
+        ],
+    },
+    HelpSection {
+        title: "Movement",
+        entries: &[
+            entry(
+                "{move-left} {move-down} {move-up} {move-right}",
+                "Move selection",
+            ),
+            entry(
+                "{half-page-left} {half-page-right}",
+                "Move selection half a page left/right",
+            ),
+            entry("{half-page-down}", "Move selection half a page down"),
+            entry("{half-page-up}", "Move selection half a page up"),
+            entry(
+                "{data-edge-left} {data-edge-down} {data-edge-up} {data-edge-right}",
+                "Jump to the edge of the data",
+            ),
+            entry("{goto}", "Go to first cell"),
+            entry("{goto-combo}<id>g", "Go to cell, e.g. `gB12g`"),
+            entry(
+                "{goto-row-start} {goto-col-start}",
+                "Go to first column/row",
+            ),
+            entry(
+                "{goto-last-row} <n>{goto-last-row}",
+                "Go to last data row of column, row n",
+            ),
+            entry("{goto-end} :goto-end", "Go to the last cell of the data"),
+            entry(":goto <id>", "Go to cell, e.g. `B12` or `R12C2`"),
+            entry(
+                "{mark-combo}<a-z> {jump-to-mark-combo}<a-z>",
+                "Set mark, jump to mark",
+            ),
+            entry(
+                "{jump-back} {jump-forward}",
+                "Walk back/forward through jumps",
+            ),
+            entry("Click, drag", "Select cell, select rectangle"),
+            entry("Wheel, Shift-Wheel", "Scroll rows, scroll columns"),
+        ],
+    },
+    HelpSection {
+        title: "Editing",
+        entries: &[
+            splash_entry("{insert}", "Edit cell"),
+            entry("{change}", "Replace cell content"),
+            entry("{edit-cell} :edit-cell", "Edit cell in $VISUAL/$EDITOR"),
+            entry("Tab (in cell input)", "Commit and edit the next cell"),
+            entry(":append-row", "Start typing below the last row"),
+            entry("{cell-preview}", "Preview cell content"),
+            splash_entry("{toggle-visual}", "Select (visual mode)"),
+            entry("{restore-selection}", "Restore previous selection"),
+            splash_entry("{yank}/{delete}/{paste}", "Yank/delete/paste"),
+            entry("{paste-csv}", "Paste yanked CSV text as cells"),
+            entry("{cycle-paste}", "After paste: paste older yank"),
+            entry(":paste-special [opts]", "Opts: transpose skip-blanks fill"),
+            entry(
+                "{register-combo}<a-z> {yank}/{delete}/{paste}",
+                "Yank/delete/paste with register",
+            ),
+            entry(":registers", "List the registers"),
+            entry("[n]{clear}", "Delete cells without yanking"),
+            entry(
+                "{fill-down} {fill-right} :fill down|right",
+                "Fill selection from first row/col",
+            ),
+            entry(":fill series [step]", "Continue numbers or dates"),
+            entry(":trim :squeeze", "Strip or collapse whitespace"),
+            entry(":upper :lower :title", "Change case of selection or table"),
+            entry(":apply <col> <expr>", "Map expr over column (`value`)"),
+            entry("{pipe} :pipe <cmd>", "Replace selection with cmd output"),
+            entry(
+                "[n]{increment} [n]{decrement}",
+                "Add/subtract n to the numbers",
+            ),
+            entry(":yank-all", "Yank table or selection as CSV text"),
+            entry(":yank-txt [opts]", "Yank as text, opts: border sep= max="),
+            entry("{clear-yank}", "Clear yank marker"),
+            entry(
+                "[n]{insert-row-below} [n]{insert-row-above}",
+                "Insert rows below/above",
+            ),
+            entry("[n]{delete-rows}", "Delete rows of the selection"),
+            entry(
+                "{duplicate-rows} :dup-row",
+                "Duplicate rows of the selection",
+            ),
+            entry(
+                "{duplicate-cols} :dup-col",
+                "Duplicate columns of the selection",
+            ),
+            entry(":row-insert [n]", "Insert rows above"),
+            entry(":row-delete [n]", "Delete rows"),
+            entry(":col-insert [col] [n]", "Insert columns before, e.g. `B`"),
+            entry(":col-delete [cols]", "Delete columns, e.g. `C:E`"),
+            entry(":split-col <col> <sep>", "Split column by separator regex"),
+            entry(":merge-cols <cols> [sep]", "Join columns like `B:D` into B"),
+            entry(":transpose", "Swap rows and cols of selection/table"),
+            entry("{undo} {redo}", "Undo/redo"),
+            entry("{repeat-change}", "Repeat last edit, with a new count"),
+            entry(
+                "{record-macro}<a-z> {record-macro}",
+                "Record macro, stop recording",
+            ),
+            entry(
+                "[n]{play-macro-combo}<a-z> [n]{play-macro-combo}@",
+                "Play macro, repeat last macro",
+            ),
+            entry(":now [format]", "Insert date and time, e.g. `%d.%m.%Y`"),
+            entry(":today [format]", "Insert date"),
+            entry(":uuid", "Fill selection with random UUIDs"),
+            entry(":shuffle [seed]", "Shuffle rows of the selection/table"),
+            entry(":reverse", "Reverse rows of the selection/table"),
+            entry(
+                ":sort <col> [desc], ..",
+                "Sort rows, e.g. `B desc, C numeric`",
+            ),
+            entry(
+                ":seq [n] [step] [row|col]",
+                "Fill selection with a sequence",
+            ),
+            entry(
+                "{next-change} {previous-change}",
+                "Next/previous change or difference",
+            ),
+            entry(":diff", "List cells changed since saving"),
+            entry(":diff <file>", "Compare file with the buffer"),
+            entry(":sql <query>", "Query table t, result in a buffer"),
+            entry(":pivot <r> <c> <v> <agg>", "Pivot table of v in a buffer"),
+            entry(":join <b> <c> <c> [kind]", "Join buf b, inner|left|outer"),
+            entry(":changes", "Changed cells in the results pane"),
+            entry(":check", "Ragged rows in the results pane"),
+            entry(":fix-ragged pad|truncate", "Same field count in all rows"),
+            entry(":note [text]", "Show or set the note of the cell"),
+            entry(":notes", "List notes (in <file>.ratcsv-notes.json)"),
+            entry(
+                ":pad <width> [char]",
+                "Left-pad selected cells (default `0`)",
+            ),
+            entry(
+                ":pad-right <width> [char]",
+                "Right-pad selected cells (default space)",
+            ),
+        ],
+    },
+    HelpSection {
+        title: "Search",
+        entries: &[
+            entry("{search}", "Search cells by regex"),
+            entry(":noh", "Stop highlighting the matches"),
+            entry("{search-next} {search-previous}", "Next/previous match"),
+            entry("Tab (in /)", "Complete from column, then table"),
+            entry("Tab (in :)", "Complete commands and files"),
+            entry("Up Down (in :)", "Recall commands, that start alike"),
+            entry(":count [pattern]", "Count matching cells and rows"),
+            entry(":matches [pattern]", "Matches in the results pane"),
+            entry("{next-result} {previous-result}", "Next/previous result"),
+            entry(":copen", "Show and focus the results"),
+            entry(":cclose", "Hide the results"),
+            entry(":set wrapscan on|off", "Continue search at the other end"),
+            entry(r"{search}\V", "Search plain text"),
+            entry(":set search regex|literal", "Search mode of / and :s"),
+            entry(":set ignorecase on|off", "Search case-insensitively"),
+            entry(":set smartcase on|off", "Case-sensitive with uppercase"),
+            entry(":s/pat/rep/[flags]", "Replace in selection"),
+            entry(":%s/pat/rep/[flags]", "Replace in whole table"),
+            entry("  flags", "g: all, c: confirm, i/I: case"),
+        ],
+    },
+    HelpSection {
+        title: "View",
+        entries: &[
+            entry("{center}", "Center selection"),
+            entry(
+                "{center-row} {center-col}",
+                "Center selection vertically/horizontally",
+            ),
+            entry(
+                "{scroll-left} {scroll-down} {scroll-up} {scroll-right}",
+                "Scroll view",
+            ),
+            entry(":hist [column] [bins]", "Histogram of a numeric column"),
+            entry("{stats} :stats", "Stats of the selection or column"),
+            entry(":freq [column]", "Value counts, Enter jumps to a row"),
+            entry(
+                ":aggregate [col] <f>|off",
+                "Footer row: sum, mean, count, …",
+            ),
+            entry(
+                ":heatmap [column] [on|off]",
+                "Color a numeric column by value",
+            ),
+            entry(":heatmap off", "Remove all heatmaps"),
+            entry(":header [on|off]", "Pin first row, use it as column labels"),
+            entry(
+                ":filter <col> <op> <v>",
+                "Hide rows, op: ~ !~ = != < <= > >=",
+            ),
+            entry(":filter <col> [!]empty", "Hide rows by empty cells"),
+            entry(":filter [clear]", "Show or remove the filter"),
+        ],
+    },
+    HelpSection {
+        title: "General",
+        entries: &[
+            splash_entry("{help}", "Help"),
+            entry(":help", "Help"),
+            entry(":echo <expr>", "Evaluate, e.g. SUM(A1:A9)/AVG(B:B)"),
+            entry("=<expr> in a cell", "Formula, that shows its value"),
+            entry(":set <option> [value]", "Show or change an option"),
+            entry(":set coords a1|r1c1", "Coordinate style"),
+            entry(":set clamp-view on|off", "Keep the view near the data"),
+            entry(":set autogrow on|off", "Widen columns for long values"),
+            entry(":set autogrowmax <n>", "Maximum width of autogrow"),
+            entry(":set hscrollbar on|off", "Show the horizontal scrollbar"),
+            entry(":set vscrollbar on|off", "Show the vertical scrollbar"),
+            entry(":set formulas on|off", "Show formulas instead of values"),
+            entry(":set calc on|off", "Store the value of = input"),
+            entry(":set quote <c>", "Quote character on save"),
+            entry(":set quote-style <s>", "Quote always|necessary|never"),
+            entry(":set escape <c>|off", "Escape quotes instead of doubling"),
+            entry(":set eol lf|crlf", "Line ending on save, as loaded"),
+            entry(":set bom on|off", "Byte order mark on save, as loaded"),
+            entry(":set encoding <name>", "Transcode on load and save"),
+            entry(":set faithful on|off", "Keep unchanged rows on save"),
+            entry(":set comment-char <c>", "Keep lines starting with c"),
+            entry("<C-r> \" / a-z", "Insert register in the console"),
+            entry("<C-r> <C-w>", "Insert cell in the console"),
+            entry("Left Right Home End", "Move the cursor in the input"),
+            entry("<C-w> <C-u>", "Delete word/all before the cursor"),
+            entry("Esc", "Cancel"),
+        ],
+    },
+];
+
+pub(crate) fn splash_entries() -> impl Iterator<Item = &'static HelpEntry> {
+    HELP_SECTIONS
+        .iter()
+        .flat_map(|section| section.entries)
+        .filter(|entry| entry.splash)
+}
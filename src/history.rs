@@ -0,0 +1,113 @@
+use std::fs;
+
+use crate::recent::state_file;
+
+const HISTORY_LIMIT: usize = 1000;
+
+/// The executed console commands, oldest first, which are kept across sessions in the state
+/// directory.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CommandHistory {
+    commands: Vec<String>,
+    /// Only a loaded history is written back, so e.g. batch runs don't touch the state file
+    persist: bool,
+}
+
+/// A command of the history, that is shown in the console instead of the typed input.
+#[derive(Clone, Debug)]
+pub(crate) struct HistoryRecall {
+    /// The input before the recall, which the recalled commands start with
+    pub(crate) prefix: String,
+    index: usize,
+}
+
+impl CommandHistory {
+    /// Reads the history from the state file. A missing or unreadable file results in an empty
+    /// history.
+    pub(crate) fn load() -> Self {
+        let mut commands = state_file("history")
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let excess = commands.len().saturating_sub(HISTORY_LIMIT);
+        commands.drain(..excess);
+        Self {
+            commands,
+            persist: true,
+        }
+    }
+
+    /// Appends `command`, unless it repeats the last one.
+    pub(crate) fn push(&mut self, command: &str) {
+        let command = command.trim();
+        if command.is_empty() || command.contains('\n') {
+            return;
+        }
+        if self.commands.last().is_some_and(|last| last == command) {
+            return;
+        }
+        self.commands.push(command.to_owned());
+        let excess = self.commands.len().saturating_sub(HISTORY_LIMIT);
+        self.commands.drain(..excess);
+        self.save();
+    }
+
+    /// The next older command, that starts with the prefix of `recall`, or with `input`, when
+    /// there is no recall yet.
+    pub(crate) fn older(
+        &self,
+        recall: Option<HistoryRecall>,
+        input: &str,
+    ) -> Option<HistoryRecall> {
+        let (prefix, end) = match recall {
+            Some(HistoryRecall { prefix, index }) => (prefix, index),
+            None => (input.to_owned(), self.commands.len()),
+        };
+        let index = self.commands[..end]
+            .iter()
+            .rposition(|command| command.starts_with(&prefix))?;
+        Some(HistoryRecall { prefix, index })
+    }
+
+    /// The next newer command, that starts with the prefix of `recall`. `None` means, that the
+    /// input is back at the typed prefix.
+    pub(crate) fn newer(&self, recall: &HistoryRecall) -> Option<HistoryRecall> {
+        let start = recall.index + 1;
+        let index = self.commands[start..]
+            .iter()
+            .position(|command| command.starts_with(&recall.prefix))?;
+        Some(HistoryRecall {
+            prefix: recall.prefix.clone(),
+            index: start + index,
+        })
+    }
+
+    pub(crate) fn command(&self, recall: &HistoryRecall) -> &str {
+        &self.commands[recall.index]
+    }
+
+    /// Writes the history to the state file. Failing to write it is ignored.
+    fn save(&self) {
+        if !self.persist {
+            return;
+        }
+        let Some(file) = state_file("history") else {
+            return;
+        };
+        if let Some(parent) = file.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let content = self
+            .commands
+            .iter()
+            .map(|command| format!("{command}\n"))
+            .collect::<String>();
+        let _ = fs::write(file, content);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashMap, str::FromStr};
+
+use color_eyre::eyre::bail;
+
+use crate::{
+    content::{CellLocation, CsvTable},
+    task::Progress,
+};
+
+/// Which rows `:join` keeps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum JoinKind {
+    /// Rows, whose key is in both tables
+    #[default]
+    Inner,
+    /// Every row of the left table
+    Left,
+    /// Every row of both tables
+    Outer,
+}
+
+impl FromStr for JoinKind {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s {
+            "inner" => JoinKind::Inner,
+            "left" => JoinKind::Left,
+            "outer" => JoinKind::Outer,
+            _ => bail!("Unknown join: {s}. Use `inner`, `left` or `outer`"),
+        };
+        Ok(res)
+    }
+}
+
+/// A table with its key column.
+pub(crate) struct JoinSide<'a> {
+    pub(crate) table: &'a CsvTable,
+    pub(crate) header: bool,
+    pub(crate) key: usize,
+}
+
+impl JoinSide<'_> {
+    fn col_count(&self) -> usize {
+        self.table.used_extent().col_count.max(self.key + 1)
+    }
+
+    fn row(&self, row: usize, col_count: usize) -> impl Iterator<Item = Option<String>> {
+        (0..col_count).map(move |col| self.table.get(CellLocation { row, col }).map(str::to_owned))
+    }
+
+    fn key(&self, row: usize) -> Option<&str> {
+        self.table
+            .get(CellLocation { row, col: self.key })
+            .filter(|key| !key.is_empty())
+    }
+
+    /// The header row or, without one, the column letters.
+    fn header_row(&self, col_count: usize) -> Vec<Option<String>> {
+        if self.header {
+            self.row(0, col_count).collect()
+        } else {
+            (0..col_count)
+                .map(|col| Some(CellLocation::col_index_to_letters(col)))
+                .collect()
+        }
+    }
+}
+
+/// Joins the rows of `left` and `right` with equal keys into rows with the columns of `left`,
+/// followed by those of `right` without its key. Empty keys match nothing. If one of the tables
+/// has a header, the result starts with a header row.
+pub(crate) fn join(
+    left: &JoinSide,
+    right: &JoinSide,
+    kind: JoinKind,
+    progress: &Progress,
+) -> color_eyre::Result<CsvTable> {
+    let left_cols = left.col_count();
+    let right_cols = right.col_count();
+    let right_row = |row| {
+        right
+            .row(row, right_cols)
+            .enumerate()
+            .filter(|(col, _)| *col != right.key)
+            .map(|(_, value)| value)
+    };
+    let left_start = usize::from(left.header);
+    let right_start = usize::from(right.header);
+    let left_rows = left.table.used_extent().row_count;
+    let right_rows = right.table.used_extent().row_count;
+
+    let mut index = HashMap::<&str, Vec<usize>>::new();
+    for row in right_start..right_rows {
+        if let Some(key) = right.key(row) {
+            index.entry(key).or_default().push(row);
+        }
+    }
+    let mut matched = vec![false; right_rows];
+    let mut rows = Vec::new();
+    if left.header || right.header {
+        let mut header = left.header_row(left_cols);
+        let right_header = right.header_row(right_cols);
+        header.extend(
+            right_header
+                .into_iter()
+                .enumerate()
+                .filter(|(col, _)| *col != right.key)
+                .map(|(_, name)| name),
+        );
+        rows.push(header);
+    }
+    for row in left_start..left_rows {
+        progress.report(row, left_rows + right_rows)?;
+        let matches = left.key(row).and_then(|key| index.get(key));
+        match matches {
+            Some(matches) => {
+                for &other in matches {
+                    matched[other] = true;
+                    rows.push(left.row(row, left_cols).chain(right_row(other)).collect());
+                }
+            }
+            None if kind == JoinKind::Inner => {}
+            None => rows.push(left.row(row, left_cols).collect()),
+        }
+    }
+    if kind == JoinKind::Outer {
+        for row in (right_start..right_rows).filter(|row| !matched[*row]) {
+            progress.report(left_rows + row, left_rows + right_rows)?;
+            let mut values = vec![None; left_cols];
+            values[left.key] = right.key(row).map(str::to_owned);
+            values.extend(right_row(row));
+            rows.push(values);
+        }
+    }
+    Ok(CsvTable::from_rows(rows, left.table.delimiter))
+}
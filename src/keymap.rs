@@ -0,0 +1,409 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+use color_eyre::eyre::{bail, eyre};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{
+    Combo,
+    config::{Binding, KeyStroke, parse_key},
+};
+
+/// What a key does in the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    CommandLine,
+    Search,
+    Help,
+    /// Starts a combo, that the next key completes
+    Combo(Combo),
+    /// Starts recording a macro or stops the recording
+    Record,
+    MoveLeft,
+    MoveDown,
+    MoveUp,
+    MoveRight,
+    /// The end of the data or the next data in the direction, like `Ctrl`+arrow in spreadsheets
+    DataEdgeLeft,
+    DataEdgeDown,
+    DataEdgeUp,
+    DataEdgeRight,
+    HalfPageLeft,
+    HalfPageDown,
+    HalfPageUp,
+    HalfPageRight,
+    ScrollLeft,
+    ScrollDown,
+    ScrollUp,
+    ScrollRight,
+    Center,
+    CenterRow,
+    CenterCol,
+    /// The first cell or, with a typed location, that cell
+    Goto,
+    /// The last non-empty cell of the column or, with a typed number, that row
+    GotoLastRow,
+    /// The bottom right cell of the used extent
+    GotoEnd,
+    GotoRowStart,
+    GotoColStart,
+    RestoreSelection,
+    /// The location before the last jump
+    JumpBack,
+    JumpForward,
+    NextChange,
+    PreviousChange,
+    NextResult,
+    PreviousResult,
+    SearchNext,
+    SearchPrevious,
+    ToggleVisual,
+    CellPreview,
+    Insert,
+    Change,
+    /// Edits the cell in `$EDITOR`
+    EditCell,
+    InsertRowBelow,
+    InsertRowAbove,
+    DeleteRows,
+    /// Inserts a copy of the selected rows below them
+    DuplicateRows,
+    /// Inserts a copy of the selected columns right of them
+    DuplicateCols,
+    Yank,
+    ClearYank,
+    Delete,
+    /// Deletes without touching the yank
+    Clear,
+    Paste,
+    /// Pastes yanked CSV text as a rectangle
+    PasteCsv,
+    /// Replaces the last paste with the next older yank
+    CyclePaste,
+    /// Copies the first row of the selection into its other rows
+    FillDown,
+    /// Copies the first column of the selection into its other columns
+    FillRight,
+    /// Adds the count to the numbers of the selection
+    Increment,
+    /// Subtracts the count from the numbers of the selection
+    Decrement,
+    /// Shows statistics of the selection or the current column
+    Stats,
+    /// Starts a `:pipe` command for the selection
+    Pipe,
+    Undo,
+    Redo,
+    /// Repeats the last edit
+    RepeatChange,
+}
+
+/// The names of the actions in the configuration.
+const ACTION_NAMES: [(&str, Action); 72] = [
+    ("command-line", Action::CommandLine),
+    ("search", Action::Search),
+    ("help", Action::Help),
+    ("view-combo", Action::Combo(Combo::View)),
+    ("goto-combo", Action::Combo(Combo::Goto)),
+    ("next-combo", Action::Combo(Combo::Next)),
+    ("previous-combo", Action::Combo(Combo::Previous)),
+    ("play-macro-combo", Action::Combo(Combo::Play)),
+    ("window-combo", Action::Combo(Combo::Window)),
+    ("mark-combo", Action::Combo(Combo::Mark)),
+    ("jump-to-mark-combo", Action::Combo(Combo::JumpToMark)),
+    ("register-combo", Action::Combo(Combo::Register)),
+    ("record-macro", Action::Record),
+    ("move-left", Action::MoveLeft),
+    ("move-down", Action::MoveDown),
+    ("move-up", Action::MoveUp),
+    ("move-right", Action::MoveRight),
+    ("data-edge-left", Action::DataEdgeLeft),
+    ("data-edge-down", Action::DataEdgeDown),
+    ("data-edge-up", Action::DataEdgeUp),
+    ("data-edge-right", Action::DataEdgeRight),
+    ("half-page-left", Action::HalfPageLeft),
+    ("half-page-down", Action::HalfPageDown),
+    ("half-page-up", Action::HalfPageUp),
+    ("half-page-right", Action::HalfPageRight),
+    ("scroll-left", Action::ScrollLeft),
+    ("scroll-down", Action::ScrollDown),
+    ("scroll-up", Action::ScrollUp),
+    ("scroll-right", Action::ScrollRight),
+    ("center", Action::Center),
+    ("center-row", Action::CenterRow),
+    ("center-col", Action::CenterCol),
+    ("goto", Action::Goto),
+    ("goto-last-row", Action::GotoLastRow),
+    ("goto-end", Action::GotoEnd),
+    ("goto-row-start", Action::GotoRowStart),
+    ("goto-col-start", Action::GotoColStart),
+    ("restore-selection", Action::RestoreSelection),
+    ("jump-back", Action::JumpBack),
+    ("jump-forward", Action::JumpForward),
+    ("next-change", Action::NextChange),
+    ("previous-change", Action::PreviousChange),
+    ("next-result", Action::NextResult),
+    ("previous-result", Action::PreviousResult),
+    ("search-next", Action::SearchNext),
+    ("search-previous", Action::SearchPrevious),
+    ("toggle-visual", Action::ToggleVisual),
+    ("cell-preview", Action::CellPreview),
+    ("insert", Action::Insert),
+    ("change", Action::Change),
+    ("edit-cell", Action::EditCell),
+    ("insert-row-below", Action::InsertRowBelow),
+    ("insert-row-above", Action::InsertRowAbove),
+    ("delete-rows", Action::DeleteRows),
+    ("duplicate-rows", Action::DuplicateRows),
+    ("duplicate-cols", Action::DuplicateCols),
+    ("yank", Action::Yank),
+    ("clear-yank", Action::ClearYank),
+    ("delete", Action::Delete),
+    ("clear", Action::Clear),
+    ("paste", Action::Paste),
+    ("paste-csv", Action::PasteCsv),
+    ("cycle-paste", Action::CyclePaste),
+    ("fill-down", Action::FillDown),
+    ("fill-right", Action::FillRight),
+    ("increment", Action::Increment),
+    ("decrement", Action::Decrement),
+    ("stats", Action::Stats),
+    ("pipe", Action::Pipe),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("repeat-change", Action::RepeatChange),
+];
+
+impl Action {
+    /// Whether `.` repeats the action.
+    pub(crate) fn is_change(self) -> bool {
+        matches!(
+            self,
+            Action::InsertRowBelow
+                | Action::InsertRowAbove
+                | Action::DeleteRows
+                | Action::DuplicateRows
+                | Action::DuplicateCols
+                | Action::Delete
+                | Action::Clear
+                | Action::Paste
+                | Action::PasteCsv
+                | Action::FillDown
+                | Action::FillRight
+                | Action::Increment
+                | Action::Decrement
+        )
+    }
+
+    /// E.g. `scroll-left`
+    pub(crate) fn name(self) -> &'static str {
+        ACTION_NAMES
+            .iter()
+            .find(|(_, action)| *action == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+}
+
+impl FromStr for Action {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ACTION_NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, action)| *action)
+            .ok_or_else(|| eyre!("Unknown action: {s}"))
+    }
+}
+
+/// The actions of the keys in the table, by the combo, that is pending.
+#[derive(Clone, Debug)]
+pub(crate) struct Keymap {
+    bindings: HashMap<(Option<Combo>, KeyStroke), Action>,
+}
+
+impl Keymap {
+    pub(crate) fn get(&self, combo: Option<Combo>, key: KeyEvent) -> Option<Action> {
+        self.bindings.get(&(combo, KeyStroke::from(key))).copied()
+    }
+
+    /// The keys, that complete `combo`, with their actions, ordered by key.
+    pub(crate) fn combo_keys(&self, combo: Combo) -> Vec<(String, Action)> {
+        let mut keys = self
+            .bindings
+            .iter()
+            .filter(|((key_combo, _), _)| *key_combo == Some(combo))
+            .map(|((_, key), action)| (key.to_string(), *action))
+            .collect::<Vec<_>>();
+        keys.sort_by(|(a, _), (b, _)| a.cmp(b));
+        keys
+    }
+
+    /// The keys of `action`, shortest first, with the key of its combo in front, e.g. `gg`.
+    pub(crate) fn keys_of(&self, action: Action) -> Vec<String> {
+        let mut keys = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            // Combos are looked up without a combo, which also ends the recursion
+            .filter(|((combo, _), _)| combo.is_none() || !matches!(action, Action::Combo(_)))
+            .filter_map(|((combo, key), _)| match combo {
+                None => Some(key.to_string()),
+                Some(combo) => {
+                    let prefix = self.keys_of(Action::Combo(*combo)).into_iter().next()?;
+                    Some(format!("{prefix}{key}"))
+                }
+            })
+            .collect::<Vec<_>>();
+        keys.sort_by(|a, b| a.chars().count().cmp(&b.chars().count()).then(a.cmp(b)));
+        keys
+    }
+
+    fn bind(&mut self, combo: Option<Combo>, key: KeyEvent, action: Action) {
+        self.bindings.insert((combo, KeyStroke::from(key)), action);
+    }
+
+    /// Takes the bindings of the configuration, e.g. `"<C-j>" = "half-page-down"` or, in the
+    /// table of a combo, `goto.e = "goto-col-start"`. The action `none` removes a binding.
+    pub(crate) fn apply(&mut self, bindings: &BTreeMap<String, Binding>) -> color_eyre::Result<()> {
+        for (key, binding) in bindings {
+            match binding {
+                Binding::Action(action) => self.apply_binding(None, key, action)?,
+                Binding::Combo(combo_bindings) => {
+                    let combo = match key.as_str() {
+                        "view" => Combo::View,
+                        "goto" => Combo::Goto,
+                        "next" => Combo::Next,
+                        "previous" => Combo::Previous,
+                        combo => bail!("Unknown combo: {combo}"),
+                    };
+                    for (key, action) in combo_bindings {
+                        self.apply_binding(Some(combo), key, action)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_binding(
+        &mut self,
+        combo: Option<Combo>,
+        key: &str,
+        action: &str,
+    ) -> color_eyre::Result<()> {
+        let key = parse_key(key)?;
+        if action == "none" {
+            self.bindings.remove(&(combo, KeyStroke::from(key)));
+        } else {
+            self.bind(combo, key, action.parse()?);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let char = |c| KeyEvent::from(KeyCode::Char(c));
+        let ctrl_key = |code| KeyEvent::new(code, KeyModifiers::CONTROL);
+        let ctrl = |c| ctrl_key(KeyCode::Char(c));
+        let alt = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT);
+        let defaults = [
+            (None, char(':'), Action::CommandLine),
+            (None, char('/'), Action::Search),
+            (None, char('?'), Action::Help),
+            (None, char('z'), Action::Combo(Combo::View)),
+            (None, char('g'), Action::Combo(Combo::Goto)),
+            (None, char(']'), Action::Combo(Combo::Next)),
+            (None, char('['), Action::Combo(Combo::Previous)),
+            (None, char('@'), Action::Combo(Combo::Play)),
+            (None, ctrl('w'), Action::Combo(Combo::Window)),
+            (None, char('m'), Action::Combo(Combo::Mark)),
+            (None, char('\''), Action::Combo(Combo::JumpToMark)),
+            (None, char('"'), Action::Combo(Combo::Register)),
+            (None, ctrl('o'), Action::JumpBack),
+            // Terminals send `Ctrl-i` as `Tab`
+            (None, KeyEvent::from(KeyCode::Tab), Action::JumpForward),
+            (None, ctrl('i'), Action::JumpForward),
+            (None, char('q'), Action::Record),
+            (None, char('h'), Action::MoveLeft),
+            (None, char('j'), Action::MoveDown),
+            (None, char('k'), Action::MoveUp),
+            (None, char('l'), Action::MoveRight),
+            (None, KeyEvent::from(KeyCode::Left), Action::MoveLeft),
+            (None, KeyEvent::from(KeyCode::Down), Action::MoveDown),
+            (None, KeyEvent::from(KeyCode::Up), Action::MoveUp),
+            (None, KeyEvent::from(KeyCode::Right), Action::MoveRight),
+            (None, ctrl_key(KeyCode::Left), Action::DataEdgeLeft),
+            (None, ctrl_key(KeyCode::Down), Action::DataEdgeDown),
+            (None, ctrl_key(KeyCode::Up), Action::DataEdgeUp),
+            (None, ctrl_key(KeyCode::Right), Action::DataEdgeRight),
+            (None, ctrl('h'), Action::DataEdgeLeft),
+            (None, ctrl('j'), Action::DataEdgeDown),
+            (None, ctrl('k'), Action::DataEdgeUp),
+            (None, ctrl('l'), Action::DataEdgeRight),
+            (None, char('G'), Action::GotoLastRow),
+            (None, char('H'), Action::HalfPageLeft),
+            (None, char('J'), Action::HalfPageDown),
+            (None, ctrl('d'), Action::HalfPageDown),
+            (None, ctrl('u'), Action::HalfPageUp),
+            (None, char('L'), Action::HalfPageRight),
+            (None, char('n'), Action::SearchNext),
+            (None, char('N'), Action::SearchPrevious),
+            (None, char('v'), Action::ToggleVisual),
+            (None, char('K'), Action::CellPreview),
+            (None, char('i'), Action::Insert),
+            (None, char('c'), Action::Change),
+            (None, char('E'), Action::EditCell),
+            (None, char('o'), Action::InsertRowBelow),
+            (None, char('O'), Action::InsertRowAbove),
+            (None, char('D'), Action::DeleteRows),
+            (None, alt('j'), Action::DuplicateRows),
+            (None, alt('l'), Action::DuplicateCols),
+            (None, char('y'), Action::Yank),
+            (None, char('Y'), Action::ClearYank),
+            (None, char('d'), Action::Delete),
+            (None, char('x'), Action::Clear),
+            (None, char('p'), Action::Paste),
+            (None, char('P'), Action::PasteCsv),
+            (None, ctrl('p'), Action::CyclePaste),
+            (None, char('F'), Action::FillDown),
+            (None, char('R'), Action::FillRight),
+            (None, ctrl('a'), Action::Increment),
+            (None, ctrl('x'), Action::Decrement),
+            (None, char('S'), Action::Stats),
+            (None, char('!'), Action::Pipe),
+            (None, char('u'), Action::Undo),
+            (None, char('U'), Action::Redo),
+            (None, ctrl('r'), Action::Redo),
+            (None, char('.'), Action::RepeatChange),
+            (Some(Combo::View), char('c'), Action::Center),
+            (Some(Combo::View), char('z'), Action::Center),
+            (Some(Combo::View), char('.'), Action::CenterRow),
+            (Some(Combo::View), char('|'), Action::CenterCol),
+            (Some(Combo::View), char('h'), Action::ScrollLeft),
+            (Some(Combo::View), char('j'), Action::ScrollDown),
+            (Some(Combo::View), char('k'), Action::ScrollUp),
+            (Some(Combo::View), char('l'), Action::ScrollRight),
+            (Some(Combo::Goto), char('g'), Action::Goto),
+            (Some(Combo::Goto), char('e'), Action::GotoEnd),
+            (Some(Combo::Goto), char('h'), Action::GotoRowStart),
+            (Some(Combo::Goto), char('k'), Action::GotoColStart),
+            (Some(Combo::Goto), char('v'), Action::RestoreSelection),
+            (Some(Combo::Next), char('c'), Action::NextChange),
+            (Some(Combo::Previous), char('c'), Action::PreviousChange),
+            (Some(Combo::Next), char('q'), Action::NextResult),
+            (Some(Combo::Previous), char('q'), Action::PreviousResult),
+        ];
+        let mut keymap = Self {
+            bindings: HashMap::new(),
+        };
+        for (combo, key, action) in defaults {
+            keymap.bind(combo, key, action);
+        }
+        keymap
+    }
+}
@@ -1,45 +1,156 @@
 mod buffer;
+mod cleanup;
+mod clipboard;
 pub(crate) mod color_ext;
+mod completion;
+mod compression;
+mod config;
 mod content;
+mod diff;
+mod download;
+mod editor;
+mod encoding;
+mod events;
+mod export;
+mod expr;
+mod filter;
+mod formula;
+mod help;
+mod history;
+mod join;
+mod keymap;
+mod notes;
+mod numeric;
+mod pipe;
+mod pivot;
+mod popup;
+mod recent;
+mod results;
+mod search;
+mod series;
+mod settings;
+mod sort;
+mod sql;
+mod substitute;
 pub(crate) mod symbols;
+mod task;
+#[cfg(test)]
+mod tests;
 pub(crate) mod undo;
 
 use clap::Parser;
 use color_eyre::{
     Result,
-    eyre::{bail, eyre},
+    eyre::{WrapErr, bail, eyre},
 };
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute,
+    terminal::{self, EnterAlternateScreen},
+};
+use encoding_rs::Encoding;
+use rand::{SeedableRng, rngs::StdRng};
 use ratatui::{
-    DefaultTerminal, Frame,
+    Frame, Terminal,
+    backend::Backend,
     buffer::Buffer,
     layout::{Alignment, Constraint, Direction, Layout, Position, Rect},
-    style::{Color, Style, Stylize},
-    widgets::{Block, Clear, Paragraph, Widget},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{
+        Block, Clear, LineGauge, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Widget,
+    },
 };
 use regex::Regex;
 use std::{
     borrow::Cow,
     cell::LazyCell,
-    fmt::{Debug, Display},
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::{Debug, Display, Write},
+    fs, io,
+    ops::Range,
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    buffer::{CsvBuffer, LoadOption, UndoAction, UndoChangeCellMode},
+    buffer::{BufferDefaults, BufferView, CsvBuffer, LoadOption, UndoAction, UndoChangeCellMode},
+    cleanup::Cleanup,
+    clipboard::ClipboardBackend,
     color_ext::ColorExt,
-    content::{CellLocation, CellRect},
+    completion::{Completion, CompletionScope},
+    config::{Config, KeyStroke},
+    content::{CellLocation, CellRect, CoordinateStyle, CsvTable, LoadSettings},
+    diff::RowDiff,
+    events::{CrosstermEvents, EventSource},
+    export::TextTableOptions,
+    expr::{Cells, Expr, WithValue},
+    filter::RowFilter,
+    formula::FormulaCells,
+    help::splash_entries,
+    history::{CommandHistory, HistoryRecall},
+    join::{JoinKind, JoinSide},
+    keymap::{Action, Keymap},
+    numeric::{AggregateCell, Histogram, Stats},
+    pivot::Pivot,
+    popup::{ComboHintWidget, Popup, PopupWidget, ValueCount},
+    recent::RecentFiles,
+    results::{ResultList, ResultListWidget},
+    search::{Search, SearchDirection},
+    settings::{SearchMode, Settings, on_off_from_str, on_off_to_str},
+    substitute::{SubstituteConfirm, Substitution},
+    task::{LongTask, Progress},
 };
 
 const LOGO: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/logo.txt"));
 const ROW_LABEL_WIDTH: u16 = 4;
+const YANK_ALL_WARN_CELL_COUNT: usize = 1_000_000;
+/// Number of yanks, that `Ctrl-p` cycles through after a paste
+const YANK_RING_LENGTH: usize = 16;
+const HISTOGRAM_DEFAULT_BINS: usize = 10;
+const HISTOGRAM_MAX_BINS: usize = 100;
+/// Default strftime format of `:now`
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// Default strftime format of `:today`
+const DATE_FORMAT: &str = "%Y-%m-%d";
+/// Interval, in which the progress of a background task is redrawn
+const TASK_TICK: Duration = Duration::from_millis(50);
+/// Rows, that one step of the mouse wheel scrolls
+const MOUSE_SCROLL_ROWS: usize = 3;
+/// Limit for macros, that play other macros or themselves
+const MACRO_MAX_DEPTH: usize = 100;
+const CONFIRM_PROMPT: &str = "replace? (y/n/a/q/l)";
+/// Maximum number of entries in the `:matches` list
+const MATCH_LIST_LIMIT: usize = 500;
+/// Time after a combo key, until the keys, that complete it, are shown
+const COMBO_HINT_DELAY: Duration = Duration::from_millis(500);
 
 fn main() -> color_eyre::Result<()> {
     let args = Args::parse();
     color_eyre::install()?;
-    let terminal = ratatui::init();
-    let result = App::new(terminal).run(args);
+    if args.no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        color_ext::set_monochrome(true);
+    }
+    let mut app = App::new();
+    let config = Config::load().and_then(|config| app.state.apply_config(&config));
+    if let Some(commands) = args.batch_commands()? {
+        config?;
+        return app.run_batch(args, &commands);
+    }
+    if let Err(err) = config {
+        app.state.console_message = Some(ConsoleMessage::error(format!("{err}")));
+    }
+    let mut terminal = ratatui::init();
+    execute!(io::stdout(), EnableMouseCapture)?;
+    app.state.recent_files = RecentFiles::load();
+    app.state.command_history = CommandHistory::load();
+    let result = app.run(&mut terminal, &mut CrosstermEvents, args);
+    let _ = execute!(io::stdout(), DisableMouseCapture);
     ratatui::restore();
     result
 }
@@ -47,7 +158,6 @@ fn main() -> color_eyre::Result<()> {
 /// The main application which holds the state and logic of the application.
 #[derive(Debug)]
 struct App {
-    terminal: DefaultTerminal,
     state: AppState,
 }
 
@@ -57,78 +167,347 @@ struct AppState {
     input: InputState,
     console_message: Option<ConsoleMessage>,
     table: Option<CsvBuffer>,
-    yank: Option<Yank>,
+    /// Open buffers besides the current one, in the order they are cycled through
+    hidden_buffers: VecDeque<CsvBuffer>,
+    /// Number of the last opened buffer
+    last_buffer_number: usize,
+    registers: Registers,
+    popup: Option<Popup>,
+    buffer_defaults: BufferDefaults,
+    settings: Settings,
+    recent_files: RecentFiles,
+    /// Executed console commands, that `Up` and `Down` recall
+    command_history: CommandHistory,
+    /// Recorded key events by register
+    macros: HashMap<char, Vec<KeyEvent>>,
+    recording: Option<MacroRecording>,
+    last_macro: Option<char>,
+    /// Number of macros, that are currently playing
+    macro_depth: usize,
+    /// Operation, that runs in the background
+    task: Option<LongTask>,
+    /// Commands run from a script without a terminal
+    batch: bool,
+    /// A second pane next to the current buffer
+    split: Option<Split>,
+    /// Keys from the configuration, that act like other keys in the table
+    key_remaps: HashMap<KeyStroke, KeyEvent>,
+    /// Actions of the keys in the table
+    keymap: Keymap,
+    /// The edit, that `.` repeats
+    last_change: Option<LastChange>,
+    /// Whether the keys of the pending combo are shown
+    combo_hint: bool,
+    /// The selected cell is edited in the external editor, once the event is handled
+    external_edit: bool,
+    /// Text, that is copied to the system clipboard through the terminal after the next render
+    clipboard: Option<String>,
+}
+
+/// Two panes, of which the focused one shows the current buffer.
+#[derive(Debug, Clone)]
+struct Split {
+    /// Side by side instead of above each other
+    vertical: bool,
+    /// Whether the focused pane is the right or lower one
+    focus_second: bool,
+    /// Number of the buffer in the other pane
+    other: usize,
+    /// View of the other pane, while it shows the current buffer as well
+    other_view: BufferView,
+}
+
+/// An edit, that `.` repeats at the selection.
+#[derive(Debug, Clone)]
+enum LastChange {
+    /// The key of an editing action with the count, that was typed before it
+    Key { key: KeyEvent, count: String },
+    /// Text, that was typed into a cell
+    CellInput(String),
+}
+
+/// A macro, that is recorded with `q<register>` until the next `q`.
+#[derive(Debug, Clone)]
+struct MacroRecording {
+    register: char,
+    keys: Vec<KeyEvent>,
 }
 
 impl App {
     /// Construct a new instance of [`App`].
-    pub fn new(terminal: DefaultTerminal) -> Self {
+    pub fn new() -> Self {
         Self {
-            terminal,
             state: Default::default(),
         }
     }
 
-    /// Run the application's main loop.
-    fn run(mut self, args: Args) -> Result<()> {
+    /// Run the application's main loop, until it quits or `events` has no more events.
+    fn run<B: Backend + ClipboardBackend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        events: &mut impl EventSource,
+        args: Args,
+    ) -> Result<()> {
         self.state.running = true;
-        self.terminal
-            .draw(|frame| frame.render_widget(SplashScreen, frame.area()))?;
+        terminal
+            .draw(|frame| frame.render_widget(SplashScreen(&self.state.keymap), frame.area()))?;
 
+        let append = args.append;
         if let Err(err) = self.try_init(args) {
             self.state.console_message = Some(ConsoleMessage::error(format!("{err}")));
+        } else if append {
+            // The view needs its dimensions from a first render
+            terminal.draw(|frame| self.state.render(frame))?;
+            self.start_append_row();
         }
         while self.state.running {
-            self.terminal.draw(|frame| self.state.render(frame))?;
-            if let Err(err) = self.handle_crossterm_events() {
+            terminal.draw(|frame| self.state.render(frame))?;
+            if let Some(text) = self.state.clipboard.take() {
+                terminal.backend_mut().copy_to_clipboard(&text)?;
+            }
+            if let Err(err) = self.handle_events(events) {
                 self.state.console_message = Some(ConsoleMessage::error(format!("{err}")));
             };
+            if let Err(err) = self.poll_task() {
+                self.state.console_message = Some(ConsoleMessage::error(format!("{err}")));
+            }
+            if self.state.external_edit {
+                self.state.external_edit = false;
+                if let Err(err) = self.edit_cell_externally(terminal) {
+                    self.state.console_message = Some(ConsoleMessage::error(format!("{err}")));
+                }
+            }
         }
         Ok(())
     }
 
-    /// Reads the crossterm events and updates the state of [`App`].
-    ///
-    /// If your application needs to perform work in between handling events, you can use the
-    /// [`event::poll`] function to check if there are any events available with a timeout.
-    fn handle_crossterm_events(&mut self) -> Result<()> {
-        match event::read()? {
+    /// Hands the terminal over to the external editor to edit the selected cell.
+    fn edit_cell_externally<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        let Some(table) = &self.state.table else {
+            return Ok(());
+        };
+        let content = table
+            .csv_table
+            .get(table.selection.primary)
+            .unwrap_or_default()
+            .to_owned();
+        let _ = execute!(io::stdout(), DisableMouseCapture);
+        ratatui::restore();
+        let edited = editor::edit_text(&content, &editor::editor());
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        terminal.clear()?;
+        let edited = edited?;
+        if edited != content {
+            self.commit_cell_input(edited)?;
+        }
+        Ok(())
+    }
+
+    /// Runs console commands without a terminal. Messages are printed to stderr and the error of
+    /// the first failing command is returned.
+    fn run_batch(mut self, args: Args, commands: &[String]) -> Result<()> {
+        self.state.running = true;
+        self.state.batch = true;
+        self.try_init(args)?;
+        for command in commands {
+            if !self.state.running {
+                break;
+            }
+            self.try_execute_batch_command(command)
+                .wrap_err_with(|| format!("Failed command: {command}"))?;
+        }
+        Ok(())
+    }
+
+    fn try_execute_batch_command(&mut self, command: &str) -> Result<()> {
+        self.try_execute_command(command)?;
+        if let Some(ConsoleMessage { severity, message }) = self.state.console_message.take() {
+            if severity == Severity::Error {
+                bail!("{message}");
+            }
+            eprintln!("{message}");
+        }
+        // Popups, prompts and cell input wait for keys, that never come
+        if self.state.popup.take().is_some() || !matches!(self.state.input, InputState::Main(_)) {
+            self.state.input = InputState::default();
+            bail!("Interactive commands can't run in batch mode!");
+        }
+        Ok(())
+    }
+
+    /// Applies the result of the background task, once it is finished.
+    fn poll_task(&mut self) -> Result<()> {
+        let Some(task) = &mut self.state.task else {
+            return Ok(());
+        };
+        let Some(res) = task.poll() else {
+            return Ok(());
+        };
+        let name = task.name;
+        self.state.task = None;
+        match res {
+            Ok(apply) => apply(&mut self.state),
+            Err(err) if err.is::<task::Cancelled>() => {
+                self.state.console_message =
+                    Some(ConsoleMessage::warning(format!("{name} cancelled")));
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads the next event and updates the state of [`App`].
+    fn handle_events(&mut self, events: &mut impl EventSource) -> Result<()> {
+        // While a task runs, the progress is redrawn regularly
+        if self.state.task.is_some() && !events.poll(TASK_TICK)? {
+            return Ok(());
+        }
+        // A pending combo shows its keys, if the next key takes a while
+        if let InputState::Main(InputModeMain { combo: Some(_), .. }) = self.state.input
+            && !self.state.combo_hint
+            && !events.poll(COMBO_HINT_DELAY)?
+        {
+            self.state.combo_hint = true;
+            return Ok(());
+        }
+        match events.read()? {
             // it's important to check KeyEventKind::Press to avoid handling key release events
-            Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key_event(key)?,
-            _ => {}
+            Some(Event::Key(key)) if key.kind == KeyEventKind::Press => self.on_key_event(key)?,
+            Some(Event::Mouse(mouse)) => self.on_mouse_event(mouse),
+            Some(_) => {}
+            None => self.quit(),
         }
         Ok(())
     }
 
+    /// Clicks select a cell, dragging selects a rectangle and the wheel scrolls the view. Clicking or
+    /// dragging a scrollbar scrolls to that point of it.
+    fn on_mouse_event(&mut self, mouse: MouseEvent) {
+        if self.state.task.is_some() || self.state.popup.is_some() {
+            return;
+        }
+        let InputState::Main(InputModeMain { mode, .. }) = &mut self.state.input else {
+            return;
+        };
+        let Some(table) = &mut self.state.table else {
+            return;
+        };
+        let position = Position::new(mouse.column, mouse.row);
+        let location = table.cell_at(position);
+        let horizontal = mouse.modifiers.contains(KeyModifiers::SHIFT);
+        // A drag, that starts on a scrollbar, keeps scrolling, even if it leaves the scrollbar
+        table.scrollbar_drag = match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => table.scrollbar_at(position),
+            MouseEventKind::Drag(MouseButton::Left) => table.scrollbar_drag,
+            _ => None,
+        };
+        if let Some(scrollbar) = table.scrollbar_drag {
+            table.scroll_to_scrollbar(scrollbar, position);
+            return;
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(location) = location else {
+                    return;
+                };
+                table.drop_selection();
+                *mode = MainMode::Normal;
+                table.move_selection_to(location);
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let Some(location) = location else {
+                    return;
+                };
+                if table.selection.opposite.is_none() {
+                    table.selection.opposite = Some(table.selection.primary);
+                    *mode = MainMode::Visual;
+                }
+                table.move_selection_to(location);
+            }
+            MouseEventKind::ScrollDown if horizontal => table.move_view(MoveDirection::Right, 1),
+            MouseEventKind::ScrollUp if horizontal => table.move_view(MoveDirection::Left, 1),
+            MouseEventKind::ScrollDown => table.move_view(MoveDirection::Down, MOUSE_SCROLL_ROWS),
+            MouseEventKind::ScrollUp => table.move_view(MoveDirection::Up, MOUSE_SCROLL_ROWS),
+            MouseEventKind::ScrollRight => table.move_view(MoveDirection::Right, 1),
+            MouseEventKind::ScrollLeft => table.move_view(MoveDirection::Left, 1),
+            _ => {}
+        }
+    }
+
     /// Handles the key events and updates the state of [`App`].
     fn on_key_event(&mut self, key: KeyEvent) -> Result<()> {
+        // The task works on a copy of the table, so nothing may change until it is finished
+        if let Some(task) = &self.state.task {
+            if key.code == KeyCode::Esc {
+                task.cancel();
+            }
+            return Ok(());
+        }
         self.state.console_message = None;
+        self.state.combo_hint = false;
+        // Keys of a playing macro are already recorded as the `@` that plays it
+        if let Some(recording) = &mut self.state.recording
+            && self.state.macro_depth == 0
+        {
+            recording.keys.push(key);
+        }
         if let (_, KeyCode::Esc) = (key.modifiers, key.code) {
-            if self.state.console_message.is_some() {
+            if self.state.popup.is_some() {
+                self.state.popup = None;
+            } else if let InputState::Confirm(_) = self.state.input {
+                self.handle_confirm_input(key);
+            } else if self.state.console_message.is_some() {
                 self.state.console_message = None;
             } else {
+                if let InputState::Main(InputModeMain {
+                    mode: MainMode::Visual,
+                    ..
+                }) = self.state.input
+                    && let Some(table) = &mut self.state.table
+                {
+                    table.drop_selection();
+                }
                 self.state.input = InputState::default();
             }
             return Ok(());
         }
+        if self.state.popup.is_some() {
+            self.handle_popup_input(key);
+            return Ok(());
+        }
+        // Remaps only apply to the table, not to typed text
+        let key = match self.state.input {
+            InputState::Main(_) => self
+                .state
+                .key_remaps
+                .get(&KeyStroke::from(key))
+                .copied()
+                .unwrap_or(key),
+            _ => key,
+        };
         match &self.state.input {
-            InputState::Main { .. } => match (key.modifiers, key.code) {
-                (_, KeyCode::Char(':')) => {
-                    self.state.input = InputState::Console(InputModeConsole {
-                        mode: ConsoleBarMode::Console,
-                        content: String::default(),
-                    })
+            InputState::Main(_) if self.state.table.is_some() => {
+                let res = self.handle_table_key_input(key);
+                if res.is_err() {
+                    self.state.input = Default::default();
+                    res?;
                 }
-                _ if self.state.table.is_some() => {
-                    let res = self.handle_table_key_input(key);
-                    if res.is_err() {
-                        self.state.input = Default::default();
-                        res?;
-                    }
+            }
+            // Without a table, only the console and the help work
+            InputState::Main(_) => match self.state.keymap.get(None, key) {
+                Some(Action::CommandLine) => {
+                    self.state.input = InputState::Console(InputModeConsole::new(
+                        ConsoleBarMode::Console,
+                        String::default(),
+                    ))
                 }
+                Some(Action::Help) => self.state.popup = Some(Popup::help()),
                 _ => {}
             },
             InputState::Console(_) => self.handle_console_input(key)?,
+            InputState::Confirm(_) => self.handle_confirm_input(key),
+            InputState::Results => self.handle_results_input(key),
         }
         Ok(())
     }
@@ -154,116 +533,298 @@ impl App {
         }
 
         let mut keep_combo = false;
+        let mut macro_to_play = None;
+        let mut pane_command = None;
+        let mut repeat_count = None;
 
+        let action = match (key.code, *combo) {
+            // Combos, that take any character as their argument
+            (KeyCode::Char(c), Some(Combo::Record)) => {
+                if !c.is_ascii_lowercase() {
+                    bail!("Invalid register: {c}");
+                }
+                self.state.recording = Some(MacroRecording {
+                    register: c,
+                    keys: Vec::new(),
+                });
+                None
+            }
+            (KeyCode::Char(c), Some(Combo::Play)) => {
+                let register = match c {
+                    '@' => self
+                        .state
+                        .last_macro
+                        .ok_or_else(|| eyre!("No previous macro!"))?,
+                    c if c.is_ascii_lowercase() => c,
+                    c => bail!("Invalid register: {c}"),
+                };
+                macro_to_play = Some((register, input_buffer.parse().unwrap_or(1)));
+                None
+            }
+            (KeyCode::Char(c), Some(Combo::Window)) => {
+                pane_command = Some(c);
+                None
+            }
+            (KeyCode::Char(c), Some(combo @ (Combo::Mark | Combo::JumpToMark))) => {
+                if !c.is_ascii_alphabetic() {
+                    bail!("Invalid mark: {c}");
+                }
+                let table = self.state.table.as_mut().unwrap();
+                if combo == Combo::Mark {
+                    table.marks.insert(c, table.selection.primary);
+                } else {
+                    let Some(location) = table.marks.get(&c) else {
+                        bail!("Mark {c} not set!");
+                    };
+                    table.jump_to(*location);
+                }
+                None
+            }
+            (KeyCode::Char(c), Some(Combo::Register)) => {
+                self.state.registers.selected = Some(Registers::parse(&c.to_string())?);
+                None
+            }
+            _ => self.state.keymap.get(*combo, key),
+        };
+        if let Some(action) = action
+            && action.is_change()
+        {
+            self.state.last_change = Some(LastChange::Key {
+                key,
+                count: input_buffer.clone(),
+            });
+        }
         let table = self.state.table.as_mut().unwrap();
-        match (key.modifiers, key.code, *combo) {
+        match action {
+            None => {}
+            Some(Action::RepeatChange) => repeat_count = Some(input_buffer.clone()),
+            Some(Action::CommandLine) => {
+                self.state.input = InputState::Console(InputModeConsole::new(
+                    ConsoleBarMode::Console,
+                    String::default(),
+                ))
+            }
+            Some(Action::Search) => {
+                self.state.input = InputState::Console(InputModeConsole::new(
+                    ConsoleBarMode::Search,
+                    String::default(),
+                ))
+            }
+            Some(Action::Help) => self.state.popup = Some(Popup::help()),
             // View
-            (_, KeyCode::Char('c' | 'z'), Some(Combo::View)) => {
+            Some(Action::Center) => {
                 table.center_primary_selection();
             }
-            (_, KeyCode::Char('h'), Some(Combo::View)) => {
+            Some(Action::CenterRow) => {
+                table.center_primary_selection_row();
+            }
+            Some(Action::CenterCol) => {
+                table.center_primary_selection_col();
+            }
+            Some(Action::ScrollLeft) => {
                 let num = input_buffer.parse().unwrap_or(1);
                 table.move_view(MoveDirection::Left, num);
             }
-            (_, KeyCode::Char('j'), Some(Combo::View)) => {
+            Some(Action::ScrollDown) => {
                 let num = input_buffer.parse().unwrap_or(1);
                 table.move_view(MoveDirection::Down, num);
             }
-            (_, KeyCode::Char('k'), Some(Combo::View)) => {
+            Some(Action::ScrollUp) => {
                 let num = input_buffer.parse().unwrap_or(1);
                 table.move_view(MoveDirection::Up, num);
             }
-            (_, KeyCode::Char('l'), Some(Combo::View)) => {
+            Some(Action::ScrollRight) => {
                 let num = input_buffer.parse().unwrap_or(1);
                 table.move_view(MoveDirection::Right, num);
             }
             // Goto
-            (_, KeyCode::Char('g'), Some(Combo::Goto)) => {
+            Some(Action::Goto) => {
                 if input_buffer.is_empty() {
-                    table.move_selection_to(CellLocation { row: 0, col: 0 });
+                    table.jump_to(CellLocation { row: 0, col: 0 });
                 } else {
                     let location_id = CsvJump::from_str(input_buffer)?;
                     let location = location_id.combine(table.selection.primary);
-                    table.move_selection_to(location);
+                    table.jump_to(location);
                 }
             }
-            (_, KeyCode::Char('h'), Some(Combo::Goto)) => {
+            Some(Action::GotoLastRow) => {
+                let col = table.selection.primary.col;
+                let row = if input_buffer.is_empty() {
+                    table.csv_table.last_row_in_col(col).unwrap_or_default()
+                } else {
+                    let row = input_buffer
+                        .parse::<usize>()
+                        .map_err(|_| eyre!("Invalid row: {input_buffer}"))?;
+                    row.saturating_sub(1)
+                };
+                table.jump_to(CellLocation { row, col });
+            }
+            Some(Action::GotoEnd) => table.jump_to(table.csv_table.last_cell()),
+            Some(Action::GotoRowStart) => {
                 table.move_selection_to(CellLocation {
                     row: table.selection.primary.row,
                     col: 0,
                 });
             }
-            (_, KeyCode::Char('k'), Some(Combo::Goto)) => {
+            Some(Action::JumpBack) => table.jump_back()?,
+            Some(Action::JumpForward) => table.jump_forward()?,
+            Some(Action::RestoreSelection) => {
+                if table.restore_selection() {
+                    *mode = MainMode::Visual;
+                }
+            }
+            Some(Action::GotoColStart) => {
                 table.move_selection_to(CellLocation {
                     row: 0,
                     col: table.selection.primary.col,
                 });
             }
+            // Changes
+            Some(action @ (Action::NextChange | Action::PreviousChange)) => {
+                let direction = if action == Action::NextChange {
+                    SearchDirection::Forward
+                } else {
+                    SearchDirection::Backward
+                };
+                self.state.console_message =
+                    next_changed_cell(table, direction, self.state.settings.wrapscan)?;
+            }
+            // Results
+            Some(action @ (Action::NextResult | Action::PreviousResult)) => {
+                let Some(results) = &mut table.results else {
+                    bail!("No results! Use `matches` or `changes`");
+                };
+                results.step(if action == Action::NextResult { 1 } else { -1 });
+                let location = results.selected();
+                self.state.console_message = Some(ConsoleMessage::new(results.status()));
+                if let Some(location) = location {
+                    table.jump_to(location);
+                }
+            }
             // No mode
-            (_, KeyCode::Char('z'), None) => {
-                *combo = Some(Combo::View);
-                keep_combo = true;
+            Some(Action::Record) => {
+                if let Some(mut recording) = self.state.recording.take() {
+                    // The `q`, that stopped the recording
+                    recording.keys.pop();
+                    self.state.console_message = Some(ConsoleMessage::new(format!(
+                        "Recorded @{} ({} keys)",
+                        recording.register,
+                        recording.keys.len()
+                    )));
+                    self.state.macros.insert(recording.register, recording.keys);
+                } else {
+                    *combo = Some(Combo::Record);
+                    keep_combo = true;
+                }
             }
-            (_, KeyCode::Char('g'), None) => {
-                *combo = Some(Combo::Goto);
-                *collect_all = true;
+            Some(Action::Combo(new_combo)) => {
+                *combo = Some(new_combo);
+                *collect_all = new_combo == Combo::Goto;
                 keep_combo = true;
             }
-            (_, KeyCode::Char('v'), None) => {
+            Some(Action::ToggleVisual) => {
                 if *mode == MainMode::Normal {
                     table.selection.opposite = Some(table.selection.primary);
                     *mode = MainMode::Visual
                 } else {
-                    table.selection.opposite = None;
+                    table.drop_selection();
                     *mode = MainMode::Normal
                 };
             }
-            (_, KeyCode::Char('H'), None) => {
+            Some(Action::DataEdgeLeft) => table.move_selection_to_data_edge(MoveDirection::Left),
+            Some(Action::DataEdgeDown) => table.move_selection_to_data_edge(MoveDirection::Down),
+            Some(Action::DataEdgeUp) => table.move_selection_to_data_edge(MoveDirection::Up),
+            Some(Action::DataEdgeRight) => table.move_selection_to_data_edge(MoveDirection::Right),
+            Some(Action::HalfPageLeft) => {
                 table.move_selection(MoveDirection::Left, table.visible_cols / 2);
             }
-            (KeyModifiers::CONTROL, KeyCode::Char('d'), None) | (_, KeyCode::Char('J'), None) => {
+            Some(Action::HalfPageDown) => {
                 table.move_selection(MoveDirection::Down, table.visible_rows / 2);
             }
-            (KeyModifiers::CONTROL, KeyCode::Char('u'), None) | (_, KeyCode::Char('K'), None) => {
+            Some(Action::HalfPageUp) => {
                 table.move_selection(MoveDirection::Up, table.visible_rows / 2);
             }
-            (_, KeyCode::Char('L'), None) => {
+            Some(Action::CellPreview) => {
+                self.state.popup = Some(Popup::cell_preview(table.selection.primary));
+            }
+            Some(Action::HalfPageRight) => {
                 table.move_selection(MoveDirection::Right, table.visible_cols / 2);
             }
-            (_, KeyCode::Char('h') | KeyCode::Left, None) => {
+            Some(Action::MoveLeft) => {
                 let num = input_buffer.parse().unwrap_or(1);
                 table.move_selection(MoveDirection::Left, num);
             }
-            (_, KeyCode::Char('j') | KeyCode::Down, None) => {
+            Some(Action::MoveDown) => {
                 let num = input_buffer.parse().unwrap_or(1);
                 table.move_selection(MoveDirection::Down, num);
             }
-            (_, KeyCode::Char('k') | KeyCode::Up, None) => {
+            Some(Action::MoveUp) => {
                 let num = input_buffer.parse().unwrap_or(1);
                 table.move_selection(MoveDirection::Up, num);
             }
-            (_, KeyCode::Char('l') | KeyCode::Right, None) => {
+            Some(Action::MoveRight) => {
                 let num = input_buffer.parse().unwrap_or(1);
                 table.move_selection(MoveDirection::Right, num);
             }
-            (_, KeyCode::Char('i'), None) => {
+            Some(Action::Insert) => {
                 let content = table
                     .csv_table
                     .get(table.selection.primary)
                     .unwrap_or_default();
-                self.state.input = InputState::Console(InputModeConsole {
-                    mode: ConsoleBarMode::CellInput,
-                    content: content.to_owned(),
+                self.state.input = InputState::Console(InputModeConsole::new(
+                    ConsoleBarMode::CellInput,
+                    content.to_owned(),
+                ));
+            }
+            Some(Action::EditCell) => self.state.external_edit = true,
+            Some(Action::Change) => {
+                self.state.input = InputState::Console(InputModeConsole::new(
+                    ConsoleBarMode::CellInput,
+                    Default::default(),
+                ));
+            }
+            // Rows
+            Some(action @ (Action::InsertRowBelow | Action::InsertRowAbove)) => {
+                let count = input_buffer.parse().unwrap_or(1);
+                let row = table.selection.primary.row;
+                let at = if action == Action::InsertRowBelow {
+                    row + 1
+                } else {
+                    row
+                };
+                table.insert_rows(at, count);
+                table.drop_selection();
+                *mode = MainMode::Normal;
+                table.move_selection_to(CellLocation {
+                    row: at,
+                    col: table.selection.primary.col,
                 });
             }
-            (_, KeyCode::Char('c'), None) => {
-                self.state.input = InputState::Console(InputModeConsole {
-                    mode: ConsoleBarMode::CellInput,
-                    content: Default::default(),
+            Some(action @ (Action::DuplicateRows | Action::DuplicateCols)) => {
+                let message = duplicate(table, action == Action::DuplicateRows);
+                *mode = MainMode::Normal;
+                self.state.console_message = Some(message);
+            }
+            Some(Action::DeleteRows) => {
+                let rect = table.selection.rect();
+                let count = if table.selection.opposite.is_some() {
+                    rect.row_count
+                } else {
+                    input_buffer.parse().unwrap_or(1)
+                };
+                let start = rect.top_left_cell_location.row;
+                let deleted = table.delete_rows(start, count);
+                table.drop_selection();
+                *mode = MainMode::Normal;
+                table.move_selection_to(CellLocation {
+                    row: start,
+                    col: table.selection.primary.col,
                 });
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{deleted} rows deleted")));
             }
-            (_, KeyCode::Char('Y'), None) => table.selection_yanked = None,
-            (_, KeyCode::Char('y'), None) => {
+            Some(Action::ClearYank) => table.selection_yanked = None,
+            Some(Action::Yank) => {
                 let Selection { primary, opposite } = table.selection;
                 let yank = if let Some(opposite) = opposite {
                     let content = table
@@ -278,11 +839,11 @@ impl App {
                     Yank::Single(content)
                 };
                 table.selection_yanked = Some(table.selection);
-                self.state.yank = Some(yank);
-                table.selection.opposite = None;
+                self.state.registers.set(yank);
+                table.drop_selection();
                 *mode = MainMode::Normal;
             }
-            (_, KeyCode::Char('d'), None) => {
+            Some(Action::Delete) => {
                 let Selection { primary, opposite } = table.selection;
                 let yank = if let Some(opposite) = opposite {
                     let rect = CellRect::from_opposite_cell_locations(primary, opposite);
@@ -309,56 +870,130 @@ impl App {
                     Yank::Single(from_value)
                 };
                 table.selection_yanked = None;
-                self.state.yank = Some(yank);
-                table.selection.opposite = None;
+                self.state.registers.set(yank);
+                table.drop_selection();
                 *mode = MainMode::Normal;
             }
-            (_, KeyCode::Char('p'), None) => {
-                let Selection { primary, opposite } = table.selection;
-                if let Some(yank) = &self.state.yank {
-                    match yank {
-                        Yank::Single(single) => {
-                            if let Some(opposite) = opposite {
-                                let rect =
-                                    CellRect::from_opposite_cell_locations(primary, opposite);
-                                let from_values = table
-                                    .csv_table
-                                    .set_rect(rect, std::iter::repeat(single.clone()));
-                                table.undo_stack.push(UndoAction::ChangeCells {
-                                    mode: buffer::UndoChangeCellMode::Fill,
-                                    rect,
-                                    values: from_values.clone(),
-                                });
-                            } else {
-                                let from_value = table.csv_table.set(primary, single.clone());
-                                table.undo_stack.push(UndoAction::ChangeCell {
-                                    mode: UndoChangeCellMode::Edit,
-                                    cell_location: primary,
-                                    value: from_value,
-                                });
-                            }
-                        }
-                        Yank::Rectangle { col_count, content } => {
-                            let rect = CellRect {
-                                top_left_cell_location: primary,
-                                col_count: *col_count,
-                                row_count: content.len() / col_count,
-                            };
-                            let from_values =
-                                table.csv_table.set_rect(rect, content.iter().cloned());
-                            table.undo_stack.push(UndoAction::ChangeCells {
-                                mode: buffer::UndoChangeCellMode::Edit,
-                                rect,
-                                values: from_values.clone(),
-                            });
-                        }
+            Some(Action::Clear) => {
+                let rect = if table.selection.opposite.is_some() {
+                    table.selection.rect()
+                } else {
+                    // Cells right of the used extent are empty anyway
+                    let primary = table.selection.primary;
+                    let max_count = table
+                        .csv_table
+                        .used_extent()
+                        .col_count
+                        .saturating_sub(primary.col);
+                    CellRect {
+                        top_left_cell_location: primary,
+                        col_count: input_buffer.parse().unwrap_or(1).clamp(1, max_count.max(1)),
+                        row_count: 1,
+                    }
+                };
+                table.delete_rect(rect);
+                table.drop_selection();
+                *mode = MainMode::Normal;
+            }
+            Some(action @ (Action::Paste | Action::PasteCsv)) => {
+                let registers = &mut self.state.registers;
+                if let Some(yank) = registers.get() {
+                    let selection = table.selection;
+                    let csv = action == Action::PasteCsv;
+                    if let Some(message) = paste(table, yank, csv)? {
+                        self.state.console_message = Some(message);
                     }
+                    // The ring starts with the unnamed register
+                    let next = usize::from(registers.selected.is_none());
+                    registers.paste_cycle = Some(PasteCycle {
+                        selection,
+                        csv,
+                        next,
+                    });
                     *mode = MainMode::Normal;
                 }
             }
-            (_, KeyCode::Char('U'), None) => table.redo(),
-            (_, KeyCode::Char('u'), None) => table.undo(),
-            _ => {}
+            Some(action @ (Action::FillDown | Action::FillRight)) => {
+                if table.selection.opposite.is_none() {
+                    bail!("Select the cells to fill!");
+                }
+                let direction = if action == Action::FillDown {
+                    MoveDirection::Down
+                } else {
+                    MoveDirection::Right
+                };
+                table.fill_rect(table.selection.rect(), direction);
+                table.drop_selection();
+                *mode = MainMode::Normal;
+            }
+            Some(action @ (Action::Increment | Action::Decrement)) => {
+                let count = input_buffer.parse::<i64>().unwrap_or(1);
+                let delta = if action == Action::Increment {
+                    count
+                } else {
+                    -count
+                };
+                let changed = table.map_selection(|value| numeric::increment(value?, delta));
+                if changed == 0 {
+                    bail!("No number in the selection!");
+                }
+                table.drop_selection();
+                *mode = MainMode::Normal;
+            }
+            Some(Action::Stats) => {
+                self.state.popup = Some(stats_popup(table, self.state.settings.coords));
+            }
+            Some(Action::Pipe) => {
+                self.state.input = InputState::Console(InputModeConsole::new(
+                    ConsoleBarMode::Console,
+                    "pipe ".to_owned(),
+                ))
+            }
+            Some(Action::CyclePaste) => {
+                let registers = &mut self.state.registers;
+                let Some(cycle) = &mut registers.paste_cycle else {
+                    bail!("Nothing pasted to cycle!");
+                };
+                if registers.ring.is_empty() {
+                    bail!("Nothing yanked!");
+                }
+                let index = cycle.next % registers.ring.len();
+                cycle.next = index + 1;
+                table.undo();
+                table.selection = cycle.selection;
+                paste(table, &registers.ring[index], cycle.csv)?;
+                self.state.console_message = Some(ConsoleMessage::new(format!(
+                    "Pasted yank {} of {}",
+                    index + 1,
+                    registers.ring.len()
+                )));
+                *mode = MainMode::Normal;
+            }
+            Some(action @ (Action::SearchNext | Action::SearchPrevious)) => {
+                let direction = if action == Action::SearchNext {
+                    SearchDirection::Forward
+                } else {
+                    SearchDirection::Backward
+                };
+                table.search_highlight = true;
+                self.state.console_message =
+                    search_next(table, direction, self.state.settings.wrapscan)?;
+            }
+            Some(Action::Redo) => table.redo(),
+            Some(Action::Undo) => table.undo(),
+        }
+        // The selected register lasts until the next action
+        if action.is_some_and(|action| !matches!(action, Action::Combo(_))) {
+            self.state.registers.selected = None;
+        }
+        // Only the paste, that was just done, may be cycled
+        if action.is_some_and(|action| {
+            !matches!(
+                action,
+                Action::Combo(_) | Action::Paste | Action::PasteCsv | Action::CyclePaste
+            )
+        }) {
+            self.state.registers.paste_cycle = None;
         }
         if let InputState::Main(InputModeMain {
             combo,
@@ -372,86 +1007,614 @@ impl App {
             *collect_all = Default::default();
             *input_buffer = Default::default();
         }
+        if let Some((register, count)) = macro_to_play {
+            self.play_macro(register, count)?;
+        }
+        if let Some(c) = pane_command {
+            self.state.pane_command(c)?;
+        }
+        if let Some(count) = repeat_count {
+            self.repeat_change(count)?;
+        }
         Ok(())
     }
 
-    fn handle_console_input(&mut self, key: KeyEvent) -> Result<()> {
-        let InputState::Console(InputModeConsole { mode, content }) = &mut self.state.input else {
-            unreachable!();
+    /// Repeats the last edit at the selection. A `count` replaces the count of the edit.
+    fn repeat_change(&mut self, count: String) -> Result<()> {
+        match self.state.last_change.clone() {
+            None => bail!("No change to repeat!"),
+            Some(LastChange::CellInput(content)) => self.commit_cell_input(content)?,
+            Some(LastChange::Key { key, count: typed }) => {
+                if let InputState::Main(main) = &mut self.state.input {
+                    main.input_buffer = if count.is_empty() { typed } else { count };
+                }
+                self.handle_table_key_input(key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Feeds the keys of a macro `count` times through [`Self::on_key_event`]. Stops at the first
+    /// error.
+    fn play_macro(&mut self, register: char, count: usize) -> Result<()> {
+        let Some(keys) = self.state.macros.get(&register).cloned() else {
+            bail!("Macro @{register} is empty!");
         };
-        match (key.modifiers, key.code) {
-            (_, KeyCode::Enter) => {
-                let content = content.clone();
-                let res = match mode {
-                    ConsoleBarMode::Console => self.try_execute_command(&content),
-                    ConsoleBarMode::CellInput => {
-                        if let Some(table) = &mut self.state.table {
-                            let from_value =
-                                table.csv_table.set(table.selection.primary, Some(content));
-                            table.undo_stack.push(UndoAction::ChangeCell {
-                                mode: UndoChangeCellMode::Edit,
-                                cell_location: table.selection.primary,
-                                value: from_value,
-                            });
-                        }
-                        Ok(())
-                    }
-                };
-                self.state.input = InputState::default();
-                res?;
+        if self.state.macro_depth >= MACRO_MAX_DEPTH {
+            bail!("Macro recursion too deep!");
+        }
+        self.state.last_macro = Some(register);
+        self.state.macro_depth += 1;
+        let res = self.play_keys(&keys, count);
+        self.state.macro_depth -= 1;
+        res
+    }
+
+    fn play_keys(&mut self, keys: &[KeyEvent], count: usize) -> Result<()> {
+        for _ in 0..count {
+            for key in keys {
+                self.on_key_event(*key)?;
             }
-            (m, KeyCode::Char(c)) => {
-                let c = if m == KeyModifiers::SHIFT {
-                    c.to_ascii_uppercase()
-                } else {
-                    c
-                };
-                content.push(c);
+        }
+        Ok(())
+    }
+
+    /// Keys of the focused results pane.
+    fn handle_results_input(&mut self, key: KeyEvent) {
+        let Some(table) = &mut self.state.table else {
+            self.state.input = InputState::default();
+            return;
+        };
+        let Some(results) = &mut table.results else {
+            self.state.input = InputState::default();
+            return;
+        };
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => results.move_cursor(1),
+            KeyCode::Char('k') | KeyCode::Up => results.move_cursor(-1),
+            KeyCode::Char('g') | KeyCode::Home => results.cursor = 0,
+            KeyCode::Char('G') | KeyCode::End => results.move_cursor(isize::MAX),
+            KeyCode::Enter => {
+                if let Some(location) = results.selected() {
+                    table.jump_to(location);
+                }
+                self.state.input = InputState::default();
             }
-            (_, KeyCode::Backspace) => {
-                content.pop();
+            KeyCode::Char('q') => {
+                table.results_open = false;
+                self.state.input = InputState::default();
             }
             _ => {}
         }
-        Ok(())
     }
 
-    fn try_execute_command(&mut self, command: &str) -> Result<()> {
-        let command_split = command
-            .split_whitespace()
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<_>>();
-        match &command_split[..] {
-            ["q!" | "quit!", ..] => {
-                self.quit();
+    fn handle_popup_input(&mut self, key: KeyEvent) {
+        let Some(popup) = &mut self.state.popup else {
+            unreachable!();
+        };
+        if let Popup::Note { location, .. } = popup
+            && let KeyCode::Char(c @ ('e' | 'd')) = key.code
+        {
+            let location = *location;
+            self.state.popup = None;
+            let Some(table) = &mut self.state.table else {
+                return;
+            };
+            if c == 'e' {
+                let note = table.notes.get(&location).map_or("", String::as_str);
+                self.state.input = InputState::Console(InputModeConsole::new(
+                    ConsoleBarMode::Console,
+                    format!("note {note}"),
+                ));
+            } else {
+                table.set_note(location, None);
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("Note of {location} deleted")));
             }
-            ["wq" | "x" | "write-quit", rest @ ..] => {
-                let file = rest.first().map(|f| PathBuf::from_str(f)).transpose()?;
-                if let Some(table) = &mut self.state.table {
-                    table.save(file, false)?;
-                };
-                self.quit();
+            return;
+        }
+        match key.code {
+            KeyCode::Enter if matches!(popup, Popup::Notes { .. } | Popup::Freq { .. }) => {
+                let location = popup.selected_location();
+                self.state.popup = None;
+                if let (Some(table), Some(location)) = (&mut self.state.table, location) {
+                    table.jump_to(location);
+                }
             }
-            ["wq!" | "x!" | "write-quit!", rest @ ..] => {
-                if let Some(table) = &mut self.state.table {
-                    let file = rest.first().map(|f| PathBuf::from_str(f)).transpose()?;
-                    table.save(file, true)?;
+            KeyCode::Enter if matches!(popup, Popup::Recent { .. }) => {
+                let Some(path) = popup.selected_path().cloned() else {
+                    return;
                 };
-                self.quit();
+                if !path.exists() {
+                    self.state.console_message = Some(ConsoleMessage::warning(format!(
+                        "{} doesn't exist anymore",
+                        path.to_string_lossy()
+                    )));
+                    return;
+                }
+                self.state.popup = None;
+                match CsvBuffer::load(
+                    LoadOption::File(path.clone()),
+                    None,
+                    &self.state.buffer_defaults,
+                ) {
+                    Ok(buffer) => {
+                        self.state.recent_files.push(&path);
+                        self.state.open_buffer(buffer);
+                    }
+                    Err(err) => {
+                        self.state.console_message = Some(ConsoleMessage::error(format!("{err}")));
+                    }
+                }
             }
-            ["q" | "quit", ..] => {
-                let Some(table) = &self.state.table else {
-                    self.quit();
-                    return Ok(());
-                };
-                if table.is_dirty() {
-                    bail!("There are unsaved changes! Use `quit!` to force quit!",);
+            KeyCode::Char('d') if matches!(popup, Popup::Recent { .. }) => {
+                if let Some(path) = popup.remove_selected_path() {
+                    self.state.recent_files.remove(&path);
                 }
-                self.quit();
             }
-            ["bc" | "buffer-close", ..] => {
-                let Some(table) = &self.state.table else {
-                    self.state.table = None;
+            KeyCode::Char('j') | KeyCode::Down => popup.scroll_down(1),
+            KeyCode::Char('k') | KeyCode::Up => popup.scroll_up(1),
+            _ => self.state.popup = None,
+        }
+    }
+
+    fn handle_console_input(&mut self, key: KeyEvent) -> Result<()> {
+        let InputState::Console(InputModeConsole {
+            mode,
+            content,
+            cursor,
+            insert_register,
+            completion,
+            history_recall,
+        }) = &mut self.state.input
+        else {
+            unreachable!();
+        };
+        if key.code == KeyCode::Tab
+            && *mode == ConsoleBarMode::Search
+            && let Some(table) = &self.state.table
+        {
+            // The first `Tab` completes from the column, the second one from the whole table
+            let settings = &self.state.settings;
+            let next = match completion.take() {
+                None => Completion::new(
+                    content.clone(),
+                    CompletionScope::Column(table.selection.primary.col),
+                    &table.csv_table,
+                    settings,
+                ),
+                Some(completion) if completion.scope != CompletionScope::Table => Completion::new(
+                    completion.original().to_owned(),
+                    CompletionScope::Table,
+                    &table.csv_table,
+                    settings,
+                ),
+                Some(mut completion) => {
+                    completion.next();
+                    completion
+                }
+            };
+            match next.input(settings) {
+                Some(input) => {
+                    *content = input;
+                    self.state.console_message = Some(ConsoleMessage::new(next.status()));
+                }
+                None => {
+                    *content = next.original().to_owned();
+                    self.state.console_message = Some(ConsoleMessage::warning(format!(
+                        "No completion in the {}",
+                        if next.scope == CompletionScope::Table {
+                            "table"
+                        } else {
+                            "column"
+                        }
+                    )));
+                }
+            }
+            *cursor = content.len();
+            *completion = Some(next);
+            return Ok(());
+        }
+        // Repeated `Tab`s cycle through the commands or files
+        if key.code == KeyCode::Tab && *mode == ConsoleBarMode::Console {
+            let next = match completion.take() {
+                Some(mut completion) => {
+                    completion.next();
+                    completion
+                }
+                None => Completion::command_line(content.clone()),
+            };
+            match next.input(&self.state.settings) {
+                Some(input) => {
+                    *content = input;
+                    self.state.console_message = Some(ConsoleMessage::new(next.status()));
+                }
+                None => {
+                    self.state.console_message = Some(ConsoleMessage::warning("No completion"));
+                }
+            }
+            *cursor = content.len();
+            *completion = Some(next);
+            return Ok(());
+        }
+        *completion = None;
+        let recall = history_recall.take();
+        if *insert_register {
+            *insert_register = false;
+            let (text, more_cells) = match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('w')) => {
+                    let Some(table) = &self.state.table else {
+                        bail!("No open buffer!");
+                    };
+                    let value = table.csv_table.get(table.selection.primary);
+                    (value.unwrap_or_default().to_owned(), false)
+                }
+                (_, KeyCode::Char(c)) => {
+                    let register = Registers::parse(&c.to_string())?;
+                    let Some(yank) = self.state.registers.yanks.get(&register) else {
+                        bail!("Register {register} is empty!");
+                    };
+                    let delimiter = self
+                        .state
+                        .table
+                        .as_ref()
+                        .and_then(|table| table.csv_table.delimiter);
+                    yank.first_cell(delimiter)?
+                }
+                _ => return Ok(()),
+            };
+            content.insert_str(*cursor, &text);
+            *cursor += text.len();
+            if more_cells {
+                self.state.console_message =
+                    Some(ConsoleMessage::warning("Inserted only the top left cell"));
+            }
+            return Ok(());
+        }
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('r')) => *insert_register = true,
+            // Only commands, that start with the typed input, are recalled
+            (_, KeyCode::Up) if *mode == ConsoleBarMode::Console => {
+                match self.state.command_history.older(recall.clone(), content) {
+                    Some(older) => {
+                        *content = self.state.command_history.command(&older).to_owned();
+                        *cursor = content.len();
+                        *history_recall = Some(older);
+                    }
+                    None => *history_recall = recall,
+                }
+            }
+            (_, KeyCode::Down) if *mode == ConsoleBarMode::Console => {
+                if let Some(recall) = recall {
+                    match self.state.command_history.newer(&recall) {
+                        Some(newer) => {
+                            *content = self.state.command_history.command(&newer).to_owned();
+                            *history_recall = Some(newer);
+                        }
+                        None => *content = recall.prefix,
+                    }
+                    *cursor = content.len();
+                }
+            }
+            (_, KeyCode::Enter) => {
+                let content = content.clone();
+                let mode = *mode;
+                if mode == ConsoleBarMode::Console {
+                    self.state.command_history.push(&content);
+                }
+                self.state.input = InputState::default();
+                let res = match mode {
+                    ConsoleBarMode::Console => self.try_execute_command(&content),
+                    ConsoleBarMode::Search => self.execute_search(&content),
+                    ConsoleBarMode::CellInput => {
+                        let res = self.commit_cell_input(content.clone());
+                        if res.is_err() {
+                            // The input stays, so it can be corrected
+                            self.state.input = InputState::Console(InputModeConsole::new(
+                                ConsoleBarMode::CellInput,
+                                content,
+                            ));
+                        }
+                        res
+                    }
+                };
+                res?;
+            }
+            // Commit and continue with the cell to the right
+            (_, KeyCode::Tab) if *mode == ConsoleBarMode::CellInput => {
+                let content = content.clone();
+                self.commit_cell_input(content)?;
+                if let Some(table) = &mut self.state.table {
+                    table.move_selection(MoveDirection::Right, 1);
+                    let content = table
+                        .csv_table
+                        .get(table.selection.primary)
+                        .unwrap_or_default();
+                    self.state.input = InputState::Console(InputModeConsole::new(
+                        ConsoleBarMode::CellInput,
+                        content.to_owned(),
+                    ));
+                }
+            }
+            (_, KeyCode::Left) => {
+                if let Some(c) = content[..*cursor].chars().next_back() {
+                    *cursor -= c.len_utf8();
+                }
+            }
+            (_, KeyCode::Right) => {
+                if let Some(c) = content[*cursor..].chars().next() {
+                    *cursor += c.len_utf8();
+                }
+            }
+            (_, KeyCode::Home) => *cursor = 0,
+            (_, KeyCode::End) => *cursor = content.len(),
+            // Deletes the word before the cursor
+            (KeyModifiers::CONTROL, KeyCode::Char('w')) => {
+                let before = content[..*cursor].trim_end_matches(char::is_whitespace);
+                let start = before
+                    .rfind(char::is_whitespace)
+                    .map_or(0, |index| index + 1);
+                content.replace_range(start..*cursor, "");
+                *cursor = start;
+            }
+            // Deletes everything before the cursor
+            (KeyModifiers::CONTROL, KeyCode::Char('u')) => {
+                content.replace_range(..*cursor, "");
+                *cursor = 0;
+            }
+            (m, KeyCode::Char(c)) => {
+                let c = if m == KeyModifiers::SHIFT {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                };
+                content.insert(*cursor, c);
+                *cursor += c.len_utf8();
+            }
+            (_, KeyCode::Backspace) => {
+                if let Some(c) = content[..*cursor].chars().next_back() {
+                    *cursor -= c.len_utf8();
+                    content.remove(*cursor);
+                }
+            }
+            (_, KeyCode::Delete) => {
+                if *cursor < content.len() {
+                    content.remove(*cursor);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Sets the primary cell. With `:set calc on`, input like `=17*12` is evaluated once and the
+    /// result is stored instead of the formula.
+    fn commit_cell_input(&mut self, content: String) -> Result<()> {
+        let Some(table) = &mut self.state.table else {
+            return Ok(());
+        };
+        self.state.last_change = Some(LastChange::CellInput(content.clone()));
+        let content = match formula::formula(&content) {
+            Some(expression) if self.state.settings.calc => {
+                expr::evaluate(expression, &table.csv_table)
+                    .map_err(|err| eyre!("{}", err.display_with(expression)))?
+                    .to_string()
+            }
+            _ => content,
+        };
+        if self.state.settings.autogrow {
+            // Widths are view state, so undoing the edit keeps the column wide
+            let text_width = content.lines().map(UnicodeWidthStr::width).max();
+            let width = (text_width.unwrap_or(0) + 2).min(self.state.settings.autogrow_max.into());
+            if table.grow_col(table.selection.primary.col, width as u16) {
+                table.ensure_selection_in_view();
+            }
+        }
+        let from_value = table.csv_table.set(table.selection.primary, Some(content));
+        table.undo_stack.push(UndoAction::ChangeCell {
+            mode: UndoChangeCellMode::Edit,
+            cell_location: table.selection.primary,
+            value: from_value,
+        });
+        Ok(())
+    }
+
+    /// Moves to the first column below the last used row and starts the cell input there.
+    fn start_append_row(&mut self) {
+        let Some(table) = &mut self.state.table else {
+            return;
+        };
+        table.drop_selection();
+        let row = table.csv_table.used_extent().row_count;
+        table.move_selection_to(CellLocation { row, col: 0 });
+        table.center_primary_selection_row();
+        self.state.input = InputState::Console(InputModeConsole::new(
+            ConsoleBarMode::CellInput,
+            String::new(),
+        ));
+    }
+
+    fn execute_search(&mut self, pattern: &str) -> Result<()> {
+        let Some(table) = &mut self.state.table else {
+            return Ok(());
+        };
+        // An empty pattern repeats the last search
+        if !pattern.is_empty() {
+            table.search = Some(Search::new(pattern, &self.state.settings)?);
+        }
+        table.search_highlight = true;
+        self.state.console_message = search_next(
+            table,
+            SearchDirection::Forward,
+            self.state.settings.wrapscan,
+        )?;
+        Ok(())
+    }
+
+    /// Handles the answer to `replace? (y/n/a/q/l)` of an interactive substitution.
+    fn handle_confirm_input(&mut self, key: KeyEvent) {
+        let InputState::Confirm(mut confirm) = std::mem::take(&mut self.state.input) else {
+            unreachable!();
+        };
+        let Some(table) = &mut self.state.table else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('y') => {
+                confirm.replace(&mut table.csv_table);
+                self.next_substitution(confirm);
+            }
+            KeyCode::Char('n') => {
+                confirm.skip(&table.csv_table);
+                self.next_substitution(confirm);
+            }
+            KeyCode::Char('a') => {
+                confirm.replace(&mut table.csv_table);
+                while confirm.advance(&table.csv_table).is_some() {
+                    confirm.replace(&mut table.csv_table);
+                }
+                self.finish_substitution(confirm);
+            }
+            KeyCode::Char('l') => {
+                confirm.replace(&mut table.csv_table);
+                self.finish_substitution(confirm);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.finish_substitution(confirm),
+            _ => self.state.input = InputState::Confirm(confirm),
+        }
+    }
+
+    /// Jumps to the next match of an interactive substitution or finishes it, if there is none.
+    fn next_substitution(&mut self, mut confirm: SubstituteConfirm) {
+        let Some(table) = &mut self.state.table else {
+            return;
+        };
+        match confirm.advance(&table.csv_table) {
+            Some((location, range)) => {
+                table.move_selection_to(location);
+                table.pending_match = Some((location, range));
+                self.state.input = InputState::Confirm(confirm);
+            }
+            None => self.finish_substitution(confirm),
+        }
+    }
+
+    /// Records all accepted replacements as one undo action.
+    fn finish_substitution(&mut self, confirm: SubstituteConfirm) {
+        let Some(table) = &mut self.state.table else {
+            return;
+        };
+        table.pending_match = None;
+        table.push_cell_list_undo(confirm.undo_values);
+        self.state.input = InputState::default();
+        self.state.console_message = Some(ConsoleMessage::new(format!(
+            "{} substitutions",
+            confirm.count
+        )));
+    }
+
+    /// Executes `:s/pattern/replacement/flags` on the selection or, with `:%s`, on the whole
+    /// table.
+    fn execute_substitute(&mut self, whole_table: bool, substitution: Substitution) -> Result<()> {
+        let Some(table) = &mut self.state.table else {
+            bail!("No open buffer!");
+        };
+        let rect = if whole_table {
+            table.csv_table.used_extent()
+        } else {
+            table.selection.rect()
+        };
+        if substitution.confirm {
+            let queue = table
+                .csv_table
+                .cells()
+                .filter(|(location, value)| {
+                    rect.contains(*location) && substitution.is_match(value)
+                })
+                .map(|(location, _)| location)
+                .collect::<VecDeque<_>>();
+            if queue.is_empty() {
+                bail!("Pattern not found: {}", substitution.pattern);
+            }
+            table.drop_selection();
+            self.next_substitution(SubstituteConfirm::new(substitution, queue));
+            return Ok(());
+        }
+        let cell_count = rect.col_count.saturating_mul(rect.row_count);
+        task::run_maybe_long(
+            &mut self.state,
+            "Substituting",
+            cell_count,
+            move |csv_table, progress| {
+                let (changes, count) = substitution.replace_in(csv_table, rect, progress)?;
+                if count == 0 {
+                    bail!("Pattern not found: {}", substitution.pattern);
+                }
+                Ok(Box::new(move |state: &mut AppState| {
+                    let cell_count = changes.len();
+                    if let Some(table) = &mut state.table {
+                        table.set_cells(changes);
+                    }
+                    state.console_message = Some(ConsoleMessage::new(format!(
+                        "{count} substitutions in {cell_count} cells"
+                    )));
+                    Ok(())
+                }))
+            },
+        )
+    }
+
+    fn try_execute_command(&mut self, command: &str) -> Result<()> {
+        let command = command.trim_start();
+        let (whole_table, rest) = match command.strip_prefix('%') {
+            Some(rest) => (true, rest),
+            None => (false, command),
+        };
+        if let Some(rest) = rest.strip_prefix('s')
+            && rest.starts_with(|c: char| !c.is_alphanumeric() && !c.is_whitespace())
+        {
+            return self.execute_substitute(
+                whole_table,
+                Substitution::parse(rest, &self.state.settings)?,
+            );
+        }
+        let command_split = command
+            .split_whitespace()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        match &command_split[..] {
+            ["q!" | "quit!", ..] => {
+                self.quit();
+            }
+            ["wq" | "x" | "write-quit", rest @ ..] => {
+                let file = rest.first().map(|f| PathBuf::from_str(f)).transpose()?;
+                if let Some(table) = &mut self.state.table {
+                    table.save(file, false)?;
+                };
+                self.quit();
+            }
+            ["wq!" | "x!" | "write-quit!", rest @ ..] => {
+                if let Some(table) = &mut self.state.table {
+                    let file = rest.first().map(|f| PathBuf::from_str(f)).transpose()?;
+                    table.save(file, true)?;
+                };
+                self.quit();
+            }
+            ["q" | "quit", ..] => {
+                let dirty = self
+                    .state
+                    .table
+                    .iter()
+                    .chain(&self.state.hidden_buffers)
+                    .any(CsvBuffer::is_dirty);
+                if dirty {
+                    bail!("There are unsaved changes! Use `quit!` to force quit!",);
+                }
+                self.quit();
+            }
+            ["bc" | "buffer-close", ..] => {
+                let Some(table) = &self.state.table else {
+                    self.state.table = None;
                     return Ok(());
                 };
                 if table.is_dirty() {
@@ -459,29 +1622,135 @@ impl App {
                         "There are unsaved changes! Use `buffer-close!` to force closing buffer!",
                     );
                 }
-                self.state.table = None;
+                self.state.table = self.state.hidden_buffers.pop_front();
+            }
+            ["o" | "open"] => {
+                let paths = self.state.recent_files.paths();
+                if paths.is_empty() {
+                    bail!("No recent files!");
+                }
+                self.state.popup = Some(Popup::recent(paths.to_vec()));
+            }
+            ["recent"] => {
+                self.state.popup = Some(Popup::recent(self.state.recent_files.paths().to_vec()));
+            }
+            ["registers"] => {
+                let registers = &self.state.registers.yanks;
+                if registers.is_empty() {
+                    bail!("Nothing yanked!");
+                }
+                let entries = registers
+                    .iter()
+                    .map(|(register, yank)| (*register, yank.summary()))
+                    .collect();
+                self.state.popup = Some(Popup::registers(entries));
+            }
+            ["o" | "open", url, rest @ ..] if download::is_url(url) => {
+                let delimiter = rest.first().and_then(|c| c.chars().next()).map(|c| c as u8);
+                self.state.open_url(url.to_string(), delimiter)?;
             }
             ["o" | "open", file, rest @ ..] => {
+                // An already open file is switched to
+                let open = self
+                    .state
+                    .table
+                    .iter()
+                    .chain(&self.state.hidden_buffers)
+                    .find(|buffer| buffer.file.as_deref() == Some(Path::new(file)));
+                if let Some(buffer) = open {
+                    self.state.switch_buffer(buffer.number)?;
+                    return Ok(());
+                }
                 let delimiter = rest.first().and_then(|c| c.chars().next()).map(|c| c as u8);
-                let res = CsvBuffer::load(LoadOption::File(PathBuf::from(file)), delimiter);
+                let res = CsvBuffer::load(
+                    LoadOption::File(PathBuf::from(file)),
+                    delimiter,
+                    &self.state.buffer_defaults,
+                );
                 match res {
-                    Ok(t) => self.state.table = Some(t),
+                    Ok(t) => {
+                        self.state.recent_files.push(Path::new(file));
+                        self.state.report_detected_delimiter(delimiter, &t);
+                        self.state.open_buffer(t);
+                    }
                     Err(err) => {
                         self.state.console_message = Some(ConsoleMessage::error(format!("{err}")));
                     }
                 }
             }
             ["n" | "new", ..] => {
+                self.state
+                    .open_buffer(CsvBuffer::new(&self.state.buffer_defaults));
+            }
+            ["bc!" | "buffer-close!", ..] => {
+                self.state.table = self.state.hidden_buffers.pop_front();
+            }
+            ["bn" | "buffer-next", ..] => {
+                let Some(next) = self.state.hidden_buffers.pop_front() else {
+                    bail!("No other buffer!");
+                };
+                if let Some(table) = self.state.table.replace(next) {
+                    self.state.hidden_buffers.push_back(table);
+                }
+            }
+            [split @ ("sp" | "split" | "vs" | "vsplit"), rest @ ..] => {
+                let number = match rest {
+                    [] => None,
+                    [number] => Some(
+                        number
+                            .parse::<usize>()
+                            .map_err(|_| eyre!("Invalid buffer number: {number}"))?,
+                    ),
+                    _ => bail!("Usage: {split} [buffer]"),
+                };
+                let vertical = matches!(*split, "vs" | "vsplit");
+                self.state.split(vertical, number)?;
+            }
+            ["only"] => self.state.split = None,
+            ["ls" | "buffers"] => {
                 if self.state.table.is_none() {
-                    self.state.table = Some(CsvBuffer::default())
+                    bail!("No buffer!");
                 }
+                self.state.console_message = Some(ConsoleMessage::new(self.state.buffer_list()));
             }
-            ["bc!" | "buffer-close!", ..] => {
-                self.state.table = None;
+            ["b" | "buffer", number] => {
+                let number = number
+                    .parse::<usize>()
+                    .map_err(|_| eyre!("Invalid buffer number: {number}"))?;
+                self.state.switch_buffer(number)?;
+            }
+            ["bp" | "buffer-previous", ..] => {
+                let Some(previous) = self.state.hidden_buffers.pop_back() else {
+                    bail!("No other buffer!");
+                };
+                if let Some(table) = self.state.table.replace(previous) {
+                    self.state.hidden_buffers.push_front(table);
+                }
+            }
+            ["echo", ..] => {
+                // The expression may contain whitespace, so it is taken from the raw command
+                let Some(expression) = command_argument(command) else {
+                    bail!("Usage: echo <expression>");
+                };
+                let empty = CsvTable::default();
+                let table = self.state.table.as_ref().map_or(&empty, |t| &t.csv_table);
+                let value = expr::evaluate(expression, table)
+                    .map_err(|err| eyre!("{}", err.display_with(expression)))?;
+                self.state.console_message = Some(ConsoleMessage::new(value.to_string()));
+            }
+            ["h" | "help", ..] => {
+                self.state.popup = Some(Popup::help());
+            }
+            ["set", name] => {
+                let value = self.state.get_option(name)?;
+                self.state.console_message = Some(ConsoleMessage::new(format!("{name}={value}")));
+            }
+            ["set", name, value] => {
+                self.state.set_option(name, value)?;
             }
             [c, ..] => {
                 let handled = if self.state.table.is_some() {
-                    self.handle_table_commands(&command_split)?
+                    self.handle_table_commands(command, &command_split)?
                 } else {
                     false
                 };
@@ -494,7 +1763,7 @@ impl App {
         Ok(())
     }
 
-    fn handle_table_commands(&mut self, command: &[&str]) -> Result<bool> {
+    fn handle_table_commands(&mut self, raw_command: &str, command: &[&str]) -> Result<bool> {
         let Some(table) = &mut self.state.table else {
             unreachable!();
         };
@@ -517,11 +1786,7 @@ impl App {
                 )))
             }
             ["delimiter"] => {
-                let message = match table.csv_table.delimiter {
-                    Some(b'\t') => r"\t".to_string(),
-                    Some(delim) => (delim as char).to_string(),
-                    None => "unset".to_string(),
-                };
+                let message = delimiter_name(table.csv_table.delimiter);
                 self.state.console_message = Some(ConsoleMessage::new(message));
             }
             ["delimiter", d, ..] => {
@@ -531,6 +1796,953 @@ impl App {
                     Some(delimiter_from_str(d)?)
                 };
             }
+            [cmd @ ("pad" | "pad-right"), width, rest @ ..] => {
+                let width = width
+                    .parse::<usize>()
+                    .map_err(|_| eyre!("Invalid width: {width}"))?;
+                let default_char = if *cmd == "pad" { '0' } else { ' ' };
+                let pad_char = rest
+                    .first()
+                    .map(|c| char_from_str(c))
+                    .transpose()?
+                    .unwrap_or(default_char);
+                let pad_right = *cmd == "pad-right";
+                let count = table.map_selection(|value| {
+                    let value = value?;
+                    let len = value.chars().count();
+                    if len >= width {
+                        return None;
+                    }
+                    let padding = pad_char.to_string().repeat(width - len);
+                    Some(if pad_right {
+                        format!("{value}{padding}")
+                    } else {
+                        format!("{padding}{value}")
+                    })
+                });
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{count} cells padded")));
+            }
+            [cmd @ ("trim" | "squeeze" | "upper" | "lower" | "title")] => {
+                let cleanup = cmd.parse::<Cleanup>()?;
+                let rect = table.selection_or_used_extent();
+                let count = table.map_rect(rect, |value| Some(cleanup.apply(value?)));
+                table.drop_selection();
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{count} cells changed")));
+            }
+            [cmd @ ("yank-all" | "yank-all!"), ..] => {
+                // The escape sequence for the clipboard would end up in the output
+                if self.state.batch {
+                    bail!("yank-all needs a terminal!");
+                }
+                let rect = table.selection_or_used_extent();
+                let cell_count = rect.col_count * rect.row_count;
+                if cell_count == 0 {
+                    bail!("Nothing to yank!");
+                }
+                if cell_count > YANK_ALL_WARN_CELL_COUNT && *cmd == "yank-all" {
+                    bail!("Yanking {cell_count} cells! Use `yank-all!` to yank anyway!");
+                }
+                let text = table.csv_table.rect_to_csv_string(rect)?;
+                self.state.console_message = Some(ConsoleMessage::new(format!(
+                    "Yanked {}x{} cells ({} bytes)",
+                    rect.col_count,
+                    rect.row_count,
+                    text.len()
+                )));
+                self.state.clipboard = Some(text.clone());
+                if table.selection.opposite.is_some() {
+                    table.selection_yanked = Some(table.selection);
+                    table.drop_selection();
+                }
+                self.state.registers.set(Yank::Text(text));
+            }
+            ["edit-cell"] => {
+                if self.state.batch {
+                    bail!("edit-cell needs a terminal!");
+                }
+                self.state.external_edit = true;
+            }
+            ["append-row"] => {
+                self.start_append_row();
+            }
+            ["note"] => {
+                let location = table.selection.primary;
+                if !table.notes.contains_key(&location) {
+                    bail!("No note at {location}! Use `note <text>` to add one");
+                }
+                self.state.popup = Some(Popup::note(location));
+            }
+            ["note", ..] => {
+                // The note may contain whitespace, so it is taken from the raw command
+                let note = command_argument(raw_command).unwrap_or_default();
+                table.set_note(table.selection.primary, Some(note.to_owned()));
+            }
+            ["fill", "series", step @ ..] => {
+                let step = match step {
+                    [] => None,
+                    [step] => Some(
+                        step.parse::<f64>()
+                            .ok()
+                            .filter(|step| step.is_finite())
+                            .ok_or_else(|| eyre!("Invalid step: {step}"))?,
+                    ),
+                    _ => bail!("Usage: fill series [step]"),
+                };
+                if table.selection.opposite.is_none() {
+                    bail!("Select the cells to fill!");
+                }
+                table.fill_series(table.selection.rect(), step)?;
+                table.drop_selection();
+            }
+            ["fill", direction] => {
+                let direction = match *direction {
+                    "down" => MoveDirection::Down,
+                    "right" => MoveDirection::Right,
+                    _ => bail!("Usage: fill down|right|series [step]"),
+                };
+                if table.selection.opposite.is_none() {
+                    bail!("Select the cells to fill!");
+                }
+                table.fill_rect(table.selection.rect(), direction);
+                table.drop_selection();
+            }
+            ["fill", ..] => bail!("Usage: fill down|right|series [step]"),
+            ["pipe", _, ..] => {
+                // The command may contain whitespace, so it is taken from the raw command
+                let command = command_argument(raw_command).unwrap_or_default().to_owned();
+                let rect = table.selection_or_used_extent();
+                let input = table.csv_table.rect_to_csv_string(rect)?;
+                let delimiter = table.csv_table.delimiter;
+                // How long it takes depends on the command, not on the number of cells
+                task::run_long(&mut self.state, "Piping", move |progress| {
+                    let output = pipe::pipe_through(&command, input, progress)?;
+                    let output = CsvTable::load(output.as_bytes(), delimiter)?;
+                    Ok(Box::new(move |state: &mut AppState| {
+                        let Some(table) = &mut state.table else {
+                            return Ok(());
+                        };
+                        let extent = output.used_extent();
+                        // Covers the selection and the output, so cells, that the output doesn't
+                        // reach, are cleared
+                        let target = CellRect {
+                            top_left_cell_location: rect.top_left_cell_location,
+                            col_count: rect.col_count.max(extent.col_count),
+                            row_count: rect.row_count.max(extent.row_count),
+                        };
+                        let values = (0..target.row_count).flat_map(|row| {
+                            let output = &output;
+                            (0..target.col_count).map(move |col| {
+                                output.get(CellLocation { row, col }).map(str::to_owned)
+                            })
+                        });
+                        let from_values = table.csv_table.set_rect(target, values);
+                        table.undo_stack.push(UndoAction::ChangeCells {
+                            mode: UndoChangeCellMode::Edit,
+                            rect: target,
+                            values: from_values,
+                        });
+                        table.drop_selection();
+                        state.console_message = Some(ConsoleMessage::new(format!(
+                            "Piped {}x{} cells, got {}x{}",
+                            rect.col_count, rect.row_count, extent.col_count, extent.row_count
+                        )));
+                        Ok(())
+                    }))
+                })?;
+            }
+            ["pipe"] => bail!("Usage: pipe <command>, e.g. `pipe sort -t, -k2`"),
+            ["paste-special", options @ ..] => {
+                let options = PasteOptions::parse(options)?;
+                let Some(yank) = self.state.registers.unnamed() else {
+                    bail!("Nothing yanked!");
+                };
+                let rect = paste_special(table, yank, options)?;
+                table.drop_selection();
+                self.state.console_message = Some(ConsoleMessage::new(format!(
+                    "Pasted {}x{} cells",
+                    rect.col_count, rect.row_count
+                )));
+            }
+            ["notes"] => {
+                if table.notes.is_empty() {
+                    bail!("No notes!");
+                }
+                let locations = table.notes.keys().copied().collect();
+                self.state.popup = Some(Popup::notes(locations));
+            }
+            ["export", "txt", file, options @ ..] => {
+                let options = TextTableOptions::parse(options)?;
+                let rect = table.selection_or_used_extent();
+                let file = PathBuf::from(file);
+                let cell_count = rect.col_count.saturating_mul(rect.row_count);
+                task::run_maybe_long(
+                    &mut self.state,
+                    "Exporting",
+                    cell_count,
+                    move |csv_table, progress| {
+                        let text = export::to_text_table(csv_table, rect, &options, progress)?;
+                        export::write_file(&file, &text, progress)?;
+                        Ok(Box::new(move |state: &mut AppState| {
+                            state.console_message = Some(ConsoleMessage::new(format!(
+                                "Exported {}x{} cells to {}",
+                                rect.col_count,
+                                rect.row_count,
+                                file.to_string_lossy()
+                            )));
+                            Ok(())
+                        }))
+                    },
+                )?;
+            }
+            ["export", ..] => bail!("Usage: export txt <file> [border] [sep=<s>] [max=<n>]"),
+            [
+                command @ ("write-yank" | "write-yank!"),
+                file,
+                register @ ..,
+            ] => {
+                let register = match register {
+                    [] => Registers::UNNAMED,
+                    [register] => Registers::parse(register)?,
+                    _ => bail!("Usage: write-yank <file> [register]"),
+                };
+                let Some(yank) = self.state.registers.yanks.get(&register) else {
+                    bail!("Register {register} is empty!");
+                };
+                let file = PathBuf::from(file);
+                if file.exists() && !command.ends_with('!') {
+                    bail!("File exists! Use `write-yank!` to overwrite it");
+                }
+                let text = yank.to_csv_text(table.csv_table.delimiter)?;
+                export::write_file(&file, &text, &Progress::none())?;
+                self.state.console_message = Some(ConsoleMessage::new(format!(
+                    "Yank written to {} ({} bytes)",
+                    file.to_string_lossy(),
+                    text.len()
+                )));
+            }
+            ["write-yank" | "write-yank!", ..] => bail!("Usage: write-yank <file> [register]"),
+            ["yank-txt", options @ ..] => {
+                if self.state.batch {
+                    bail!("yank-txt needs a terminal!");
+                }
+                let options = TextTableOptions::parse(options)?;
+                let rect = table.selection_or_used_extent();
+                if rect.col_count * rect.row_count == 0 {
+                    bail!("Nothing to yank!");
+                }
+                if table.selection.opposite.is_some() {
+                    table.selection_yanked = Some(table.selection);
+                    table.drop_selection();
+                }
+                task::run_maybe_long(
+                    &mut self.state,
+                    "Yanking",
+                    rect.col_count.saturating_mul(rect.row_count),
+                    move |csv_table, progress| {
+                        let text = export::to_text_table(csv_table, rect, &options, progress)?;
+                        Ok(Box::new(move |state: &mut AppState| {
+                            state.console_message = Some(ConsoleMessage::new(format!(
+                                "Yanked {}x{} cells as text ({} bytes)",
+                                rect.col_count,
+                                rect.row_count,
+                                text.len()
+                            )));
+                            state.clipboard = Some(text);
+                            Ok(())
+                        }))
+                    },
+                )?;
+            }
+            [cmd @ ("count" | "matches"), ..] => {
+                // The pattern may contain whitespace, so it is taken from the raw command
+                let search = match command_argument(raw_command) {
+                    Some(pattern) => Cow::Owned(Search::new(pattern, &self.state.settings)?),
+                    None => Cow::Borrowed(
+                        table
+                            .search
+                            .as_ref()
+                            .ok_or_else(|| eyre!("No previous search pattern!"))?,
+                    ),
+                };
+                let mut locations = Vec::new();
+                let mut count = 0;
+                let mut row_count = 0;
+                let mut last_row = None;
+                for (location, value) in table.csv_table.cells() {
+                    if !search.is_match(value) {
+                        continue;
+                    }
+                    if last_row != Some(location.row) {
+                        row_count += 1;
+                        last_row = Some(location.row);
+                    }
+                    if count < MATCH_LIST_LIMIT {
+                        locations.push(location);
+                    }
+                    count += 1;
+                }
+                if count == 0 {
+                    bail!("Pattern not found: {}", search.pattern);
+                }
+                if *cmd == "count" {
+                    self.state.console_message = Some(ConsoleMessage::new(format!(
+                        "{count} matches in {row_count} rows"
+                    )));
+                } else {
+                    let title = if count > locations.len() {
+                        format!("/{} · first {} of {count}", search.pattern, locations.len())
+                    } else {
+                        format!("/{} · {count} matches", search.pattern)
+                    };
+                    let entries = locations
+                        .into_iter()
+                        .map(|location| {
+                            let text = table.csv_table.get(location).unwrap_or_default();
+                            (location, text.to_owned())
+                        })
+                        .collect();
+                    table.results = Some(ResultList::new(title, entries));
+                    table.results_open = true;
+                }
+            }
+            ["stats"] => self.state.popup = Some(stats_popup(table, self.state.settings.coords)),
+            ["apply", col, _, ..] => {
+                let col = CellLocation::col_id_to_index(col)?;
+                // The expression may contain whitespace, so it is taken from the raw command
+                let expression = command_argument(raw_command)
+                    .and_then(command_argument)
+                    .unwrap_or_default();
+                let expr = Expr::parse(expression)
+                    .map_err(|err| eyre!("{}", err.display_with(expression)))?;
+                let start = usize::from(table.header);
+                let rect = CellRect {
+                    top_left_cell_location: CellLocation { row: start, col },
+                    col_count: 1,
+                    row_count: table
+                        .csv_table
+                        .used_extent()
+                        .row_count
+                        .saturating_sub(start),
+                };
+                let cells = FormulaCells::new(&table.csv_table);
+                let mut written = 0;
+                let values = (rect.top_left_cell_location.row..start + rect.row_count)
+                    .map(|row| {
+                        let location = CellLocation { row, col };
+                        // Empty cells stay empty instead of counting as 0
+                        if table.csv_table.get(location).is_none_or(str::is_empty) {
+                            return Ok(None);
+                        }
+                        written += 1;
+                        let value = cells.value(location).map_err(|err| eyre!(err))?;
+                        let with_value = WithValue {
+                            cells: &cells,
+                            value,
+                        };
+                        let result = expr
+                            .eval(&with_value)
+                            .map_err(|err| eyre!("{location}: {}", err.display_with(expression)))?;
+                        Ok(Some(result.to_string()))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let from_values = table.csv_table.set_rect(rect, values);
+                table.undo_stack.push(UndoAction::ChangeCells {
+                    mode: UndoChangeCellMode::Edit,
+                    rect,
+                    values: from_values,
+                });
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{written} cells written")));
+            }
+            ["apply", ..] => bail!("Usage: apply <col> <expression>, e.g. `apply B value * 1.2`"),
+            ["aggregate" | "agg", "off"] => table.aggregates.clear(),
+            ["aggregate" | "agg", rest @ ..] => {
+                let (col, function) = match rest {
+                    [function] => (table.selection.primary.col, *function),
+                    [col, function] => (CellLocation::col_id_to_index(col)?, *function),
+                    _ => bail!("Usage: aggregate [column] <function>|off"),
+                };
+                if function == "off" {
+                    table.aggregates.remove(&col);
+                } else {
+                    let aggregate = AggregateCell::new(function.parse()?);
+                    table.aggregates.insert(col, aggregate);
+                }
+            }
+            ["freq", rest @ ..] => {
+                let col = match rest {
+                    [] => table.selection.primary.col,
+                    [col] => CellLocation::col_id_to_index(col)?,
+                    _ => bail!("Usage: freq [column]"),
+                };
+                // The header is a label, not a value
+                let values = table.csv_table.column_values(col).enumerate();
+                let entries = value_counts(values.skip(usize::from(table.header)));
+                if entries.is_empty() {
+                    bail!("The column is empty!");
+                }
+                self.state.popup = Some(Popup::freq(col, entries));
+            }
+            ["hist", rest @ ..] => {
+                let col = rest
+                    .first()
+                    .map(|id| CellLocation::col_id_to_index(id))
+                    .transpose()?
+                    .unwrap_or(table.selection.primary.col);
+                let bins = rest
+                    .get(1)
+                    .map(|bins| {
+                        bins.parse::<usize>()
+                            .ok()
+                            .filter(|bins| (1..=HISTOGRAM_MAX_BINS).contains(bins))
+                            .ok_or_else(|| eyre!("Invalid number of bins: {bins}"))
+                    })
+                    .transpose()?
+                    .unwrap_or(HISTOGRAM_DEFAULT_BINS);
+                let histogram = Histogram::new(table.csv_table.column_values(col), bins)
+                    .ok_or_else(|| eyre!("No numeric values in the column!"))?;
+                self.state.popup = Some(Popup::histogram(col, histogram));
+            }
+            ["heatmap", "off"] => table.heatmaps.clear(),
+            ["heatmap", rest @ ..] => {
+                let (col, on) = match rest {
+                    [] | ["on"] => (table.selection.primary.col, true),
+                    [col] => (CellLocation::col_id_to_index(col)?, true),
+                    [col, on] => (CellLocation::col_id_to_index(col)?, on_off_from_str(on)?),
+                    _ => bail!("Usage: heatmap [column] [on|off]"),
+                };
+                if on {
+                    table.heatmaps.entry(col).or_default();
+                } else {
+                    table.heatmaps.remove(&col);
+                }
+            }
+            [cmd @ ("now" | "today"), ..] => {
+                let default_format = if *cmd == "now" {
+                    DATE_TIME_FORMAT
+                } else {
+                    DATE_FORMAT
+                };
+                let format = command_argument(raw_command).unwrap_or(default_format);
+                let mut stamp = String::new();
+                write!(stamp, "{}", chrono::Local::now().format(format))
+                    .map_err(|_| eyre!("Invalid date format: {format}"))?;
+                let count = table.map_selection(|_| Some(stamp.clone()));
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{count} cells stamped")));
+            }
+            ["uuid", ..] => {
+                let rect = table.selection.rect();
+                table.map_selection(|_| Some(uuid::Uuid::new_v4().to_string()));
+                self.state.console_message = Some(ConsoleMessage::new(format!(
+                    "{} cells written",
+                    rect.col_count * rect.row_count
+                )));
+            }
+            ["seq", rest @ ..] => {
+                let (order, numbers) = match rest {
+                    [numbers @ .., order @ ("row" | "col")] => (Some(*order), numbers),
+                    numbers => (None, numbers),
+                };
+                let [start, step] = [numbers.first(), numbers.get(1)].map(|n| {
+                    n.map(|n| {
+                        n.parse::<i64>()
+                            .map_err(|_| eyre!("Expected an integer: {n}"))
+                    })
+                });
+                if numbers.len() > 2 {
+                    bail!("Usage: seq [start] [step] [row|col]");
+                }
+                let start = start.transpose()?.unwrap_or(1);
+                let step = step.transpose()?.unwrap_or(1);
+                let row_major = order == Some("row");
+
+                // The cells are visited row-major
+                let rect = table.selection.rect();
+                let mut i = 0;
+                table.map_selection(|_| {
+                    let (row, col) = (i / rect.col_count, i % rect.col_count);
+                    let index = if row_major {
+                        i
+                    } else {
+                        col * rect.row_count + row
+                    };
+                    i += 1;
+                    Some(
+                        start
+                            .wrapping_add(step.wrapping_mul(index as i64))
+                            .to_string(),
+                    )
+                });
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{i} cells written")));
+            }
+            ["shuffle", rest @ ..] => {
+                let (seed, rest) = seed_argument(rest)?;
+                // The seed may be given without `seed=`
+                let seed = match rest[..] {
+                    [] => seed,
+                    [value] if seed.is_none() => Some(
+                        value
+                            .parse::<u64>()
+                            .map_err(|_| eyre!("Invalid seed: {value}"))?,
+                    ),
+                    _ => bail!("Usage: shuffle [seed]"),
+                };
+                let (start, row_count) = rows_below_header(table);
+                table.shuffle_rows(start, row_count, &mut rng_from_seed(seed));
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{row_count} rows shuffled")));
+            }
+            ["reverse"] => {
+                let (start, row_count) = rows_below_header(table);
+                table.reverse_rows(start, row_count);
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{row_count} rows reversed")));
+            }
+            ["sort", rest @ ..] => {
+                let keys = sort::parse_keys(rest)?;
+                let (start, row_count) = rows_below_header(table);
+                table.sort_rows(start, row_count, &keys);
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{row_count} rows sorted")));
+            }
+            ["sample", rest @ ..] => {
+                let (seed, rest) = seed_argument(rest)?;
+                let amount = match rest[..] {
+                    [amount] => Some(amount),
+                    _ => None,
+                };
+                let Some(amount) = amount else {
+                    bail!("Usage: sample <n|p%> [seed=<n>]");
+                };
+                // The header row is kept on top and isn't part of the sample
+                let header = usize::from(table.header);
+                let row_count = table
+                    .csv_table
+                    .used_extent()
+                    .row_count
+                    .saturating_sub(header);
+                let n = match amount.strip_suffix('%') {
+                    Some(percent) => {
+                        let percent = percent
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|p| (0.0..=100.0).contains(p))
+                            .ok_or_else(|| eyre!("Invalid percentage: {amount}"))?;
+                        (row_count as f64 * percent / 100.0).round() as usize
+                    }
+                    None => amount
+                        .parse::<usize>()
+                        .map_err(|_| eyre!("Invalid row count: {amount}"))?,
+                }
+                .min(row_count);
+
+                let mut rng = rng_from_seed(seed);
+                let mut rows = rand::seq::index::sample(&mut rng, row_count, n).into_vec();
+                rows.sort_unstable();
+                let rows: Vec<_> = (0..header)
+                    .chain(rows.into_iter().map(|row| row + header))
+                    .collect();
+                let mut sample = CsvBuffer::new(&self.state.buffer_defaults);
+                sample.csv_table = table.csv_table.rows_to_table(&rows);
+                sample.header = table.header;
+                self.state.console_message = Some(ConsoleMessage::new(format!(
+                    "Sampled {n} of {row_count} rows into a new buffer"
+                )));
+                self.state.open_buffer(sample);
+            }
+            ["join", number, left_col, right_col, kind @ ..] => {
+                let kind = match kind {
+                    [] => JoinKind::default(),
+                    [kind] => kind.parse()?,
+                    _ => bail!("Usage: join <buffer> <left-col> <right-col> [inner|left|outer]"),
+                };
+                let number = number
+                    .parse::<usize>()
+                    .map_err(|_| eyre!("Invalid buffer number: {number}"))?;
+                let left_key = CellLocation::col_id_to_index(left_col)?;
+                let right_key = CellLocation::col_id_to_index(right_col)?;
+                let left_header = table.header;
+                let left_extent = table.csv_table.used_extent();
+                let Some(right) = self
+                    .state
+                    .table
+                    .iter()
+                    .chain(&self.state.hidden_buffers)
+                    .find(|buffer| buffer.number == number)
+                else {
+                    bail!("No buffer {number}!");
+                };
+                let right_header = right.header;
+                let right_table = right.csv_table.clone();
+                let right_extent = right_table.used_extent();
+                let cell_count = (left_extent.col_count * left_extent.row_count)
+                    .saturating_add(right_extent.col_count * right_extent.row_count);
+                task::run_maybe_long(
+                    &mut self.state,
+                    "Joining",
+                    cell_count,
+                    move |csv_table, progress| {
+                        let left = JoinSide {
+                            table: csv_table,
+                            header: left_header,
+                            key: left_key,
+                        };
+                        let right = JoinSide {
+                            table: &right_table,
+                            header: right_header,
+                            key: right_key,
+                        };
+                        let joined = join::join(&left, &right, kind, progress)?;
+                        Ok(Box::new(move |state: &mut AppState| {
+                            let header = left_header || right_header;
+                            let row_count = (joined.used_extent().row_count)
+                                .saturating_sub(usize::from(header));
+                            let mut buffer = CsvBuffer::new(&state.buffer_defaults);
+                            buffer.csv_table = joined;
+                            buffer.header = header;
+                            state.console_message =
+                                Some(ConsoleMessage::new(format!("{row_count} rows joined")));
+                            state.open_buffer(buffer);
+                            Ok(())
+                        }))
+                    },
+                )?;
+            }
+            ["join", ..] => bail!("Usage: join <buffer> <left-col> <right-col> [inner|left|outer]"),
+            ["pivot", row_col, col_col, value_col, aggregate] => {
+                let pivot = Pivot {
+                    row_col: CellLocation::col_id_to_index(row_col)?,
+                    col_col: CellLocation::col_id_to_index(col_col)?,
+                    value_col: CellLocation::col_id_to_index(value_col)?,
+                    aggregate: aggregate.parse()?,
+                };
+                let header = table.header;
+                let extent = table.csv_table.used_extent();
+                task::run_maybe_long(
+                    &mut self.state,
+                    "Pivoting",
+                    extent.col_count.saturating_mul(extent.row_count),
+                    move |csv_table, progress| {
+                        let pivoted = pivot.build(csv_table, header, progress)?;
+                        Ok(Box::new(move |state: &mut AppState| {
+                            let extent = pivoted.used_extent();
+                            let mut buffer = CsvBuffer::new(&state.buffer_defaults);
+                            buffer.csv_table = pivoted;
+                            buffer.header = true;
+                            state.console_message = Some(ConsoleMessage::new(format!(
+                                "Pivot table with {}x{} cells",
+                                extent.col_count - 1,
+                                extent.row_count - 1
+                            )));
+                            state.open_buffer(buffer);
+                            Ok(())
+                        }))
+                    },
+                )?;
+            }
+            ["pivot", ..] => bail!("Usage: pivot <row-col> <col-col> <value-col> <aggregate>"),
+            ["sql", _, ..] => {
+                // The query may contain whitespace, so it is taken from the raw command
+                let query = command_argument(raw_command).unwrap_or_default().to_owned();
+                let header = table.header;
+                let extent = table.csv_table.used_extent();
+                task::run_maybe_long(
+                    &mut self.state,
+                    "Querying",
+                    extent.col_count.saturating_mul(extent.row_count),
+                    move |csv_table, progress| {
+                        let result = sql::query(csv_table, header, &query, progress)?;
+                        Ok(Box::new(move |state: &mut AppState| {
+                            let row_count = result.used_extent().row_count.saturating_sub(1);
+                            let mut buffer = CsvBuffer::new(&state.buffer_defaults);
+                            buffer.csv_table = result;
+                            buffer.header = true;
+                            state.console_message =
+                                Some(ConsoleMessage::new(format!("{row_count} rows")));
+                            state.open_buffer(buffer);
+                            Ok(())
+                        }))
+                    },
+                )?;
+            }
+            ["sql"] => bail!(
+                "Usage: sql <query>, e.g. `sql SELECT * FROM {}`",
+                sql::TABLE_NAME
+            ),
+            ["diff", file] => {
+                let settings = LoadSettings {
+                    faithful: false,
+                    ..table.csv_table.load_settings()
+                };
+                let (read, _) = compression::open(Path::new(file))?;
+                let old = CsvTable::load_with(read, table.csv_table.delimiter, settings)?;
+                let buffer = CsvBuffer::diff(&old, &table.csv_table, &self.state.buffer_defaults);
+                if let Some(diff) = &buffer.diff {
+                    self.state.console_message = Some(ConsoleMessage::new(diff.summary()));
+                }
+                self.state.open_buffer(buffer);
+            }
+            ["diff", ..] => {
+                if table.csv_table.changed_cells().is_empty() {
+                    bail!("No changed cells!");
+                }
+                self.state.popup = Some(Popup::diff());
+            }
+            ["changes"] => {
+                let changed = table.csv_table.changed_cells();
+                if changed.is_empty() {
+                    bail!("No changed cells!");
+                }
+                let title = format!("{} changed cells", changed.len());
+                let entries = changed
+                    .into_iter()
+                    .map(|location| {
+                        let saved = table
+                            .csv_table
+                            .saved_value(location)
+                            .flatten()
+                            .unwrap_or_default();
+                        let current = table.csv_table.get(location).unwrap_or_default();
+                        (location, format!("{saved} → {current}"))
+                    })
+                    .collect();
+                table.results = Some(ResultList::new(title, entries));
+                table.results_open = true;
+            }
+            ["check"] => {
+                let majority = table.csv_table.majority_field_count();
+                let ragged = table.csv_table.ragged_rows();
+                if ragged.is_empty() {
+                    self.state.console_message = Some(ConsoleMessage::success(format!(
+                        "No ragged rows, all have {majority} fields"
+                    )));
+                    return Ok(true);
+                }
+                let title = format!("{} ragged rows · most have {majority} fields", ragged.len());
+                let entries = ragged
+                    .into_iter()
+                    .map(|row| {
+                        let count = table.csv_table.field_count(row);
+                        (CellLocation { row, col: 0 }, format!("{count} fields"))
+                    })
+                    .collect();
+                table.results = Some(ResultList::new(title, entries));
+                table.results_open = true;
+            }
+            ["fix-ragged", fix] => {
+                let count = table.fix_ragged(fix.parse()?);
+                if count == 0 {
+                    bail!("No ragged rows!");
+                }
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("Fixed {count} rows")));
+            }
+            ["fix-ragged", ..] => bail!("Usage: fix-ragged pad|truncate"),
+            ["col-insert", rest @ ..] => {
+                let col = rest
+                    .first()
+                    .map(|id| CellLocation::col_id_to_index(id))
+                    .transpose()?
+                    .unwrap_or(table.selection.primary.col);
+                let count = rest
+                    .get(1)
+                    .map(|count| {
+                        count
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or_else(|| eyre!("Invalid column count: {count}"))
+                    })
+                    .transpose()?
+                    .unwrap_or(1);
+                table.insert_cols(col, count);
+            }
+            ["split-col", col, _, ..] => {
+                let col = CellLocation::col_id_to_index(col)?;
+                // The separator may be whitespace, so it is taken from the raw command
+                let separator = command_argument(raw_command)
+                    .and_then(command_argument)
+                    .unwrap_or_default();
+                let (regex, _) = search::build_regex(separator, &self.state.settings, None)?;
+                let row_count = table.csv_table.used_extent().row_count;
+                let mut cols = (0..row_count)
+                    .map(|row| {
+                        let value = table.csv_table.get(CellLocation { row, col });
+                        match value {
+                            Some(value) if !(table.header && row == 0) => regex
+                                .split(value)
+                                .map(|part| Some(part.to_owned()))
+                                .collect(),
+                            value => vec![value.map(str::to_owned)],
+                        }
+                    })
+                    .collect::<Vec<Vec<_>>>();
+                let count = cols.iter().map(Vec::len).max().unwrap_or(0);
+                if count < 2 {
+                    bail!("Separator not found: {separator}");
+                }
+                // The new columns are named after the split column
+                if table.header
+                    && let Some(header) = cols.first_mut()
+                {
+                    let name = header[0].clone().unwrap_or_default();
+                    header.extend((2..=count).map(|n| Some(format!("{name} {n}"))));
+                }
+                table.replace_cols(col, 1, count, cols);
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("Split into {count} columns")));
+            }
+            ["split-col", ..] => bail!("Usage: split-col <col> <separator>"),
+            [cmd @ ("dup-row" | "dup-col")] => {
+                self.state.console_message = Some(duplicate(table, *cmd == "dup-row"));
+            }
+            ["transpose"] => {
+                let rect = table.selection_or_used_extent();
+                task::run_maybe_long(
+                    &mut self.state,
+                    "Transposing",
+                    rect.col_count.saturating_mul(rect.row_count),
+                    move |csv_table, progress| {
+                        let transposed = buffer::transposed(csv_table, rect, progress)?;
+                        Ok(Box::new(move |state: &mut AppState| {
+                            let Some(table) = &mut state.table else {
+                                return Ok(());
+                            };
+                            let transposed = table.transpose_rect(rect, transposed);
+                            table.drop_selection();
+                            state.console_message = Some(ConsoleMessage::new(format!(
+                                "Transposed into {}x{} cells",
+                                transposed.col_count, transposed.row_count
+                            )));
+                            Ok(())
+                        }))
+                    },
+                )?;
+            }
+            ["merge-cols", range, ..] => {
+                let (start, count) = col_range(range)?;
+                if count < 2 {
+                    bail!("Give at least two columns, e.g. `B:D`!");
+                }
+                // The separator may contain whitespace, so it is taken from the raw command
+                let separator = command_argument(raw_command)
+                    .and_then(command_argument)
+                    .unwrap_or(" ");
+                let row_count = table.csv_table.used_extent().row_count;
+                let cols = (0..row_count)
+                    .map(|row| {
+                        let values = (start..start + count)
+                            .filter_map(|col| table.csv_table.get(CellLocation { row, col }))
+                            .filter(|value| !value.is_empty());
+                        // The header keeps the name of the first column
+                        let value = if table.header && row == 0 {
+                            values.take(1).collect::<String>()
+                        } else {
+                            values.collect::<Vec<_>>().join(separator)
+                        };
+                        vec![Some(value).filter(|value| !value.is_empty())]
+                    })
+                    .collect();
+                table.replace_cols(start, count, 1, cols);
+                table.drop_selection();
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{count} columns merged")));
+            }
+            ["merge-cols", ..] => bail!("Usage: merge-cols <cols> [separator], e.g. `B:D -`"),
+            ["col-delete", rest @ ..] => {
+                // A range like `C:E`, or else the columns of the selection
+                let (start, count) = match rest.first() {
+                    Some(range) => col_range(range)?,
+                    None => {
+                        let rect = table.selection.rect();
+                        (rect.top_left_cell_location.col, rect.col_count)
+                    }
+                };
+                table.delete_cols(start, count);
+                table.drop_selection();
+                self.state.console_message =
+                    Some(ConsoleMessage::new(format!("{count} columns deleted")));
+            }
+            [cmd @ ("row-insert" | "row-delete"), rest @ ..] => {
+                let count = rest
+                    .first()
+                    .map(|count| {
+                        count
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or_else(|| eyre!("Invalid row count: {count}"))
+                    })
+                    .transpose()?
+                    .unwrap_or(1);
+                let row = table.selection.primary.row;
+                if *cmd == "row-insert" {
+                    table.insert_rows(row, count);
+                } else {
+                    let deleted = table.delete_rows(row, count);
+                    self.state.console_message =
+                        Some(ConsoleMessage::new(format!("{deleted} rows deleted")));
+                }
+            }
+            ["filter"] => {
+                let message = match &table.filter {
+                    Some(filter) => format!("Filter: {}", filter.expr),
+                    None => "No filter".to_owned(),
+                };
+                self.state.console_message = Some(ConsoleMessage::new(message));
+            }
+            ["filter", "clear"] => {
+                table.filter = None;
+                table.ensure_selection_in_view();
+            }
+            ["filter", ..] => {
+                let expr = command_argument(raw_command).unwrap_or_default();
+                let mut filter = RowFilter::parse(expr)?;
+                filter.update(&table.csv_table, table.header);
+                let message = format!(
+                    "{} of {} rows shown",
+                    filter.shown_count(),
+                    table.csv_table.used_extent().row_count
+                );
+                table.filter = Some(filter);
+                // The selection moves to the next shown row
+                let row = table.shown_row(table.shown_index(table.selection.primary.row));
+                table.move_selection_to(CellLocation {
+                    row,
+                    ..table.selection.primary
+                });
+                self.state.console_message = Some(ConsoleMessage::new(message));
+            }
+            ["header", args @ ..] => {
+                table.header = match args {
+                    [] => !table.header,
+                    ["on"] => true,
+                    ["off"] => false,
+                    _ => bail!("Usage: header [on|off]"),
+                };
+            }
+            ["noh" | "nohlsearch"] => {
+                table.search_highlight = false;
+            }
+            ["copen"] => {
+                if table.results.is_none() {
+                    bail!("No results! Use `matches` or `changes`");
+                }
+                table.results_open = true;
+                self.state.input = InputState::Results;
+            }
+            ["cclose"] => {
+                table.results_open = false;
+            }
+            ["goto", id] => {
+                let location = CsvJump::from_str(id)?.combine(table.selection.primary);
+                table.jump_to(location);
+            }
+            ["goto-end"] => table.jump_to(table.csv_table.last_cell()),
             ["save-path", ..] => {
                 let message = table
                     .file
@@ -547,9 +2759,34 @@ impl App {
     fn try_init(&mut self, args: Args) -> color_eyre::Result<()> {
         let Args {
             delimiter,
+            encoding,
+            faithful,
             file,
+            diff,
             stdin,
+            cell_width,
+            cell_height,
+            ..
         } = args;
+        if let Some(cell_width) = cell_width {
+            self.state.buffer_defaults.cell_width = cell_width;
+        }
+        if let Some(cell_height) = cell_height {
+            self.state.buffer_defaults.cell_height = cell_height;
+        }
+        let load = &mut self.state.buffer_defaults.load;
+        if let Some(encoding) = encoding {
+            load.encoding = encoding::unless_utf8(encoding);
+        }
+        load.faithful |= faithful;
+        if let Some(url) = file.as_ref().and_then(|file| file.to_str())
+            && download::is_url(url)
+        {
+            if diff.is_some() {
+                bail!("Only local files can be compared with --diff!");
+            }
+            return self.state.open_url(url.to_string(), delimiter);
+        }
         let load_option = if let Some(file) = file {
             LoadOption::File(file)
         } else if stdin {
@@ -557,8 +2794,31 @@ impl App {
         } else {
             return Ok(());
         };
-        let table = CsvBuffer::load(load_option, delimiter)?;
-        self.state.table = Some(table);
+        let table = CsvBuffer::load(load_option, delimiter, &self.state.buffer_defaults)?;
+        if let Some(path) = &table.file {
+            self.state.recent_files.push(path);
+        }
+        self.state.report_detected_delimiter(delimiter, &table);
+        let diff = match diff {
+            Some(other) => {
+                let settings = LoadSettings {
+                    faithful: false,
+                    ..self.state.buffer_defaults.load
+                };
+                let (read, _) = compression::open(&other)?;
+                let old = CsvTable::load_with(read, delimiter, settings)?;
+                Some(CsvBuffer::diff(
+                    &old,
+                    &table.csv_table,
+                    &self.state.buffer_defaults,
+                ))
+            }
+            None => None,
+        };
+        self.state.open_buffer(table);
+        if let Some(buffer) = diff {
+            self.state.open_buffer(buffer);
+        }
         Ok(())
     }
 
@@ -569,6 +2829,383 @@ impl App {
 }
 
 impl AppState {
+    /// The keys, that complete `combo`.
+    fn combo_hint(&self, combo: Combo) -> ComboHintWidget {
+        let hints = match combo {
+            Combo::Record => vec![("a-z", "record into register")],
+            Combo::Mark => vec![("a-z A-Z", "set mark")],
+            Combo::Register => vec![("a-z", "named register"), ("\"", "unnamed register")],
+            Combo::JumpToMark => {
+                let marks = self.table.iter().flat_map(|table| &table.marks);
+                return ComboHintWidget {
+                    prefix: combo.to_string(),
+                    hints: marks
+                        .map(|(mark, location)| (mark.to_string(), location.to_string()))
+                        .collect(),
+                };
+            }
+            Combo::Play => vec![("a-z", "play register"), ("@", "play last macro")],
+            Combo::Window => vec![
+                ("w", "other pane"),
+                ("h", "pane left"),
+                ("j", "pane below"),
+                ("k", "pane above"),
+                ("l", "pane right"),
+                ("o", "close other pane"),
+            ],
+            combo => {
+                let hints = self
+                    .keymap
+                    .combo_keys(combo)
+                    .into_iter()
+                    .map(|(key, action)| (key, action.name().replace('-', " ")));
+                return ComboHintWidget {
+                    prefix: combo.to_string(),
+                    hints: hints.collect(),
+                };
+            }
+        };
+        ComboHintWidget {
+            prefix: combo.to_string(),
+            hints: hints
+                .into_iter()
+                .map(|(key, description)| (key.to_owned(), description.to_owned()))
+                .collect(),
+        }
+    }
+
+    /// Takes the buffer defaults, styles and key remaps of the configuration.
+    fn apply_config(&mut self, config: &Config) -> Result<()> {
+        let defaults = &mut self.buffer_defaults;
+        if let Some(cell_width) = config.cell_width {
+            defaults.cell_width = cell_width.max(3);
+        }
+        if let Some(cell_height) = config.cell_height {
+            defaults.cell_height = cell_height.max(1);
+        }
+        if let Some(delimiter) = config.delimiter {
+            defaults.delimiter = Some(delimiter_from_str(&delimiter.to_string())?);
+        }
+        for (name, style) in &config.style {
+            let Some(target) = defaults.style.style_mut(name) else {
+                bail!("Unknown style: {name}");
+            };
+            *target = target.patch(style.to_style()?);
+        }
+        self.key_remaps = config.key_remaps()?;
+        self.keymap.apply(&config.bind)?;
+        Ok(())
+    }
+
+    /// Tells the delimiter of a loaded buffer, if it was detected as another one than a comma.
+    fn report_detected_delimiter(&mut self, requested: Option<u8>, buffer: &CsvBuffer) {
+        if requested.or(self.buffer_defaults.delimiter).is_none()
+            && let Some(delimiter) = buffer.csv_table.delimiter
+        {
+            self.console_message = Some(ConsoleMessage::new(format!(
+                "Detected delimiter: {}",
+                delimiter_name(Some(delimiter))
+            )));
+        }
+    }
+
+    /// Downloads `url` into a new buffer without a save path, showing the progress.
+    fn open_url(&mut self, url: String, delimiter: Option<u8>) -> Result<()> {
+        let defaults = self.buffer_defaults.clone();
+        task::run_long(self, "Downloading", move |progress| {
+            let bytes = download::fetch(&url, progress)?;
+            let buffer = CsvBuffer::from_read(bytes.as_slice(), delimiter, &defaults)?;
+            Ok(Box::new(move |state: &mut AppState| {
+                state.console_message = Some(ConsoleMessage::new(format!(
+                    "Downloaded {} rows from {url}",
+                    buffer.csv_table.used_extent().row_count
+                )));
+                state.report_detected_delimiter(delimiter, &buffer);
+                state.open_buffer(buffer);
+                Ok(())
+            }))
+        })
+    }
+
+    /// Makes `buffer` the current buffer and keeps the previous one open in the background.
+    fn open_buffer(&mut self, mut buffer: CsvBuffer) {
+        self.last_buffer_number += 1;
+        buffer.number = self.last_buffer_number;
+        if let Some(table) = self.table.replace(buffer) {
+            self.hidden_buffers.push_front(table);
+        }
+    }
+
+    /// Makes the buffer with `number` the current one, as if cycling to it with `:bn`.
+    fn switch_buffer(&mut self, number: usize) -> Result<()> {
+        if self
+            .table
+            .as_ref()
+            .is_some_and(|table| table.number == number)
+        {
+            return Ok(());
+        }
+        let Some(index) = self
+            .hidden_buffers
+            .iter()
+            .position(|buffer| buffer.number == number)
+        else {
+            bail!("No buffer {number}!");
+        };
+        if let Some(table) = self.table.take() {
+            self.hidden_buffers.push_back(table);
+        }
+        self.hidden_buffers.rotate_left(index);
+        self.table = self.hidden_buffers.pop_front();
+        Ok(())
+    }
+
+    /// Splits the main area, so the other pane shows the buffer with `number` or, without one,
+    /// the current buffer with its own view.
+    fn split(&mut self, vertical: bool, number: Option<usize>) -> Result<()> {
+        let Some(table) = &self.table else {
+            bail!("No buffer!");
+        };
+        let other = number.unwrap_or(table.number);
+        let is_open = other == table.number
+            || self
+                .hidden_buffers
+                .iter()
+                .any(|buffer| buffer.number == other);
+        if !is_open {
+            bail!("No buffer {other}!");
+        }
+        self.split = Some(Split {
+            vertical,
+            focus_second: false,
+            other,
+            other_view: table.view(),
+        });
+        Ok(())
+    }
+
+    /// Runs the pane command after `Ctrl-w`: `w` focuses the other pane, `h j k l` the pane in
+    /// that direction and `o` closes the other pane.
+    fn pane_command(&mut self, c: char) -> Result<()> {
+        let Some(split) = &self.split else {
+            bail!("No split!");
+        };
+        let towards_other = match c {
+            'w' => true,
+            'h' => split.vertical && split.focus_second,
+            'l' => split.vertical && !split.focus_second,
+            'k' => !split.vertical && split.focus_second,
+            'j' => !split.vertical && !split.focus_second,
+            'o' => {
+                self.split = None;
+                return Ok(());
+            }
+            _ => bail!("Unknown pane command: {c}"),
+        };
+        if towards_other {
+            self.switch_pane()?;
+        }
+        Ok(())
+    }
+
+    /// Moves the focus to the other pane of the split.
+    fn switch_pane(&mut self) -> Result<()> {
+        let (Some(split), Some(table)) = (&mut self.split, &mut self.table) else {
+            bail!("No split!");
+        };
+        split.focus_second = !split.focus_second;
+        if split.other == table.number {
+            table.swap_view(&mut split.other_view);
+            return Ok(());
+        }
+        let other = std::mem::replace(&mut split.other, table.number);
+        self.switch_buffer(other)
+    }
+
+    /// The open buffers by number, e.g. `%1 data.csv +  2 [No Name]`, where `%` marks the current
+    /// and `+` the modified ones.
+    fn buffer_list(&self) -> String {
+        let mut buffers = self
+            .table
+            .iter()
+            .chain(&self.hidden_buffers)
+            .collect::<Vec<_>>();
+        buffers.sort_by_key(|buffer| buffer.number);
+        buffers
+            .into_iter()
+            .map(|buffer| {
+                let current = self
+                    .table
+                    .as_ref()
+                    .is_some_and(|table| table.number == buffer.number);
+                let name = buffer
+                    .file
+                    .as_deref()
+                    .and_then(Path::file_name)
+                    .map_or("[No Name]".into(), |name| name.to_string_lossy());
+                format!(
+                    "{}{} {name}{}",
+                    if current { "%" } else { "" },
+                    buffer.number,
+                    if buffer.is_dirty() { " +" } else { "" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+
+    fn get_option(&self, name: &str) -> Result<String> {
+        let value = match name {
+            "coords" => self.settings.coords.to_string(),
+            "wrapscan" => on_off_to_str(self.settings.wrapscan).to_owned(),
+            "ignorecase" => on_off_to_str(self.settings.ignorecase).to_owned(),
+            "smartcase" => on_off_to_str(self.settings.smartcase).to_owned(),
+            "search" => self.settings.search.to_string(),
+            "autogrow" => on_off_to_str(self.settings.autogrow).to_owned(),
+            "hscrollbar" => on_off_to_str(self.settings.hscrollbar).to_owned(),
+            "vscrollbar" => on_off_to_str(self.settings.vscrollbar).to_owned(),
+            "formulas" => on_off_to_str(self.settings.formulas).to_owned(),
+            "calc" => on_off_to_str(self.settings.calc).to_owned(),
+            "autogrowmax" => self.settings.autogrow_max.to_string(),
+            "quote" | "quote-style" | "escape" | "eol" | "bom" => {
+                let Some(table) = &self.table else {
+                    bail!("No buffer!");
+                };
+                let quoting = table.csv_table.quoting;
+                match name {
+                    "quote" => (quoting.quote as char).to_string(),
+                    "quote-style" => quoting.style.to_string(),
+                    "eol" => table.csv_table.eol.to_string(),
+                    "bom" => on_off_to_str(table.csv_table.bom).to_owned(),
+                    _ => quoting
+                        .escape
+                        .map_or("off".to_owned(), |escape| (escape as char).to_string()),
+                }
+            }
+            "encoding" | "faithful" | "comment-char" => {
+                let settings = self
+                    .table
+                    .as_ref()
+                    .map(|table| table.csv_table.load_settings())
+                    .unwrap_or(self.buffer_defaults.load);
+                match name {
+                    "encoding" => encoding::name(settings.encoding),
+                    "faithful" => on_off_to_str(settings.faithful).to_owned(),
+                    _ => settings
+                        .comment
+                        .map_or("off".to_owned(), |comment| (comment as char).to_string()),
+                }
+            }
+            "clamp-view" => on_off_to_str(
+                self.table
+                    .as_ref()
+                    .map(|table| table.clamp_view)
+                    .unwrap_or(self.buffer_defaults.clamp_view),
+            )
+            .to_owned(),
+            _ => bail!("Unknown option: {name}"),
+        };
+        Ok(value)
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "coords" => self.settings.coords = value.parse()?,
+            "wrapscan" => self.settings.wrapscan = on_off_from_str(value)?,
+            "autogrow" => self.settings.autogrow = on_off_from_str(value)?,
+            "hscrollbar" => self.settings.hscrollbar = on_off_from_str(value)?,
+            "vscrollbar" => self.settings.vscrollbar = on_off_from_str(value)?,
+            "formulas" => self.settings.formulas = on_off_from_str(value)?,
+            "calc" => self.settings.calc = on_off_from_str(value)?,
+            "autogrowmax" => {
+                self.settings.autogrow_max = value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|width| *width >= 3)
+                    .ok_or_else(|| eyre!("Expected a width of at least 3: {value}"))?;
+            }
+            "ignorecase" | "smartcase" | "search" => {
+                match name {
+                    "ignorecase" => self.settings.ignorecase = on_off_from_str(value)?,
+                    "smartcase" => self.settings.smartcase = on_off_from_str(value)?,
+                    _ => self.settings.search = SearchMode::from_str(value)?,
+                }
+                // Rebuild the last search, so `n` and `N` use the new options
+                if let Some(table) = &mut self.table
+                    && let Some(search) = &table.search
+                {
+                    table.search = Some(Search::new(&search.pattern, &self.settings)?);
+                }
+            }
+            "eol" | "bom" => {
+                let Some(table) = &mut self.table else {
+                    bail!("No buffer!");
+                };
+                match name {
+                    "eol" => table.csv_table.eol = value.parse()?,
+                    _ => table.csv_table.bom = on_off_from_str(value)?,
+                }
+            }
+            "quote" | "quote-style" | "escape" => {
+                let Some(table) = &mut self.table else {
+                    bail!("No buffer!");
+                };
+                let byte = || match value.as_bytes() {
+                    [byte] if byte.is_ascii() => Ok(*byte),
+                    _ => Err(eyre!("Expected a single ASCII character: {value}")),
+                };
+                let quoting = &mut table.csv_table.quoting;
+                match name {
+                    "quote" => quoting.quote = byte()?,
+                    "quote-style" => quoting.style = value.parse()?,
+                    _ if value == "off" => quoting.escape = None,
+                    _ => quoting.escape = Some(byte()?),
+                }
+            }
+            "encoding" | "faithful" | "comment-char" => {
+                let change = |settings: &mut LoadSettings| -> Result<()> {
+                    match name {
+                        "encoding" => {
+                            settings.encoding = encoding::unless_utf8(encoding::from_label(value)?)
+                        }
+                        "faithful" => settings.faithful = on_off_from_str(value)?,
+                        _ => {
+                            settings.comment = match value.as_bytes() {
+                                _ if value == "off" => None,
+                                [byte] if byte.is_ascii() => Some(*byte),
+                                _ => bail!("Expected a single ASCII character or off: {value}"),
+                            }
+                        }
+                    }
+                    Ok(())
+                };
+                change(&mut self.buffer_defaults.load)?;
+                if let Some(table) = &mut self.table {
+                    let mut settings = table.csv_table.load_settings();
+                    change(&mut settings)?;
+                    if table.set_load_settings(settings)? {
+                        self.console_message =
+                            Some(ConsoleMessage::new(format!("Reloaded with {name} {value}")));
+                    } else if table.csv_table.load_settings() != settings {
+                        self.console_message = Some(ConsoleMessage::warning(
+                            "Changed buffers aren't read again, save and set it again",
+                        ));
+                    }
+                }
+            }
+            "clamp-view" => {
+                let clamp_view = on_off_from_str(value)?;
+                self.buffer_defaults.clamp_view = clamp_view;
+                if let Some(table) = &mut self.table {
+                    table.clamp_view = clamp_view;
+                    table.ensure_selection_in_view();
+                }
+            }
+            _ => bail!("Unknown option: {name}"),
+        }
+        Ok(())
+    }
+
     /// Renders the user interface.
     ///
     /// This is where you add new widgets. See the following resources for more information:
@@ -576,50 +3213,125 @@ impl AppState {
     /// - <https://docs.rs/ratatui/latest/ratatui/widgets/index.html>
     /// - <https://github.com/ratatui/ratatui/tree/main/ratatui-widgets/examples>
     fn render(&mut self, frame: &mut Frame) {
-        let [column_labels_area, main_area, console_bar] = Layout::vertical([
-            Constraint::Min(1),
-            Constraint::Percentage(100),
-            Constraint::Min(1),
-        ])
-        .areas(frame.area());
+        let [tables_area, console_bar] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
 
-        frame.render_widget(Block::new(), main_area);
+        frame.render_widget(Block::new(), tables_area);
         if let Some(table) = &mut self.table {
-            let [corner, col_labels_area] = Layout::horizontal([
-                Constraint::Min(ROW_LABEL_WIDTH),
-                Constraint::Percentage(100),
-            ])
-            .areas(column_labels_area);
-            let [row_labels_area, main_area] = Layout::horizontal([
-                Constraint::Min(ROW_LABEL_WIDTH),
-                Constraint::Percentage(100),
-            ])
-            .areas(main_area);
-
-            table.recalculate_dimensions(main_area.width, main_area.height);
-
-            // Render labels: Could also use one widget with the whole area
-            Block::new()
-                .style(table.style.label_normal)
-                .render(corner, frame.buffer_mut());
-            frame.render_widget(ColLabelsWidget(table), col_labels_area);
-            frame.render_widget(RowLabelsWidget(table), row_labels_area);
-
-            frame.render_widget(MainTableWidget(table), main_area);
+            // The split ends, when the buffer of the other pane is closed
+            let other_open = self.split.as_ref().is_some_and(|split| {
+                split.other == table.number
+                    || self
+                        .hidden_buffers
+                        .iter()
+                        .any(|buffer| buffer.number == split.other)
+            });
+            if !other_open {
+                self.split = None;
+            }
+            let mut area = tables_area;
+            if let Some(split) = &mut self.split {
+                let direction = if split.vertical {
+                    Direction::Horizontal
+                } else {
+                    Direction::Vertical
+                };
+                let [first, second] = Layout::new(direction, [Constraint::Fill(1); 2])
+                    .spacing(1)
+                    .areas(tables_area);
+                let other_area;
+                (area, other_area) = if split.focus_second {
+                    (second, first)
+                } else {
+                    (first, second)
+                };
+                // The focused pane is rendered last, so its area is the one for the mouse
+                if split.other == table.number {
+                    table.swap_view(&mut split.other_view);
+                    render_buffer(table, frame, other_area, &self.settings, None, true);
+                    table.swap_view(&mut split.other_view);
+                } else if let Some(other) = self
+                    .hidden_buffers
+                    .iter_mut()
+                    .find(|buffer| buffer.number == split.other)
+                {
+                    render_buffer(other, frame, other_area, &self.settings, None, true);
+                }
+            }
+            let split = self.split.is_some();
+            render_buffer(table, frame, area, &self.settings, Some(&self.input), split);
         } else {
-            frame.render_widget(SplashScreen, main_area);
+            frame.render_widget(SplashScreen(&self.keymap), tables_area);
         }
         let [main_console, status] =
-            Layout::horizontal([Constraint::Percentage(100), Constraint::Min(22)])
+            Layout::horizontal([Constraint::Percentage(100), Constraint::Min(36)])
                 .areas(console_bar);
 
+        // The terminal cursor is only shown, while the console bar takes input
         if let InputState::Console(console) = &self.input {
+            let line = console.line();
             frame.render_widget(console, main_console);
+            frame.set_cursor_position(cursor_after(main_console, &console.line_before_cursor()));
+            // Messages while typing are shown right of the input
+            if let Some(console_message) = &self.console_message {
+                let input_width = Line::raw(line.as_str()).width() as u16 + 1;
+                let message_width = console_message.width() + 1;
+                if input_width + message_width <= main_console.width {
+                    let [_, message_area] = Layout::horizontal([
+                        Constraint::Fill(1),
+                        Constraint::Length(message_width),
+                    ])
+                    .areas(main_console);
+                    frame.render_widget(console_message, message_area);
+                }
+            }
+        } else if let Some(task) = &self.task {
+            let label = if task.is_cancelled() {
+                "Cancelling…".to_owned()
+            } else {
+                format!("{} {:>3}% (Esc to cancel)", task.name, task.progress)
+            };
+            let gauge = LineGauge::default()
+                .label(label)
+                .ratio(f64::from(task.progress) / 100.0);
+            frame.render_widget(gauge, main_console);
+        } else if let InputState::Confirm(_) = &self.input {
+            frame.render_widget(&ConsoleMessage::new(CONFIRM_PROMPT), main_console);
+            frame.set_cursor_position(cursor_after(main_console, CONFIRM_PROMPT));
         } else if let Some(console_message) = &self.console_message {
             frame.render_widget(console_message, main_console);
+        } else if let InputState::Main(_) = &self.input
+            && let Some(table) = &self.table
+        {
+            // The comment lines before the selected row
+            let comments = table.csv_table.comments(table.selection.primary.row);
+            Paragraph::new(comments.join("  "))
+                .style(table.style.comment)
+                .render(main_console, frame.buffer_mut());
         }
 
         frame.render_widget(StatusWidget(self), status);
+
+        if let InputState::Main(InputModeMain {
+            combo: Some(combo), ..
+        }) = self.input
+            && self.combo_hint
+        {
+            frame.render_widget(self.combo_hint(combo), tables_area);
+        }
+
+        if let Some(popup) = &mut self.popup {
+            let area = frame.area();
+            frame.render_widget(
+                PopupWidget {
+                    popup,
+                    table: self.table.as_ref(),
+                    keymap: &self.keymap,
+                    coords: self.settings.coords,
+                },
+                area,
+            );
+        }
     }
 }
 
@@ -631,28 +3343,140 @@ struct CsvTableWidgetStyle {
     normal_10: Style,
     normal_11: Style,
     primary_selection: Style,
+    /// Style for the selected cells besides the primary one. Mixed from `primary_selection` and
+    /// the cell background, if unset.
+    selection: Option<Style>,
     yanked: Style,
+    /// Style of the match, that a substitution asks for confirmation for
+    pending_match: Style,
+    /// Patched onto cells, that match the search
+    search_match: Style,
+    /// Patched onto cells, that differ from the last save
+    modified: Style,
+    /// Background of the lowest value in a heatmap column
+    heatmap_low: Style,
+    /// Background of the highest value in a heatmap column
+    heatmap_high: Style,
+    /// Marker in the corner of cells with a note
+    note_marker: Style,
+    /// Cells of the pinned header row
+    header: Style,
+    /// Patched onto rows, that a diff adds
+    diff_added: Style,
+    /// Patched onto rows, that a diff removes
+    diff_removed: Style,
+    /// Patched onto cells, that differ in a diff
+    diff_changed: Style,
+    /// Comment lines of the selected row and the marker of rows with them
+    comment: Style,
+    /// Patched onto the labels of rows with another number of fields than most rows
+    ragged: Style,
     label_normal: Style,
     label_primary_selection: Style,
 }
 
+impl CsvTableWidgetStyle {
+    /// Style without any colors, that only uses text attributes.
+    fn monochrome() -> Self {
+        Self {
+            normal_00: Style::new(),
+            normal_01: Style::new(),
+            normal_10: Style::new().add_modifier(Modifier::DIM),
+            normal_11: Style::new().add_modifier(Modifier::DIM),
+            primary_selection: Style::new().add_modifier(Modifier::REVERSED),
+            selection: Some(Style::new().add_modifier(Modifier::BOLD | Modifier::ITALIC)),
+            yanked: Style::new().add_modifier(Modifier::UNDERLINED),
+            pending_match: Style::new()
+                .remove_modifier(Modifier::REVERSED)
+                .add_modifier(Modifier::UNDERLINED),
+            search_match: Style::new().add_modifier(Modifier::UNDERLINED),
+            modified: Style::new().add_modifier(Modifier::ITALIC),
+            heatmap_low: Style::new(),
+            heatmap_high: Style::new(),
+            note_marker: Style::new().add_modifier(Modifier::BOLD),
+            header: Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            diff_added: Style::new().add_modifier(Modifier::BOLD),
+            diff_removed: Style::new().add_modifier(Modifier::CROSSED_OUT),
+            diff_changed: Style::new().add_modifier(Modifier::UNDERLINED),
+            comment: Style::new().add_modifier(Modifier::DIM),
+            ragged: Style::new().add_modifier(Modifier::UNDERLINED),
+            label_normal: Style::new().add_modifier(Modifier::BOLD),
+            label_primary_selection: Style::new().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+        }
+    }
+}
+
+impl CsvTableWidgetStyle {
+    /// The style by its name in the configuration, e.g. `primary-selection`.
+    fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+        let style = match name {
+            "normal-00" => &mut self.normal_00,
+            "normal-01" => &mut self.normal_01,
+            "normal-10" => &mut self.normal_10,
+            "normal-11" => &mut self.normal_11,
+            "primary-selection" => &mut self.primary_selection,
+            "selection" => self.selection.get_or_insert_default(),
+            "yanked" => &mut self.yanked,
+            "pending-match" => &mut self.pending_match,
+            "search-match" => &mut self.search_match,
+            "modified" => &mut self.modified,
+            "heatmap-low" => &mut self.heatmap_low,
+            "heatmap-high" => &mut self.heatmap_high,
+            "note-marker" => &mut self.note_marker,
+            "header" => &mut self.header,
+            "diff-added" => &mut self.diff_added,
+            "diff-removed" => &mut self.diff_removed,
+            "diff-changed" => &mut self.diff_changed,
+            "comment" => &mut self.comment,
+            "ragged" => &mut self.ragged,
+            "label-normal" => &mut self.label_normal,
+            "label-primary-selection" => &mut self.label_primary_selection,
+            _ => return None,
+        };
+        Some(style)
+    }
+}
+
 impl Default for CsvTableWidgetStyle {
     fn default() -> Self {
+        if color_ext::is_monochrome() {
+            return Self::monochrome();
+        }
         Self {
             normal_00: Style::new().bg(Color::Rgb(30, 30, 30)).fg(Color::White),
             normal_01: Style::new().bg(Color::Rgb(31, 31, 31)).fg(Color::White),
             normal_10: Style::new().bg(Color::Rgb(39, 39, 39)).fg(Color::White),
             normal_11: Style::new().bg(Color::Rgb(41, 41, 41)).fg(Color::White),
             primary_selection: Style::new().bg(Color::LightBlue).fg(Color::Black),
+            selection: None,
             yanked: Style::new().fg(Color::Green),
+            pending_match: Style::new().bg(Color::Yellow).fg(Color::Black),
+            search_match: Style::new().bg(Color::Rgb(100, 85, 20)),
+            modified: Style::new().fg(Color::Rgb(230, 200, 120)),
+            heatmap_low: Style::new().bg(Color::Rgb(30, 70, 140)),
+            heatmap_high: Style::new().bg(Color::Rgb(170, 40, 40)),
+            note_marker: Style::new().fg(Color::Rgb(255, 170, 0)),
+            header: Style::new()
+                .bg(Color::Rgb(45, 50, 65))
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            diff_added: Style::new().bg(Color::Rgb(30, 75, 40)),
+            diff_removed: Style::new().bg(Color::Rgb(90, 35, 35)),
+            diff_changed: Style::new().bg(Color::Rgb(110, 90, 25)),
+            comment: Style::new()
+                .fg(Color::Rgb(120, 120, 120))
+                .add_modifier(Modifier::DIM),
+            ragged: Style::new().fg(Color::LightRed),
             label_normal: Style::new().bg(Color::Black).fg(Color::Rgb(160, 160, 160)),
             label_primary_selection: Style::new().bg(Color::Black).fg(Color::LightBlue),
         }
     }
 }
 
+/// The table and, while editing a cell, the value that is typed into it. Cells, that match the
+/// search, are highlighted. Formulas show their value, unless the last field is set.
 #[derive(Clone, Debug)]
-struct MainTableWidget<'a>(&'a CsvBuffer);
+struct MainTableWidget<'a>(&'a CsvBuffer, Option<&'a str>, Option<&'a Search>, bool);
 
 /// https://ratatui.rs/recipes/layout/grid/
 impl Widget for MainTableWidget<'_> {
@@ -661,12 +3485,14 @@ impl Widget for MainTableWidget<'_> {
             visible_cols,
             visible_rows,
             cell_height,
-            cell_width,
+            col_view_widths,
             style,
             top_left_cell_location,
             csv_table,
             selection,
             selection_yanked,
+            pending_match,
+            formulas,
             ..
         } = self.0;
 
@@ -676,12 +3502,25 @@ impl Widget for MainTableWidget<'_> {
             normal_10,
             normal_11,
             primary_selection,
+            selection: selection_style,
             yanked,
+            pending_match: pending_match_style,
+            search_match,
+            modified,
+            heatmap_low,
+            heatmap_high,
+            note_marker,
+            header,
+            diff_added,
+            diff_removed,
+            diff_changed,
             ..
         } = style;
 
         let Selection { opposite, primary } = selection;
-        let col_constraints = (0..*visible_cols).map(|_| Constraint::Length(*cell_width));
+        let col_constraints = col_view_widths
+            .iter()
+            .map(|width| Constraint::Length(*width));
         let row_constraints = (0..*visible_rows).map(|_| Constraint::Length(*cell_height));
         let horizontal = Layout::horizontal(col_constraints).spacing(0);
         let vertical = Layout::vertical(row_constraints).spacing(0);
@@ -698,14 +3537,21 @@ impl Widget for MainTableWidget<'_> {
         for (i, cell) in cells.enumerate() {
             let row_view = i / visible_cols;
             let col_view = i % visible_cols;
-            let cell_location @ CellLocation { col, .. } = *top_left_cell_location
-                + CellLocation {
-                    row: row_view,
-                    col: col_view,
-                };
-            let text = csv_table.get(cell_location).unwrap_or_default();
+            let cell_location @ CellLocation { col, .. } = CellLocation {
+                row: self.0.view_row(row_view),
+                col: top_left_cell_location.col + col_view,
+            };
+            let text = match self.1 {
+                Some(cell_input) if cell_location == *primary => cell_input,
+                _ if self.3 => csv_table.get(cell_location).unwrap_or_default(),
+                _ => formulas
+                    .value(cell_location)
+                    .or(csv_table.get(cell_location))
+                    .unwrap_or_default(),
+            };
 
             let normal = match (row_view % 2, col_view % 2) {
+                _ if self.0.header && cell_location.row == 0 => header,
                 (0, 0) => normal_00,
                 (0, 1) => normal_01,
                 (1, 0) => normal_10,
@@ -732,17 +3578,20 @@ impl Widget for MainTableWidget<'_> {
                         .contains(cell_location)
                 })
                 .unwrap_or_default()
-                && let Some(primary_bg) = primary_selection.bg
-                && let Some(normal_bg) = normal.bg
+                && let Some(style) = selection_style.or_else(|| {
+                    let primary_bg = primary_selection.bg?;
+                    let normal_bg = normal.bg?;
+                    let mut style = Style::new().bg(primary_bg.mix(normal_bg, 0.7, false).mix(
+                        Color::Rgb(0, 0, 0),
+                        0.1,
+                        false,
+                    ));
+                    if let Some(primary_fg) = primary_selection.fg {
+                        style = style.fg(primary_fg);
+                    }
+                    Some(style)
+                })
             {
-                let mut style = Style::new().bg(primary_bg.mix(normal_bg, 0.7, false).mix(
-                    Color::Rgb(0, 0, 0),
-                    0.1,
-                    false,
-                ));
-                if let Some(primary_fg) = primary_selection.fg {
-                    style = style.fg(primary_fg);
-                }
                 style
             } else if is_yanked
                 && let Some(Selection { primary, opposite }) = selection_yanked
@@ -752,11 +3601,39 @@ impl Widget for MainTableWidget<'_> {
                     })
                     .unwrap_or(cell_location == *primary)
             {
-                let bg = yanked.bg.or(yanked.fg).unwrap_or(Color::LightGreen);
-                let bg = normal.bg.map(|n| bg.mix(n, 0.9, false)).unwrap_or(bg);
-                normal.bg(bg)
+                if let Some(bg) = yanked.bg.or(yanked.fg) {
+                    let bg = normal.bg.map(|n| bg.mix(n, 0.9, false)).unwrap_or(bg);
+                    normal.bg(bg)
+                } else {
+                    normal.patch(*yanked)
+                }
             } else {
-                *normal
+                let mut style = *normal;
+                if let Some(position) = self.0.heatmap_position(cell_location)
+                    && let (Some(low), Some(high)) = (heatmap_low.bg, heatmap_high.bg)
+                {
+                    let heat = low.mix(high, position, false);
+                    style = style.bg(normal.bg.map_or(heat, |bg| heat.mix(bg, 0.3, false)));
+                }
+                if let Some(search) = self.2
+                    && search.is_match(text)
+                {
+                    style = style.patch(*search_match);
+                }
+                if csv_table.is_changed(cell_location) {
+                    style = style.patch(*modified);
+                }
+                if let Some(diff) = &self.0.diff {
+                    match diff.row(cell_location.row) {
+                        RowDiff::Added => style = style.patch(*diff_added),
+                        RowDiff::Removed => style = style.patch(*diff_removed),
+                        RowDiff::Changed(cols) if cols.contains(&col) => {
+                            style = style.patch(*diff_changed)
+                        }
+                        _ => {}
+                    }
+                }
+                style
             };
 
             // Border for yanked left and right
@@ -834,14 +3711,201 @@ impl Widget for MainTableWidget<'_> {
                 cell
             };
 
+            let text = match pending_match {
+                Some((location, range)) if *location == cell_location => {
+                    highlight_text(text, range.clone(), *pending_match_style)
+                }
+                _ => Text::raw(text),
+            };
             Paragraph::new(text)
                 .alignment(Alignment::Center)
                 .style(style)
                 .render(area, buf);
+            if self.0.notes.contains_key(&cell_location)
+                && let Some(corner) =
+                    buf.cell_mut(Position::new(cell.right().saturating_sub(1), cell.y))
+            {
+                corner
+                    .set_symbol(symbols::NOTE_MARKER)
+                    .set_style(style.patch(*note_marker));
+            }
         }
     }
 }
 
+/// Renders a buffer with its labels, scrollbars and results pane. Only the focused pane gets
+/// `input`, so it shows the typed cell value and search. In a `split`, the focused pane is marked
+/// in the corner.
+fn render_buffer(
+    table: &mut CsvBuffer,
+    frame: &mut Frame,
+    area: Rect,
+    settings: &Settings,
+    input: Option<&InputState>,
+    split: bool,
+) {
+    let [column_labels_area, main_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+    // The results pane takes its height from the table
+    let results_height = match &table.results {
+        Some(results) if table.results_open => results.height(),
+        _ => 0,
+    };
+    let [main_area, results_area] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(results_height)])
+            .areas(main_area);
+    let [main_area, hscrollbar_area] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(u16::from(settings.hscrollbar)),
+    ])
+    .areas(main_area);
+    let [main_area, footer_area] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(u16::from(!table.aggregates.is_empty())),
+    ])
+    .areas(main_area);
+    // The vertical scrollbar takes one column next to the row labels
+    let gutter_width = ROW_LABEL_WIDTH + u16::from(settings.vscrollbar);
+    let [corner, col_labels_area] =
+        Layout::horizontal([Constraint::Min(gutter_width), Constraint::Percentage(100)])
+            .areas(column_labels_area);
+    let [row_labels_area, main_area] =
+        Layout::horizontal([Constraint::Min(gutter_width), Constraint::Percentage(100)])
+            .areas(main_area);
+
+    table.recalculate_dimensions(main_area.width, main_area.height);
+    table.update_heatmaps();
+    table.update_aggregates();
+    table.update_formulas();
+    table.update_filter();
+    table.area = main_area;
+
+    // Render labels: Could also use one widget with the whole area
+    Block::new()
+        .style(table.style.label_normal)
+        .render(corner, frame.buffer_mut());
+    if split && input.is_some() {
+        Paragraph::new(symbols::FOCUSED_PANE)
+            .style(table.style.label_primary_selection)
+            .render(corner, frame.buffer_mut());
+    }
+    frame.render_widget(ColLabelsWidget(table, settings.coords), col_labels_area);
+    let [row_labels_area, vscrollbar_area] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(u16::from(settings.vscrollbar)),
+    ])
+    .areas(row_labels_area);
+    frame.render_widget(RowLabelsWidget(table), row_labels_area);
+    table.vscrollbar_area = vscrollbar_area;
+    if settings.vscrollbar {
+        let mut state = scrollbar_state(
+            table.shown_index(table.top_left_cell_location.row),
+            table.shown_index(table.max_top_left_cell_location().row),
+            table.visible_rows,
+        );
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .track_style(table.style.label_normal)
+                .thumb_style(table.style.label_primary_selection),
+            vscrollbar_area,
+            &mut state,
+        );
+    }
+
+    let cell_input = match input {
+        Some(InputState::Console(InputModeConsole {
+            mode: ConsoleBarMode::CellInput,
+            content,
+            ..
+        })) => Some(content.as_str()),
+        _ => None,
+    };
+    // While typing a search, its matches are highlighted instead of the last search
+    let incremental_search = match input {
+        Some(InputState::Console(InputModeConsole {
+            mode: ConsoleBarMode::Search,
+            content,
+            ..
+        })) if !content.is_empty() => Search::new(content, settings).ok(),
+        _ => None,
+    };
+    let search = incremental_search
+        .as_ref()
+        .or(table.search.as_ref().filter(|_| table.search_highlight));
+    frame.render_widget(
+        MainTableWidget(table, cell_input, search, settings.formulas),
+        main_area,
+    );
+    if !table.aggregates.is_empty() {
+        let [footer_corner, footer_area] =
+            Layout::horizontal([Constraint::Min(gutter_width), Constraint::Percentage(100)])
+                .areas(footer_area);
+        Paragraph::new(symbols::AGGREGATES)
+            .style(table.style.label_normal)
+            .render(footer_corner, frame.buffer_mut());
+        frame.render_widget(FooterWidget(table), footer_area);
+    }
+
+    let [hscrollbar_corner, hscrollbar_area] =
+        Layout::horizontal([Constraint::Min(gutter_width), Constraint::Percentage(100)])
+            .areas(hscrollbar_area);
+    table.hscrollbar_area = hscrollbar_area;
+    if settings.hscrollbar {
+        Block::new()
+            .style(table.style.label_normal)
+            .render(hscrollbar_corner, frame.buffer_mut());
+        let mut state = scrollbar_state(
+            table.top_left_cell_location.col,
+            table.max_top_left_cell_location().col,
+            table.visible_cols,
+        );
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::HorizontalBottom)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .track_style(table.style.label_normal)
+                .thumb_style(table.style.label_primary_selection),
+            hscrollbar_area,
+            &mut state,
+        );
+    }
+    if let Some(results) = &mut table.results
+        && table.results_open
+    {
+        let focused = matches!(input, Some(InputState::Results));
+        frame.render_widget(ResultListWidget { results, focused }, results_area);
+    }
+}
+
+/// The state of a scrollbar, whose view starts at `position` and can be scrolled up to
+/// `max_position`, which is the end of the used extent.
+fn scrollbar_state(position: usize, max_position: usize, viewport: usize) -> ScrollbarState {
+    ScrollbarState::new(max_position.max(position) + 1)
+        .position(position)
+        .viewport_content_length(viewport)
+}
+
+/// Splits `text` into lines and styles the byte `range`.
+fn highlight_text(text: &str, range: Range<usize>, style: Style) -> Text<'_> {
+    let mut start = 0;
+    text.split('\n')
+        .map(|line| {
+            let end = start + line.len();
+            let highlight_start = range.start.clamp(start, end) - start;
+            let highlight_end = range.end.clamp(start, end) - start;
+            start = end + 1;
+            Line::from(vec![
+                Span::raw(&line[..highlight_start]),
+                Span::styled(&line[highlight_start..highlight_end], style),
+                Span::raw(&line[highlight_end..]),
+            ])
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
 #[derive(Clone, Debug, Default)]
 pub(crate) struct ConsoleMessage {
     severity: Severity,
@@ -868,7 +3932,6 @@ impl ConsoleMessage {
         }
     }
 
-    #[expect(unused)]
     pub(crate) fn warning(message: impl Into<Cow<'static, str>>) -> Self {
         Self {
             message: message.into(),
@@ -876,7 +3939,15 @@ impl ConsoleMessage {
         }
     }
 
-    #[expect(unused)]
+    /// Width of the rendered message including the prefix.
+    fn width(&self) -> u16 {
+        let prefix = match self.severity {
+            Severity::Error => 2,
+            _ => 0,
+        };
+        prefix + Line::raw(self.message.as_ref()).width() as u16
+    }
+
     pub(crate) fn success(message: impl Into<Cow<'static, str>>) -> Self {
         Self {
             message: message.into(),
@@ -893,10 +3964,16 @@ impl Widget for &ConsoleMessage {
         let ConsoleMessage { severity, message } = self;
         let (prefix, color) = match *severity {
             Severity::Error => ("! ", Color::Red),
+            Severity::Warning => ("", Color::Yellow),
             _ => ("", Color::Reset),
         };
+        let style = if color_ext::is_monochrome() {
+            Style::new()
+        } else {
+            Style::new().fg(color)
+        };
         Clear.render(area, buf);
-        let paragraph = Paragraph::new(format!("{prefix}{message}")).fg(color);
+        let paragraph = Paragraph::new(format!("{prefix}{message}")).style(style);
         paragraph.render(area, buf);
     }
 }
@@ -905,6 +3982,44 @@ impl Widget for &ConsoleMessage {
 struct InputModeConsole {
     mode: ConsoleBarMode,
     content: String,
+    /// Byte position of the cursor in `content`
+    cursor: usize,
+    /// `Ctrl-r` was pressed and the next key selects, what to insert
+    insert_register: bool,
+    /// Values, that `Tab` cycles through in the search input
+    completion: Option<Completion>,
+    /// The command of the history, that `Up` and `Down` moved to
+    history_recall: Option<HistoryRecall>,
+}
+
+impl InputModeConsole {
+    fn new(mode: ConsoleBarMode, content: String) -> Self {
+        Self {
+            mode,
+            cursor: content.len(),
+            content,
+            insert_register: false,
+            completion: None,
+            history_recall: None,
+        }
+    }
+
+    /// The input with the prefix of the mode.
+    fn line(&self) -> String {
+        let after = &self.content[self.cursor..];
+        format!("{}{after}", self.line_before_cursor())
+    }
+
+    /// The part of [`Self::line`], that the terminal cursor is shown after.
+    fn line_before_cursor(&self) -> String {
+        let prefix = match self.mode {
+            ConsoleBarMode::Console => ":",
+            ConsoleBarMode::CellInput => ">",
+            ConsoleBarMode::Search => "/",
+        };
+        let register_marker = if self.insert_register { "\"" } else { "" };
+        format!("{prefix}{}{register_marker}", &self.content[..self.cursor])
+    }
 }
 
 impl Widget for &InputModeConsole {
@@ -912,21 +4027,22 @@ impl Widget for &InputModeConsole {
     where
         Self: Sized,
     {
-        let InputModeConsole { mode, content } = self;
-        let prefix = match mode {
-            ConsoleBarMode::Console => ":",
-            ConsoleBarMode::CellInput => ">",
-        };
         Clear.render(area, buf);
-        let paragraph = Paragraph::new(format!("{prefix}{content}"));
+        let paragraph = Paragraph::new(self.line());
         paragraph.render(area, buf);
     }
 }
 
+/// Position of the terminal cursor after `text` in a single line `area`.
+fn cursor_after(area: Rect, text: &str) -> Position {
+    let width = Line::raw(text).width() as u16;
+    Position::new(area.x + width.min(area.width.saturating_sub(1)), area.y)
+}
+
 #[derive(Clone, Debug)]
-struct SplashScreen;
+struct SplashScreen<'a>(&'a Keymap);
 
-impl Widget for SplashScreen {
+impl Widget for SplashScreen<'_> {
     fn render(self, area: Rect, buf: &mut Buffer)
     where
         Self: Sized,
@@ -934,9 +4050,19 @@ impl Widget for SplashScreen {
         let lines: Vec<&str> = LOGO.lines().collect();
         let logo_height = lines.len() as u16;
 
+        // Drop cheat sheet lines that don't fit below the logo (one line spacing)
+        let cheat_sheet = cheat_sheet_lines(self.0);
+        let cheat_sheet_height =
+            (cheat_sheet.len() as u16).min(area.height.saturating_sub(logo_height + 1));
+        let total_height = if cheat_sheet_height > 0 {
+            logo_height + 1 + cheat_sheet_height
+        } else {
+            logo_height
+        };
+
         // Vertikale Zentrierung
-        let start_y = if area.height > logo_height {
-            area.y + (area.height - logo_height) / 2
+        let start_y = if area.height > total_height {
+            area.y + (area.height - total_height) / 2
         } else {
             area.y
         };
@@ -953,28 +4079,106 @@ impl Widget for SplashScreen {
         };
 
         paragraph.render(logo_area, buf);
+
+        if cheat_sheet_height == 0 {
+            return;
+        }
+        let cheat_sheet_area = Rect {
+            x: area.x,
+            y: start_y + logo_height + 1,
+            width: area.width,
+            height: cheat_sheet_height,
+        };
+        let style = if color_ext::is_monochrome() {
+            Style::new().add_modifier(Modifier::DIM)
+        } else {
+            Style::new().fg(Color::Gray)
+        };
+        Paragraph::new(cheat_sheet.join("\n"))
+            .alignment(Alignment::Center)
+            .style(style)
+            .render(cheat_sheet_area, buf);
     }
 }
 
+/// Lines of the splash screen cheat sheet, padded to the same width so they stay aligned when
+/// centered.
+fn cheat_sheet_lines(keymap: &Keymap) -> Vec<String> {
+    let keys_width = splash_entries()
+        .map(|entry| entry.keys(keymap).chars().count())
+        .max()
+        .unwrap_or_default();
+    let description_width = splash_entries()
+        .map(|entry| entry.description.chars().count())
+        .max()
+        .unwrap_or_default();
+    splash_entries()
+        .map(|entry| {
+            format!(
+                "{:>keys_width$}  {:<description_width$}",
+                entry.keys(keymap),
+                entry.description
+            )
+        })
+        .collect()
+}
+
+/// The row below the table with the aggregates of the visible columns.
 #[derive(Clone, Debug)]
-struct ColLabelsWidget<'a>(&'a CsvBuffer);
+struct FooterWidget<'a>(&'a CsvBuffer);
 
-impl<'a> Widget for ColLabelsWidget<'a> {
-    fn render(self, area: Rect, buf: &mut Buffer)
-    where
-        Self: Sized,
-    {
-        let ColLabelsWidget(CsvBuffer {
+impl Widget for FooterWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let FooterWidget(CsvBuffer {
             visible_cols,
-            cell_width,
+            col_view_widths,
             style,
             top_left_cell_location,
-            selection,
+            aggregates,
             ..
         }) = self;
+        Block::new().style(style.header).render(area, buf);
+        let col_constraints = col_view_widths
+            .iter()
+            .map(|width| Constraint::Length(*width));
+        let cells = Layout::horizontal(col_constraints).spacing(0).split(area);
+        for i in 0..*visible_cols {
+            let Some(aggregate) = aggregates.get(&(top_left_cell_location.col + i)) else {
+                continue;
+            };
+            Paragraph::new(aggregate.text())
+                .alignment(Alignment::Right)
+                .render(cells[i], buf);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ColLabelsWidget<'a>(&'a CsvBuffer, CoordinateStyle);
+
+impl<'a> Widget for ColLabelsWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let ColLabelsWidget(
+            CsvBuffer {
+                visible_cols,
+                col_view_widths,
+                style,
+                top_left_cell_location,
+                selection,
+                csv_table,
+                header,
+                ..
+            },
+            coords,
+        ) = self;
 
         let CellLocation { col: col_left, .. } = top_left_cell_location;
-        let col_constraints = (0..*visible_cols).map(|_| Constraint::Length(*cell_width));
+        let col_constraints = col_view_widths
+            .iter()
+            .map(|width| Constraint::Length(*width));
         let labels = Layout::horizontal(col_constraints).spacing(0).split(area);
 
         for col_label in 0..*visible_cols {
@@ -984,7 +4188,14 @@ impl<'a> Widget for ColLabelsWidget<'a> {
             } else {
                 style.label_normal
             };
-            Paragraph::new(CellLocation::col_index_to_id(col))
+            let name = csv_table
+                .get(CellLocation { row: 0, col })
+                .filter(|name| *header && !name.is_empty());
+            let label = match name {
+                Some(name) => Cow::Borrowed(name.lines().next().unwrap_or_default()),
+                None => Cow::Owned(CellLocation::col_index_to_id(col, coords)),
+            };
+            Paragraph::new(label)
                 .style(style)
                 .alignment(Alignment::Center)
                 .render(labels[col_label], buf);
@@ -1000,30 +4211,39 @@ impl<'a> Widget for RowLabelsWidget<'a> {
     where
         Self: Sized,
     {
-        let RowLabelsWidget(CsvBuffer {
-            visible_rows,
-            cell_height,
-            style,
-            top_left_cell_location,
-            selection,
-            ..
-        }) = self;
+        let RowLabelsWidget(
+            table @ CsvBuffer {
+                visible_rows,
+                cell_height,
+                style,
+                selection,
+                ..
+            },
+        ) = self;
 
-        let CellLocation { row: row_top, .. } = top_left_cell_location;
         let row_constraints = (0..*visible_rows).map(|_| Constraint::Length(*cell_height));
         let labels = Layout::vertical(row_constraints).spacing(0).split(area);
 
         for row_label in 0..*visible_rows {
-            let row = row_top + row_label;
-            let style = if selection.primary.row == row {
+            let row = table.view_row(row_label);
+            let mut style = if selection.primary.row == row {
                 style.label_primary_selection
             } else {
                 style.label_normal
             };
+            if table.csv_table.is_ragged(row) {
+                style = style.patch(table.style.ragged);
+            }
             Paragraph::new(CellLocation::row_index_to_id(row))
                 .style(style)
                 .alignment(Alignment::Center)
                 .render(labels[row_label], buf);
+            if !table.csv_table.comments(row).is_empty()
+                && let Some(cell) = buf.cell_mut(labels[row_label].as_position())
+            {
+                cell.set_symbol(symbols::COMMENT_MARKER)
+                    .set_style(style.patch(table.style.comment));
+            }
         }
     }
 }
@@ -1032,6 +4252,10 @@ impl<'a> Widget for RowLabelsWidget<'a> {
 enum InputState {
     Main(InputModeMain),
     Console(InputModeConsole),
+    /// An interactive substitution asks, whether to replace the current match
+    Confirm(SubstituteConfirm),
+    /// The results pane takes the keys
+    Results,
 }
 
 impl Default for InputState {
@@ -1069,28 +4293,45 @@ impl<'a> Widget for StatusWidget<'a> {
                 input_buffer,
                 ..
             }) => {
-                let disp = (*mode == MainMode::Visual)
-                    .then(|| ("SEL", Style::default().bg(Color::Blue).fg(Color::Black)));
+                let disp = if *mode == MainMode::Visual {
+                    Some(("SEL", mode_style(Color::Blue)))
+                } else {
+                    state
+                        .recording
+                        .is_some()
+                        .then(|| ("REC", mode_style(Color::Red)))
+                };
                 (
                     disp,
                     Some(input_buffer),
                     combo.as_ref().map(ToString::to_string),
                 )
             }
-            InputState::Console(InputModeConsole { mode, .. }) => match mode {
+            InputState::Console(InputModeConsole { mode, content, .. }) => match mode {
                 ConsoleBarMode::Console => (Some(("CON", Style::default())), None, None),
-                ConsoleBarMode::CellInput => (
-                    Some(("INS", Style::default().bg(Color::Yellow).fg(Color::Black))),
-                    None,
-                    None,
-                ),
+                ConsoleBarMode::Search if search::is_literal(content, &state.settings) => {
+                    (Some(("LIT", Style::default())), None, None)
+                }
+                ConsoleBarMode::Search => (Some(("FND", Style::default())), None, None),
+                ConsoleBarMode::CellInput => (Some(("INS", mode_style(Color::Yellow))), None, None),
             },
+            InputState::Confirm(_) => (Some(("RPL", mode_style(Color::Magenta))), None, None),
+            InputState::Results => (Some(("LST", Style::default())), None, None),
         };
-        let [mode_area, buffer_area, combo_area, coords_area] = Layout::horizontal([
+        let [
+            mode_area,
+            buffer_area,
+            combo_area,
+            search_area,
+            percentage_area,
+            coords_area,
+        ] = Layout::horizontal([
             Constraint::Length(3),
             Constraint::Length(9),
             Constraint::Length(1),
-            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(5),
+            Constraint::Length(11),
         ])
         .areas(area);
         if let Some((mode_str, style)) = mode {
@@ -1108,20 +4349,47 @@ impl<'a> Widget for StatusWidget<'a> {
         }
 
         if let Some(table) = &state.table {
-            Paragraph::new(table.selection.primary.to_string())
+            if let Some(search) = &table.search {
+                let literal = if search.literal { r"\V " } else { "" };
+                Paragraph::new(format!("{literal}{}", state.settings.case_indicator()))
+                    .alignment(Alignment::Right)
+                    .render(search_area, buf);
+            }
+            // Vertical scroll position, 100% when scrolled to the end of the used extent
+            let max_top_row = table.shown_index(table.max_top_left_cell_location().row);
+            let top_row = table.shown_index(table.top_left_cell_location.row);
+            let percentage = if max_top_row == 0 && top_row == 0 {
+                "All".to_owned()
+            } else {
+                format!("{}%", (top_row * 100 / max_top_row.max(1)).min(100))
+            };
+            Paragraph::new(percentage)
+                .alignment(Alignment::Right)
+                .render(percentage_area, buf);
+            Paragraph::new(table.selection.primary.id(state.settings.coords))
                 .alignment(Alignment::Right)
                 .render(coords_area, buf);
         };
     }
 }
 
+/// Style of a mode indicator in the status bar.
+fn mode_style(color: Color) -> Style {
+    if color_ext::is_monochrome() {
+        Style::new().add_modifier(Modifier::REVERSED)
+    } else {
+        Style::new().bg(color).fg(Color::Black)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum ConsoleBarMode {
     Console,
     CellInput,
+    Search,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 enum Severity {
     #[default]
     Neutral,
@@ -1133,25 +4401,111 @@ enum Severity {
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = "Minimalistic Csv Editor")]
 struct Args {
-    /// delimiter used for the FILE
+    /// delimiter used for the FILE, detected from its start if not given
     ///
     /// [default: ,]
     #[arg(short, long, value_parser = delimiter_from_str)]
     delimiter: Option<u8>,
+    /// Encoding of the FILE, e.g. latin1, windows-1252 or utf-16le, which is kept on save
+    ///
+    /// [default: utf-8]
+    #[arg(short, long, value_parser = encoding::from_label)]
+    encoding: Option<&'static Encoding>,
+    /// Save unchanged rows byte for byte as they were read, which keeps diffs small
+    #[arg(long)]
+    faithful: bool,
     /// Read csv file from stdin
     #[arg(long, conflicts_with = "file")]
     stdin: bool,
-    /// Optional CSV File that will be loaded at start
+    /// Width of the cells in characters
+    ///
+    /// [default: 25]
+    #[arg(long, value_parser = clap::value_parser!(u16).range(3..))]
+    cell_width: Option<u16>,
+    /// Height of the cells in lines
+    ///
+    /// [default: 1]
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    cell_height: Option<u16>,
+    /// Render without colors, only using text attributes
+    ///
+    /// Also enabled by setting the `NO_COLOR` environment variable
+    #[arg(long)]
+    no_color: bool,
+    /// Start typing in a new row below the last used row
+    #[arg(long)]
+    append: bool,
+    /// Run the console commands separated by `;` without a terminal, e.g. `'%s/a/b/; w out.csv'`.
+    /// A literal `;` is written as `\;`
+    #[arg(long, value_name = "COMMANDS", conflicts_with_all = ["script", "append"])]
+    batch: Vec<String>,
+    /// Run the console commands of a file, one per line, without a terminal
+    #[arg(long, conflicts_with = "append")]
+    script: Option<PathBuf>,
+    /// Compare FILE with an older version OTHER and show the differences in a new buffer
+    #[arg(long, value_name = "OTHER", requires = "file")]
+    diff: Option<PathBuf>,
+    /// Optional CSV File that will be loaded at start, which may be gzip or zstd compressed or
+    /// an http(s) URL to download
     #[arg(conflicts_with = "stdin")]
     file: Option<PathBuf>,
 }
 
+impl Args {
+    /// The commands of `--batch` or `--script`. Empty lines and lines starting with `#` are
+    /// skipped and a leading `:` is optional.
+    fn batch_commands(&self) -> Result<Option<Vec<String>>> {
+        let commands = if !self.batch.is_empty() {
+            self.batch
+                .iter()
+                .flat_map(|batch| split_batch(batch))
+                .collect::<Vec<_>>()
+        } else if let Some(script) = &self.script {
+            let content = fs::read_to_string(script)
+                .map_err(|err| eyre!("Can't read {}: {err}", script.to_string_lossy()))?;
+            content.lines().map(str::to_owned).collect()
+        } else {
+            return Ok(None);
+        };
+        let commands = commands
+            .iter()
+            .map(|command| command.trim())
+            .filter(|command| !command.is_empty() && !command.starts_with('#'))
+            .map(|command| command.strip_prefix(':').unwrap_or(command).to_owned())
+            .collect();
+        Ok(Some(commands))
+    }
+}
+
+/// Splits the commands of `--batch` at `;` and newlines, where `\;` stands for a literal `;`.
+fn split_batch(batch: &str) -> Vec<String> {
+    let mut commands = vec![String::new()];
+    let mut chars = batch.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&';') => {
+                chars.next();
+                commands.last_mut().unwrap().push(';');
+            }
+            ';' | '\n' => commands.push(String::new()),
+            c => commands.last_mut().unwrap().push(c),
+        }
+    }
+    commands
+}
+
 #[derive(Debug, Copy, Clone, Default)]
 struct Selection {
     primary: CellLocation,
     opposite: Option<CellLocation>,
 }
 
+impl Selection {
+    fn rect(&self) -> CellRect {
+        CellRect::from_opposite_cell_locations(self.primary, self.opposite.unwrap_or(self.primary))
+    }
+}
+
 #[derive(Debug, Clone)]
 enum Yank {
     Single(Option<String>),
@@ -1159,6 +4513,316 @@ enum Yank {
         col_count: usize,
         content: Vec<Option<String>>,
     },
+    /// CSV text, that is pasted verbatim into a cell or parsed into a rectangle
+    Text(String),
+}
+
+impl Yank {
+    /// Content of the top left cell and whether there are more cells.
+    fn first_cell(&self, delimiter: Option<u8>) -> Result<(String, bool)> {
+        let res = match self {
+            Yank::Single(value) => (value.clone().unwrap_or_default(), false),
+            Yank::Rectangle { content, .. } => (
+                content.first().cloned().flatten().unwrap_or_default(),
+                content.len() > 1,
+            ),
+            Yank::Text(text) => return Yank::from_csv_text(text, delimiter)?.first_cell(delimiter),
+        };
+        Ok(res)
+    }
+
+    /// Serializes the yanked cells as CSV, where a single value is a one-cell file.
+    fn to_csv_text(&self, delimiter: Option<u8>) -> Result<String> {
+        let (col_count, content) = match self {
+            Yank::Single(value) => (1, vec![value.clone()]),
+            Yank::Rectangle { col_count, content } => (*col_count, content.clone()),
+            Yank::Text(text) => return Ok(text.clone()),
+        };
+        let rect = CellRect {
+            top_left_cell_location: CellLocation::default(),
+            col_count,
+            row_count: content.len() / col_count.max(1),
+        };
+        let mut table = CsvTable::default();
+        table.delimiter = delimiter;
+        let _ = table.set_rect(rect, content);
+        table.rect_to_csv_string(rect)
+    }
+
+    /// The yanked cells as column count and values, where text is parsed as CSV.
+    fn to_rectangle(&self, delimiter: Option<u8>) -> Result<(usize, Vec<Option<String>>)> {
+        match self {
+            Yank::Single(value) => Ok((1, vec![value.clone()])),
+            Yank::Rectangle { col_count, content } => Ok((*col_count, content.clone())),
+            Yank::Text(text) => Yank::from_csv_text(text, delimiter)?.to_rectangle(delimiter),
+        }
+    }
+
+    /// Size and start of the content for the list of registers.
+    fn summary(&self) -> String {
+        match self {
+            Yank::Single(value) => value.clone().unwrap_or_default(),
+            Yank::Rectangle { col_count, content } => format!(
+                "{}x{} {}",
+                col_count,
+                content.len() / col_count.max(&1),
+                content.first().cloned().flatten().unwrap_or_default()
+            ),
+            Yank::Text(text) => format!("text {}", text.lines().next().unwrap_or_default()),
+        }
+    }
+
+    fn from_csv_text(text: &str, delimiter: Option<u8>) -> Result<Self> {
+        let table = CsvTable::load(text.as_bytes(), delimiter)?;
+        let rect = table.used_extent();
+        if rect.col_count == 0 {
+            return Ok(Yank::Single(None));
+        }
+        Ok(Yank::Rectangle {
+            col_count: rect.col_count,
+            content: table.get_rect_cloned(rect),
+        })
+    }
+}
+
+/// The distinct values of `(row, value)` pairs, most frequent first and otherwise in the order
+/// of their first row.
+fn value_counts<'a>(values: impl Iterator<Item = (usize, Option<&'a str>)>) -> Vec<ValueCount> {
+    let mut indices = HashMap::new();
+    let mut entries = Vec::<ValueCount>::new();
+    for (row, value) in values {
+        let value = value.unwrap_or_default();
+        let index = *indices.entry(value).or_insert_with(|| {
+            entries.push(ValueCount {
+                value: value.to_owned(),
+                count: 0,
+                first_row: row,
+            });
+            entries.len() - 1
+        });
+        entries[index].count += 1;
+    }
+    // Stable, so equal counts keep the order of the rows
+    entries.sort_by(|a, b| b.count.cmp(&a.count));
+    entries
+}
+
+/// Statistics of the selection or, without one, the current column.
+fn stats_popup(table: &CsvBuffer, coords: CoordinateStyle) -> Popup {
+    if table.selection.opposite.is_some() {
+        let rect = table.selection.rect();
+        let values = table.csv_table.get_rect_cloned(rect);
+        let stats = Stats::new(values.iter().map(Option::as_deref));
+        let bottom_right = CellLocation {
+            row: rect.top_left_cell_location.row + rect.row_count - 1,
+            col: rect.top_left_cell_location.col + rect.col_count - 1,
+        };
+        let title = format!(
+            "{}:{}",
+            rect.top_left_cell_location.id(coords),
+            bottom_right.id(coords)
+        );
+        Popup::stats(title, stats)
+    } else {
+        let col = table.selection.primary.col;
+        // The header is a label, not a value
+        let values = table.csv_table.column_values(col);
+        let stats = Stats::new(values.skip(usize::from(table.header)));
+        Popup::stats(CellLocation::col_index_to_id(col, coords), stats)
+    }
+}
+
+/// Pastes `yank` at the selection, where `csv` parses yanked CSV text into a rectangle.
+fn paste(table: &mut CsvBuffer, yank: &Yank, csv: bool) -> Result<Option<ConsoleMessage>> {
+    let Selection { primary, opposite } = table.selection;
+    // `P` pastes yanked CSV text as a rectangle
+    let reparsed;
+    let yank = match yank {
+        Yank::Text(text) if csv => {
+            reparsed = Yank::from_csv_text(text, table.csv_table.delimiter)?;
+            &reparsed
+        }
+        yank => yank,
+    };
+    let message = match yank {
+        Yank::Single(_) | Yank::Text(_) => {
+            let single = match yank {
+                Yank::Text(text) => Some(text.clone()),
+                Yank::Single(single) => single.clone(),
+                Yank::Rectangle { .. } => unreachable!(),
+            };
+            if let Some(opposite) = opposite {
+                let rect = CellRect::from_opposite_cell_locations(primary, opposite);
+                let from_values = table.csv_table.set_rect(rect, std::iter::repeat(single));
+                table.undo_stack.push(UndoAction::ChangeCells {
+                    mode: buffer::UndoChangeCellMode::Fill,
+                    rect,
+                    values: from_values.clone(),
+                });
+            } else {
+                let from_value = table.csv_table.set(primary, single);
+                table.undo_stack.push(UndoAction::ChangeCell {
+                    mode: UndoChangeCellMode::Edit,
+                    cell_location: primary,
+                    value: from_value,
+                });
+            }
+            None
+        }
+        Yank::Rectangle { col_count, content } => {
+            let yank_row_count = content.len() / col_count;
+            let rect = if let Some(opposite) = opposite {
+                CellRect::from_opposite_cell_locations(primary, opposite)
+            } else {
+                CellRect {
+                    top_left_cell_location: primary,
+                    col_count: *col_count,
+                    row_count: yank_row_count,
+                }
+            };
+            // Over a selection, the yank is repeated to fill it
+            let values = (0..rect.row_count).flat_map(|row| {
+                (0..rect.col_count).map(move |col| {
+                    content[(row % yank_row_count) * col_count + col % col_count].clone()
+                })
+            });
+            let from_values = table.csv_table.set_rect(rect, values);
+            table.undo_stack.push(UndoAction::ChangeCells {
+                mode: buffer::UndoChangeCellMode::Edit,
+                rect,
+                values: from_values.clone(),
+            });
+            Some(ConsoleMessage::new(format!(
+                "Pasted {}x{} cells",
+                rect.col_count, rect.row_count
+            )))
+        }
+    };
+    Ok(message)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PasteOptions {
+    /// Swap the rows and columns of the yank
+    transpose: bool,
+    /// Keep the existing values, where the yank is empty
+    skip_blanks: bool,
+    /// Repeat the yank over the whole selection
+    fill: bool,
+}
+
+impl PasteOptions {
+    fn parse(args: &[&str]) -> Result<Self> {
+        let mut options = Self::default();
+        for arg in args {
+            match *arg {
+                "transpose" => options.transpose = true,
+                "skip-blanks" => options.skip_blanks = true,
+                "fill" => options.fill = true,
+                _ => bail!("Unknown option: {arg}. Use `transpose`, `skip-blanks` or `fill`"),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Pastes `yank` as a rectangle at the top left of the selection with the given `options`.
+fn paste_special(table: &mut CsvBuffer, yank: &Yank, options: PasteOptions) -> Result<CellRect> {
+    let (mut col_count, mut content) = yank.to_rectangle(table.csv_table.delimiter)?;
+    let mut row_count = content.len() / col_count;
+    if options.transpose {
+        content = (0..col_count)
+            .flat_map(|col| (0..row_count).map(move |row| row * col_count + col))
+            .map(|index| content[index].clone())
+            .collect();
+        (col_count, row_count) = (row_count, col_count);
+    }
+    let selection = table.selection.rect();
+    let rect = if options.fill {
+        selection
+    } else {
+        CellRect {
+            top_left_cell_location: selection.top_left_cell_location,
+            col_count,
+            row_count,
+        }
+    };
+    let top_left = rect.top_left_cell_location;
+    let values = (0..rect.row_count)
+        .flat_map(|row| (0..rect.col_count).map(move |col| (row, col)))
+        .map(|(row, col)| {
+            let value = &content[(row % row_count) * col_count + col % col_count];
+            if options.skip_blanks && value.as_deref().unwrap_or_default().is_empty() {
+                let location = CellLocation {
+                    row: top_left.row + row,
+                    col: top_left.col + col,
+                };
+                table.csv_table.get(location).map(ToOwned::to_owned)
+            } else {
+                value.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    let from_values = table.csv_table.set_rect(rect, values);
+    table.undo_stack.push(UndoAction::ChangeCells {
+        mode: buffer::UndoChangeCellMode::Edit,
+        rect,
+        values: from_values,
+    });
+    Ok(rect)
+}
+
+/// Yanks by register, where `"` is the unnamed register
+#[derive(Debug, Default)]
+struct Registers {
+    yanks: BTreeMap<char, Yank>,
+    /// The register, that was selected with `"` for the next yank or paste
+    selected: Option<char>,
+    /// The last yanks, newest first
+    ring: VecDeque<Yank>,
+    /// The last paste, as long as it may be cycled through the ring
+    paste_cycle: Option<PasteCycle>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PasteCycle {
+    /// The selection before the paste
+    selection: Selection,
+    csv: bool,
+    /// The index into the ring of the next yank to paste
+    next: usize,
+}
+
+impl Registers {
+    const UNNAMED: char = '"';
+
+    fn parse(name: &str) -> Result<char> {
+        match name.chars().collect::<Vec<_>>()[..] {
+            [c] if c == Self::UNNAMED || c.is_ascii_lowercase() => Ok(c),
+            _ => bail!("Invalid register: {name}"),
+        }
+    }
+
+    fn unnamed(&self) -> Option<&Yank> {
+        self.yanks.get(&Self::UNNAMED)
+    }
+
+    /// The yank of the selected register or else the unnamed one.
+    fn get(&self) -> Option<&Yank> {
+        self.yanks.get(&self.selected.unwrap_or(Self::UNNAMED))
+    }
+
+    /// Stores into the unnamed register and the selected one.
+    fn set(&mut self, yank: Yank) {
+        if let Some(register) = self.selected
+            && register != Self::UNNAMED
+        {
+            self.yanks.insert(register, yank.clone());
+        }
+        self.ring.push_front(yank.clone());
+        self.ring.truncate(YANK_RING_LENGTH);
+        self.yanks.insert(Self::UNNAMED, yank);
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -1169,17 +4833,39 @@ enum MoveDirection {
     Right,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Combo {
     View,
     Goto,
+    Next,
+    Previous,
+    /// `q`: Record a macro into the following register
+    Record,
+    /// `@`: Play the macro of the following register
+    Play,
+    /// `Ctrl-w`: Move between the panes of a split
+    Window,
+    /// `m`: Set the mark of the following letter
+    Mark,
+    /// `'`: Jump to the mark of the following letter
+    JumpToMark,
+    /// `"`: Use the following register for the next yank or paste
+    Register,
 }
 
 impl Display for Combo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
-            Combo::View => "v",
+            Combo::View => "z",
             Combo::Goto => "g",
+            Combo::Next => "]",
+            Combo::Previous => "[",
+            Combo::Record => "q",
+            Combo::Play => "@",
+            Combo::Window => "^w",
+            Combo::Mark => "m",
+            Combo::JumpToMark => "'",
+            Combo::Register => "\"",
         };
         f.write_str(s)
     }
@@ -1229,7 +4915,7 @@ impl FromStr for CsvJump {
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         thread_local! {
-            static RE: LazyCell<Regex> = LazyCell::new(|| Regex::new(r#"^(?P<sign>[+-])?(?P<col>[[:alpha:]]+)?(?P<row>\d+)?$"#).unwrap());
+            static RE: LazyCell<Regex> = LazyCell::new(|| Regex::new(r#"^(?P<sign>[+-])?(?:(?i:R)(?P<r1c1_row>\d+)(?i:C)(?P<r1c1_col>\d+)|(?P<col>[[:alpha:]]+)?(?P<row>\d+)?)$"#).unwrap());
         }
         let Some(caps) = RE.with(|i| i.captures(s)) else {
             return Err(eyre!("Not a valid location id!"));
@@ -1241,6 +4927,22 @@ impl FromStr for CsvJump {
             _ => None,
         };
 
+        let parse_index = |index: &str| {
+            index
+                .parse::<usize>()
+                .map(|u| u.saturating_sub(1))
+                .map_err(|_| eyre!("Location id too big!"))
+        };
+
+        // R1C1 notation, e.g. `R42C7`
+        if let (Some(row), Some(col)) = (caps.name("r1c1_row"), caps.name("r1c1_col")) {
+            return Ok(Self {
+                sign,
+                row: Some(parse_index(row.as_str())?),
+                col: Some(parse_index(col.as_str())?),
+            });
+        }
+
         let row = caps
             .name("row")
             .map(|row| row.as_str().parse::<usize>().map(|u| u.saturating_sub(1)))
@@ -1248,20 +4950,7 @@ impl FromStr for CsvJump {
             .map_err(|_| eyre!("Column id too big!"))?;
         let col = caps
             .name("col")
-            .map(|col| -> Result<_> {
-                let mut result = 0usize;
-                for c in col.as_str().chars() {
-                    assert!(c.is_ascii_alphabetic());
-                    let val = (c.to_ascii_uppercase() as u8 - b'A') as usize + 1;
-                    result = result
-                        .checked_mul(26)
-                        .ok_or_else(|| eyre!("Row id too big!"))?;
-                    result = result
-                        .checked_add(val)
-                        .ok_or_else(|| eyre!("Row id too big!"))?;
-                }
-                Ok(result - 1)
-            })
+            .map(|col| CellLocation::col_letters_to_index(col.as_str()))
             .transpose()?;
         if row.is_none() && col.is_none() {
             return Err(eyre!("Emtpy location id!"));
@@ -1270,6 +4959,183 @@ impl FromStr for CsvJump {
     }
 }
 
+/// Moves the selection to the next match of the last search and returns the message to show.
+fn search_next(
+    table: &mut CsvBuffer,
+    direction: SearchDirection,
+    wrapscan: bool,
+) -> Result<Option<ConsoleMessage>> {
+    let Some(search) = &table.search else {
+        bail!("No previous search pattern!");
+    };
+    let Some((location, wrapped)) =
+        table
+            .csv_table
+            .find(table.selection.primary, direction, wrapscan, |value| {
+                search.is_match(value)
+            })
+    else {
+        if table
+            .csv_table
+            .find(CellLocation::default(), direction, true, |value| {
+                search.is_match(value)
+            })
+            .is_some()
+        {
+            let message = match direction {
+                SearchDirection::Forward => "No more matches below",
+                SearchDirection::Backward => "No more matches above",
+            };
+            return Ok(Some(ConsoleMessage::warning(message)));
+        }
+        bail!("Pattern not found: {}", search.pattern);
+    };
+    table.jump_to(location);
+    let message = wrapped.then(|| {
+        ConsoleMessage::warning(match direction {
+            SearchDirection::Forward => "search hit BOTTOM, continuing at TOP",
+            SearchDirection::Backward => "search hit TOP, continuing at BOTTOM",
+        })
+    });
+    Ok(message)
+}
+
+/// The first row and the number of rows of the selection or table, that reordering commands
+/// move. The header row stays on top.
+fn rows_below_header(table: &CsvBuffer) -> (usize, usize) {
+    let CellRect {
+        top_left_cell_location: CellLocation { row: start, .. },
+        row_count,
+        ..
+    } = table.selection_or_used_extent();
+    if table.header && start == 0 && row_count > 0 {
+        (1, row_count - 1)
+    } else {
+        (start, row_count)
+    }
+}
+
+/// Splits off the `seed=<n>` argument of random commands.
+fn seed_argument<'a>(args: &[&'a str]) -> Result<(Option<u64>, Vec<&'a str>)> {
+    let mut seed = None;
+    let mut rest = Vec::new();
+    for arg in args {
+        match arg.strip_prefix("seed=") {
+            Some(value) => {
+                seed = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| eyre!("Invalid seed: {value}"))?,
+                );
+            }
+            None => rest.push(*arg),
+        }
+    }
+    Ok((seed, rest))
+}
+
+/// A reproducible random number generator for a seed, or a random one.
+fn rng_from_seed(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => rand::make_rng(),
+    }
+}
+
+/// Inserts a copy of the selected rows or columns next to them and selects the copy of the
+/// primary cell.
+fn duplicate(table: &mut CsvBuffer, rows: bool) -> ConsoleMessage {
+    let rect = table.selection.rect();
+    let CellLocation { row, col } = table.selection.primary;
+    table.drop_selection();
+    if rows {
+        table.duplicate_rows(rect.top_left_cell_location.row, rect.row_count);
+        table.move_selection_to(CellLocation {
+            row: row + rect.row_count,
+            col,
+        });
+        ConsoleMessage::new(format!("{} rows duplicated", rect.row_count))
+    } else {
+        table.duplicate_cols(rect.top_left_cell_location.col, rect.col_count);
+        table.move_selection_to(CellLocation {
+            row,
+            col: col + rect.col_count,
+        });
+        ConsoleMessage::new(format!("{} columns duplicated", rect.col_count))
+    }
+}
+
+/// The first column and the number of columns of a range like `C:E` or a single column.
+fn col_range(range: &str) -> Result<(usize, usize)> {
+    let (first, last) = range.split_once(':').unwrap_or((range, range));
+    let first = CellLocation::col_id_to_index(first)?;
+    let last = CellLocation::col_id_to_index(last)?;
+    Ok((first.min(last), first.abs_diff(last) + 1))
+}
+
+/// Everything after the command name, e.g. the pattern of `count a b`.
+fn command_argument(command: &str) -> Option<&str> {
+    command
+        .trim()
+        .split_once(char::is_whitespace)
+        .map(|(_, argument)| argument.trim_start())
+}
+
+/// Moves the selection to the next cell, that differs from the last save.
+fn next_changed_cell(
+    table: &mut CsvBuffer,
+    direction: SearchDirection,
+    wrapscan: bool,
+) -> Result<Option<ConsoleMessage>> {
+    // A diff buffer jumps between the differences instead
+    let changed = match &table.diff {
+        Some(diff) => diff.locations(),
+        None => table.csv_table.changed_cells(),
+    };
+    let from = table.selection.primary;
+    let next = match direction {
+        SearchDirection::Forward => changed.iter().find(|location| **location > from),
+        SearchDirection::Backward => changed.iter().rev().find(|location| **location < from),
+    };
+    let (location, wrapped) = match next {
+        Some(location) => (*location, false),
+        None => {
+            let first = match direction {
+                SearchDirection::Forward => changed.first(),
+                SearchDirection::Backward => changed.last(),
+            };
+            match first {
+                None => bail!("No changed cells!"),
+                Some(_) if !wrapscan => {
+                    let message = match direction {
+                        SearchDirection::Forward => "No more changes below",
+                        SearchDirection::Backward => "No more changes above",
+                    };
+                    return Ok(Some(ConsoleMessage::warning(message)));
+                }
+                Some(location) => (*location, true),
+            }
+        }
+    };
+    table.jump_to(location);
+    let message = wrapped.then(|| {
+        ConsoleMessage::warning(match direction {
+            SearchDirection::Forward => "hit BOTTOM, continuing at TOP",
+            SearchDirection::Backward => "hit TOP, continuing at BOTTOM",
+        })
+    });
+    Ok(message)
+}
+
+/// The delimiter as it is given to `:delimiter`, e.g. `\t`.
+fn delimiter_name(delimiter: Option<u8>) -> String {
+    match delimiter {
+        Some(b'\t') => r"\t".to_string(),
+        Some(delimiter) => (delimiter as char).to_string(),
+        None => "unset".to_string(),
+    }
+}
+
 fn delimiter_from_str(d: &str) -> Result<u8> {
     let res = match d {
         r"\t" => b'\t',
@@ -1278,3 +5144,11 @@ fn delimiter_from_str(d: &str) -> Result<u8> {
     };
     Ok(res)
 }
+
+fn char_from_str(c: &str) -> Result<char> {
+    let mut chars = c.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => bail!("Expected a single character: {c}"),
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::WrapErr;
+
+use crate::content::CellLocation;
+
+/// Notes of cells, that are kept outside of the CSV file.
+pub(crate) type Notes = BTreeMap<CellLocation, String>;
+
+/// The sidecar file next to a CSV file, e.g. `data.csv.ratcsv-notes.json`.
+pub(crate) fn sidecar_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".ratcsv-notes.json");
+    path.with_file_name(file_name)
+}
+
+/// Reads the notes of the CSV file at `path`. Without a sidecar file, there are no notes.
+pub(crate) fn load(path: &Path) -> color_eyre::Result<Notes> {
+    let sidecar_path = sidecar_path(path);
+    let content = match fs::read_to_string(&sidecar_path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Notes::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let invalid = || format!("Invalid notes file: {}", sidecar_path.to_string_lossy());
+    let by_id: BTreeMap<String, String> = serde_json::from_str(&content).wrap_err_with(invalid)?;
+    by_id
+        .into_iter()
+        .map(|(id, note)| Ok((CellLocation::from_a1(&id).wrap_err_with(invalid)?, note)))
+        .collect()
+}
+
+/// Writes the notes of the CSV file at `path` keyed by A1 coordinates. Without notes, the sidecar
+/// file is removed.
+pub(crate) fn save(path: &Path, notes: &Notes) -> color_eyre::Result<()> {
+    let sidecar_path = sidecar_path(path);
+    if notes.is_empty() {
+        return match fs::remove_file(&sidecar_path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        };
+    }
+    let by_id = notes
+        .iter()
+        .map(|(location, note)| (location.to_a1(), note))
+        .collect::<BTreeMap<_, _>>();
+    fs::write(&sidecar_path, serde_json::to_string_pretty(&by_id)?)?;
+    Ok(())
+}
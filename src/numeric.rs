@@ -0,0 +1,342 @@
+use std::{collections::HashSet, str::FromStr};
+
+use color_eyre::eyre::bail;
+
+/// Parses a number, as it is commonly written in CSV files of different locales, e.g. `1234.5`,
+/// `1,234.5`, `1.234,5`, `1 234,5` or `1,5`. Returns `None` for non-numeric and non-finite
+/// values.
+pub(crate) fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(number) = value.parse::<f64>() {
+        return number.is_finite().then_some(number);
+    }
+
+    // Group separators, that are never decimal separators
+    let value = value
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '\'' | '\u{a0}' | '\u{202f}'))
+        .collect::<String>();
+    let normalized = match (value.rfind('.'), value.rfind(',')) {
+        // The last separator is the decimal separator
+        (Some(dot), Some(comma)) if dot > comma => value.replace(',', ""),
+        (Some(_), Some(_)) => value.replace('.', "").replace(',', "."),
+        (None, Some(comma)) => {
+            let is_decimal = value.matches(',').count() == 1 && value.len() - comma - 1 != 3;
+            if is_decimal {
+                value.replace(',', ".")
+            } else {
+                value.replace(',', "")
+            }
+        }
+        _ => value,
+    };
+    let number = normalized.parse::<f64>().ok()?;
+    number.is_finite().then_some(number)
+}
+
+/// Adds `delta` to a plain number like `42`, `007` or `-1.50`, keeping its leading zeros and
+/// decimal places. Returns `None` for other values.
+pub(crate) fn increment(value: &str, delta: i64) -> Option<String> {
+    let (sign, digits) = match value.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (int, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() || !(int.chars().chain(fraction.chars())).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Calculate in units of the last decimal place, so no precision is lost
+    let scale = 10i128.checked_pow(fraction.len() as u32)?;
+    let units = sign * format!("{int}{fraction}").parse::<i128>().ok()?;
+    let units = units.checked_add(i128::from(delta).checked_mul(scale)?)?;
+    let abs = units.unsigned_abs();
+    let width = if int.len() > 1 && int.starts_with('0') {
+        int.len()
+    } else {
+        1
+    };
+    let mut result = format!("{:0width$}", abs / scale as u128);
+    if !fraction.is_empty() {
+        result = format!(
+            "{result}.{:0len$}",
+            abs % scale as u128,
+            len = fraction.len()
+        );
+    }
+    if units < 0 {
+        result.insert(0, '-');
+    }
+    Some(result)
+}
+
+/// Formats a number compactly for labels.
+pub(crate) fn format_number(number: f64) -> String {
+    let abs = number.abs();
+    if abs != 0.0 && !(1e-3..1e7).contains(&abs) {
+        return format!("{number:.2e}");
+    }
+    let formatted = format!("{number:.3}");
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    if formatted == "-0" {
+        return "0".to_owned();
+    }
+    formatted.to_owned()
+}
+
+/// Counts of numeric values in equally wide bins between their minimum and maximum.
+#[derive(Clone, Debug)]
+pub(crate) struct Histogram {
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+    pub(crate) bins: Vec<usize>,
+    /// Number of empty and non-numeric values
+    pub(crate) excluded: usize,
+}
+
+impl Histogram {
+    /// Returns `None`, if there is no numeric value. A constant column results in a single bin.
+    pub(crate) fn new<'a>(
+        values: impl IntoIterator<Item = Option<&'a str>>,
+        bin_count: usize,
+    ) -> Option<Self> {
+        let mut excluded = 0;
+        let numbers = values
+            .into_iter()
+            .filter_map(|value| {
+                let number = value.and_then(parse_number);
+                if number.is_none() {
+                    excluded += 1;
+                }
+                number
+            })
+            .collect::<Vec<_>>();
+        let min = numbers.iter().copied().reduce(f64::min)?;
+        let max = numbers.iter().copied().reduce(f64::max)?;
+
+        let bin_count = if min == max { 1 } else { bin_count.max(1) };
+        let mut bins = vec![0; bin_count];
+        // Halved, so that huge ranges don't overflow to infinity
+        let range = max / 2.0 - min / 2.0;
+        for number in numbers {
+            let position = if range > 0.0 {
+                (number / 2.0 - min / 2.0) / range
+            } else {
+                0.0
+            };
+            let index = ((position * bin_count as f64) as usize).min(bin_count - 1);
+            bins[index] += 1;
+        }
+        Some(Self {
+            min,
+            max,
+            bins,
+            excluded,
+        })
+    }
+
+    /// The lower and upper bound of a bin.
+    pub(crate) fn bin_range(&self, index: usize) -> (f64, f64) {
+        let count = self.bins.len() as f64;
+        let lerp = |i: f64| self.min + (self.max / count - self.min / count) * i;
+        (lerp(index as f64), lerp(index as f64 + 1.0))
+    }
+}
+
+/// Summary statistics of the numeric values among some cells.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Stats {
+    /// Number of numeric values
+    pub(crate) count: usize,
+    pub(crate) sum: f64,
+    pub(crate) mean: Option<f64>,
+    pub(crate) min: Option<f64>,
+    pub(crate) max: Option<f64>,
+    pub(crate) median: Option<f64>,
+    /// Number of different non-empty values, including non-numeric ones
+    pub(crate) distinct: usize,
+    /// Number of non-empty, non-numeric values
+    pub(crate) excluded: usize,
+}
+
+impl Stats {
+    pub(crate) fn new<'a>(values: impl IntoIterator<Item = Option<&'a str>>) -> Self {
+        let mut distinct = HashSet::new();
+        let mut excluded = 0;
+        let mut numbers = Vec::new();
+        for value in values.into_iter().flatten() {
+            if value.trim().is_empty() {
+                continue;
+            }
+            distinct.insert(value);
+            match parse_number(value) {
+                Some(number) => numbers.push(number),
+                None => excluded += 1,
+            }
+        }
+        numbers.sort_by(f64::total_cmp);
+        let count = numbers.len();
+        let sum = numbers.iter().sum::<f64>();
+        let median = match count {
+            0 => None,
+            count if count % 2 == 0 => {
+                Some(numbers[count / 2 - 1] / 2.0 + numbers[count / 2] / 2.0)
+            }
+            count => Some(numbers[count / 2]),
+        };
+        Self {
+            count,
+            sum,
+            mean: (count > 0).then(|| sum / count as f64),
+            min: numbers.first().copied(),
+            max: numbers.last().copied(),
+            median,
+            distinct: distinct.len(),
+            excluded,
+        }
+    }
+}
+
+/// A function of the values of a column, that is shown in the footer row or a pivot table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Aggregate {
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    Median,
+    Distinct,
+}
+
+impl Aggregate {
+    fn name(self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Mean => "mean",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+            Aggregate::Median => "median",
+            Aggregate::Distinct => "distinct",
+        }
+    }
+
+    pub(crate) fn apply(self, stats: &Stats) -> String {
+        let number = match self {
+            Aggregate::Count => return stats.count.to_string(),
+            Aggregate::Distinct => return stats.distinct.to_string(),
+            Aggregate::Sum => Some(stats.sum),
+            Aggregate::Mean => stats.mean,
+            Aggregate::Min => stats.min,
+            Aggregate::Max => stats.max,
+            Aggregate::Median => stats.median,
+        };
+        number.map_or("-".to_owned(), format_number)
+    }
+}
+
+impl FromStr for Aggregate {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s {
+            "count" => Aggregate::Count,
+            "sum" => Aggregate::Sum,
+            "mean" | "avg" => Aggregate::Mean,
+            "min" => Aggregate::Min,
+            "max" => Aggregate::Max,
+            "median" => Aggregate::Median,
+            "distinct" => Aggregate::Distinct,
+            _ => bail!(
+                "Unknown aggregate: {s}. Use `count`, `sum`, `mean`, `min`, `max`, `median` or \
+                 `distinct`"
+            ),
+        };
+        Ok(res)
+    }
+}
+
+/// An aggregate of a column with its value for a revision of the table.
+#[derive(Clone, Debug)]
+pub(crate) struct AggregateCell {
+    pub(crate) aggregate: Aggregate,
+    value: String,
+    /// Revision of the table and whether a header row was left out, that the value was
+    /// computed for
+    revision: Option<(u64, bool)>,
+}
+
+impl AggregateCell {
+    pub(crate) fn new(aggregate: Aggregate) -> Self {
+        Self {
+            aggregate,
+            value: String::new(),
+            revision: None,
+        }
+    }
+
+    /// Recomputes the value, if the table or its header changed since the last update.
+    pub(crate) fn update<'a>(
+        &mut self,
+        revision: u64,
+        header: bool,
+        values: impl Iterator<Item = Option<&'a str>>,
+    ) {
+        if self.revision == Some((revision, header)) {
+            return;
+        }
+        self.revision = Some((revision, header));
+        self.value = self
+            .aggregate
+            .apply(&Stats::new(values.skip(usize::from(header))));
+    }
+
+    /// The name of the aggregate and its value, e.g. `sum 42`.
+    pub(crate) fn text(&self) -> String {
+        format!("{} {}", self.aggregate.name(), self.value)
+    }
+}
+
+/// A column, whose cells are colored by their value between the minimum and maximum of the
+/// column.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Heatmap {
+    /// Minimum and maximum of the numeric values
+    range: Option<(f64, f64)>,
+    /// Revision of the table, that the range was computed for
+    revision: Option<u64>,
+}
+
+impl Heatmap {
+    /// Recomputes the range, if the table changed since the last update.
+    pub(crate) fn update<'a>(
+        &mut self,
+        revision: u64,
+        values: impl IntoIterator<Item = Option<&'a str>>,
+    ) {
+        if self.revision == Some(revision) {
+            return;
+        }
+        self.revision = Some(revision);
+        self.range = values
+            .into_iter()
+            .filter_map(|value| value.and_then(parse_number))
+            .fold(None, |range, number| match range {
+                None => Some((number, number)),
+                Some((min, max)) => Some((number.min(min), number.max(max))),
+            });
+    }
+
+    /// Position of the value between the minimum (`0.0`) and maximum (`1.0`) of the column.
+    pub(crate) fn position(&self, value: &str) -> Option<f32> {
+        let (min, max) = self.range?;
+        let number = parse_number(value)?;
+        if max == min {
+            return Some(0.5);
+        }
+        Some(((number / 2.0 - min / 2.0) / (max / 2.0 - min / 2.0)) as f32)
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use color_eyre::eyre::{bail, eyre};
+
+use crate::task::Progress;
+
+/// How often a running command is checked for having exited or its task for being cancelled
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs `command` with `sh -c`, writes `input` to its stdin and returns its stdout. If the task
+/// is cancelled, the command is killed.
+pub(crate) fn pipe_through(
+    command: &str,
+    input: String,
+    progress: &Progress,
+) -> color_eyre::Result<String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| eyre!("Failed to run {command}: {err}"))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // Written and read from other threads, so a command, that outputs before reading everything,
+    // can't block on a full pipe
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let read_all = |mut pipe: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut bytes = Vec::new();
+            pipe.read_to_end(&mut bytes).map(|_| bytes)
+        })
+    };
+    let stdout = read_all(Box::new(child.stdout.take().expect("stdout is piped")));
+    let stderr = read_all(Box::new(child.stderr.take().expect("stderr is piped")));
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if let Err(err) = progress.check_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    // Commands like `head` may exit without reading their whole input
+    let _ = writer.join();
+    let join = |reader: thread::JoinHandle<std::io::Result<Vec<u8>>>| {
+        reader
+            .join()
+            .map_err(|_| eyre!("Reading the output of {command} failed"))?
+            .map_err(|err| eyre!("Reading the output of {command} failed: {err}"))
+    };
+    let (stdout, stderr) = (join(stdout)?, join(stderr)?);
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        match stderr.lines().find(|line| !line.trim().is_empty()) {
+            Some(line) => bail!("{command}: {line}"),
+            None => bail!("{command} exited with {status}"),
+        }
+    }
+    String::from_utf8(stdout).map_err(|_| eyre!("{command} wrote invalid UTF-8"))
+}
@@ -0,0 +1,73 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    content::{CellLocation, CsvTable},
+    numeric::{Aggregate, Stats},
+    sort,
+    task::Progress,
+};
+
+/// The columns and the aggregate of a pivot table.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Pivot {
+    /// Column, whose values become the rows
+    pub(crate) row_col: usize,
+    /// Column, whose values become the columns
+    pub(crate) col_col: usize,
+    /// Column, whose values are aggregated
+    pub(crate) value_col: usize,
+    pub(crate) aggregate: Aggregate,
+}
+
+impl Pivot {
+    /// A table with a row for every value of the row column and a column for every value of the
+    /// column column, both sorted, where each cell aggregates the values of the rows with both.
+    /// The first row and column hold the values and the top left cell names the row column.
+    pub(crate) fn build(
+        &self,
+        table: &CsvTable,
+        header: bool,
+        progress: &Progress,
+    ) -> color_eyre::Result<CsvTable> {
+        let row_count = table.used_extent().row_count;
+        let get = |row, col| table.get(CellLocation { row, col }).unwrap_or_default();
+        let mut groups = HashMap::<(&str, &str), Vec<Option<&str>>>::new();
+        let mut row_keys = HashSet::new();
+        let mut col_keys = HashSet::new();
+        for row in usize::from(header)..row_count {
+            progress.report(row, row_count)?;
+            let row_key = get(row, self.row_col);
+            let col_key = get(row, self.col_col);
+            row_keys.insert(row_key);
+            col_keys.insert(col_key);
+            groups
+                .entry((row_key, col_key))
+                .or_default()
+                .push(Some(get(row, self.value_col)));
+        }
+        let mut row_keys = row_keys.into_iter().collect::<Vec<_>>();
+        let mut col_keys = col_keys.into_iter().collect::<Vec<_>>();
+        sort::sort_values(&mut row_keys);
+        sort::sort_values(&mut col_keys);
+
+        let corner = if header {
+            get(0, self.row_col).to_owned()
+        } else {
+            CellLocation::col_index_to_letters(self.row_col)
+        };
+        let mut rows = vec![
+            std::iter::once(Some(corner))
+                .chain(col_keys.iter().map(|key| Some((*key).to_owned())))
+                .collect(),
+        ];
+        rows.extend(row_keys.iter().map(|row_key| {
+            std::iter::once(Some((*row_key).to_owned()))
+                .chain(col_keys.iter().map(|col_key| {
+                    let values = groups.get(&(*row_key, *col_key))?;
+                    Some(self.aggregate.apply(&Stats::new(values.iter().copied())))
+                }))
+                .collect()
+        }));
+        Ok(CsvTable::from_rows(rows, table.delimiter))
+    }
+}
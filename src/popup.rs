@@ -0,0 +1,742 @@
+use std::path::PathBuf;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::{
+    buffer::CsvBuffer,
+    content::{CellLocation, CoordinateStyle},
+    help::HELP_SECTIONS,
+    keymap::Keymap,
+    numeric::{Histogram, Stats, format_number},
+};
+
+const CELL_PREVIEW_WIDTH: u16 = 48;
+const CELL_PREVIEW_MAX_HEIGHT: u16 = 14;
+const NOTES_WIDTH: u16 = 64;
+const DIFF_WIDTH: u16 = 72;
+const HISTOGRAM_WIDTH: u16 = 64;
+/// Partial blocks for the bars, in eighths
+const BAR_EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+const DIFF_MAX_HEIGHT: u16 = 16;
+const NOTES_MAX_HEIGHT: u16 = 16;
+const RECENT_WIDTH: u16 = 72;
+const RECENT_MAX_HEIGHT: u16 = 16;
+const REGISTERS_WIDTH: u16 = 64;
+const STATS_WIDTH: u16 = 36;
+const FREQ_WIDTH: u16 = 64;
+const FREQ_MAX_HEIGHT: u16 = 16;
+const REGISTERS_MAX_HEIGHT: u16 = 16;
+
+/// A distinct value of a column and how often it occurs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ValueCount {
+    pub(crate) value: String,
+    pub(crate) count: usize,
+    pub(crate) first_row: usize,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Popup {
+    Help {
+        scroll: u16,
+    },
+    CellPreview {
+        location: CellLocation,
+        scroll: u16,
+    },
+    /// Cells, that differ from the last save
+    Diff {
+        scroll: u16,
+    },
+    Histogram {
+        col: usize,
+        histogram: Histogram,
+        scroll: u16,
+    },
+    /// The note of a cell
+    Note {
+        location: CellLocation,
+        scroll: u16,
+    },
+    /// Navigable list of the cells with notes
+    Notes {
+        locations: Vec<CellLocation>,
+        selected: usize,
+        scroll: u16,
+    },
+    /// Distinct values of a column, most frequent first
+    Freq {
+        col: usize,
+        entries: Vec<ValueCount>,
+        selected: usize,
+        /// The first shown entry, as a column can have more distinct values than `u16` counts
+        scroll: usize,
+    },
+    /// Statistics of the cells, that `title` names
+    Stats {
+        title: String,
+        stats: Stats,
+        scroll: u16,
+    },
+    /// Summaries of the yank registers
+    Registers {
+        entries: Vec<(char, String)>,
+        scroll: u16,
+    },
+    /// Recently opened files, newest first
+    Recent {
+        paths: Vec<PathBuf>,
+        selected: usize,
+        scroll: u16,
+    },
+}
+
+impl Popup {
+    pub(crate) fn help() -> Self {
+        Self::Help { scroll: 0 }
+    }
+
+    pub(crate) fn cell_preview(location: CellLocation) -> Self {
+        Self::CellPreview {
+            location,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn note(location: CellLocation) -> Self {
+        Self::Note {
+            location,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn notes(locations: Vec<CellLocation>) -> Self {
+        Self::Notes {
+            locations,
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn recent(paths: Vec<PathBuf>) -> Self {
+        Self::Recent {
+            paths,
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn freq(col: usize, entries: Vec<ValueCount>) -> Self {
+        Self::Freq {
+            col,
+            entries,
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn stats(title: String, stats: Stats) -> Self {
+        Self::Stats {
+            title,
+            stats,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn registers(entries: Vec<(char, String)>) -> Self {
+        Self::Registers { entries, scroll: 0 }
+    }
+
+    pub(crate) fn histogram(col: usize, histogram: Histogram) -> Self {
+        Self::Histogram {
+            col,
+            histogram,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn diff() -> Self {
+        Self::Diff { scroll: 0 }
+    }
+
+    /// The scroll offset of popups, that aren't lists.
+    fn scroll_mut(&mut self) -> Option<&mut u16> {
+        let scroll = match self {
+            Popup::Help { scroll }
+            | Popup::Diff { scroll }
+            | Popup::Histogram { scroll, .. }
+            | Popup::CellPreview { scroll, .. }
+            | Popup::Note { scroll, .. }
+            | Popup::Notes { scroll, .. }
+            | Popup::Registers { scroll, .. }
+            | Popup::Stats { scroll, .. }
+            | Popup::Recent { scroll, .. } => scroll,
+            Popup::Freq { .. } => return None,
+        };
+        Some(scroll)
+    }
+
+    /// The selected entry and number of entries of lists.
+    fn list_selection_mut(&mut self) -> Option<(&mut usize, usize)> {
+        match self {
+            Popup::Notes {
+                locations,
+                selected,
+                ..
+            } => Some((selected, locations.len())),
+            Popup::Recent {
+                paths, selected, ..
+            } => Some((selected, paths.len())),
+            Popup::Freq {
+                entries, selected, ..
+            } => Some((selected, entries.len())),
+            _ => None,
+        }
+    }
+
+    /// Scrolls down or, in lists, moves the selected entry down.
+    pub(crate) fn scroll_down(&mut self, n: u16) {
+        if let Some((selected, len)) = self.list_selection_mut() {
+            *selected = (*selected + n as usize).min(len.saturating_sub(1));
+            return;
+        }
+        if let Some(scroll) = self.scroll_mut() {
+            *scroll = scroll.saturating_add(n);
+        }
+    }
+
+    /// Scrolls up or, in lists, moves the selected entry up.
+    pub(crate) fn scroll_up(&mut self, n: u16) {
+        if let Some((selected, _)) = self.list_selection_mut() {
+            *selected = selected.saturating_sub(n as usize);
+            return;
+        }
+        if let Some(scroll) = self.scroll_mut() {
+            *scroll = scroll.saturating_sub(n);
+        }
+    }
+
+    /// The selected location of the list of notes or the first cell with the selected value.
+    pub(crate) fn selected_location(&self) -> Option<CellLocation> {
+        match self {
+            Popup::Notes {
+                locations,
+                selected,
+                ..
+            } => locations.get(*selected).copied(),
+            Popup::Freq {
+                col,
+                entries,
+                selected,
+                ..
+            } => entries.get(*selected).map(|entry| CellLocation {
+                row: entry.first_row,
+                col: *col,
+            }),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn selected_path(&self) -> Option<&PathBuf> {
+        match self {
+            Popup::Recent {
+                paths, selected, ..
+            } => paths.get(*selected),
+            _ => None,
+        }
+    }
+
+    /// Removes the selected entry of the recent files.
+    pub(crate) fn remove_selected_path(&mut self) -> Option<PathBuf> {
+        let Popup::Recent {
+            paths, selected, ..
+        } = self
+        else {
+            return None;
+        };
+        if *selected >= paths.len() {
+            return None;
+        }
+        let path = paths.remove(*selected);
+        *selected = (*selected).min(paths.len().saturating_sub(1));
+        Some(path)
+    }
+}
+
+pub(crate) struct PopupWidget<'a> {
+    pub(crate) popup: &'a mut Popup,
+    pub(crate) table: Option<&'a CsvBuffer>,
+    pub(crate) keymap: &'a Keymap,
+    pub(crate) coords: CoordinateStyle,
+}
+
+impl Widget for PopupWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let PopupWidget {
+            popup,
+            table,
+            keymap,
+            coords,
+        } = self;
+        match popup {
+            Popup::Help { scroll } => {
+                let lines = help_lines(keymap);
+                let popup_area = centered_rect(area, 64, lines.len() as u16 + 2);
+                render_scrollable(popup_area, buf, " Help ", lines, scroll);
+            }
+            Popup::CellPreview { location, scroll } => {
+                let Some(table) = table else {
+                    return;
+                };
+                let content = table.csv_table.get(*location).unwrap_or_default();
+                let title = format!(
+                    " {location} · {} bytes · {} chars ",
+                    content.len(),
+                    content.chars().count()
+                );
+                let paragraph = Paragraph::new(content).wrap(Wrap { trim: false });
+                let width = CELL_PREVIEW_WIDTH.min(area.width);
+                let line_count = paragraph.line_count(width.saturating_sub(2)).max(1) as u16;
+                let height = (line_count + 2).min(CELL_PREVIEW_MAX_HEIGHT);
+                let anchor = table.cell_area(*location).unwrap_or_default();
+                let popup_area = anchored_rect(area, anchor, width, height);
+
+                let max_scroll = line_count.saturating_sub(popup_area.height.saturating_sub(2));
+                *scroll = (*scroll).min(max_scroll);
+                Clear.render(popup_area, buf);
+                paragraph
+                    .block(Block::bordered().title(title))
+                    .scroll((*scroll, 0))
+                    .render(popup_area, buf);
+            }
+            Popup::Note { location, scroll } => {
+                let Some(table) = table else {
+                    return;
+                };
+                let note = table.notes.get(location).map_or("", String::as_str);
+                let title = format!(" Note of {location} · e: edit, d: delete ");
+                let paragraph = Paragraph::new(note).wrap(Wrap { trim: false });
+                let width = CELL_PREVIEW_WIDTH.min(area.width);
+                let line_count = paragraph.line_count(width.saturating_sub(2)).max(1) as u16;
+                let height = (line_count + 2).min(CELL_PREVIEW_MAX_HEIGHT);
+                let anchor = table.cell_area(*location).unwrap_or_default();
+                let popup_area = anchored_rect(area, anchor, width, height);
+
+                let max_scroll = line_count.saturating_sub(popup_area.height.saturating_sub(2));
+                *scroll = (*scroll).min(max_scroll);
+                Clear.render(popup_area, buf);
+                paragraph
+                    .block(Block::bordered().title(title))
+                    .scroll((*scroll, 0))
+                    .render(popup_area, buf);
+            }
+            Popup::Notes {
+                locations,
+                selected,
+                scroll,
+            } => {
+                let Some(table) = table else {
+                    return;
+                };
+                let text_width = NOTES_WIDTH.saturating_sub(2 + 12) as usize;
+                let lines = locations
+                    .iter()
+                    .enumerate()
+                    .map(|(i, location)| {
+                        let note = table.notes.get(location).map_or("", String::as_str);
+                        let line = Line::raw(format!(
+                            " {:<9} {}",
+                            location.to_string(),
+                            truncate(note, text_width)
+                        ));
+                        if i == *selected {
+                            line.reversed()
+                        } else {
+                            line
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let height = (lines.len() as u16 + 2).min(NOTES_MAX_HEIGHT);
+                let popup_area = centered_rect(area, NOTES_WIDTH, height);
+                scroll_to_selected(popup_area, *selected, scroll);
+                let title = format!(" {} notes ", locations.len());
+                render_scrollable(popup_area, buf, &title, lines, scroll);
+            }
+            Popup::Diff { scroll } => {
+                let Some(table) = table else {
+                    return;
+                };
+                let changed = table.csv_table.changed_cells();
+                let text_width = (DIFF_WIDTH.saturating_sub(2 + 11) as usize - 3) / 2;
+                let lines = changed
+                    .iter()
+                    .map(|location| {
+                        let saved = table
+                            .csv_table
+                            .saved_value(*location)
+                            .flatten()
+                            .unwrap_or_default();
+                        let current = table.csv_table.get(*location).unwrap_or_default();
+                        Line::from(vec![
+                            Span::raw(format!(" {:<9} ", location.to_string())),
+                            Span::raw(truncate(saved, text_width)).crossed_out(),
+                            Span::raw(" → "),
+                            Span::raw(truncate(current, text_width)).bold(),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                let height = (lines.len() as u16 + 2).min(DIFF_MAX_HEIGHT);
+                let popup_area = centered_rect(area, DIFF_WIDTH, height);
+                let title = format!(" {} changed cells ", changed.len());
+                render_scrollable(popup_area, buf, &title, lines, scroll);
+            }
+            Popup::Histogram {
+                col,
+                histogram,
+                scroll,
+            } => {
+                let lines = histogram_lines(histogram, HISTOGRAM_WIDTH.saturating_sub(2));
+                let popup_area = centered_rect(area, HISTOGRAM_WIDTH, lines.len() as u16 + 2);
+                let title = format!(
+                    " Histogram of {} ",
+                    CellLocation::col_index_to_id(*col, coords)
+                );
+                render_scrollable(popup_area, buf, &title, lines, scroll);
+            }
+            Popup::Freq {
+                col,
+                entries,
+                selected,
+                scroll,
+            } => {
+                let height = (entries.len() + 2).min(usize::from(FREQ_MAX_HEIGHT)) as u16;
+                let popup_area = centered_rect(area, FREQ_WIDTH, height);
+                // Only the shown entries become lines, as there can be many
+                let shown = usize::from(popup_area.height.saturating_sub(2)).max(1);
+                *scroll = (*scroll)
+                    .min(*selected)
+                    .max((*selected + 1).saturating_sub(shown));
+                let text_width = FREQ_WIDTH.saturating_sub(2 + 9) as usize;
+                let lines = entries
+                    .iter()
+                    .enumerate()
+                    .skip(*scroll)
+                    .take(shown)
+                    .map(|(i, entry)| {
+                        let value = if entry.value.is_empty() {
+                            Span::raw("(empty)").dim()
+                        } else {
+                            Span::raw(truncate(&entry.value, text_width))
+                        };
+                        let line =
+                            Line::from(vec![Span::raw(format!(" {:>6}  ", entry.count)), value]);
+                        if i == *selected {
+                            line.reversed()
+                        } else {
+                            line
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let title = format!(
+                    " Values of {} · {} distinct ",
+                    CellLocation::col_index_to_id(*col, coords),
+                    entries.len()
+                );
+                render_scrollable(popup_area, buf, &title, lines, &mut 0);
+            }
+            Popup::Stats {
+                title,
+                stats,
+                scroll,
+            } => {
+                let number = |number: Option<f64>| number.map_or("-".to_owned(), format_number);
+                let rows = [
+                    ("Count", stats.count.to_string()),
+                    ("Sum", format_number(stats.sum)),
+                    ("Mean", number(stats.mean)),
+                    ("Min", number(stats.min)),
+                    ("Max", number(stats.max)),
+                    ("Median", number(stats.median)),
+                    ("Distinct", stats.distinct.to_string()),
+                    ("Non-numeric", stats.excluded.to_string()),
+                ];
+                let lines = rows
+                    .into_iter()
+                    .map(|(label, value)| {
+                        Line::from(vec![
+                            Span::raw(format!(" {label:<12}")).bold(),
+                            Span::raw(value),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                let popup_area = centered_rect(area, STATS_WIDTH, lines.len() as u16 + 2);
+                render_scrollable(
+                    popup_area,
+                    buf,
+                    &format!(" Stats of {title} "),
+                    lines,
+                    scroll,
+                );
+            }
+            Popup::Registers { entries, scroll } => {
+                let text_width = REGISTERS_WIDTH.saturating_sub(2 + 4) as usize;
+                let lines = entries
+                    .iter()
+                    .map(|(register, summary)| {
+                        Line::from(vec![
+                            Span::raw(format!(" \"{register} ")).bold(),
+                            Span::raw(truncate(summary, text_width)),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                let height = (lines.len() as u16 + 2).min(REGISTERS_MAX_HEIGHT);
+                let popup_area = centered_rect(area, REGISTERS_WIDTH, height);
+                render_scrollable(popup_area, buf, " Registers ", lines, scroll);
+            }
+            Popup::Recent {
+                paths,
+                selected,
+                scroll,
+            } => {
+                let text_width = RECENT_WIDTH.saturating_sub(4) as usize;
+                let lines = paths
+                    .iter()
+                    .enumerate()
+                    .map(|(i, path)| {
+                        let line = Line::raw(format!(
+                            " {}",
+                            truncate_start(&path.to_string_lossy(), text_width)
+                        ));
+                        let line = if path.exists() { line } else { line.dim() };
+                        if i == *selected {
+                            line.reversed()
+                        } else {
+                            line
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let height = (lines.len() as u16 + 2).min(RECENT_MAX_HEIGHT);
+                let popup_area = centered_rect(area, RECENT_WIDTH, height);
+                scroll_to_selected(popup_area, *selected, scroll);
+                render_scrollable(popup_area, buf, " Recent files · d: remove ", lines, scroll);
+            }
+        }
+    }
+}
+
+/// Keeps the selected entry of a list in the visible part of the popup.
+fn scroll_to_selected(popup_area: Rect, selected: usize, scroll: &mut u16) {
+    let inner_height = popup_area.height.saturating_sub(2).max(1);
+    let selected = selected as u16;
+    if selected < *scroll {
+        *scroll = selected;
+    } else if selected >= *scroll + inner_height {
+        *scroll = selected + 1 - inner_height;
+    }
+}
+
+fn histogram_lines(histogram: &Histogram, width: u16) -> Vec<Line<'static>> {
+    let labels = (0..histogram.bins.len())
+        .map(|i| {
+            let (low, high) = histogram.bin_range(i);
+            if histogram.bins.len() == 1 {
+                format_number(low)
+            } else {
+                format!("{} – {}", format_number(low), format_number(high))
+            }
+        })
+        .collect::<Vec<_>>();
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let max_count = histogram.bins.iter().copied().max().unwrap_or(0).max(1);
+    let count_width = max_count.to_string().len();
+    let bar_width = (width as usize).saturating_sub(label_width + count_width + 4);
+
+    let mut lines = labels
+        .into_iter()
+        .zip(&histogram.bins)
+        .map(|(label, count)| {
+            let eighths = count * bar_width * 8 / max_count;
+            let mut bar = BAR_EIGHTHS[7].to_string().repeat(eighths / 8);
+            let partial = eighths % 8;
+            if partial > 0 {
+                bar.push(BAR_EIGHTHS[partial - 1]);
+            }
+            Line::from(vec![
+                Span::raw(format!(" {label:>label_width$} ")),
+                Span::raw(format!("{bar:<bar_width$}")).bold(),
+                Span::raw(format!(" {count:>count_width$}")),
+            ])
+        })
+        .collect::<Vec<_>>();
+    lines.push(Line::default());
+    lines.push(Line::raw(format!(
+        " {} values, {} empty or non-numeric cells excluded",
+        histogram.bins.iter().sum::<usize>(),
+        histogram.excluded
+    )));
+    lines
+}
+
+/// The first line of `text`, cut to `width` characters.
+fn truncate(text: &str, width: usize) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() <= width && !text.contains('\n') {
+        return line.to_owned();
+    }
+    let mut truncated = line
+        .chars()
+        .take(width.saturating_sub(1))
+        .collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// `text` cut from the start to `width` characters, so the end of long paths stays visible.
+fn truncate_start(text: &str, width: usize) -> String {
+    let count = text.chars().count();
+    if count <= width {
+        return text.to_owned();
+    }
+    let mut truncated = "…".to_owned();
+    truncated.extend(text.chars().skip(count + 1 - width.max(1)));
+    truncated
+}
+
+fn help_lines(keymap: &Keymap) -> Vec<Line<'static>> {
+    let keys_width = HELP_SECTIONS
+        .iter()
+        .flat_map(|section| section.entries)
+        .map(|entry| entry.keys(keymap).chars().count())
+        .max()
+        .unwrap_or_default();
+
+    let mut lines = Vec::new();
+    for (i, section) in HELP_SECTIONS.iter().enumerate() {
+        if i > 0 {
+            lines.push(Line::default());
+        }
+        lines.push(Line::from(section.title.bold()));
+        for entry in section.entries {
+            lines.push(Line::from(vec![
+                Span::raw(format!("  {:<keys_width$}  ", entry.keys(keymap))),
+                Span::raw(entry.description),
+            ]));
+        }
+    }
+    lines
+}
+
+/// The keys, that complete a pending combo, in the bottom right corner of the table.
+pub(crate) struct ComboHintWidget {
+    /// The keys of the combo so far, e.g. `g`
+    pub(crate) prefix: String,
+    /// Keys with what they do
+    pub(crate) hints: Vec<(String, String)>,
+}
+
+impl Widget for ComboHintWidget {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let key_width = self
+            .hints
+            .iter()
+            .map(|(key, _)| key.chars().count())
+            .max()
+            .unwrap_or_default();
+        let lines = self
+            .hints
+            .iter()
+            .map(|(key, description)| {
+                Line::from(vec![
+                    Span::raw(format!(" {key:<key_width$} ")).bold(),
+                    Span::raw(format!("{description} ")),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let title = format!(" {} ", self.prefix);
+        let width = lines
+            .iter()
+            .map(Line::width)
+            .max()
+            .unwrap_or_default()
+            .max(title.chars().count()) as u16
+            + 2;
+        let width = width.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let popup_area = Rect {
+            x: area.right() - width,
+            y: area.bottom() - height,
+            width,
+            height,
+        };
+        Clear.render(popup_area, buf);
+        Paragraph::new(lines)
+            .block(Block::bordered().title(title))
+            .render(popup_area, buf);
+    }
+}
+
+/// Renders lines in a bordered box and clamps the scroll offset to the content.
+fn render_scrollable(
+    area: Rect,
+    buf: &mut Buffer,
+    title: &str,
+    lines: Vec<Line<'_>>,
+    scroll: &mut u16,
+) {
+    let inner_height = area.height.saturating_sub(2);
+    let max_scroll = (lines.len() as u16).saturating_sub(inner_height);
+    *scroll = (*scroll).min(max_scroll);
+
+    Clear.render(area, buf);
+    Paragraph::new(lines)
+        .block(Block::bordered().title(title))
+        .style(Style::new())
+        .scroll((*scroll, 0))
+        .render(area, buf);
+}
+
+/// A rectangle of the given size centered in `area` and clamped to it.
+pub(crate) fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// A rectangle of the given size below (or, if there is no space, above) `anchor`, clamped to
+/// `area`.
+fn anchored_rect(area: Rect, anchor: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    let below = anchor.bottom();
+    let y = if below + height <= area.bottom() {
+        below
+    } else if anchor.y >= area.y + height {
+        anchor.y - height
+    } else {
+        area.bottom() - height
+    };
+    let x = anchor.x.clamp(area.x, area.right() - width);
+    Rect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+const RECENT_FILES_LIMIT: usize = 50;
+
+/// The most recently opened files, newest first, which are kept across sessions in the state
+/// directory.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RecentFiles {
+    paths: Vec<PathBuf>,
+    /// Only a loaded list is written back, so e.g. batch runs don't touch the state file
+    persist: bool,
+}
+
+impl RecentFiles {
+    /// Reads the list from the state file. A missing or unreadable file results in an empty list.
+    pub(crate) fn load() -> Self {
+        let paths = state_file("recent")
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from)
+                    .take(RECENT_FILES_LIMIT)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            paths,
+            persist: true,
+        }
+    }
+
+    pub(crate) fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Moves `path` to the front of the list.
+    pub(crate) fn push(&mut self, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(RECENT_FILES_LIMIT);
+        self.save();
+    }
+
+    pub(crate) fn remove(&mut self, path: &Path) {
+        self.paths.retain(|p| p != path);
+        self.save();
+    }
+
+    /// Writes the list to the state file. The list is only a convenience, so failing to write it
+    /// is ignored.
+    fn save(&self) {
+        if !self.persist {
+            return;
+        }
+        let Some(file) = state_file("recent") else {
+            return;
+        };
+        if let Some(parent) = file.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let content = self
+            .paths
+            .iter()
+            .map(|path| format!("{}\n", path.to_string_lossy()))
+            .collect::<String>();
+        let _ = fs::write(file, content);
+    }
+}
+
+/// `$XDG_STATE_HOME/ratcsv/<name>`, falling back to `~/.local/state/ratcsv/<name>`.
+pub(crate) fn state_file(name: &str) -> Option<PathBuf> {
+    let state_dir = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))?;
+    Some(state_dir.join("ratcsv").join(name))
+}
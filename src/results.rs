@@ -0,0 +1,119 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::content::CellLocation;
+
+/// Entries, that are visible in the pane at most
+const RESULTS_MAX_HEIGHT: u16 = 8;
+
+/// A list of locations with a description, e.g. search matches, that is shown in a pane below
+/// the table and navigated with `]q` and `[q`.
+#[derive(Clone, Debug)]
+pub(crate) struct ResultList {
+    pub(crate) title: String,
+    pub(crate) entries: Vec<(CellLocation, String)>,
+    pub(crate) cursor: usize,
+    scroll: usize,
+}
+
+impl ResultList {
+    pub(crate) fn new(title: String, entries: Vec<(CellLocation, String)>) -> Self {
+        Self {
+            title,
+            entries,
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn selected(&self) -> Option<CellLocation> {
+        self.entries.get(self.cursor).map(|(location, _)| *location)
+    }
+
+    /// Moves the cursor by `delta` entries, wrapping around at the ends.
+    pub(crate) fn step(&mut self, delta: isize) {
+        let len = self.entries.len() as isize;
+        if len > 0 {
+            self.cursor = (self.cursor as isize + delta).rem_euclid(len) as usize;
+        }
+    }
+
+    /// Moves the cursor by `delta` entries, stopping at the ends.
+    pub(crate) fn move_cursor(&mut self, delta: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(delta)
+            .min(self.entries.len().saturating_sub(1));
+    }
+
+    /// E.g. `(2 of 10) A5: foo`
+    pub(crate) fn status(&self) -> String {
+        let Some((location, text)) = self.entries.get(self.cursor) else {
+            return "No entries".to_owned();
+        };
+        let text = text.lines().next().unwrap_or_default();
+        format!(
+            "({} of {}) {location}: {text}",
+            self.cursor + 1,
+            self.entries.len()
+        )
+    }
+
+    /// Height of the pane including its title.
+    pub(crate) fn height(&self) -> u16 {
+        (self.entries.len() as u16).clamp(1, RESULTS_MAX_HEIGHT) + 1
+    }
+}
+
+pub(crate) struct ResultListWidget<'a> {
+    pub(crate) results: &'a mut ResultList,
+    /// Whether the pane takes the keys, so the cursor is highlighted
+    pub(crate) focused: bool,
+}
+
+impl Widget for ResultListWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let ResultListWidget { results, focused } = self;
+        let hint = if focused {
+            "Enter: jump, q: close"
+        } else {
+            ":copen to focus"
+        };
+        let title = format!(" {} · {hint} ", results.title);
+        let block = Block::new().borders(Borders::TOP).title(title);
+        let inner_height = block.inner(area).height.max(1) as usize;
+        if results.cursor < results.scroll {
+            results.scroll = results.cursor;
+        } else if results.cursor >= results.scroll + inner_height {
+            results.scroll = results.cursor + 1 - inner_height;
+        }
+        let lines = results
+            .entries
+            .iter()
+            .enumerate()
+            .skip(results.scroll)
+            .take(inner_height)
+            .map(|(i, (location, text))| {
+                let text = text.lines().next().unwrap_or_default();
+                let line = Line::raw(format!(" {:<9} {text}", location.to_string()));
+                match (i == results.cursor, focused) {
+                    (true, true) => line.reversed(),
+                    (true, false) => line.bold(),
+                    _ => line,
+                }
+            })
+            .collect::<Vec<_>>();
+        Paragraph::new(lines)
+            .block(block)
+            .style(Style::new())
+            .render(area, buf);
+    }
+}
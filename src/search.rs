@@ -0,0 +1,62 @@
+use color_eyre::eyre::eyre;
+use regex::{Regex, RegexBuilder};
+
+use crate::settings::{SearchMode, Settings};
+
+/// Prefix, that makes a pattern literal regardless of the `search` option
+pub(crate) const LITERAL_PREFIX: &str = r"\V";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+/// The last search of a buffer.
+#[derive(Clone, Debug)]
+pub(crate) struct Search {
+    pub(crate) pattern: String,
+    pub(crate) literal: bool,
+    regex: Regex,
+}
+
+impl Search {
+    pub(crate) fn new(pattern: &str, settings: &Settings) -> color_eyre::Result<Self> {
+        let (regex, literal) = build_regex(pattern, settings, None)?;
+        Ok(Self {
+            pattern: pattern.to_owned(),
+            literal,
+            regex,
+        })
+    }
+
+    pub(crate) fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+/// Whether a pattern typed by the user is matched as plain text.
+pub(crate) fn is_literal(pattern: &str, settings: &Settings) -> bool {
+    pattern.starts_with(LITERAL_PREFIX) || settings.search == SearchMode::Literal
+}
+
+/// Compiles a pattern typed by the user and returns, whether it is matched as plain text. Without
+/// `ignore_case`, the case options of `settings` apply.
+pub(crate) fn build_regex(
+    pattern: &str,
+    settings: &Settings,
+    ignore_case: Option<bool>,
+) -> color_eyre::Result<(Regex, bool)> {
+    let literal = is_literal(pattern, settings);
+    let pattern = match pattern.strip_prefix(LITERAL_PREFIX) {
+        Some(text) => regex::escape(text),
+        None if literal => regex::escape(pattern),
+        None => pattern.to_owned(),
+    };
+    let ignore_case = ignore_case.unwrap_or_else(|| settings.ignore_case(&pattern));
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|err| eyre!("Invalid pattern: {err}"))?;
+    Ok((regex, literal))
+}
@@ -0,0 +1,88 @@
+use chrono::{Days, NaiveDate};
+use color_eyre::eyre::{bail, eyre};
+
+const ISO_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// An arithmetic sequence of numbers or ISO dates, that `:fill series` continues.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Series {
+    Number {
+        start: f64,
+        step: f64,
+        /// Decimal places of the seed and step, that every value is written with
+        decimals: usize,
+    },
+    /// Steps are whole days
+    Date { start: NaiveDate, step: i64 },
+}
+
+impl Series {
+    /// Detects the series from the first cells. Without a `step`, a second seed of the same kind
+    /// defines it and otherwise it is 1. Returns the series with the number of seeds, that it
+    /// was detected from, which are kept as they are written.
+    pub(crate) fn detect(
+        first: &str,
+        second: Option<&str>,
+        step: Option<f64>,
+    ) -> color_eyre::Result<(Self, usize)> {
+        let first = first.trim();
+        let second = second.map(str::trim).filter(|second| !second.is_empty());
+        if let Ok(start) = NaiveDate::parse_from_str(first, ISO_DATE_FORMAT) {
+            let (step, seeds) = match step {
+                Some(step) if step.fract() != 0.0 => bail!("Dates need a step of whole days!"),
+                Some(step) if step.abs() >= i64::MAX as f64 => bail!("Step out of range!"),
+                Some(step) => (step as i64, 1),
+                None => second
+                    .and_then(|second| NaiveDate::parse_from_str(second, ISO_DATE_FORMAT).ok())
+                    .map_or((1, 1), |second| ((second - start).num_days(), 2)),
+            };
+            return Ok((Series::Date { start, step }, seeds));
+        }
+        let Ok(start) = first.parse::<f64>() else {
+            bail!("No number or date to continue: {first}");
+        };
+        let (step, step_decimals, seeds) = match step {
+            Some(step) => (step, decimals(&step.to_string()), 1),
+            None => match second.and_then(|second| Some((second.parse::<f64>().ok()?, second))) {
+                Some((second, text)) => (second - start, decimals(text), 2),
+                None => (1.0, 0, 1),
+            },
+        };
+        let series = Series::Number {
+            start,
+            step,
+            decimals: decimals(first).max(step_decimals),
+        };
+        Ok((series, seeds))
+    }
+
+    /// The value `n` steps after the start.
+    pub(crate) fn nth(&self, n: usize) -> color_eyre::Result<String> {
+        match *self {
+            Series::Number {
+                start,
+                step,
+                decimals,
+            } => Ok(format!("{:.*}", decimals, start + step * n as f64)),
+            Series::Date { start, step } => {
+                let days = i64::try_from(n)
+                    .ok()
+                    .and_then(|n| step.checked_mul(n))
+                    .ok_or_else(|| eyre!("Date out of range!"))?;
+                let date = if days < 0 {
+                    start.checked_sub_days(Days::new(days.unsigned_abs()))
+                } else {
+                    start.checked_add_days(Days::new(days as u64))
+                };
+                let date = date.ok_or_else(|| eyre!("Date out of range!"))?;
+                Ok(date.format(ISO_DATE_FORMAT).to_string())
+            }
+        }
+    }
+}
+
+fn decimals(number: &str) -> usize {
+    number
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len())
+}
@@ -0,0 +1,140 @@
+use std::{fmt::Display, str::FromStr};
+
+use color_eyre::eyre::bail;
+
+use crate::content::CoordinateStyle;
+
+/// Options, that are changed with `:set` and apply to the whole application.
+#[derive(Clone, Debug)]
+pub(crate) struct Settings {
+    /// Continue searching at the other end of the table
+    pub(crate) wrapscan: bool,
+    /// Search case-insensitively
+    pub(crate) ignorecase: bool,
+    /// With `ignorecase`, patterns containing uppercase letters are case-sensitive
+    pub(crate) smartcase: bool,
+    /// Whether search patterns are regular expressions or plain text
+    pub(crate) search: SearchMode,
+    /// Widen a column, when a committed cell value doesn't fit into it
+    pub(crate) autogrow: bool,
+    /// Columns don't grow wider than this with `autogrow`
+    pub(crate) autogrow_max: u16,
+    /// Show the horizontal scroll position below the table
+    pub(crate) hscrollbar: bool,
+    /// Show the vertical scroll position next to the row labels
+    pub(crate) vscrollbar: bool,
+    /// Show the formulas of cells instead of their values
+    pub(crate) formulas: bool,
+    /// Evaluate cell input like `=17*12` once and store the result instead of the formula
+    pub(crate) calc: bool,
+    /// How cell coordinates are shown in the status bar, the column labels and popups
+    pub(crate) coords: CoordinateStyle,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            wrapscan: true,
+            ignorecase: false,
+            smartcase: false,
+            search: SearchMode::default(),
+            autogrow: false,
+            autogrow_max: 60,
+            hscrollbar: true,
+            vscrollbar: true,
+            formulas: false,
+            calc: false,
+            coords: CoordinateStyle::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Whether the regex `pattern` should be matched case-insensitively. Inline flags like `(?i)` or
+    /// `(?-i)` in the pattern take precedence over the options.
+    pub(crate) fn ignore_case(&self, pattern: &str) -> bool {
+        if has_inline_case_flag(pattern) {
+            return false;
+        }
+        self.ignorecase && !(self.smartcase && has_uppercase(pattern))
+    }
+
+    /// Short indicator of the case options for the status bar.
+    pub(crate) fn case_indicator(&self) -> &'static str {
+        match (self.ignorecase, self.smartcase) {
+            (false, _) => "cs",
+            (true, false) => "ic",
+            (true, true) => "sc",
+        }
+    }
+}
+
+/// Whether the pattern contains a literal uppercase letter. Escaped characters like `\S` don't
+/// count.
+fn has_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c.is_uppercase() => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Whether the pattern sets or clears the `i` flag itself, e.g. `(?i)` or `(?-i:...)`.
+fn has_inline_case_flag(pattern: &str) -> bool {
+    pattern.match_indices("(?").any(|(i, _)| {
+        pattern[i + 2..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic() || *c == '-')
+            .any(|c| c == 'i')
+    })
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum SearchMode {
+    #[default]
+    Regex,
+    /// Match the pattern as plain substring
+    Literal,
+}
+
+impl FromStr for SearchMode {
+    type Err = color_eyre::eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s {
+            "regex" => Self::Regex,
+            "literal" => Self::Literal,
+            _ => bail!("Unknown search mode: {s}. Use `regex` or `literal`"),
+        };
+        Ok(res)
+    }
+}
+
+impl Display for SearchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Regex => "regex",
+            Self::Literal => "literal",
+        };
+        f.write_str(s)
+    }
+}
+
+pub(crate) fn on_off_from_str(value: &str) -> color_eyre::Result<bool> {
+    let res = match value {
+        "on" | "true" => true,
+        "off" | "false" => false,
+        _ => bail!("Expected `on` or `off`: {value}"),
+    };
+    Ok(res)
+}
+
+pub(crate) fn on_off_to_str(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
@@ -0,0 +1,135 @@
+use std::cmp::Ordering;
+
+use color_eyre::eyre::bail;
+
+use crate::{
+    content::{CellLocation, CsvTable},
+    numeric::parse_number,
+};
+
+/// How the values of a sort column are compared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SortCompare {
+    /// By their numeric value, non-numeric values come after the numbers
+    Numeric,
+    Lexical,
+}
+
+/// A column, that rows are sorted by, e.g. `B desc numeric`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SortKey {
+    pub(crate) col: usize,
+    pub(crate) descending: bool,
+    /// Detected from the values of the column, if unset
+    pub(crate) compare: Option<SortCompare>,
+}
+
+impl SortKey {
+    /// Parses a column id followed by the optional words `asc`, `desc`, `numeric` and `lexical`.
+    pub(crate) fn parse(words: &[&str]) -> color_eyre::Result<Self> {
+        let Some((col, options)) = words.split_first() else {
+            bail!("Missing sort column!");
+        };
+        let mut key = Self {
+            col: CellLocation::col_id_to_index(col)?,
+            descending: false,
+            compare: None,
+        };
+        for option in options {
+            match *option {
+                "asc" => key.descending = false,
+                "desc" => key.descending = true,
+                "numeric" => key.compare = Some(SortCompare::Numeric),
+                "lexical" => key.compare = Some(SortCompare::Lexical),
+                option => bail!("Invalid sort option: {option}"),
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// Parses sort keys separated by commas, e.g. `B desc, C asc`.
+pub(crate) fn parse_keys(words: &[&str]) -> color_eyre::Result<Vec<SortKey>> {
+    words
+        .join(" ")
+        .split(',')
+        .map(|key| SortKey::parse(&key.split_whitespace().collect::<Vec<_>>()))
+        .collect()
+}
+
+/// The order of the `row_count` rows starting at `start`, as a permutation for
+/// [`CsvTable::permute_rows`]. Later keys break ties of earlier ones, equal rows keep their order
+/// and empty cells come last in both directions.
+pub(crate) fn sort_permutation(
+    table: &CsvTable,
+    start: usize,
+    row_count: usize,
+    keys: &[SortKey],
+) -> Vec<usize> {
+    let columns = keys
+        .iter()
+        .map(|key| {
+            let values = (start..start + row_count)
+                .map(|row| {
+                    table
+                        .get(CellLocation { row, col: key.col })
+                        .filter(|value| !value.is_empty())
+                })
+                .collect::<Vec<_>>();
+            let compare = key.compare.unwrap_or_else(|| {
+                let is_numeric = values
+                    .iter()
+                    .flatten()
+                    .all(|value| parse_number(value).is_some());
+                if is_numeric {
+                    SortCompare::Numeric
+                } else {
+                    SortCompare::Lexical
+                }
+            });
+            (key, compare, values)
+        })
+        .collect::<Vec<_>>();
+
+    let mut permutation = (0..row_count).collect::<Vec<_>>();
+    permutation.sort_by(|a, b| {
+        columns
+            .iter()
+            .map(|(key, compare, values)| match (values[*a], values[*b]) {
+                (Some(a), Some(b)) => {
+                    let ordering = compare_values(a, b, *compare);
+                    if key.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                }
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    permutation
+}
+
+/// Sorts `values` numerically, if they are all numbers, and otherwise lexically.
+pub(crate) fn sort_values(values: &mut [&str]) {
+    let compare = if values.iter().all(|value| parse_number(value).is_some()) {
+        SortCompare::Numeric
+    } else {
+        SortCompare::Lexical
+    };
+    values.sort_by(|a, b| compare_values(a, b, compare));
+}
+
+fn compare_values(a: &str, b: &str, compare: SortCompare) -> Ordering {
+    if compare == SortCompare::Numeric {
+        match (parse_number(a), parse_number(b)) {
+            (Some(a), Some(b)) => return a.total_cmp(&b),
+            (Some(_), None) => return Ordering::Less,
+            (None, Some(_)) => return Ordering::Greater,
+            (None, None) => {}
+        }
+    }
+    a.cmp(b)
+}
@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use color_eyre::eyre::{bail, eyre};
+use rusqlite::{
+    Connection,
+    types::{Value, ValueRef},
+};
+
+use crate::{
+    content::{CellLocation, CsvTable},
+    task::Progress,
+};
+
+/// The name, that queries refer to the buffer with, like `SELECT * FROM t`
+pub(crate) const TABLE_NAME: &str = "t";
+
+/// Runs `query` over `table` in an in-memory SQLite database and returns the result set with the
+/// column names in the first row. Columns are named after the header row or, without one or for
+/// empty and repeated names, after their letters.
+pub(crate) fn query(
+    table: &CsvTable,
+    header: bool,
+    query: &str,
+    progress: &Progress,
+) -> color_eyre::Result<CsvTable> {
+    let mut connection = Connection::open_in_memory()?;
+    let extent = table.used_extent();
+    let names = column_names(table, header, extent.col_count);
+    let columns = names
+        .iter()
+        .map(|name| quote(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    connection.execute(&format!("CREATE TABLE {TABLE_NAME} ({columns})"), [])?;
+    let placeholders = vec!["?"; names.len()].join(", ");
+    // One transaction for all rows, instead of one per insert
+    let transaction = connection.transaction()?;
+    {
+        let mut insert =
+            transaction.prepare(&format!("INSERT INTO {TABLE_NAME} VALUES ({placeholders})"))?;
+        for row in usize::from(header)..extent.row_count {
+            progress.report(row, extent.row_count)?;
+            let values = (0..extent.col_count)
+                .map(|col| sql_value(table.get(CellLocation { row, col }).unwrap_or_default()));
+            insert.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+    transaction.commit()?;
+    let mut statement = connection
+        .prepare(query)
+        .map_err(|err| eyre!("SQL: {err}"))?;
+    let col_count = statement.column_count();
+    if col_count == 0 {
+        bail!("The query returns no columns!");
+    }
+    let header_row = statement
+        .column_names()
+        .into_iter()
+        .map(|name| Some(name.to_owned()))
+        .collect();
+    let mut rows = vec![header_row];
+    let mut result = statement.query([]).map_err(|err| eyre!("SQL: {err}"))?;
+    while let Some(row) = result.next().map_err(|err| eyre!("SQL: {err}"))? {
+        // The number of result rows isn't known, so a runaway query can only be cancelled
+        progress.check_cancelled()?;
+        let values = (0..col_count)
+            .map(|col| Ok(cell_text(row.get_ref(col)?)))
+            .collect::<rusqlite::Result<_>>()?;
+        rows.push(values);
+    }
+    Ok(CsvTable::from_rows(rows, table.delimiter))
+}
+
+fn column_names(table: &CsvTable, header: bool, col_count: usize) -> Vec<String> {
+    let mut used = HashSet::new();
+    (0..col_count)
+        .map(|col| {
+            let name = table
+                .get(CellLocation { row: 0, col })
+                .map(str::trim)
+                .filter(|name| header && !name.is_empty())
+                .filter(|name| !used.contains(&name.to_lowercase()));
+            let name = name.map(str::to_owned).unwrap_or_else(|| {
+                // A header may already use the letters of the column
+                let letters = CellLocation::col_index_to_letters(col);
+                (1..)
+                    .map(|n| match n {
+                        1 => letters.clone(),
+                        n => format!("{letters}_{n}"),
+                    })
+                    .find(|name| !used.contains(&name.to_lowercase()))
+                    .unwrap_or(letters)
+            });
+            used.insert(name.to_lowercase());
+            name
+        })
+        .collect()
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Empty cells are `NULL` and numbers are stored as such, unless that would change their text,
+/// like the leading zeros of `007`.
+fn sql_value(text: &str) -> Value {
+    if text.is_empty() {
+        return Value::Null;
+    }
+    if let Ok(integer) = text.parse::<i64>()
+        && integer.to_string() == text
+    {
+        return Value::Integer(integer);
+    }
+    if let Ok(real) = text.parse::<f64>()
+        && real.is_finite()
+        && real.to_string() == text
+    {
+        return Value::Real(real);
+    }
+    Value::Text(text.to_owned())
+}
+
+fn cell_text(value: ValueRef) -> Option<String> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(integer) => Some(integer.to_string()),
+        ValueRef::Real(real) => Some(real.to_string()),
+        ValueRef::Text(text) | ValueRef::Blob(text) => {
+            Some(String::from_utf8_lossy(text).into_owned())
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::{borrow::Cow, collections::VecDeque, ops::Range};
+
+use color_eyre::eyre::bail;
+use regex::Regex;
+
+use crate::{
+    content::{CellLocation, CellRect, CsvTable},
+    search,
+    settings::Settings,
+    task::Progress,
+};
+
+/// A parsed `:s/pattern/replacement/flags` command.
+///
+/// The replacement uses the syntax of [`regex::Regex::replace`], e.g. `$1` for the first group.
+#[derive(Clone, Debug)]
+pub(crate) struct Substitution {
+    pub(crate) pattern: String,
+    regex: Regex,
+    replacement: String,
+    /// Flag `g`: Replace every match in a cell instead of only the first one
+    pub(crate) global: bool,
+    /// Flag `c`: Ask for confirmation before each replacement
+    pub(crate) confirm: bool,
+}
+
+impl Substitution {
+    /// Parses everything after the `s`, e.g. `/foo/bar/gc`. The first character is the
+    /// delimiter, which can be escaped with a backslash inside the pattern and replacement.
+    /// Without the `i` or `I` flag, the case options of `settings` apply. The pattern is literal
+    /// with `:set search literal` or the `\V` prefix.
+    pub(crate) fn parse(s: &str, settings: &Settings) -> color_eyre::Result<Self> {
+        let mut chars = s.chars();
+        let Some(delimiter) = chars.next() else {
+            bail!("Expected a pattern, e.g. `s/foo/bar/g`");
+        };
+        if delimiter.is_alphanumeric() || delimiter.is_whitespace() || delimiter == '\\' {
+            bail!("Invalid delimiter: {delimiter}");
+        }
+        let mut parts = vec![String::new()];
+        let mut chars = chars.peekable();
+        while let Some(c) = chars.next() {
+            let part_count = parts.len();
+            let part = parts.last_mut().unwrap();
+            match c {
+                '\\' if chars.peek() == Some(&delimiter) => {
+                    part.push(delimiter);
+                    chars.next();
+                }
+                '\\' => {
+                    part.push(c);
+                    if let Some(c) = chars.next() {
+                        part.push(c);
+                    }
+                }
+                c if c == delimiter && part_count < 3 => parts.push(String::new()),
+                c => part.push(c),
+            }
+        }
+        let mut parts = parts.into_iter();
+        let pattern = parts.next().unwrap_or_default();
+        let replacement = parts.next().unwrap_or_default();
+        let flags = parts.next().unwrap_or_default();
+        if pattern.is_empty() {
+            bail!("Empty pattern!");
+        }
+
+        let mut global = false;
+        let mut confirm = false;
+        let mut case_insensitive = None;
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'c' => confirm = true,
+                'i' => case_insensitive = Some(true),
+                'I' => case_insensitive = Some(false),
+                _ => bail!("Unknown flag: {flag}"),
+            }
+        }
+        let (regex, _) = search::build_regex(&pattern, settings, case_insensitive)?;
+        Ok(Self {
+            pattern,
+            regex,
+            replacement,
+            global,
+            confirm,
+        })
+    }
+
+    pub(crate) fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+
+    /// Replaces the first or, with the `g` flag, every match. Returns the new value and the
+    /// number of replacements.
+    pub(crate) fn replace<'a>(&self, value: &'a str) -> (Cow<'a, str>, usize) {
+        let limit = if self.global { 0 } else { 1 };
+        let mut count = 0;
+        let replaced = self.regex.replacen(value, limit, |caps: &regex::Captures| {
+            count += 1;
+            let mut dst = String::new();
+            caps.expand(&self.replacement, &mut dst);
+            dst
+        });
+        (replaced, count)
+    }
+
+    /// The new values of the cells in `rect`, that change, and the number of replacements.
+    pub(crate) fn replace_in(
+        &self,
+        table: &CsvTable,
+        rect: CellRect,
+        progress: &Progress,
+    ) -> color_eyre::Result<(Vec<(CellLocation, String)>, usize)> {
+        let mut count = 0;
+        let mut changes = Vec::new();
+        for (location, value) in table
+            .cells()
+            .filter(|(location, _)| rect.contains(*location))
+        {
+            progress.report(
+                location.row - rect.top_left_cell_location.row,
+                rect.row_count,
+            )?;
+            let (replaced, n) = self.replace(value);
+            count += n;
+            if replaced != value {
+                changes.push((location, replaced.into_owned()));
+            }
+        }
+        Ok((changes, count))
+    }
+
+    /// The byte range of the next match, that starts at or after `from`.
+    fn find_at(&self, value: &str, from: usize) -> Option<Range<usize>> {
+        if from > value.len() {
+            return None;
+        }
+        self.regex.find_at(value, from).map(|m| m.range())
+    }
+
+    /// Replaces the match at `range`. Returns the new value and the byte offset after the
+    /// inserted replacement.
+    fn replace_at(&self, value: &str, range: Range<usize>) -> (String, usize) {
+        let mut replaced = value[..range.start].to_owned();
+        if let Some(caps) = self.regex.captures_at(value, range.start) {
+            caps.expand(&self.replacement, &mut replaced);
+        }
+        let end = replaced.len();
+        replaced.push_str(&value[range.end..]);
+        (replaced, end)
+    }
+}
+
+/// The state of an interactive `:s///c`, that asks before each replacement.
+#[derive(Clone, Debug)]
+pub(crate) struct SubstituteConfirm {
+    substitution: Substitution,
+    /// Cells, that still have to be checked for matches. The first one is the current cell.
+    queue: VecDeque<CellLocation>,
+    /// Byte offset in the current cell, where the next match is searched
+    offset: usize,
+    /// The match, that is currently asked for
+    current: Option<(CellLocation, Range<usize>)>,
+    /// Values of the changed cells before the substitution, which become one undo action
+    pub(crate) undo_values: Vec<(CellLocation, Option<String>)>,
+    pub(crate) count: usize,
+}
+
+impl SubstituteConfirm {
+    pub(crate) fn new(substitution: Substitution, queue: VecDeque<CellLocation>) -> Self {
+        Self {
+            substitution,
+            queue,
+            offset: 0,
+            current: None,
+            undo_values: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// Moves to the next match and returns it.
+    pub(crate) fn advance(&mut self, table: &CsvTable) -> Option<(CellLocation, Range<usize>)> {
+        self.current = None;
+        while let Some(&location) = self.queue.front() {
+            let value = table.get(location).unwrap_or_default();
+            if let Some(range) = self.substitution.find_at(value, self.offset) {
+                self.current = Some((location, range));
+                break;
+            }
+            self.next_cell();
+        }
+        self.current.clone()
+    }
+
+    /// Skips the current match.
+    pub(crate) fn skip(&mut self, table: &CsvTable) {
+        let Some((location, range)) = self.current.take() else {
+            return;
+        };
+        if self.substitution.global {
+            let value = table.get(location).unwrap_or_default();
+            self.offset = next_offset(value, range.end, range.is_empty());
+        } else {
+            self.next_cell();
+        }
+    }
+
+    /// Replaces the current match.
+    pub(crate) fn replace(&mut self, table: &mut CsvTable) {
+        let Some((location, range)) = self.current.take() else {
+            return;
+        };
+        let value = table.get(location).unwrap_or_default();
+        let (replaced, end) = self.substitution.replace_at(value, range.clone());
+        if self.substitution.global {
+            self.offset = next_offset(&replaced, end, range.is_empty());
+        } else {
+            self.next_cell();
+        }
+        let old_value = table.set(location, Some(replaced));
+        if self.undo_values.last().map(|(l, _)| *l) != Some(location) {
+            self.undo_values.push((location, old_value));
+        }
+        self.count += 1;
+    }
+
+    fn next_cell(&mut self) {
+        self.queue.pop_front();
+        self.offset = 0;
+    }
+}
+
+/// The offset to continue searching at. Steps over one character after an empty match, so the
+/// same position isn't matched again.
+fn next_offset(value: &str, end: usize, empty_match: bool) -> usize {
+    if !empty_match {
+        return end;
+    }
+    end + value[end..].chars().next().map_or(1, char::len_utf8)
+}
@@ -1,2 +1,10 @@
 pub(crate) const HALF_BLOCK_LEFT: &str = "▌";
 pub(crate) const HALF_BLOCK_RIGHT: &str = "▐";
+/// Top right corner of cells with a note
+pub(crate) const NOTE_MARKER: &str = "◥";
+/// Corner of the focused pane of a split
+pub(crate) const FOCUSED_PANE: &str = "◆";
+/// Corner of the footer row with the aggregates
+pub(crate) const AGGREGATES: &str = "Σ";
+/// Left of the labels of rows with comment lines before them
+pub(crate) const COMMENT_MARKER: &str = "▸";
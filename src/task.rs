@@ -0,0 +1,184 @@
+use std::{
+    cell::Cell,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread,
+};
+
+use color_eyre::eyre::{bail, eyre};
+
+use crate::{AppState, content::CsvTable};
+
+/// Operations on at least this many cells run as a [`LongTask`]
+pub(crate) const LONG_TASK_CELL_THRESHOLD: usize = 200_000;
+
+/// Applies the result of a task to the state, e.g. swaps in changed cells as one undo action.
+pub(crate) type TaskApply = Box<dyn FnOnce(&mut AppState) -> color_eyre::Result<()> + Send>;
+
+/// Error of work, that stopped, because its task was cancelled.
+#[derive(Debug)]
+pub(crate) struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cancelled!")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+enum TaskMessage {
+    Progress(u8),
+    Done(color_eyre::Result<TaskApply>),
+}
+
+/// Work, that runs on a worker thread, while the UI shows its progress. Other input than `Esc`,
+/// which cancels it, is ignored until it is finished, so the work can use a copy of the table
+/// without it getting outdated.
+pub(crate) struct LongTask {
+    pub(crate) name: &'static str,
+    /// In percent
+    pub(crate) progress: u8,
+    receiver: Receiver<TaskMessage>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for LongTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LongTask")
+            .field("name", &self.name)
+            .field("progress", &self.progress)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LongTask {
+    pub(crate) fn spawn(
+        name: &'static str,
+        work: impl FnOnce(&Progress) -> color_eyre::Result<TaskApply> + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let progress = Progress {
+            sender: Some(sender.clone()),
+            last: Cell::new(0),
+            cancelled: cancelled.clone(),
+        };
+        thread::spawn(move || {
+            let res = work(&progress);
+            let _ = sender.send(TaskMessage::Done(res));
+        });
+        Self {
+            name,
+            progress: 0,
+            receiver,
+            cancelled,
+        }
+    }
+
+    /// Signals the work to stop. If it stops with [`Cancelled`], its result is discarded, so the
+    /// buffer stays as before. Work, that finished anyway, is still applied.
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Takes the reported progress and returns the result, once the work is done.
+    pub(crate) fn poll(&mut self) -> Option<color_eyre::Result<TaskApply>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(TaskMessage::Progress(progress)) => self.progress = progress,
+                Ok(TaskMessage::Done(res)) => return Some(res),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    return Some(Err(eyre!("{} failed unexpectedly!", self.name)));
+                }
+            }
+        }
+    }
+}
+
+/// Reports the progress of a task. Work, that runs directly on the main thread, reports to
+/// nowhere.
+pub(crate) struct Progress {
+    sender: Option<Sender<TaskMessage>>,
+    /// Last reported percentage, so only changes are sent
+    last: Cell<u8>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Progress {
+    pub(crate) fn none() -> Self {
+        Self {
+            sender: None,
+            last: Cell::new(0),
+            cancelled: Default::default(),
+        }
+    }
+
+    /// Fails with [`Cancelled`], if the task was cancelled, so the work stops with `?`.
+    pub(crate) fn check_cancelled(&self) -> color_eyre::Result<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+
+    /// Reports the progress and fails, if the task was cancelled.
+    pub(crate) fn report(&self, done: usize, total: usize) -> color_eyre::Result<()> {
+        self.check_cancelled()?;
+        let Some(sender) = &self.sender else {
+            return Ok(());
+        };
+        let percentage = (done.saturating_mul(100) / total.max(1)).min(100) as u8;
+        if percentage != self.last.replace(percentage) {
+            let _ = sender.send(TaskMessage::Progress(percentage));
+        }
+        Ok(())
+    }
+}
+
+/// Runs `work` as a [`LongTask`], or directly in macros and scripts, that can't wait for it.
+pub(crate) fn run_long(
+    state: &mut AppState,
+    name: &'static str,
+    work: impl FnOnce(&Progress) -> color_eyre::Result<TaskApply> + Send + 'static,
+) -> color_eyre::Result<()> {
+    if state.macro_depth == 0 && !state.batch {
+        state.task = Some(LongTask::spawn(name, work));
+        return Ok(());
+    }
+    let apply = work(&Progress::none())?;
+    apply(state)
+}
+
+/// Runs `work` on the table of the current buffer. If `cell_count` reaches
+/// [`LONG_TASK_CELL_THRESHOLD`], it runs as a [`LongTask`] on a copy of the table, or else
+/// directly.
+pub(crate) fn run_maybe_long(
+    state: &mut AppState,
+    name: &'static str,
+    cell_count: usize,
+    work: impl FnOnce(&CsvTable, &Progress) -> color_eyre::Result<TaskApply> + Send + 'static,
+) -> color_eyre::Result<()> {
+    let Some(table) = &state.table else {
+        bail!("No open buffer!");
+    };
+    // Macros and scripts run the following keys or commands directly, so they can't wait for a
+    // task
+    if cell_count >= LONG_TASK_CELL_THRESHOLD && state.macro_depth == 0 && !state.batch {
+        let csv_table = table.csv_table.clone();
+        state.task = Some(LongTask::spawn(name, move |progress| {
+            work(&csv_table, progress)
+        }));
+        return Ok(());
+    }
+    let apply = work(&table.csv_table, &Progress::none())?;
+    apply(state)
+}
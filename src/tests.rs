@@ -0,0 +1,1808 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs,
+    io::{Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    process,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use clap::Parser;
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::{Terminal, backend::TestBackend};
+
+use crate::{
+    App, Args, CsvJump, InputState, Severity,
+    clipboard::ClipboardBackend,
+    compression::{self, Compression},
+    config::Config,
+    content::{CellLocation, CoordinateStyle, CsvTable, LoadSettings, sniff_delimiter},
+    diff::RowDiff,
+    editor,
+    events::EventSource,
+    expr::{self, Value},
+    formula::Formulas,
+    help::HELP_SECTIONS,
+    notes,
+    popup::Popup,
+    symbols,
+};
+
+const WIDTH: u16 = 60;
+const HEIGHT: u16 = 8;
+
+thread_local! {
+    /// The text, that the app last copied to the clipboard of the test terminal
+    static CLIPBOARD: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+impl ClipboardBackend for TestBackend {
+    fn copy_to_clipboard(&mut self, text: &str) -> std::io::Result<()> {
+        CLIPBOARD.set(Some(text.to_owned()));
+        Ok(())
+    }
+}
+
+/// A fixed sequence of events, that ends the app, when it runs out.
+struct QueuedEvents(VecDeque<Event>);
+
+impl EventSource for QueuedEvents {
+    fn poll(&mut self, _timeout: Duration) -> color_eyre::Result<bool> {
+        Ok(!self.0.is_empty())
+    }
+
+    fn read(&mut self) -> color_eyre::Result<Option<Event>> {
+        Ok(self.0.pop_front())
+    }
+}
+
+/// Key presses of `keys`, where `\n` is `Enter` and `\x1b` is `Esc`.
+fn keys(keys: &str) -> QueuedEvents {
+    let events = keys
+        .chars()
+        .map(|c| {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\x1b' => KeyCode::Esc,
+                '\t' => KeyCode::Tab,
+                c => KeyCode::Char(c),
+            };
+            Event::Key(KeyEvent::from(code))
+        })
+        .collect();
+    QueuedEvents(events)
+}
+
+/// A CSV file in the temporary directory, that is unique to the test.
+struct Fixture(PathBuf);
+
+impl Fixture {
+    fn new(name: &str, content: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("ratcsv-{}-{name}.csv", process::id()));
+        fs::write(&path, content).unwrap();
+        Self(path)
+    }
+
+    fn read(&self) -> String {
+        fs::read_to_string(&self.0).unwrap()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Opens `fixture`, feeds `events` and returns the app and the last rendered screen.
+fn run(fixture: &Fixture, events: QueuedEvents) -> (App, Vec<String>) {
+    run_app(App::new(), fixture, events)
+}
+
+/// Like [`run`], but with an already set up `app`.
+fn run_app(mut app: App, fixture: &Fixture, mut events: QueuedEvents) -> (App, Vec<String>) {
+    let args = Args::parse_from(["rat".as_ref(), fixture.0.as_os_str()]);
+    let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+    app.run(&mut terminal, &mut events, args).unwrap();
+    // The last events are only visible after another render
+    terminal.draw(|frame| app.state.render(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let lines = (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+                .trim_end()
+                .to_owned()
+        })
+        .collect();
+    (app, lines)
+}
+
+/// The used extent of the current table as CSV.
+fn table_csv(app: &App) -> String {
+    let table = app.state.table.as_ref().unwrap();
+    table
+        .csv_table
+        .rect_to_csv_string(table.csv_table.used_extent())
+        .unwrap()
+}
+
+#[test]
+fn edit_and_save() {
+    let fixture = Fixture::new("edit", "a,b\n1,2\n");
+    let (app, _) = run(&fixture, keys("jlcx\n:w\n"));
+    assert_eq!(fixture.read(), "a,b\n1,x\n");
+    assert!(!app.state.table.unwrap().is_dirty());
+}
+
+#[test]
+fn config_remaps_keys() {
+    let config: Config = toml::from_str(
+        r#"
+        cell-width = 5
+
+        [style.primary-selection]
+        bg = "red"
+        modifiers = ["bold"]
+
+        [keys]
+        "ö" = "l"
+        "#,
+    )
+    .unwrap();
+    let mut app = App::new();
+    app.state.apply_config(&config).unwrap();
+    let fixture = Fixture::new("config", "a,b\n1,2\n");
+    // The remap doesn't apply to typed text
+    let (app, _) = run_app(app, &fixture, keys("öcö\n:w\n"));
+    assert_eq!(fixture.read(), "a,ö\n1,2\n");
+    assert_eq!(app.state.table.unwrap().cell_width_wanted, 5);
+}
+
+#[test]
+fn config_binds_actions() {
+    let config: Config = toml::from_str(
+        r#"
+        [bind]
+        "ö" = "move-down"
+        "x" = "none"
+        goto.e = "goto-row-start"
+        "#,
+    )
+    .unwrap();
+    let mut app = App::new();
+    app.state.apply_config(&config).unwrap();
+    let fixture = Fixture::new("bind", "a,b\n1,2\n");
+    run_app(app, &fixture, keys("lögexcx\n:w\n"));
+    assert_eq!(fixture.read(), "a,b\nx,2\n");
+}
+
+#[test]
+fn help_shows_remapped_keys() {
+    let help_keys = |app: &App, description: &str| {
+        let mut entries = HELP_SECTIONS.iter().flat_map(|section| section.entries);
+        let entry = entries.find(|entry| entry.description == description);
+        entry.unwrap().keys(&app.state.keymap)
+    };
+    let mut app = App::new();
+    for entry in HELP_SECTIONS.iter().flat_map(|section| section.entries) {
+        assert!(!entry.keys(&app.state.keymap).contains('{'));
+    }
+    assert_eq!(help_keys(&app, "Move selection"), "h j k l");
+    assert_eq!(help_keys(&app, "Go to first column/row"), "gh gk");
+    assert_eq!(help_keys(&app, "Undo/redo"), "u U");
+    let config: Config = toml::from_str(
+        r#"
+        [bind]
+        "ö" = "move-down"
+        "j" = "none"
+        "<C-g>" = "goto-combo"
+        "g" = "none"
+        goto.e = "goto-row-start"
+        "#,
+    )
+    .unwrap();
+    app.state.apply_config(&config).unwrap();
+    assert_eq!(help_keys(&app, "Move selection"), "h ö k l");
+    assert_eq!(help_keys(&app, "Go to first column/row"), "<C-g>e <C-g>k");
+}
+
+#[test]
+fn config_rejects_unknown_style() {
+    let config: Config = toml::from_str("[style.nope]\nfg = \"red\"").unwrap();
+    assert!(App::new().state.apply_config(&config).is_err());
+    assert!(toml::from_str::<Config>("cell-widht = 5").is_err());
+    let config: Config = toml::from_str("[bind]\nx = \"explode\"").unwrap();
+    assert!(App::new().state.apply_config(&config).is_err());
+}
+
+#[test]
+fn combo_shows_its_keys() {
+    let fixture = Fixture::new("combo-hint", "a,b\n1,2\n");
+    let (_, lines) = run(&fixture, keys("g"));
+    assert!(lines.iter().any(|line| line.contains("h goto row start")));
+    // The hint is only shown, when the next key takes a while
+    let (_, lines) = run(&fixture, keys("gh"));
+    assert!(!lines.iter().any(|line| line.contains("goto row start")));
+}
+
+#[test]
+fn complete_commands_and_paths() {
+    let fixture = Fixture::new("complete", "a,b\n1,2\n");
+    let (app, _) = run(&fixture, keys(":write-q\t"));
+    let InputState::Console(console) = app.state.input else {
+        panic!("console closed");
+    };
+    assert_eq!(console.content, "write-quit");
+
+    // The fixture is the only file of its name in the temporary directory
+    let path = fixture.0.to_string_lossy();
+    let typed = &path[..path.len() - 4];
+    let (app, _) = run(&fixture, keys(&format!(":w {typed}\t")));
+    let InputState::Console(console) = app.state.input else {
+        panic!("console closed");
+    };
+    assert_eq!(console.content, format!("w {path}"));
+}
+
+#[test]
+fn recall_commands_by_prefix() {
+    let fixture = Fixture::new("history", "a,b\n1,2\n");
+    let recall = |arrows: &[KeyCode]| {
+        let mut events = keys(":echo 1\n:noh\n:echo 2\n:e");
+        for code in arrows {
+            events.0.push_back(Event::Key(KeyEvent::from(*code)));
+        }
+        let (app, _) = run(&fixture, events);
+        let InputState::Console(console) = app.state.input else {
+            panic!("console closed");
+        };
+        console.content
+    };
+    assert_eq!(recall(&[KeyCode::Up]), "echo 2");
+    assert_eq!(recall(&[KeyCode::Up, KeyCode::Up, KeyCode::Up]), "echo 1");
+    assert_eq!(recall(&[KeyCode::Up, KeyCode::Up, KeyCode::Down]), "echo 2");
+    assert_eq!(recall(&[KeyCode::Up, KeyCode::Down]), "e");
+}
+
+#[test]
+fn edit_in_the_middle_of_the_input() {
+    let fixture = Fixture::new("line-edit", "a,b\n1,2\n");
+    let mut events = keys("cfoo bar baz");
+    let push = |events: &mut QueuedEvents, code, modifiers| {
+        events
+            .0
+            .push_back(Event::Key(KeyEvent::new(code, modifiers)));
+    };
+    // `foo bar| baz`, delete `bar`, then `Home`, delete `f`, insert `x`
+    for _ in 0..4 {
+        push(&mut events, KeyCode::Left, KeyModifiers::NONE);
+    }
+    push(&mut events, KeyCode::Char('w'), KeyModifiers::CONTROL);
+    push(&mut events, KeyCode::Home, KeyModifiers::NONE);
+    push(&mut events, KeyCode::Delete, KeyModifiers::NONE);
+    events.0.extend(keys("x\n:w\n").0);
+    run(&fixture, events);
+    assert_eq!(fixture.read(), "xoo  baz,b\n1,2\n");
+}
+
+#[test]
+fn edit_text_in_editor() {
+    let edited = editor::edit_text("a\nb", "sed -i s/a/x/").unwrap();
+    assert_eq!(edited, "x\nb");
+    assert!(editor::edit_text("a", "false").is_err());
+}
+
+#[test]
+fn parse_and_display_a1_and_r1c1_locations() {
+    let parse = |id: &str| {
+        let jump = CsvJump::from_str(id).unwrap();
+        (jump.sign, jump.row, jump.col)
+    };
+    assert_eq!(parse("G42"), (None, Some(41), Some(6)));
+    assert_eq!(parse("R42C7"), (None, Some(41), Some(6)));
+    assert_eq!(parse("r42c7"), (None, Some(41), Some(6)));
+    assert_eq!(parse("-R2C3"), (Some(-1), Some(1), Some(2)));
+    assert_eq!(parse("+AA"), (Some(1), None, Some(26)));
+    // Without a column number, it's column R in A1 notation
+    assert_eq!(parse("R42"), (None, Some(41), Some(17)));
+    assert!(CsvJump::from_str("R1C").is_err());
+    let location = CellLocation { row: 41, col: 78 };
+    assert_eq!(location.id(CoordinateStyle::A1), "CA42");
+    assert_eq!(location.id(CoordinateStyle::R1C1), "R42C79");
+    for style in [CoordinateStyle::A1, CoordinateStyle::R1C1] {
+        for location in [
+            CellLocation::default(),
+            CellLocation { row: 9, col: 25 },
+            location,
+        ] {
+            let parsed = CsvJump::from_str(&location.id(style)).unwrap();
+            assert_eq!(parsed.combine(CellLocation::default()), location);
+        }
+    }
+}
+
+#[test]
+fn show_coordinates_in_r1c1() {
+    let fixture = Fixture::new("coords", "a,b\nc,d\n");
+    let (app, lines) = run(&fixture, keys(":set coords r1c1\njl"));
+    assert_eq!(app.state.settings.coords, CoordinateStyle::R1C1);
+    assert_eq!(lines[0].split_whitespace().next(), Some("2"));
+    assert!(lines[7].ends_with("R2C2"));
+    // Messages keep A1 notation, however the coordinates are shown
+    assert_eq!(
+        app.state
+            .table
+            .as_ref()
+            .unwrap()
+            .selection
+            .primary
+            .to_string(),
+        "B2"
+    );
+    let (app, _) = run(&fixture, keys(""));
+    assert_eq!(app.state.settings.coords, CoordinateStyle::A1);
+}
+
+#[test]
+fn jump_to_data_edges() {
+    let fixture = Fixture::new("data-edge", "a,b,,d\n1,,,\n2,,,\n,,,\n5,,,\n");
+    let jump = |directions: &str| {
+        let mut events = QueuedEvents(VecDeque::new());
+        for c in directions.chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+            events.0.push_back(Event::Key(key));
+        }
+        let (app, _) = run(&fixture, events);
+        app.state.table.unwrap().selection.primary.to_string()
+    };
+    assert_eq!(jump("j"), "A3");
+    assert_eq!(jump("jj"), "A5");
+    assert_eq!(jump("jjj"), "A5");
+    assert_eq!(jump("l"), "B1");
+    assert_eq!(jump("ll"), "D1");
+    assert_eq!(jump("llh"), "B1");
+    assert_eq!(jump("jjk"), "A3");
+}
+
+#[test]
+fn jump_to_the_end() {
+    let fixture = Fixture::new("goto-end", "a,b,\n1,,\n2,x,\n,,c\n");
+    let primary = |keys: QueuedEvents| {
+        let (app, _) = run(&fixture, keys);
+        app.state.table.unwrap().selection.primary.to_string()
+    };
+    assert_eq!(primary(keys("G")), "A3");
+    assert_eq!(primary(keys("lG")), "B3");
+    assert_eq!(primary(keys("2G")), "A2");
+    assert_eq!(primary(keys("ge")), "C4");
+    assert_eq!(primary(keys(":goto-end\n")), "C4");
+}
+
+#[test]
+fn center_a_single_axis_near_the_origin() {
+    let fixture = Fixture::new("center-axis", "a,b\n1,2\n");
+    let view = |keys: QueuedEvents| {
+        let (app, _) = run(&fixture, keys);
+        let table = app.state.table.unwrap();
+        (table.top_left_cell_location, table.selection.primary)
+    };
+    let location = |row, col| CellLocation { row, col };
+    let (top_left, primary) = view(keys("jl5zj3zl"));
+    assert_eq!(primary, location(1, 1));
+    assert!(top_left.row > 1 && top_left.col > 1);
+    // Centering saturates at the first row and column and keeps the other axis
+    assert_eq!(
+        view(keys("jl5zj3zlz.")),
+        (location(0, top_left.col), primary)
+    );
+    assert_eq!(
+        view(keys("jl5zj3zlz|")),
+        (location(top_left.row, 0), primary)
+    );
+    assert_eq!(view(keys("jl5zj3zlzz")), (location(0, 0), primary));
+}
+
+#[test]
+fn marks_and_jumps() {
+    let fixture = Fixture::new("marks", "a,b,c\n1,2,3\n4,5,6\n");
+    // Sets mark `a` at B3, jumps to A1 and back to the mark, then walks the jumplist
+    let primary = |back: usize, forward: &str| {
+        let mut events = keys("jjlmagg'a");
+        for _ in 0..back {
+            let key = KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL);
+            events.0.push_back(Event::Key(key));
+        }
+        events.0.extend(keys(forward).0);
+        let (app, _) = run(&fixture, events);
+        app.state.table.unwrap().selection.primary.to_string()
+    };
+    assert_eq!(primary(0, ""), "B3");
+    assert_eq!(primary(1, ""), "A1");
+    assert_eq!(primary(2, ""), "B3");
+    assert_eq!(primary(2, "\t"), "A1");
+}
+
+#[test]
+fn search_wraps_around_a_single_match() {
+    let fixture = Fixture::new("search-wrap", "a,b\nc,match\nd,e\n");
+    let search = |keys: QueuedEvents| {
+        let (app, _) = run(&fixture, keys);
+        let message = app.state.console_message.unwrap();
+        let primary = app.state.table.unwrap().selection.primary;
+        (primary.to_string(), message.severity, message.message)
+    };
+    let wrapped = |message: &str| {
+        (
+            "B2".to_owned(),
+            Severity::Warning,
+            message.to_owned().into(),
+        )
+    };
+    for n in ["n", "nn", "nnn"] {
+        assert_eq!(
+            search(keys(&format!("/match\n{n}"))),
+            wrapped("search hit BOTTOM, continuing at TOP")
+        );
+    }
+    assert_eq!(
+        search(keys("/match\nN")),
+        wrapped("search hit TOP, continuing at BOTTOM")
+    );
+    assert_eq!(
+        search(keys(":set wrapscan off\n/match\nn")),
+        wrapped("No more matches below")
+    );
+}
+
+#[test]
+fn repeat_last_change() {
+    let fixture = Fixture::new("repeat", "a,b,c\n1,2,3\n4,5,6\n7,8,9\n");
+    run(&fixture, keys("cx\nj.:w\n"));
+    assert_eq!(fixture.read(), "x,b,c\nx,2,3\n4,5,6\n7,8,9\n");
+    // A count replaces the count of the change
+    run(&fixture, keys("2xj.j3.:w\n"));
+    assert_eq!(fixture.read(), ",,c\n,,3\n,,\n7,8,9\n");
+}
+
+#[test]
+fn record_and_play_macro() {
+    let fixture = Fixture::new("macro", "a,b\n1,2\n3,4\n5,6\n");
+    let (app, _) = run(&fixture, keys("qacx\njq2@a:w\n"));
+    assert_eq!(fixture.read(), "x,b\nx,2\nx,4\n5,6\n");
+    assert_eq!(app.state.macros[&'a'].len(), 4);
+}
+
+#[test]
+fn yank_and_paste() {
+    let fixture = Fixture::new("yank", "a,b\n1,2\n");
+    run(&fixture, keys("vjykllp:w\n"));
+    assert_eq!(fixture.read(), "a,b,a\n1,2,1\n");
+}
+
+#[test]
+fn clear_keeps_the_yank() {
+    let fixture = Fixture::new("clear", "a,b,c,d\n1,2,3,4\n");
+    // A count, a visual rectangle and a single cell are cleared, then the yank of A1 is pasted
+    run(&fixture, keys("yl2xjvlxkhxllp:w\n"));
+    assert_eq!(fixture.read(), "a,,,a\n1,,,4\n");
+}
+
+#[test]
+fn yank_into_named_registers() {
+    let fixture = Fixture::new("registers", "a,b,c\n");
+    let (_, screen) = run(&fixture, keys("\"aylyl\"ap:w\n:registers\n"));
+    assert_eq!(fixture.read(), "a,b,a\n");
+    assert!(screen.iter().any(|line| line.contains("\"\" b")));
+    assert!(screen.iter().any(|line| line.contains("\"a a")));
+
+    // `Ctrl-r` inserts a register or, with `Ctrl-w`, the cell into the console
+    let ctrl = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+    let fixture = Fixture::new("insert-registers", "a,b,c\n");
+    let mut events = keys("\"aylyl:echo \"");
+    events
+        .0
+        .extend([ctrl('r'), Event::Key(KeyCode::Char('a').into())]);
+    events
+        .0
+        .extend([ctrl('r'), Event::Key(KeyCode::Char('"').into())]);
+    events.0.extend([ctrl('r'), ctrl('w')]);
+    events.0.extend(keys("\"\n").0);
+    let (app, _) = run(&fixture, events);
+    assert_eq!(app.state.console_message.unwrap().message, "abc");
+}
+
+#[test]
+fn yank_to_the_clipboard() {
+    let fixture = Fixture::new("clipboard", "a,b\nc,dd\n");
+    run(&fixture, keys(":yank-all\n"));
+    assert_eq!(CLIPBOARD.take().as_deref(), Some("a,b\nc,dd\n"));
+    run(&fixture, keys("vjl:yank-txt\n"));
+    assert_eq!(CLIPBOARD.take().as_deref(), Some("a  b\nc  dd\n"));
+    // Without a terminal, the escape sequence would end up in the output
+    let args = Args::parse_from([
+        "rat".as_ref(),
+        fixture.0.as_os_str(),
+        "--batch".as_ref(),
+        "yank-all".as_ref(),
+    ]);
+    let commands = args.batch_commands().unwrap().unwrap();
+    let err = App::new().run_batch(args, &commands).unwrap_err();
+    assert_eq!(err.root_cause().to_string(), "yank-all needs a terminal!");
+}
+
+#[test]
+fn cycle_paste_through_older_yanks() {
+    let fixture = Fixture::new("yank_ring", "a,b,c\n");
+    let mut events = keys("ylylp");
+    let ctrl_p = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL);
+    events.0.push_back(Event::Key(ctrl_p));
+    events.0.extend(keys(":w\nu").0);
+    let (app, _) = run(&fixture, events);
+    assert_eq!(fixture.read(), "a,b,a\n");
+    let table = app.state.table.unwrap();
+    assert_eq!(
+        table.csv_table.get(CellLocation { row: 0, col: 2 }),
+        Some("c")
+    );
+}
+
+#[test]
+fn paste_special() {
+    let fixture = Fixture::new("paste_special", "1,,3\nx,y,z\nq,r,s\n");
+    run(
+        &fixture,
+        keys(
+            "vllyhhj:paste-special transpose skip-blanks\n\
+             lvjl:paste-special fill\n:w\n",
+        ),
+    );
+    assert_eq!(fixture.read(), "1,,3\n1,1,\nq,1,\n3,,\n");
+}
+
+#[test]
+fn fill_down_and_right() {
+    let fixture = Fixture::new("fill", "a,b,c\n1,2,3\n4,5,6\n");
+    let (app, _) = run(&fixture, keys("vjlFvjl:fill right\n:w\nuu"));
+    assert_eq!(fixture.read(), "a,b,c\na,b,b\n4,5,5\n");
+    // Both undos restore the cells, that the fills changed
+    let table = app.state.table.unwrap();
+    let cell = |row, col| table.csv_table.get(CellLocation { row, col });
+    assert_eq!((cell(1, 0), cell(1, 1)), (Some("1"), Some("2")));
+    assert_eq!((cell(1, 2), cell(2, 2)), (Some("3"), Some("6")));
+}
+
+#[test]
+fn fill_series() {
+    let fixture = Fixture::new("series", "1,2024-01-30,5\n,,\n,,\n");
+    run(
+        &fixture,
+        keys("vjjl:fill series\nlkkvjj:fill series 0.5\n:w\n"),
+    );
+    assert_eq!(
+        fixture.read(),
+        "1,2024-01-30,5\n2,2024-01-31,5.5\n3,2024-02-01,6.0\n"
+    );
+    // A second seed defines the step and keeps its formatting
+    let fixture = Fixture::new("series-seeds", "4.50\n5\n\n");
+    run(&fixture, keys("vjj:fill series\n:w\n"));
+    assert_eq!(fixture.read(), "4.50\n5\n5.50\n");
+    // Steps, that leave the dates, fail instead of overflowing
+    let fixture = Fixture::new("series-overflow", "2024-01-30\n\n\n");
+    for step in ["9000000000000000000", "1e30"] {
+        let (app, _) = run(&fixture, keys(&format!("vjj:fill series {step}\n")));
+        let message = app.state.console_message.unwrap();
+        assert_eq!(message.severity, Severity::Error);
+        assert!(message.message.contains("out of range"));
+    }
+}
+
+#[test]
+fn increment_numbers() {
+    let fixture = Fixture::new("increment", "id,v\n007,1.50\n009,-1\n");
+    let mut events = keys("vjjl3");
+    let ctrl = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+    events.0.push_back(ctrl('a'));
+    events.0.extend(keys("gg").0);
+    events.0.push_back(ctrl('x'));
+    events.0.extend(keys(":w\n").0);
+    run(&fixture, events);
+    assert_eq!(fixture.read(), "id,v\n010,4.50\n012,2\n");
+}
+
+#[test]
+fn stats_of_column_and_selection() {
+    let fixture = Fixture::new("stats", "n\n1\n2\nx\n4\n");
+    let (app, _) = run(&fixture, keys("S"));
+    let Some(Popup::Stats { title, stats, .. }) = app.state.popup else {
+        panic!("No stats popup");
+    };
+    assert_eq!(title, "A");
+    assert_eq!((stats.count, stats.sum, stats.median), (3, 7.0, Some(2.0)));
+    assert_eq!((stats.distinct, stats.excluded), (5, 2));
+
+    // A header row is no value
+    let (app, _) = run(&fixture, keys(":header on\nS"));
+    let Some(Popup::Stats { stats, .. }) = app.state.popup else {
+        panic!("No stats popup");
+    };
+    assert_eq!((stats.distinct, stats.excluded), (4, 1));
+
+    let (app, _) = run(&fixture, keys("jvj:stats\n"));
+    let Some(Popup::Stats { title, stats, .. }) = app.state.popup else {
+        panic!("No stats popup");
+    };
+    assert_eq!(title, "A2:A3");
+    assert_eq!((stats.mean, stats.median), (Some(1.5), Some(1.5)));
+}
+
+#[test]
+fn value_counts_jump_to_the_first_row() {
+    let fixture = Fixture::new("freq", "k,v\nb,1\na,2\nb,3\nc,4\na,5\nb,6\n");
+    let counts = |app: App| {
+        let Some(Popup::Freq { entries, .. }) = app.state.popup else {
+            panic!("No value counts popup");
+        };
+        let counts = entries
+            .iter()
+            .map(|entry| (entry.value.clone(), entry.count));
+        counts.collect::<Vec<_>>()
+    };
+    let (app, _) = run(&fixture, keys(":freq\n"));
+    let expected = [("b", 3), ("a", 2), ("k", 1), ("c", 1)];
+    assert_eq!(
+        counts(app),
+        expected.map(|(value, count)| (value.to_owned(), count))
+    );
+    // A header row is no value
+    let (app, _) = run(&fixture, keys(":header on\n:freq\n"));
+    let expected = [("b", 3), ("a", 2), ("c", 1)];
+    assert_eq!(
+        counts(app),
+        expected.map(|(value, count)| (value.to_owned(), count))
+    );
+
+    // Long lists scroll with the selected value
+    let content = (0..300).map(|n| format!("{n}\n")).collect::<String>();
+    let fixture_long = Fixture::new("freq-long", &content);
+    let (_, screen) = run(&fixture_long, keys(&format!(":freq\n{}", "j".repeat(250))));
+    assert!(screen.iter().any(|line| line.contains("1  250")));
+
+    let (app, _) = run(&fixture, keys(":header on\n:freq\nj\n"));
+    assert!(app.state.popup.is_none());
+    let table = app.state.table.unwrap();
+    assert_eq!(table.selection.primary, CellLocation { row: 2, col: 0 });
+}
+
+#[test]
+fn footer_shows_aggregates() {
+    let fixture = Fixture::new("aggregate", "n,m\n1,x\n2,y\n4,z\n");
+    let (_, screen) = run(&fixture, keys(":aggregate sum\n:agg B count\njc5\n"));
+    let footer = screen.iter().find(|line| line.starts_with('Σ')).unwrap();
+    assert!(footer.contains("sum 11"));
+    assert!(footer.contains("count 0"));
+}
+
+/// Evaluates `input` on a table with `content`.
+fn eval(content: &str, input: &str) -> Result<Value, String> {
+    let table = CsvTable::load_with(content.as_bytes(), None, LoadSettings::default()).unwrap();
+    expr::evaluate(input, &table).map_err(|err| err.display_with(input))
+}
+
+#[test]
+fn expressions_follow_precedence() {
+    for (input, expected) in [
+        ("1+2*3", 7.0),
+        ("(1+2)*3", 9.0),
+        ("10-4-3", 3.0),
+        ("8/4/2", 1.0),
+        ("2^3^2", 512.0),
+        ("-2^2", -4.0),
+        ("2*-3", -6.0),
+        ("A1+B1*2", 5.0),
+    ] {
+        assert_eq!(eval("1,2\n", input), Ok(Value::Number(expected)), "{input}");
+    }
+}
+
+#[test]
+fn expressions_concatenate_text() {
+    for (input, expected) in [
+        (r#""a"&"b""#, "ab"),
+        (r#""say ""hi""""#, r#"say "hi""#),
+        // `&` binds looser than arithmetic
+        ("1+2&3", "33"),
+        ("A1&B1", "x2"),
+        (r#"UPPER(A1)&LEN("abc")"#, "X3"),
+    ] {
+        assert_eq!(
+            eval("x,2\n", input),
+            Ok(Value::Text(expected.to_owned())),
+            "{input}"
+        );
+    }
+}
+
+#[test]
+fn expression_errors_show_their_position() {
+    for (input, expected) in [
+        ("(1+2", r#"Expected ")": (1+2‸"#),
+        ("SUM(A1 B1)", r#"Expected ",": SUM(A1 ‸B1)"#),
+        ("1 +", "Expected a value: 1 +‸"),
+        ("1 2", r#"Unexpected "2": 1 ‸2"#),
+        ("1/0", "Division by zero: 1‸/0"),
+        (r#"1+"ab"#, r#"Unterminated string: 1+‸"ab"#),
+        ("FOO(1)", "Unknown function: FOO: ‸FOO(1)"),
+        ("A1+1", r#"Not a number: "x": ‸A1+1"#),
+        ("A1:B1", r#"Unexpected ":": A1‸:B1"#),
+        ("LEN(1, 2)", "Wrong number of arguments for LEN: ‸LEN(1, 2)"),
+    ] {
+        assert_eq!(eval("x\n", input), Err(expected.to_owned()), "{input}");
+    }
+}
+
+#[test]
+fn expressions_over_ranges() {
+    let content = "1,2\n3,x\n,5\n";
+    for (input, expected) in [
+        ("SUM(A1:B3)", 11.0),
+        ("SUM(B3:A1)", 11.0),
+        ("COUNT(A1:B3)", 4.0),
+        ("COUNTA(A1:B3)", 5.0),
+        ("SUM(A:B)", 11.0),
+        ("AVERAGE(B:B)", 3.5),
+        ("MAX(A1:A100, 4)", 4.0),
+        ("SUM(C1:D9)", 0.0),
+    ] {
+        assert_eq!(eval(content, input), Ok(Value::Number(expected)), "{input}");
+    }
+}
+
+#[test]
+fn formulas_show_their_values() {
+    let fixture = Fixture::new("formulas", "1,2,=A1+B1*2\n=C1*2,=A2+1,=1/0\n=B3,=A3,x\n");
+    let (app, screen) = run(&fixture, keys(":echo B2\n"));
+    let table = app.state.table.unwrap();
+    let value = |row, col| table.formulas.value(CellLocation { row, col });
+    assert_eq!(value(0, 2), Some("5"));
+    assert_eq!(value(1, 0), Some("10"));
+    assert_eq!(value(1, 1), Some("11"));
+    assert_eq!(value(1, 2), Some("#ERROR!"));
+    assert_eq!(value(2, 0), Some("#CYCLE!"));
+    assert_eq!(value(2, 2), None);
+    assert_eq!(
+        screen.last().unwrap().trim_start().split(' ').next(),
+        Some("11")
+    );
+
+    let (_, screen) = run(&fixture, keys(":set formulas on\n"));
+    assert!(screen.iter().any(|line| line.contains("=C1*2")));
+}
+
+#[test]
+fn formulas_evaluate_long_chains() {
+    // Every row adds 1 to the row above
+    let content = (1..600).fold("0\n".to_owned(), |content, row| {
+        content + &format!("=A{row}+1\n")
+    });
+    let fixture = Fixture::new("formula-chain", &content);
+    let (app, _) = run(&fixture, keys(":echo A600\n"));
+    assert_eq!(app.state.console_message.unwrap().message, "599");
+    let table = app.state.table.unwrap();
+    assert_eq!(
+        table.formulas.value(CellLocation { row: 599, col: 0 }),
+        Some("599")
+    );
+
+    // The error names only the innermost failing formula
+    let (app, _) = run(&fixture, keys("cx\n:echo A600\n"));
+    let message = app.state.console_message.unwrap();
+    assert_eq!(message.severity, Severity::Error);
+    assert!(message.message.starts_with("A2: Not a number"));
+    assert!(message.message.len() < 100);
+    let table = app.state.table.unwrap();
+    assert_eq!(
+        table.formulas.value(CellLocation { row: 599, col: 0 }),
+        Some("#ERROR!")
+    );
+}
+
+#[test]
+fn formulas_follow_edits() {
+    let fixture = Fixture::new("formula-edits", "1,=A1*2,=B1+1\n2,=SUM(A:A),=B2&C1\n");
+    // Each step edits cells, after which the values match a fresh evaluation
+    let steps = [
+        "c5\n",
+        "lc=A1+C1\n",
+        "jhc3\n",
+        "u",
+        "kcx\n",
+        "u",
+        // A cycle through the range of B2 and its removal
+        ":goto A2\nc=C2\n",
+        "u",
+        "U",
+        "x",
+    ];
+    let mut events = String::new();
+    for step in steps {
+        events.push_str(step);
+        let (app, _) = run(&fixture, keys(&events));
+        let table = app.state.table.unwrap();
+        let mut fresh = Formulas::default();
+        fresh.update(table.csv_table.revision(), &table.csv_table);
+        if step.starts_with(":goto") {
+            assert_eq!(
+                fresh.value(CellLocation { row: 1, col: 0 }),
+                Some("#CYCLE!")
+            );
+        }
+        for (location, _) in table.csv_table.cells() {
+            assert_eq!(
+                table.formulas.value(location),
+                fresh.value(location),
+                "{location} after {events:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn formulas_over_ranges() {
+    let fixture = Fixture::new("ranges", "n,m,f\n1,2,=SUM(A:A)\n3,4,=AVG(A1:B3)\n");
+    let (app, _) = run(&fixture, keys("jc10\n"));
+    let table = app.state.table.unwrap();
+    let value = |row, col| table.formulas.value(CellLocation { row, col });
+    assert_eq!(value(1, 2), Some("13"));
+    assert_eq!(value(2, 2), Some("4.75"));
+}
+
+#[test]
+fn calc_stores_the_value_of_cell_input() {
+    let fixture = Fixture::new("calc", "a,b,c\n");
+    let (app, _) = run(
+        &fixture,
+        keys(":set calc on\nc=17*12\nlc=A1&\"x\"\nlc=1/0\n"),
+    );
+    assert!(matches!(app.state.input, InputState::Console(_)));
+    let table = app.state.table.unwrap();
+    let value = |col| table.csv_table.get(CellLocation { row: 0, col });
+    assert_eq!(
+        (value(0), value(1), value(2)),
+        (Some("204"), Some("204x"), Some("c"))
+    );
+}
+
+#[test]
+fn apply_maps_an_expression_over_a_column() {
+    let fixture = Fixture::new("apply", "2,x\n3, y \n");
+    let (app, _) = run(
+        &fixture,
+        keys(":apply A value * 1.5\n:apply B upper(trim(value))\n:apply B value & \"!\"\nu"),
+    );
+    let table = app.state.table.unwrap();
+    let column = |col| -> Vec<_> {
+        (0..2)
+            .map(|row| table.csv_table.get(CellLocation { row, col }).unwrap())
+            .collect()
+    };
+    assert_eq!(column(0), ["3", "4.5"]);
+    assert_eq!(column(1), ["X", "Y"]);
+
+    // Empty cells stay empty
+    let fixture = Fixture::new("apply-empty", "2,a\n,b\n3,c\n");
+    let (app, _) = run(&fixture, keys(":apply A value * 2\n"));
+    assert_eq!(
+        app.state.console_message.unwrap().message,
+        "2 cells written"
+    );
+    run(&fixture, keys(":apply A value * 2\n:w\n"));
+    assert_eq!(fixture.read(), "4,a\n,b\n6,c\n");
+}
+
+#[test]
+fn pipe_selection_through_a_command() {
+    let fixture = Fixture::new("pipe", "c,3\na,1\nb,2\nz,0\n");
+    // A pipe runs as a task, so it has to be the last command
+    let (app, _) = run(&fixture, keys("vjjl!sort\n"));
+    assert_eq!(table_csv(&app), "a,1\nb,2\nc,3\nz,0\n");
+    let (app, _) = run(&fixture, keys(":pipe head -n 2\n"));
+    assert_eq!(table_csv(&app), "c,3\na,1\n");
+    let mut table = app.state.table.unwrap();
+    table.undo();
+    assert_eq!(
+        table
+            .csv_table
+            .rect_to_csv_string(table.csv_table.used_extent())
+            .unwrap(),
+        "c,3\na,1\nb,2\nz,0\n"
+    );
+    let (app, _) = run(&fixture, keys(":pipe false\n"));
+    let message = app.state.console_message.as_ref().unwrap();
+    assert_eq!(message.severity, Severity::Error);
+    assert_eq!(table_csv(&app), "c,3\na,1\nb,2\nz,0\n");
+
+    // Cancelling kills the command and keeps the table, however few cells it gets
+    let start = std::time::Instant::now();
+    let (app, _) = run(&fixture, keys("vj:pipe sleep 10\n\x1b"));
+    assert!(start.elapsed().as_secs() < 5);
+    let message = app.state.console_message.unwrap();
+    assert_eq!(message.severity, Severity::Warning);
+    assert_eq!(app.state.table.unwrap().csv_table.revision(), 0);
+}
+
+#[test]
+fn sql_query_opens_the_result_in_a_new_buffer() {
+    let fixture = Fixture::new("sql", "name,qty\na,2\nb,3\na,5\nc,007\n");
+    let (app, _) = run(
+        &fixture,
+        keys(
+            ":header on\n:sql SELECT name, SUM(qty) AS total, MAX(qty) AS top FROM t GROUP BY name\n",
+        ),
+    );
+    assert_eq!(app.state.hidden_buffers.len(), 1);
+    assert!(app.state.table.as_ref().unwrap().header);
+    assert_eq!(table_csv(&app), "name,total,top\na,7,5\nb,3,3\nc,7,007\n");
+
+    // Letters of columns without a name don't repeat the names of others
+    let fixture = Fixture::new("sql-names", "B,\n1,2\n");
+    let (app, _) = run(&fixture, keys(":header on\n:sql SELECT * FROM t\n"));
+    assert_eq!(table_csv(&app), "B,B_2\n1,2\n");
+
+    // A query without end can be cancelled
+    let fixture = Fixture::new("sql-cancel", &"1,2\n".repeat(100_000));
+    let query = "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) SELECT x FROM n";
+    let (app, _) = run(&fixture, keys(&format!(":sql {query}\n\x1b")));
+    let message = app.state.console_message.unwrap();
+    assert_eq!(message.severity, Severity::Warning);
+    assert!(app.state.hidden_buffers.is_empty());
+}
+
+#[test]
+fn join_buffers_on_key_columns() {
+    let left = Fixture::new("join-left", "id,name\n1,ann\n2,bob\n3,cy\n");
+    let right = Fixture::new("join-right", "city,id\nRome,2\nOslo,1\nKyiv,4\n");
+    let commands = |join| {
+        format!(
+            ":o {}\n:header on\n:b 1\n:header on\n:join {join}\n",
+            right.0.display()
+        )
+    };
+    let (app, _) = run(&left, keys(&commands("2 A B")));
+    assert_eq!(table_csv(&app), "id,name,city\n1,ann,Oslo\n2,bob,Rome\n");
+    let (app, _) = run(&left, keys(&commands("2 A B outer")));
+    assert_eq!(
+        table_csv(&app),
+        "id,name,city\n1,ann,Oslo\n2,bob,Rome\n3,cy,\n4,,Kyiv\n"
+    );
+    // Empty headers and no hits leave nothing, that is used
+    let left = Fixture::new("join-empty-left", ",\n1,x\n");
+    let right = Fixture::new("join-empty-right", ",\n2,y\n");
+    let commands = format!(
+        ":o {}\n:header on\n:b 1\n:header on\n:join 2 A A\n",
+        right.0.display()
+    );
+    let (app, _) = run(&left, keys(&commands));
+    assert_eq!(
+        app.state.console_message.as_ref().unwrap().message,
+        "0 rows joined"
+    );
+    assert_eq!(table_csv(&app), "");
+}
+
+#[test]
+fn pivot_table_in_a_new_buffer() {
+    let fixture = Fixture::new(
+        "pivot",
+        "region,month,sales\nnorth,10,10\nsouth,9,5\nnorth,9,3\nnorth,10,4.5\n",
+    );
+    let (app, _) = run(&fixture, keys(":header on\n:pivot A B C sum\n"));
+    assert!(app.state.table.as_ref().unwrap().header);
+    assert_eq!(table_csv(&app), "region,9,10\nnorth,3,14.5\nsouth,5,\n");
+}
+
+#[test]
+fn cleanup_text_of_table_and_selection() {
+    let fixture = Fixture::new("cleanup", "  ann   lee ,x\nBOB o'neil,y\n");
+    let (app, _) = run(&fixture, keys(":squeeze\n:trim\n:title\nlvj:upper\n"));
+    let table = app.state.table.unwrap();
+    let column = |col| -> Vec<_> {
+        (0..2)
+            .map(|row| table.csv_table.get(CellLocation { row, col }).unwrap())
+            .collect()
+    };
+    assert_eq!(column(0), ["Ann Lee", "Bob O'neil"]);
+    assert_eq!(column(1), ["X", "Y"]);
+    let (app, _) = run(&fixture, keys(":lower\n:upper\nu"));
+    let table = app.state.table.unwrap();
+    let value = table.csv_table.get(CellLocation { row: 1, col: 0 });
+    assert_eq!(value, Some("bob o'neil"));
+}
+
+#[test]
+fn split_column_into_new_columns() {
+    let fixture = Fixture::new(
+        "split-col",
+        "id,name,x\n1,Ann Lee,a\n2,Bob  O Neil,b\n3,,c\n",
+    );
+    let (app, _) = run(&fixture, keys(":header on\n:split-col B \\s+\n"));
+    assert_eq!(
+        table_csv(&app),
+        "id,name,name 2,name 3,x\n1,Ann,Lee,,a\n2,Bob,O,Neil,b\n3,,,,c\n"
+    );
+    let (app, _) = run(&fixture, keys(":split-col B \\s+\nu"));
+    assert_eq!(
+        table_csv(&app),
+        "id,name,x\n1,Ann Lee,a\n2,Bob  O Neil,b\n3,,c\n"
+    );
+}
+
+#[test]
+fn merge_columns_into_the_first() {
+    let fixture = Fixture::new("merge-cols", "first,last,x\nAnn,Lee,1\nBob,,2\n");
+    let (app, _) = run(&fixture, keys(":header on\n:merge-cols A:B\n"));
+    assert_eq!(table_csv(&app), "first,x\nAnn Lee,1\nBob,2\n");
+    let (app, _) = run(&fixture, keys(":merge-cols C:A / \n"));
+    assert_eq!(table_csv(&app), "first/last/x\nAnn/Lee/1\nBob/2\n");
+    let (app, _) = run(&fixture, keys(":merge-cols A:C\nu"));
+    assert_eq!(table_csv(&app), "first,last,x\nAnn,Lee,1\nBob,,2\n");
+}
+
+#[test]
+fn transpose_table_and_selection() {
+    let fixture = Fixture::new("transpose", "a,b,c\n1,2,3\n");
+    let (app, _) = run(&fixture, keys(":transpose\n"));
+    assert_eq!(table_csv(&app), "a,1\nb,2\nc,3\n");
+    // The transposed row would overwrite the cells below it
+    let (app, _) = run(&fixture, keys("vll:transpose\n"));
+    assert_eq!(table_csv(&app), "a,b,c\n1,2,3\n");
+    let fixture = Fixture::new("transpose-selection", "a,b,x\nc,d,y\n");
+    let (app, _) = run(&fixture, keys("vjl:transpose\n"));
+    assert_eq!(table_csv(&app), "a,c,x\nb,d,y\n");
+    let (app, _) = run(&fixture, keys("vjl:transpose\nu"));
+    assert_eq!(table_csv(&app), "a,b,x\nc,d,y\n");
+    let (app, _) = run(&fixture, keys(":transpose\nu"));
+    assert_eq!(table_csv(&app), "a,b,x\nc,d,y\n");
+    let fixture = Fixture::new("transpose-row", "a,b,c\n\"\",\n\"\",\nx,y,z\n");
+    let (app, _) = run(&fixture, keys("vll:transpose\n"));
+    assert_eq!(table_csv(&app), "a,,\nb,,\nc,,\nx,y,z\n");
+}
+
+#[test]
+fn duplicate_rows_and_columns() {
+    let fixture = Fixture::new("duplicate", "a,b\nc,d\ne,f\n");
+    let mut events = keys("vj");
+    let alt_j = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::ALT);
+    events.0.push_back(Event::Key(alt_j));
+    events.0.extend(keys(":dup-col\n").0);
+    let (app, _) = run(&fixture, events);
+    assert_eq!(table_csv(&app), "a,a,b\nc,c,d\na,a,b\nc,c,d\ne,e,f\n");
+    let location = app.state.table.as_ref().unwrap().selection.primary;
+    assert_eq!(location, CellLocation { row: 3, col: 1 });
+    let (app, _) = run(&fixture, keys(":dup-row\n:dup-col\nuu"));
+    assert_eq!(table_csv(&app), "a,b\nc,d\ne,f\n");
+}
+
+#[test]
+fn reverse_and_shuffle_shown_rows_below_the_header() {
+    let column = |app: &App| -> Vec<String> {
+        let table = app.state.table.as_ref().unwrap();
+        table
+            .csv_table
+            .column_values(0)
+            .map(|value| value.unwrap_or_default().to_owned())
+            .collect()
+    };
+    let fixture = Fixture::new("reverse", "n,k\na,1\nb,2\nc,1\nd,1\n");
+    let (app, _) = run(&fixture, keys(":header on\n:filter B = 1\n:reverse\n"));
+    assert_eq!(column(&app), ["n", "d", "b", "c", "a"]);
+    let (app, _) = run(&fixture, keys(":reverse\nu"));
+    assert_eq!(column(&app), ["n", "a", "b", "c", "d"]);
+    let shuffled = |seed| {
+        let (app, _) = run(&fixture, keys(&format!(":header on\n:shuffle {seed}\n")));
+        column(&app)
+    };
+    assert_eq!(shuffled(7), shuffled(7));
+    assert_eq!(shuffled(7)[0], "n");
+}
+
+#[test]
+fn sample_keeps_the_header_row() {
+    let fixture = Fixture::new("sample", "n\na\nb\nc\nd\n");
+    let sample = |keys: QueuedEvents| {
+        let (app, _) = run(&fixture, keys);
+        assert_eq!(app.state.hidden_buffers.len(), 1);
+        let table = app.state.table.unwrap();
+        let column = table
+            .csv_table
+            .column_values(0)
+            .map(|value| value.unwrap_or_default().to_owned())
+            .collect::<Vec<_>>();
+        (table.header, column)
+    };
+    let (header, column) = sample(keys(":header on\n:sample 2 seed=3\n"));
+    assert!(header);
+    assert_eq!(column.len(), 3);
+    assert_eq!(column[0], "n");
+    assert!(column[1..].iter().all(|value| value != "n"));
+    let (header, column) = sample(keys(":header on\n:sample 100%\n"));
+    assert!(header);
+    assert_eq!(column, ["n", "a", "b", "c", "d"]);
+    let (header, column) = sample(keys(":sample 100%\n"));
+    assert!(!header);
+    assert_eq!(column, ["n", "a", "b", "c", "d"]);
+}
+
+#[test]
+fn detect_the_delimiter_on_load() {
+    assert_eq!(sniff_delimiter(b"\"x;y\",1\n\"z;w\",2\n"), Some(b','));
+    assert_eq!(sniff_delimiter(b"a|b,c|d\n1|2|3"), Some(b'|'));
+    assert_eq!(sniff_delimiter(b"a;b\n1;2\n3,5;4\n"), Some(b';'));
+    assert_eq!(sniff_delimiter(b"abc\n"), None);
+    let fixture = Fixture::new("sniff", "a\tb;c\n1\t2\n");
+    let (app, _) = run(&fixture, QueuedEvents(VecDeque::new()));
+    let message = app.state.console_message.unwrap().message;
+    assert_eq!(message, r"Detected delimiter: \t");
+    let table = app.state.table.unwrap();
+    assert_eq!(table.csv_table.delimiter, Some(b'\t'));
+    let value = table.csv_table.get(CellLocation { row: 0, col: 1 });
+    assert_eq!(value, Some("b;c"));
+}
+
+#[test]
+fn quoting_options_on_save() {
+    let fixture = Fixture::new("quoting", "a,b c\n\"x\"\"y\",1\n");
+    let (app, _) = run(
+        &fixture,
+        keys(":set quote-style always\n:set escape \\\n:w\n:set escape\n"),
+    );
+    assert_eq!(fixture.read(), "\"a\",\"b c\"\n\"x\\\"y\",\"1\"\n");
+    assert_eq!(app.state.console_message.unwrap().message, r"escape=\");
+    let fixture = Fixture::new("quote-char", "a,\"b,c\"\n");
+    run(&fixture, keys(":set quote '\n:w\n"));
+    assert_eq!(fixture.read(), "a,'b,c'\n");
+}
+
+#[test]
+fn keep_line_endings_and_bom_on_save() {
+    let fixture = Fixture::new("crlf", "\u{feff}a,b\r\nc,d\r\n");
+    let (app, _) = run(&fixture, keys("cx\n:w\n:set eol\n"));
+    assert_eq!(fixture.read(), "\u{feff}x,b\r\nc,d\r\n");
+    assert_eq!(app.state.console_message.unwrap().message, "eol=crlf");
+    let table = app.state.table.unwrap();
+    assert_eq!(table.csv_table.get(Default::default()), Some("x"));
+    run(&fixture, keys(":set bom off\n:set eol lf\n:w\n"));
+    assert_eq!(fixture.read(), "x,b\nc,d\n");
+}
+
+#[test]
+fn transcode_encoding_on_load_and_save() {
+    let fixture = Fixture::new("latin1", "");
+    fs::write(&fixture.0, b"caf\xe9,1\n").unwrap();
+    let open = format!(":set encoding latin1\n:o {}\n", fixture.0.display());
+    let (app, _) = run(
+        &fixture,
+        keys(&format!("{open}lc\u{fc}\n:w\n:set encoding\n")),
+    );
+    assert_eq!(fs::read(&fixture.0).unwrap(), b"caf\xe9,\xfc\n");
+    assert_eq!(
+        app.state.console_message.unwrap().message,
+        "encoding=windows-1252"
+    );
+    let table = app.state.table.unwrap();
+    assert_eq!(table.csv_table.get(Default::default()), Some("caf\u{e9}"));
+
+    // An unchanged buffer is read again
+    let fixture = Fixture::new("reload", "caf\u{e9}\n");
+    let (app, _) = run(&fixture, keys(":set encoding latin1\n:w\n"));
+    let table = app.state.table.unwrap();
+    assert_eq!(
+        table.csv_table.get(Default::default()),
+        Some("caf\u{c3}\u{a9}")
+    );
+    assert_eq!(fixture.read(), "caf\u{e9}\n");
+
+    // A changed buffer keeps its cells
+    let fixture = Fixture::new("utf-16", "a,b\n");
+    run(&fixture, keys("cx\n:set encoding utf-16le\n:w\n"));
+    let expected: Vec<u8> = "x,b\n".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    assert_eq!(fs::read(&fixture.0).unwrap(), expected);
+
+    // Bytes, that aren't valid in the encoding, aren't replaced
+    let fixture = Fixture::new("invalid", "a\0,\0b\0\n\0x");
+    let (app, _) = run(&fixture, keys(":set encoding utf-16le\n"));
+    let message = app.state.console_message.unwrap();
+    assert_eq!(message.severity, Severity::Error);
+    assert_eq!(message.message, "The file isn't valid UTF-16LE!");
+    let table = app.state.table.unwrap();
+    assert_eq!(table.csv_table.get(Default::default()), Some("a\0"));
+}
+
+#[test]
+fn faithful_save_keeps_unchanged_rows() {
+    let content = "\"a\",b\r\n\"x\",\"y\"\n1,2";
+    let fixture = Fixture::new("faithful", content);
+    let (app, _) = run(&fixture, keys(":set faithful on\njcz\n:w\n:set faithful\n"));
+    assert_eq!(fixture.read(), "\"a\",b\r\nz,y\r\n1,2");
+    assert_eq!(app.state.console_message.unwrap().message, "faithful=on");
+
+    // Moved rows are kept too
+    let fixture = Fixture::new("faithful-reverse", content);
+    run(&fixture, keys(":set faithful on\n:reverse\n:w\n"));
+    assert_eq!(fixture.read(), "1,2\r\n\"x\",\"y\"\n\"a\",b\r\n");
+    run(&fixture, keys(":set faithful on\n:set faithful off\n:w\n"));
+    assert_eq!(fixture.read(), "1,2\r\nx,y\r\na,b\r\n");
+}
+
+#[test]
+fn keep_comment_lines_with_their_rows() {
+    let fixture = Fixture::new("comments", "# title\na,b\n# note\nc,d\n#end\n");
+    let open = format!(":set comment-char #\n:o {}\n", fixture.0.display());
+    let (app, lines) = run(&fixture, keys(&open));
+    let table = app.state.table.unwrap();
+    assert_eq!(table.csv_table.comments(1), ["# note"]);
+    assert_eq!(
+        table.csv_table.get(CellLocation { row: 1, col: 0 }),
+        Some("c")
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains(symbols::COMMENT_MARKER))
+    );
+
+    run(&fixture, keys(&format!("{open}:reverse\nlc#x\n:w\n")));
+    assert_eq!(fixture.read(), "# note\nc,d\n# title\na,\"#x\"\n#end\n");
+}
+
+#[test]
+fn check_and_fix_ragged_rows() {
+    let content = "a,b,c\n1,2\n3,4,5,6\nx,y,z\n";
+    let fixture = Fixture::new("ragged", content);
+    let (app, _) = run(&fixture, keys(":check\n"));
+    let table = app.state.table.unwrap();
+    let results = table.results.unwrap();
+    assert_eq!(results.title, "2 ragged rows · most have 3 fields");
+    assert_eq!(results.entries[1].0, CellLocation { row: 2, col: 0 });
+    assert!(table.csv_table.is_ragged(1));
+
+    // Undo restores the field counts and redo fixes them again
+    let ragged_title = |app: App| app.state.table.unwrap().results.unwrap().title;
+    let (app, _) = run(&fixture, keys(":fix-ragged truncate\nu:check\n"));
+    assert_eq!(ragged_title(app), "2 ragged rows · most have 3 fields");
+    let (app, _) = run(&fixture, keys(":fix-ragged truncate\nuU:check\n"));
+    assert_eq!(
+        app.state.console_message.unwrap().message,
+        "No ragged rows, all have 3 fields"
+    );
+    let (app, _) = run(&fixture, keys("cx\n:fix-ragged pad\nu:check\n"));
+    let table = app.state.table.as_ref().unwrap();
+    assert_eq!(table.csv_table.get(CellLocation::default()), Some("x"));
+    assert_eq!(ragged_title(app), "2 ragged rows · most have 3 fields");
+
+    let (app, _) = run(&fixture, keys(":fix-ragged truncate\n:w\nu"));
+    assert_eq!(fixture.read(), "a,b,c\n1,2,\n3,4,5\nx,y,z\n");
+    let table = app.state.table.unwrap();
+    assert_eq!(
+        table.csv_table.get(CellLocation { row: 2, col: 3 }),
+        Some("6")
+    );
+
+    let fixture = Fixture::new("ragged-pad", content);
+    let (app, _) = run(&fixture, keys(":fix-ragged pad\n:w\n:check\n"));
+    assert_eq!(fixture.read(), "a,b,c,\n1,2,,\n3,4,5,6\nx,y,z,\n");
+    assert_eq!(
+        app.state.console_message.unwrap().message,
+        "No ragged rows, all have 4 fields"
+    );
+}
+
+#[test]
+fn keep_compression_on_save() {
+    let fixture = Fixture::new("gzip", "");
+    let gzip = Compression::Gzip.compress(b"a,b\n".to_vec()).unwrap();
+    fs::write(&fixture.0, gzip).unwrap();
+    let zstd_path = fixture.0.with_extension("csv.zst");
+    let (app, _) = run(
+        &fixture,
+        keys(&format!("cx\n:w\n:w! {}\n", zstd_path.display())),
+    );
+    assert_eq!(app.state.table.unwrap().compression, Compression::Zstd);
+    for (path, compression) in [
+        (&fixture.0, Compression::Gzip),
+        (&zstd_path, Compression::Zstd),
+    ] {
+        let (mut read, detected) = compression::open(path).unwrap();
+        let mut content = String::new();
+        read.read_to_string(&mut content).unwrap();
+        assert_eq!((content.as_str(), detected), ("x,b\n", compression));
+    }
+    fs::remove_file(zstd_path).unwrap();
+}
+
+#[test]
+fn open_url() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/data.csv", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            stream.read_exact(&mut byte).unwrap();
+            request.push(byte[0]);
+        }
+        let body = "a,b\n1,2\n";
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+    });
+    let fixture = Fixture::new("url", "x\n");
+    let (app, _) = run(&fixture, keys(&format!(":o {url}\n")));
+    server.join().unwrap();
+    let table = app.state.table.unwrap();
+    assert_eq!(table.file, None);
+    assert_eq!(
+        table.csv_table.get(CellLocation { row: 1, col: 1 }),
+        Some("2")
+    );
+    assert_eq!(app.state.hidden_buffers.len(), 1);
+}
+
+#[test]
+fn run_batch_commands() {
+    let fixture = Fixture::new("batch", "a;b,c\n");
+    let batch_on = |fixture: &Fixture, commands: &[&str]| {
+        let mut args = vec!["rat".to_owned(), fixture.0.to_string_lossy().into_owned()];
+        for command in commands {
+            args.extend(["--batch".to_owned(), (*command).to_owned()]);
+        }
+        let args = Args::parse_from(args);
+        let commands = args.batch_commands().unwrap().unwrap();
+        App::new().run_batch(args, &commands)
+    };
+    let batch = |commands: &[&str]| batch_on(&fixture, commands);
+    // `;` separates the commands, unless it is escaped
+    batch(&[":%s/\\;/-/; delimiter \\;", "w"]).unwrap();
+    assert_eq!(fixture.read(), "a-b;c\n");
+    let err = batch(&["%s/x/y/; w"]).unwrap_err();
+    assert_eq!(err.to_string(), "Failed command: %s/x/y/");
+    assert_eq!(fixture.read(), "a-b;c\n");
+
+    // The example of the request without `dedup A`, which isn't a command
+    let sort = Fixture::new("batch-sort", "x, 1\ny ,3\nz,2\n");
+    let out = Fixture::new("batch-out", "");
+    let commands = format!("sort B desc; trim; w {}; q", out.0.display());
+    batch_on(&sort, &[&commands]).unwrap();
+    assert_eq!(out.read(), "y,3\nz,2\nx,1\n");
+}
+
+#[test]
+fn undo_edit() {
+    let fixture = Fixture::new("undo", "a,b\n1,2\n");
+    let (app, _) = run(&fixture, keys("cx\nu"));
+    let table = app.state.table.unwrap();
+    assert_eq!(table.csv_table.get(Default::default()), Some("a"));
+    assert!(!table.is_dirty());
+}
+
+#[test]
+fn redo_paste() {
+    let fixture = Fixture::new("redo", "a,b\n");
+    let mut events = keys("ylpu");
+    let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+    events.0.push_back(Event::Key(ctrl_r));
+    let (app, _) = run(&fixture, events);
+    let table = app.state.table.unwrap();
+    assert_eq!(
+        table.csv_table.get(CellLocation { row: 0, col: 1 }),
+        Some("a")
+    );
+}
+
+#[test]
+fn save_and_load_notes() {
+    let fixture = Fixture::new("notes", "a,b\nc,d\n");
+    let sidecar = notes::sidecar_path(&fixture.0);
+    // The second note is beyond the used extent of the table
+    run(
+        &fixture,
+        keys("l:note first one\n:goto C5\n:note far\n:w\n"),
+    );
+    assert_eq!(fixture.read(), "a,b\nc,d\n");
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&sidecar).unwrap()).unwrap();
+    assert_eq!(json, serde_json::json!({ "B1": "first one", "C5": "far" }));
+    let (app, _) = run(&fixture, keys(""));
+    let notes = (app.state.table.unwrap().notes.into_iter())
+        .map(|(location, note)| format!("{location} {note}"))
+        .collect::<Vec<_>>();
+    assert_eq!(notes, ["B1 first one", "C5 far"]);
+    run(&fixture, keys("l:note\nd:goto C5\n:note\nd:w\n"));
+    assert!(!sidecar.exists());
+    assert_eq!(
+        run(&fixture, keys("")).0.state.table.unwrap().notes.len(),
+        0
+    );
+}
+
+#[test]
+fn insert_and_delete_rows() {
+    let fixture = Fixture::new("rows", "a\nb\nc\n");
+    run(&fixture, keys("ocxx\njD:w\n"));
+    assert_eq!(fixture.read(), "a\nxx\nc\n");
+    let (app, _) = run(&fixture, keys("j2O"));
+    let table = app.state.table.unwrap();
+    let column = table.csv_table.column_values(0);
+    let column = column.map(Option::unwrap_or_default).collect::<Vec<_>>();
+    assert_eq!(column, ["a", "", "", "xx", "c"]);
+    run(&fixture, keys("j2O:w\nu:w\n"));
+    assert_eq!(fixture.read(), "a\nxx\nc\n");
+    // Notes and marks move with their rows, are deleted with them and come back on undo
+    let anchored = |keys: QueuedEvents| {
+        let (app, _) = run(&fixture, keys);
+        let table = app.state.table.unwrap();
+        let notes = table
+            .notes
+            .keys()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        (notes, table.marks.get(&'a').map(ToString::to_string))
+    };
+    let at = |location: &str| (vec![location.to_owned()], Some(location.to_owned()));
+    assert_eq!(anchored(keys("jj:note n\nmagg2O")), at("A5"));
+    assert_eq!(anchored(keys("jj:note n\nmagg2Ou")), at("A3"));
+    assert_eq!(anchored(keys("jj:note n\nmaggD")), at("A2"));
+    assert_eq!(anchored(keys("jj:note n\nmaD")), (vec![], None));
+    assert_eq!(anchored(keys("jj:note n\nmaDu")), at("A3"));
+    assert_eq!(anchored(keys("jj:note n\nmaDuU")), (vec![], None));
+}
+
+#[test]
+fn insert_and_delete_cols() {
+    let fixture = Fixture::new("cols", "a,b,c,d\n1,2,3,4\n");
+    run(&fixture, keys(":col-insert B\n:col-delete D:E\n:w\n"));
+    assert_eq!(fixture.read(), "a,,b\n1,,2\n");
+    run(&fixture, keys("lvl:col-delete\n:w\nu"));
+    assert_eq!(fixture.read(), "a\n1\n");
+    // Notes, marks, widths, heatmaps and aggregates move with their columns, are deleted with
+    // them and come back on undo
+    let fixture = Fixture::new("cols-anchored", "a,b\n1,2\n");
+    let setup = format!(
+        ":set autogrow on\nlc{}\n:note n\nma:heatmap B\n:aggregate B sum\n",
+        "x".repeat(30)
+    );
+    let anchored = |then: &str| {
+        let (app, _) = run(&fixture, keys(&format!("{setup}{then}")));
+        let table = app.state.table.unwrap();
+        let notes = table
+            .notes
+            .keys()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let mark = table.marks.get(&'a').map(ToString::to_string);
+        let widths = table.col_widths.keys().copied().collect::<Vec<_>>();
+        let heatmaps = table.heatmaps.keys().copied().collect::<Vec<_>>();
+        let aggregates = table.aggregates.keys().copied().collect::<Vec<_>>();
+        (notes, mark, widths, heatmaps, aggregates)
+    };
+    let at = |location: &str, col: usize| {
+        (
+            vec![location.to_owned()],
+            Some(location.to_owned()),
+            vec![col],
+            vec![col],
+            vec![col],
+        )
+    };
+    assert_eq!(anchored(""), at("B1", 1));
+    assert_eq!(anchored(":col-insert A 2\n"), at("D1", 3));
+    assert_eq!(anchored(":col-insert A 2\nu"), at("B1", 1));
+    assert_eq!(anchored(":col-delete A\n"), at("A1", 0));
+    assert_eq!(
+        anchored(":col-delete B\n"),
+        (vec![], None, vec![], vec![], vec![])
+    );
+    assert_eq!(anchored(":col-delete B\nu"), at("B1", 1));
+    assert_eq!(
+        anchored(":col-delete B\nuU"),
+        (vec![], None, vec![], vec![], vec![])
+    );
+}
+
+#[test]
+fn substitute_with_groups() {
+    let fixture = Fixture::new("substitute", "a-1,b-2\nc-3,d-4\n");
+    run(&fixture, keys(":%s/(\\w)-(\\d)/$2$1/\n:w\n"));
+    assert_eq!(fixture.read(), "1a,2b\n3c,4d\n");
+    // The selection limits the substitution, which is undone as a whole
+    run(&fixture, keys("vj:s/\\d/x/\n:w\nu:w!\n"));
+    assert_eq!(fixture.read(), "1a,2b\n3c,4d\n");
+    run(&fixture, keys("vj:s/\\d/x/\n:w\n"));
+    assert_eq!(fixture.read(), "xa,2b\nxc,4d\n");
+}
+
+#[test]
+fn ignorecase_and_smartcase() {
+    let content = "apple\nApple\nAPPLE\nbanana\n";
+    let fixture = Fixture::new("case", content);
+    // Matches of `apple` and `Apple` for each combination of ignorecase and smartcase
+    let cases = [
+        ("off", "off", 1, 1),
+        ("on", "off", 3, 3),
+        ("off", "on", 1, 1),
+        ("on", "on", 3, 1),
+    ];
+    for (ignorecase, smartcase, lower, upper) in cases {
+        let set = format!(":set ignorecase {ignorecase}\n:set smartcase {smartcase}\n");
+        for (pattern, expected) in [("apple", lower), ("Apple", upper)] {
+            let (app, _) = run(&fixture, keys(&format!("{set}/{pattern}\n:count\n")));
+            let message = app.state.console_message.unwrap().message;
+            assert_eq!(message, format!("{expected} matches in {expected} rows"));
+            run(&fixture, keys(&format!("{set}:%s/{pattern}/x/\n:w\n")));
+            let replaced = fixture.read().lines().filter(|line| *line == "x").count();
+            assert_eq!(replaced, expected, "{ignorecase} {smartcase} {pattern}");
+            fs::write(&fixture.0, content).unwrap();
+        }
+    }
+}
+
+#[test]
+fn cancelled_substitution_keeps_the_table() {
+    let content = "a,b,c,d\n".repeat(100_000);
+    let fixture = Fixture::new("cancel", &content);
+    let (untouched, _) = run(&fixture, keys(""));
+    // Esc cancels the substitution, that runs as a task on this many cells
+    let (app, _) = run(&fixture, keys(":%s/[a-d]/x/\n\x1b"));
+    let message = app.state.console_message.unwrap();
+    assert_eq!(
+        (message.severity, message.message),
+        (Severity::Warning, "Substituting cancelled".into())
+    );
+    let (table, untouched) = (app.state.table.unwrap(), untouched.state.table.unwrap());
+    assert!(table.csv_table.cells().eq(untouched.csv_table.cells()));
+    assert_eq!(table.csv_table.revision(), untouched.csv_table.revision());
+    assert!(!table.is_dirty());
+}
+
+#[test]
+fn substitute_with_confirmation() {
+    let fixture = Fixture::new("confirm", "a,a\na,a\n");
+    // Replace, skip, replace, then quit before the last match
+    run(&fixture, keys(":%s/a/b/c\nynyq:w\n"));
+    assert_eq!(fixture.read(), "b,a\nb,a\n");
+    // Skip, then replace all remaining
+    run(&fixture, keys(":%s/a/c/c\nna:w\n"));
+    assert_eq!(fixture.read(), "b,a\nb,c\n");
+}
+
+#[test]
+fn mouse_drag_selects() {
+    let fixture = Fixture::new("mouse", "a,b\n1,2\n3,4\n");
+    let mouse = |kind, column, row| {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    };
+    let mut events = QueuedEvents(VecDeque::from([
+        mouse(MouseEventKind::Down(MouseButton::Left), 10, 1),
+        mouse(MouseEventKind::Drag(MouseButton::Left), 40, 2),
+    ]));
+    events.0.extend(keys("d:w\n").0);
+    run(&fixture, events);
+    assert_eq!(fixture.read(), ",\n,\n3,4\n");
+}
+
+#[test]
+fn mouse_drag_scrolls_the_scrollbars() {
+    let content = "a,b,c,d,e,f,g,h,i,j,k,l\n".repeat(100);
+    let fixture = Fixture::new("mouse-scrollbar", &content);
+    let (app, _) = run(&fixture, QueuedEvents(VecDeque::new()));
+    let table = app.state.table.as_ref().unwrap();
+    let (vscrollbar, hscrollbar) = (table.vscrollbar_area, table.hscrollbar_area);
+    let max = table.max_top_left_cell_location();
+    assert!(max.row > 0 && max.col > 0);
+    let mouse = |kind, column, row| {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    };
+    let top_left = |events: Vec<Event>| {
+        let (app, _) = run(&fixture, QueuedEvents(VecDeque::from(events)));
+        app.state.table.unwrap().top_left_cell_location
+    };
+    // Dragging beyond the end of the track scrolls to the end
+    let down = MouseEventKind::Down(MouseButton::Left);
+    let drag = MouseEventKind::Drag(MouseButton::Left);
+    let location = top_left(vec![
+        mouse(down, vscrollbar.x, vscrollbar.y),
+        mouse(drag, 0, HEIGHT),
+    ]);
+    assert_eq!((location.row, location.col), (max.row, 0));
+    let location = top_left(vec![mouse(down, hscrollbar.right() - 1, hscrollbar.y)]);
+    assert_eq!((location.row, location.col), (0, max.col));
+    // Releasing the scrollbar ends the drag, which then selects cells again
+    let location = top_left(vec![
+        mouse(down, hscrollbar.right() - 1, hscrollbar.y),
+        mouse(MouseEventKind::Up(MouseButton::Left), 0, 0),
+        mouse(drag, hscrollbar.x, hscrollbar.y),
+    ]);
+    assert_eq!(location.col, max.col);
+}
+
+#[test]
+fn sort_rows() {
+    let fixture = Fixture::new("sort", "name,n\nb,10\na,9\nc,\n");
+    run(&fixture, keys(":header on\n:sort B\n:w\n"));
+    assert_eq!(fixture.read(), "name,n\na,9\nb,10\nc,\n");
+    run(&fixture, keys(":header on\n:sort A desc\n:w\n"));
+    assert_eq!(fixture.read(), "name,n\nc,\nb,10\na,9\n");
+    run(&fixture, keys(":header on\n:sort B lexical\n:w\n"));
+    assert_eq!(fixture.read(), "name,n\nb,10\na,9\nc,\n");
+    // Notes and marks move with their rows and back on undo
+    let fixture = Fixture::new("sort-anchored", "b\na\nc\n");
+    let anchored = |then: &str| {
+        let (app, _) = run(&fixture, keys(&format!(":note about-b\nmaj{then}")));
+        let table = app.state.table.unwrap();
+        let notes = (table.notes.iter())
+            .map(|(location, note)| format!("{location} {note}"))
+            .collect::<Vec<_>>();
+        (notes, table.marks.get(&'a').map(ToString::to_string))
+    };
+    let at = |location: &str| {
+        (
+            vec![format!("{location} about-b")],
+            Some(location.to_owned()),
+        )
+    };
+    assert_eq!(anchored(":sort A\n"), at("A2"));
+    assert_eq!(anchored(":sort A\nu"), at("A1"));
+    assert_eq!(anchored(":sort A\nuU"), at("A2"));
+    assert_eq!(anchored(":reverse\n"), at("A3"));
+}
+
+#[test]
+fn sort_rows_by_several_keys() {
+    let fixture = Fixture::new("sort-keys", "x,2,a\ny,1,b\nx,1,c\ny,2,d\n");
+    run(&fixture, keys(":sort A desc, B asc\n:w\n"));
+    assert_eq!(fixture.read(), "y,1,b\ny,2,d\nx,1,c\nx,2,a\n");
+}
+
+#[test]
+fn filter_rows() {
+    let fixture = Fixture::new("filter", "name,n\na,1\nb,20\nc,3\nd,40\n");
+    // Moving and editing only reaches the shown rows
+    let (app, lines) = run(&fixture, keys(":header\n:filter B > 10\njjlc99\n:w\n"));
+    assert_eq!(fixture.read(), "name,n\na,1\nb,20\nc,3\nd,99\n");
+    assert!(lines[2].starts_with("  3 ") && lines[3].starts_with("  5 "));
+    assert!(app.state.table.unwrap().filter.is_some());
+    let (_, lines) = run(&fixture, keys(":filter A ~ [ab]\n:filter clear\n"));
+    assert!(lines[3].starts_with("  3 "));
+}
+
+#[test]
+fn render_table() {
+    let fixture = Fixture::new("render", "name,count\nfoo,1\nbar,22\n");
+    let (_, lines) = run(&fixture, keys("j"));
+    assert_eq!(
+        lines,
+        [
+            "                   A                          B",
+            "  1 █            name                       count",
+            "  2 █             foo                         1",
+            "  3 █             bar                        22",
+            "  4 █",
+            "  5 █",
+            "     █████████████████████████████████████══════════════════",
+            "                                             All         A2",
+        ]
+    );
+}
+
+#[test]
+fn header_row_is_pinned() {
+    let content = (1..=20).map(|i| format!("{i},{i}\n")).collect::<String>();
+    let fixture = Fixture::new("header", &format!("name,count\n{content}"));
+    let (_, lines) = run(&fixture, keys(":header\n10j"));
+    assert!(lines[0].contains("name") && lines[0].contains("count"));
+    assert!(lines[1].contains("name"));
+    assert!(!lines[2].contains("name"));
+}
+
+#[test]
+fn switch_buffers() {
+    let first = Fixture::new("buffer-1", "a\n");
+    let second = Fixture::new("buffer-2", "b\n");
+    let open = format!(":o {}\n", second.0.display());
+    let (app, _) = run(&first, keys(&format!("{open}cx\n:b 1\ncy\n:ls\n")));
+    let message = app.state.console_message.unwrap().message;
+    assert_eq!(
+        message,
+        format!(
+            "%1 {} +  2 {} +",
+            first.0.file_name().unwrap().display(),
+            second.0.file_name().unwrap().display()
+        )
+    );
+    // Each buffer keeps its own content
+    let table = app.state.table.unwrap();
+    assert_eq!(table.csv_table.get(Default::default()), Some("y"));
+    assert_eq!(
+        app.state.hidden_buffers[0]
+            .csv_table
+            .get(Default::default()),
+        Some("x")
+    );
+}
+
+#[test]
+fn split_panes_keep_their_views() {
+    let fixture = Fixture::new("split", "a\nb\nc\n");
+    let ctrl_w = || Event::Key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+    let mut events = keys(":vsplit\n");
+    events.0.push_back(ctrl_w());
+    events.0.extend(keys("ljj").0);
+    let (_, lines) = run(&fixture, events);
+    assert!(!lines[0].starts_with('◆') && lines[0].contains('◆'));
+    assert!(lines[HEIGHT as usize - 1].ends_with("A3"));
+    events = keys(":vsplit\nj");
+    events.0.push_back(ctrl_w());
+    events.0.extend(keys("w").0);
+    // The other pane still shows the first cell
+    let (_, lines) = run(&fixture, events);
+    assert!(!lines[0].starts_with('◆'));
+    assert!(lines[HEIGHT as usize - 1].ends_with("A1"));
+}
+
+#[test]
+fn diff_with_file() {
+    let new = Fixture::new("diff-new", "id,name\n1,a\n2,B\n4,d\n5,e\n");
+    let old = Fixture::new("diff-old", "id,name\n1,a\n2,b\n3,c\n4,d\n");
+    let diff = format!(":diff {}\n", old.0.display());
+    let (app, _) = run(&new, keys(&diff));
+    let message = app.state.console_message.unwrap().message;
+    assert_eq!(message, "1 added, 1 removed, 1 changed rows");
+    // `]c` jumps to the changed cell, then to the removed row
+    let (app, _) = run(&new, keys(&format!("{diff}]c]c")));
+    let table = app.state.table.unwrap();
+    let diff = table.diff.unwrap();
+    assert_eq!(
+        diff.rows,
+        [
+            RowDiff::Same,
+            RowDiff::Same,
+            RowDiff::Changed(vec![1]),
+            RowDiff::Removed,
+            RowDiff::Same,
+            RowDiff::Added,
+        ]
+    );
+    assert_eq!(
+        table.csv_table.get(CellLocation { row: 3, col: 1 }),
+        Some("c")
+    );
+    assert_eq!(table.selection.primary, CellLocation { row: 3, col: 0 });
+}
+
+#[test]
+fn console_message() {
+    let fixture = Fixture::new("message", "1,2\n3,4\n");
+    let (_, lines) = run(&fixture, keys(":echo SUM(A1:B2)\n"));
+    assert!(lines[HEIGHT as usize - 1].starts_with("10 "));
+}
@@ -18,6 +18,12 @@ pub(crate) trait Undoee {
     fn redo(&mut self, action: Self::RedoAction) -> Self::UndoAction;
 }
 
+impl<U: Undoee> Default for UndoStack<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<U: Undoee> UndoStack<U> {
     pub(crate) fn new() -> Self {
         Self {